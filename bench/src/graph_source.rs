@@ -0,0 +1,147 @@
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use graco::generators::{
+    barabasi_albert, delaunay, erdos_renyi, grid_2d, grid_3d, random_geometric, rmat,
+    BarabasiAlbertConfig, DelaunayConfig, ErdosRenyiConfig, GeneratedGraph, Grid2DConfig,
+    Grid3DConfig, RandomGeometricConfig, RmatConfig,
+};
+
+use crate::load::{load_matrix_market, load_metis};
+
+// The average degree targeted by the generators for which the degree is a free parameter.
+const TARGET_AVERAGE_DEGREE: f64 = 8.0;
+
+/// Describes a benchmark input graph.
+///
+/// Parsed from `kind:argument` strings, see [USAGE](crate::USAGE) for the supported kinds.
+#[derive(Clone, PartialEq, Debug)]
+pub enum GraphSource {
+    Grid2D(u32),
+    Grid3D(u32),
+    RandomGeometric(u32),
+    ErdosRenyi(u32),
+    Rmat(u32),
+    BarabasiAlbert(u32),
+    Delaunay(u32),
+    Metis(String),
+    MatrixMarket(String),
+}
+
+impl GraphSource {
+    pub fn default_matrix() -> Vec<GraphSource> {
+        vec![
+            GraphSource::Grid2D(1000),
+            GraphSource::Grid3D(100),
+            GraphSource::RandomGeometric(1_000_000),
+            GraphSource::ErdosRenyi(1_000_000),
+            GraphSource::Rmat(20),
+            GraphSource::BarabasiAlbert(1_000_000),
+            GraphSource::Delaunay(1_000_000),
+        ]
+    }
+
+    pub fn load(&self) -> Result<GeneratedGraph, Box<dyn Error>> {
+        let graph = match *self {
+            GraphSource::Grid2D(size) => grid_2d(&Grid2DConfig {
+                width: size,
+                height: size,
+                jitter: 0.45,
+                output_positions: false,
+                ..Default::default()
+            }),
+            GraphSource::Grid3D(size) => grid_3d(&Grid3DConfig {
+                width: size,
+                height: size,
+                depth: size,
+                jitter: 0.45,
+                output_positions: false,
+                ..Default::default()
+            }),
+            GraphSource::RandomGeometric(node_count) => {
+                // The expected degree of a node is `node_count * PI * radius^2` (ignoring boundary
+                // effects).
+                let radius = (TARGET_AVERAGE_DEGREE / (node_count as f64 * PI)).sqrt();
+
+                random_geometric(&RandomGeometricConfig {
+                    node_count,
+                    radius: radius as f32,
+                    output_positions: false,
+                    ..Default::default()
+                })
+            }
+            GraphSource::ErdosRenyi(node_count) => erdos_renyi(&ErdosRenyiConfig {
+                node_count,
+                edge_probability: (TARGET_AVERAGE_DEGREE / node_count as f64).min(1.0),
+                ..Default::default()
+            }),
+            GraphSource::Rmat(scale) => rmat(&RmatConfig {
+                scale,
+                ..Default::default()
+            }),
+            GraphSource::BarabasiAlbert(node_count) => barabasi_albert(&BarabasiAlbertConfig {
+                node_count,
+                edges_per_node: (TARGET_AVERAGE_DEGREE / 2.0) as u32,
+                ..Default::default()
+            }),
+            GraphSource::Delaunay(node_count) => delaunay(&DelaunayConfig {
+                node_count,
+                output_positions: false,
+                ..Default::default()
+            }),
+            GraphSource::Metis(ref path) => load_metis(path)?,
+            GraphSource::MatrixMarket(ref path) => load_matrix_market(path)?,
+        };
+
+        Ok(graph)
+    }
+}
+
+impl FromStr for GraphSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, argument) = s
+            .split_once(':')
+            .ok_or_else(|| format!("graph `{}` is not of the form `kind:argument`", s))?;
+
+        let parse_size = || {
+            argument
+                .parse::<u32>()
+                .map_err(|_| format!("graph `{}` does not specify a valid size", s))
+        };
+
+        match kind {
+            "grid2d" => parse_size().map(GraphSource::Grid2D),
+            "grid3d" => parse_size().map(GraphSource::Grid3D),
+            "geometric" => parse_size().map(GraphSource::RandomGeometric),
+            "erdos-renyi" => parse_size().map(GraphSource::ErdosRenyi),
+            "rmat" => parse_size().map(GraphSource::Rmat),
+            "barabasi-albert" => parse_size().map(GraphSource::BarabasiAlbert),
+            "delaunay" => parse_size().map(GraphSource::Delaunay),
+            "metis" => Ok(GraphSource::Metis(argument.to_string())),
+            "mtx" => Ok(GraphSource::MatrixMarket(argument.to_string())),
+            _ => Err(format!("unknown graph kind `{}`", kind)),
+        }
+    }
+}
+
+impl fmt::Display for GraphSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphSource::Grid2D(size) => write!(f, "grid2d:{}", size),
+            GraphSource::Grid3D(size) => write!(f, "grid3d:{}", size),
+            GraphSource::RandomGeometric(node_count) => write!(f, "geometric:{}", node_count),
+            GraphSource::ErdosRenyi(node_count) => write!(f, "erdos-renyi:{}", node_count),
+            GraphSource::Rmat(scale) => write!(f, "rmat:{}", scale),
+            GraphSource::BarabasiAlbert(node_count) => {
+                write!(f, "barabasi-albert:{}", node_count)
+            }
+            GraphSource::Delaunay(node_count) => write!(f, "delaunay:{}", node_count),
+            GraphSource::Metis(path) => write!(f, "metis:{}", path),
+            GraphSource::MatrixMarket(path) => write!(f, "mtx:{}", path),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::fs;
+
+use graco::generators::GeneratedGraph;
+
+/// Loads a graph in the METIS graph format (as used by METIS, KaHIP and the DIMACS 10 challenge).
+///
+/// Vertex sizes and vertex weights are ignored. If the file does not specify edge weights, all
+/// edges get a weight of `1`.
+pub fn load_metis(path: &str) -> Result<GeneratedGraph, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+
+    // Note that empty lines are significant: they represent nodes without any edges. Only comment
+    // lines are skipped.
+    let mut lines = contents.lines().filter(|line| !line.starts_with('%'));
+
+    let header = lines.next().ok_or("METIS file is missing its header")?;
+    let header: Vec<&str> = header.split_whitespace().collect();
+
+    let node_count: usize = header
+        .first()
+        .ok_or("METIS header is missing the node count")?
+        .parse()?;
+    let format = header.get(2).copied().unwrap_or("0");
+    let constraint_count: usize = header.get(3).map(|s| s.parse()).transpose()?.unwrap_or(1);
+
+    // The format is a 3 digit flag string, where leading zeros may be omitted.
+    let format = format!("{:0>3}", format);
+    let format = format.as_bytes();
+
+    let has_vertex_sizes = format[0] == b'1';
+    let has_vertex_weights = format[1] == b'1';
+    let has_edge_weights = format[2] == b'1';
+
+    let skip = has_vertex_sizes as usize + has_vertex_weights as usize * constraint_count;
+
+    let mut edges = Vec::new();
+
+    for node in 0..node_count {
+        let line = lines.next().unwrap_or("");
+        let mut values = line.split_whitespace().skip(skip);
+
+        while let Some(target) = values.next() {
+            let target: u32 = target.parse()?;
+
+            if target == 0 || target as usize > node_count {
+                return Err(format!(
+                    "METIS file references node `{}` for a graph with `{}` nodes",
+                    target, node_count
+                )
+                .into());
+            }
+
+            let weight: u32 = if has_edge_weights {
+                values
+                    .next()
+                    .ok_or("METIS file is missing an edge weight")?
+                    .parse()?
+            } else {
+                1
+            };
+
+            // METIS node indices are 1-based.
+            edges.push((node as u32, target - 1, weight));
+        }
+    }
+
+    Ok(GeneratedGraph::from_edges(node_count, edges))
+}
+
+/// Loads a graph from the adjacency matrix stored in a MatrixMarket coordinate format file.
+///
+/// The matrix is interpreted as the adjacency matrix of an undirected graph, regardless of its
+/// declared symmetry. Numeric values are used as edge weights after taking the absolute value and
+/// rounding to the nearest integer (but never smaller than `1`); for `pattern` matrices, all edges
+/// get a weight of `1`.
+pub fn load_matrix_market(path: &str) -> Result<GeneratedGraph, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+
+    let mut lines = contents.lines();
+
+    let banner = lines.next().ok_or("MatrixMarket file is empty")?;
+    let banner: Vec<String> = banner
+        .split_whitespace()
+        .map(|s| s.to_ascii_lowercase())
+        .collect();
+
+    if banner.len() < 4 || banner[0] != "%%matrixmarket" || banner[1] != "matrix" {
+        return Err("MatrixMarket file has an invalid banner".into());
+    }
+
+    if banner[2] != "coordinate" {
+        return Err("only MatrixMarket files in the coordinate format are supported".into());
+    }
+
+    let is_pattern = banner.get(3).map(|s| s.as_str()) == Some("pattern");
+
+    let mut lines = lines.filter(|line| !line.starts_with('%') && !line.trim().is_empty());
+
+    let size = lines
+        .next()
+        .ok_or("MatrixMarket file is missing its size line")?;
+    let size: Vec<usize> = size
+        .split_whitespace()
+        .map(|s| s.parse())
+        .collect::<Result<_, _>>()?;
+
+    if size.len() != 3 {
+        return Err("MatrixMarket file has an invalid size line".into());
+    }
+
+    let node_count = size[0].max(size[1]);
+    let entry_count = size[2];
+
+    let mut edges = Vec::with_capacity(entry_count);
+
+    for line in lines.take(entry_count) {
+        let mut values = line.split_whitespace();
+
+        let mut parse_index = || -> Result<u32, Box<dyn Error>> {
+            let index: u32 = values
+                .next()
+                .ok_or("MatrixMarket entry is missing an index")?
+                .parse()?;
+
+            if index == 0 || index as usize > node_count {
+                return Err(format!(
+                    "MatrixMarket entry references index `{}` for a matrix of size `{}`",
+                    index, node_count
+                )
+                .into());
+            }
+
+            // MatrixMarket indices are 1-based.
+            Ok(index - 1)
+        };
+
+        let row = parse_index()?;
+        let col = parse_index()?;
+
+        let weight = if is_pattern {
+            1
+        } else {
+            // For complex matrices we only consider the real part.
+            let value: f64 = values
+                .next()
+                .ok_or("MatrixMarket entry is missing a value")?
+                .parse()?;
+
+            (value.abs().round() as u32).max(1)
+        };
+
+        edges.push((row, col, weight));
+    }
+
+    Ok(GeneratedGraph::from_edges(node_count, edges))
+}
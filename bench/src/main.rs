@@ -0,0 +1,203 @@
+#![feature(future_join)]
+
+//! Native benchmark harness for matching and coarsening.
+//!
+//! Runs `MatchPairsByEdgeWeight` and `CoarsenGraph` level-by-level over a matrix of generated
+//! and/or file-loaded graphs, measures each stage with GPU timestamp queries, and writes the
+//! results as JSON lines (see [report::Record]).
+
+mod graph_source;
+mod load;
+mod report;
+mod run;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+use empa::adapter::Feature;
+use empa::device::{Device, DeviceDescriptor};
+use empa::native::{Instance, PowerPreference, RequestAdapterOptions};
+use graco::matching::MatchPairsByEdgeWeightConfig;
+
+use crate::graph_source::GraphSource;
+use crate::report::Record;
+use crate::run::{RunConfig, Runner};
+
+const USAGE: &str = "\
+Usage: graco-bench [OPTIONS]
+
+Options:
+    --graph <KIND:ARG>    Adds a graph to the benchmark matrix (may be repeated). If no graphs
+                          are specified, a default matrix of generated graphs is used.
+    --runs <N>            The number of times to run each graph [default: 5]
+    --levels <N>          The maximum number of levels to coarsen [default: 16]
+    --min-nodes <N>       Stop coarsening once a level has fewer nodes [default: 64]
+    --rounds <N>          The number of matching rounds per level [default: 8]
+    --profile             Include a per-stage breakdown of the matching and coarsening times
+    --output <PATH>       Write the results to a file, rather than to stdout
+    --help                Print this message
+
+Graph kinds:
+    grid2d:<SIZE>             A SIZE x SIZE grid with jittered node positions
+    grid3d:<SIZE>             A SIZE x SIZE x SIZE grid with jittered node positions
+    geometric:<NODES>         A random geometric graph
+    erdos-renyi:<NODES>       An Erdos-Renyi random graph
+    rmat:<SCALE>              An R-MAT graph with 2^SCALE nodes
+    barabasi-albert:<NODES>   A Barabasi-Albert preferential attachment graph
+    delaunay:<NODES>          The Delaunay triangulation of random points
+    metis:<PATH>              A graph file in the METIS format
+    mtx:<PATH>                A MatrixMarket coordinate format file
+
+The generated graphs (other than the grids) target an average degree of 8.
+";
+
+struct Args {
+    graphs: Vec<GraphSource>,
+    runs: usize,
+    run_config: RunConfig,
+    matching_config: MatchPairsByEdgeWeightConfig,
+    output: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut graphs = Vec::new();
+    let mut runs = 5;
+    let mut run_config = RunConfig::default();
+    let mut matching_config = MatchPairsByEdgeWeightConfig::default();
+    let mut output = None;
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for `{}`", arg))
+        };
+
+        match arg.as_str() {
+            "--graph" => graphs.push(value()?.parse()?),
+            "--runs" => {
+                runs = value()?
+                    .parse()
+                    .map_err(|_| "`--runs` must be a positive integer")?
+            }
+            "--levels" => {
+                run_config.max_levels = value()?
+                    .parse()
+                    .map_err(|_| "`--levels` must be a positive integer")?
+            }
+            "--min-nodes" => {
+                run_config.min_node_count = value()?
+                    .parse()
+                    .map_err(|_| "`--min-nodes` must be a positive integer")?
+            }
+            "--rounds" => {
+                matching_config.rounds = value()?
+                    .parse()
+                    .map_err(|_| "`--rounds` must be a positive integer")?
+            }
+            "--profile" => run_config.profile = true,
+            "--output" => output = Some(value()?),
+            "--help" => {
+                print!("{}", USAGE);
+
+                process::exit(0);
+            }
+            _ => return Err(format!("unknown argument `{}`", arg)),
+        }
+    }
+
+    if graphs.is_empty() {
+        graphs = GraphSource::default_matrix();
+    }
+
+    Ok(Args {
+        graphs,
+        runs,
+        run_config,
+        matching_config,
+        output,
+    })
+}
+
+async fn request_device() -> Result<Device, Box<dyn Error>> {
+    let instance = Instance::default();
+
+    let adapter = instance
+        .request_adapter(&RequestAdapterOptions {
+            power_preference: PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+        })
+        .await
+        .ok_or("adapter not found")?;
+    let device = adapter
+        .request_device(&DeviceDescriptor {
+            required_features: Feature::TimestampQuery,
+            required_limits: Default::default(),
+        })
+        .await?;
+
+    Ok(device)
+}
+
+async fn bench(args: Args) -> Result<(), Box<dyn Error>> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    let device = request_device().await?;
+    let mut runner = Runner::init(device, args.matching_config).await;
+
+    for source in &args.graphs {
+        let name = source.to_string();
+
+        eprintln!("Preparing `{}`...", name);
+
+        let graph = source.load()?;
+
+        eprintln!(
+            "Running `{}` ({} nodes, {} edge references)...",
+            name,
+            graph.node_count(),
+            graph.edge_ref_count()
+        );
+
+        for run in 0..args.runs {
+            let measurements = runner.run(&graph, &args.run_config).await?;
+
+            for measurement in measurements {
+                let record = Record {
+                    graph: &name,
+                    run,
+                    measurement,
+                };
+
+                writeln!(out, "{}", record.to_json())?;
+            }
+        }
+
+        out.flush()?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = futures::executor::block_on(bench(args)) {
+        eprintln!("error: {}", err);
+
+        process::exit(1);
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt::Write;
+
+use graco::ProfileStage;
+
+use crate::run::LevelMeasurement;
+
+/// A single line of benchmark output.
+///
+/// Records are written as JSON lines: one self-contained JSON object per line, so that results
+/// from different runs can simply be concatenated and processed with standard tooling.
+pub struct Record<'a> {
+    pub graph: &'a str,
+    pub run: usize,
+    pub measurement: LevelMeasurement,
+}
+
+impl Record<'_> {
+    pub fn to_json(&self) -> String {
+        let LevelMeasurement {
+            level,
+            node_count,
+            edge_ref_count,
+            coarse_node_count,
+            coarse_edge_ref_count,
+            match_ns,
+            coarsen_ns,
+            match_encode_ns,
+            coarsen_encode_ns,
+            ref match_stages,
+            ref coarsen_stages,
+        } = self.measurement;
+
+        let total_ns = match_ns + coarsen_ns;
+
+        // Every undirected edge is stored as 2 edge references.
+        let edge_count = edge_ref_count / 2;
+
+        let per_second = |count: u32| -> f64 {
+            if total_ns > 0 {
+                count as f64 / (total_ns as f64 * 1e-9)
+            } else {
+                0.0
+            }
+        };
+
+        let ratio = |coarse: u32, fine: u32| -> f64 {
+            if fine > 0 {
+                coarse as f64 / fine as f64
+            } else {
+                1.0
+            }
+        };
+
+        let mut json = String::new();
+
+        json.push('{');
+        write!(json, "\"graph\":{}", JsonString(self.graph)).unwrap();
+        write!(json, ",\"run\":{}", self.run).unwrap();
+        write!(json, ",\"level\":{}", level).unwrap();
+        write!(json, ",\"node_count\":{}", node_count).unwrap();
+        write!(json, ",\"edge_count\":{}", edge_count).unwrap();
+        write!(json, ",\"edge_ref_count\":{}", edge_ref_count).unwrap();
+        write!(json, ",\"coarse_node_count\":{}", coarse_node_count).unwrap();
+        write!(json, ",\"coarse_edge_ref_count\":{}", coarse_edge_ref_count).unwrap();
+        write!(json, ",\"match_ns\":{}", match_ns).unwrap();
+        write!(json, ",\"coarsen_ns\":{}", coarsen_ns).unwrap();
+        write!(json, ",\"total_ns\":{}", total_ns).unwrap();
+        write!(json, ",\"match_encode_ns\":{}", match_encode_ns).unwrap();
+        write!(json, ",\"coarsen_encode_ns\":{}", coarsen_encode_ns).unwrap();
+        write!(json, ",\"nodes_per_second\":{:.1}", per_second(node_count)).unwrap();
+        write!(json, ",\"edges_per_second\":{:.1}", per_second(edge_count)).unwrap();
+        write!(
+            json,
+            ",\"node_reduction_ratio\":{:.6}",
+            ratio(coarse_node_count, node_count)
+        )
+        .unwrap();
+        write!(
+            json,
+            ",\"edge_reduction_ratio\":{:.6}",
+            ratio(coarse_edge_ref_count, edge_ref_count)
+        )
+        .unwrap();
+
+        if !match_stages.is_empty() || !coarsen_stages.is_empty() {
+            write!(json, ",\"match_stages\":{}", JsonStages(match_stages)).unwrap();
+            write!(json, ",\"coarsen_stages\":{}", JsonStages(coarsen_stages)).unwrap();
+        }
+
+        json.push('}');
+
+        json
+    }
+}
+
+// Formats the stages as an object that maps each stage label to its duration in nanoseconds.
+struct JsonStages<'a>(&'a [ProfileStage]);
+
+impl std::fmt::Display for JsonStages<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_char('{')?;
+
+        for (i, stage) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_char(',')?;
+            }
+
+            write!(f, "{}:{}", JsonString(stage.label), stage.duration_ns)?;
+        }
+
+        f.write_char('}')
+    }
+}
+
+struct JsonString<'a>(&'a str);
+
+impl std::fmt::Display for JsonString<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_char('"')?;
+
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+
+        f.write_char('"')
+    }
+}
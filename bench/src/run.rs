@@ -0,0 +1,418 @@
+use std::error::Error;
+use std::future::join;
+use std::mem;
+use std::time::Instant;
+
+use empa::buffer;
+use empa::buffer::Buffer;
+use empa::device::Device;
+use empa::type_flag::{O, X};
+use graco::generators::GeneratedGraph;
+use graco::matching::{
+    MatchPairsByEdgeWeight, MatchPairsByEdgeWeightConfig, MatchPairsByEdgeWeightInput,
+    MatchPairsByEdgeWeightsCounts,
+};
+use graco::{
+    CoarsenCounts, CoarsenGraph, CoarsenGraphInput, CoarsenGraphOutput, ProfileStage, Profiler,
+};
+
+// The profilers are cleared before each level, so they only need to hold the timestamps of a
+// single `encode_profiled` call: 1 start timestamp plus 1 timestamp per stage.
+//
+// The matching has up to 4 stages outside of its rounds and 3 stages per round.
+const MATCH_PROFILER_FIXED_TIMESTAMPS: usize = 5;
+const MATCH_PROFILER_TIMESTAMPS_PER_ROUND: usize = 3;
+
+// The coarsening has at most 19 stages when no edge channels are used, independent of the graph.
+const COARSEN_PROFILER_CAPACITY: usize = 20;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RunConfig {
+    /// The maximum number of levels to coarsen.
+    pub max_levels: usize,
+    /// Coarsening stops once a level has fewer nodes than this.
+    pub min_node_count: u32,
+    /// Whether to record a per-stage breakdown for both pipelines.
+    pub profile: bool,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            max_levels: 16,
+            min_node_count: 64,
+            profile: false,
+        }
+    }
+}
+
+/// The measurements for matching and coarsening a single level of the hierarchy.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LevelMeasurement {
+    pub level: usize,
+    pub node_count: u32,
+    pub edge_ref_count: u32,
+    pub coarse_node_count: u32,
+    pub coarse_edge_ref_count: u32,
+    pub match_ns: u64,
+    pub coarsen_ns: u64,
+    /// The CPU time spent encoding the matching commands.
+    pub match_encode_ns: u64,
+    /// The CPU time spent encoding the coarsening commands.
+    pub coarsen_encode_ns: u64,
+    /// The per-stage breakdown of the matching time, if profiling was enabled.
+    pub match_stages: Vec<ProfileStage>,
+    /// The per-stage breakdown of the coarsening time, if profiling was enabled.
+    pub coarsen_stages: Vec<ProfileStage>,
+}
+
+struct GraphLevel {
+    nodes_edge_offset: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, X, O, O>>,
+    nodes_edges: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, X, O, O>>,
+    nodes_edge_weights: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    node_count: Buffer<u32, buffer::Usages<O, O, X, X, O, O, O, X, O, O>>,
+    edge_ref_count: Buffer<u32, buffer::Usages<O, O, X, X, O, O, O, X, O, O>>,
+}
+
+impl GraphLevel {
+    fn from_graph(device: &Device, graph: &GeneratedGraph) -> Self {
+        let nodes_edge_offset = device.create_buffer(
+            graph.nodes_edge_offset.as_slice(),
+            buffer::Usages::storage_binding().and_copy_src(),
+        );
+        let nodes_edges = device.create_buffer(
+            graph.nodes_edges.as_slice(),
+            buffer::Usages::storage_binding().and_copy_src(),
+        );
+        let nodes_edge_weights = device.create_buffer(
+            graph.nodes_edge_weights.as_slice(),
+            buffer::Usages::storage_binding()
+                .and_copy_dst()
+                .and_copy_src(),
+        );
+        let node_count = device.create_buffer(
+            graph.node_count() as u32,
+            buffer::Usages::uniform_binding()
+                .and_storage_binding()
+                .and_copy_src(),
+        );
+        let edge_ref_count = device.create_buffer(
+            graph.edge_ref_count() as u32,
+            buffer::Usages::uniform_binding()
+                .and_storage_binding()
+                .and_copy_src(),
+        );
+
+        GraphLevel {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            node_count,
+            edge_ref_count,
+        }
+    }
+
+    fn with_capacity(device: &Device, node_count: usize, edge_ref_count: usize) -> Self {
+        let nodes_edge_offset = device.create_slice_buffer_zeroed(
+            node_count,
+            buffer::Usages::storage_binding().and_copy_src(),
+        );
+        let nodes_edges = device.create_slice_buffer_zeroed(
+            edge_ref_count,
+            buffer::Usages::storage_binding().and_copy_src(),
+        );
+        let nodes_edge_weights = device.create_slice_buffer_zeroed(
+            edge_ref_count,
+            buffer::Usages::storage_binding()
+                .and_copy_dst()
+                .and_copy_src(),
+        );
+        let node_count = device.create_buffer(
+            0,
+            buffer::Usages::uniform_binding()
+                .and_storage_binding()
+                .and_copy_src(),
+        );
+        let edge_ref_count = device.create_buffer(
+            0,
+            buffer::Usages::uniform_binding()
+                .and_storage_binding()
+                .and_copy_src(),
+        );
+
+        GraphLevel {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            node_count,
+            edge_ref_count,
+        }
+    }
+}
+
+pub struct Runner {
+    device: Device,
+    matcher: MatchPairsByEdgeWeight,
+    coarsen_graph: CoarsenGraph,
+    match_profiler: Profiler,
+    coarsen_profiler: Profiler,
+}
+
+impl Runner {
+    pub async fn init(device: Device, matching_config: MatchPairsByEdgeWeightConfig) -> Self {
+        let (matcher, coarsen_graph) = join!(
+            MatchPairsByEdgeWeight::init(device.clone(), matching_config),
+            CoarsenGraph::init(device.clone()),
+        )
+        .await;
+
+        let match_profiler_capacity = MATCH_PROFILER_FIXED_TIMESTAMPS
+            + MATCH_PROFILER_TIMESTAMPS_PER_ROUND * matching_config.rounds;
+
+        let match_profiler = Profiler::new(&device, match_profiler_capacity);
+        let coarsen_profiler = Profiler::new(&device, COARSEN_PROFILER_CAPACITY);
+
+        Runner {
+            device,
+            matcher,
+            coarsen_graph,
+            match_profiler,
+            coarsen_profiler,
+        }
+    }
+
+    /// Repeatedly matches and coarsens the `graph` until one of the stopping criteria in the
+    /// `config` is met, and returns the measurements for each level.
+    ///
+    /// Each level is submitted separately, so that the counts for the coarse level can be read
+    /// back to decide whether to continue. Only the GPU time between the timestamps is measured,
+    /// the readback does not affect the measurements.
+    pub async fn run(
+        &mut self,
+        graph: &GeneratedGraph,
+        config: &RunConfig,
+    ) -> Result<Vec<LevelMeasurement>, Box<dyn Error>> {
+        let device = &self.device;
+
+        let node_count = graph.node_count();
+        let edge_ref_count = graph.edge_ref_count();
+
+        // Coarse levels are never larger than the base level, so we can ping-pong between 2 sets
+        // of buffers sized for the base level.
+        let mut parent_level = GraphLevel::from_graph(device, graph);
+        let mut child_level = GraphLevel::with_capacity(device, node_count, edge_ref_count);
+
+        let nodes_matching = device.create_slice_buffer_zeroed(
+            node_count,
+            buffer::Usages::storage_binding()
+                .and_copy_src()
+                .and_copy_dst(),
+        );
+
+        let fine_nodes_mapping = device.create_slice_buffer_zeroed(
+            node_count,
+            buffer::Usages::storage_binding().and_copy_src(),
+        );
+        let coarse_nodes_mapping_offset = device.create_slice_buffer_zeroed(
+            node_count,
+            buffer::Usages::storage_binding().and_copy_src(),
+        );
+        let coarse_nodes_mapping = device.create_slice_buffer_zeroed(
+            node_count,
+            buffer::Usages::storage_binding().and_copy_src(),
+        );
+
+        let temporary_storage_len =
+            CoarsenGraph::required_temporary_storage(node_count, edge_ref_count);
+
+        let temporary_storage_0 = device.create_slice_buffer_zeroed(
+            temporary_storage_len,
+            buffer::Usages::storage_binding()
+                .and_copy_dst()
+                .and_copy_src(),
+        );
+        let temporary_storage_1 = device.create_slice_buffer_zeroed(
+            temporary_storage_len,
+            buffer::Usages::storage_binding()
+                .and_copy_dst()
+                .and_copy_src(),
+        );
+
+        let timestamp_query_set = device.create_timestamp_query_set(3);
+        let timestamps =
+            device.create_slice_buffer_zeroed(3, buffer::Usages::query_resolve().and_copy_src());
+        let timestamps_readback =
+            device.create_slice_buffer_zeroed(3, buffer::Usages::copy_dst().and_map_read());
+
+        let node_count_readback =
+            device.create_buffer(0u32, buffer::Usages::copy_dst().and_map_read());
+        let edge_ref_count_readback =
+            device.create_buffer(0u32, buffer::Usages::copy_dst().and_map_read());
+
+        let mut node_count = node_count as u32;
+        let mut edge_ref_count = edge_ref_count as u32;
+
+        let mut measurements = Vec::new();
+
+        for level in 0..config.max_levels {
+            let mut encoder = device.create_command_encoder();
+
+            encoder = encoder.clear_buffer_slice(nodes_matching.view());
+
+            encoder = encoder.write_timestamp(&timestamp_query_set, 0);
+
+            let match_input = MatchPairsByEdgeWeightInput {
+                nodes_edge_offset: parent_level.nodes_edge_offset.view(),
+                nodes_edges: parent_level.nodes_edges.view(),
+                nodes_edge_weights: parent_level.nodes_edge_weights.view(),
+                count: Some(MatchPairsByEdgeWeightsCounts {
+                    node_count: parent_level.node_count.uniform(),
+                    edge_ref_count: parent_level.edge_ref_count.uniform(),
+                }),
+            };
+
+            let match_encode_start = Instant::now();
+
+            if config.profile {
+                self.match_profiler.clear();
+
+                encoder = self.matcher.encode_profiled(
+                    encoder,
+                    match_input,
+                    nodes_matching.view(),
+                    &mut self.match_profiler,
+                );
+            } else {
+                encoder = self
+                    .matcher
+                    .encode(encoder, match_input, nodes_matching.view());
+            }
+
+            let match_encode_ns = match_encode_start.elapsed().as_nanos() as u64;
+
+            encoder = encoder.write_timestamp(&timestamp_query_set, 1);
+
+            let coarsen_input = CoarsenGraphInput {
+                fine_nodes_edge_offset: parent_level.nodes_edge_offset.view(),
+                fine_nodes_edges: parent_level.nodes_edges.view(),
+                fine_nodes_edge_weights: parent_level.nodes_edge_weights.view(),
+                fine_nodes_matching: nodes_matching.view(),
+                temporary_storage_0: temporary_storage_0.view(),
+                temporary_storage_1: temporary_storage_1.view(),
+                edge_channels: &[],
+                counts: Some(CoarsenCounts {
+                    node_count: parent_level.node_count.uniform(),
+                    edge_ref_count: parent_level.edge_ref_count.uniform(),
+                }),
+            };
+            let coarsen_output = CoarsenGraphOutput {
+                fine_nodes_mapping: fine_nodes_mapping.view(),
+                coarse_nodes_mapping_offset: coarse_nodes_mapping_offset.view(),
+                coarse_nodes_mapping: coarse_nodes_mapping.view(),
+                coarse_node_count: child_level.node_count.view(),
+                coarse_edge_ref_count: child_level.edge_ref_count.view(),
+                coarse_nodes_edge_offset: child_level.nodes_edge_offset.view(),
+                coarse_nodes_edges: child_level.nodes_edges.view(),
+                coarse_nodes_edge_weights: child_level.nodes_edge_weights.view(),
+                fine_edges_coarse_edge: None,
+            };
+
+            let coarsen_encode_start = Instant::now();
+
+            if config.profile {
+                self.coarsen_profiler.clear();
+
+                encoder = self.coarsen_graph.encode_profiled(
+                    encoder,
+                    coarsen_input,
+                    coarsen_output,
+                    &mut self.coarsen_profiler,
+                );
+            } else {
+                encoder = self
+                    .coarsen_graph
+                    .encode(encoder, coarsen_input, coarsen_output);
+            }
+
+            let coarsen_encode_ns = coarsen_encode_start.elapsed().as_nanos() as u64;
+
+            encoder = encoder.write_timestamp(&timestamp_query_set, 2);
+
+            encoder =
+                encoder.resolve_timestamp_query_set(&timestamp_query_set, 0, timestamps.view());
+            encoder =
+                encoder.copy_buffer_to_buffer_slice(timestamps.view(), timestamps_readback.view());
+            encoder = encoder
+                .copy_buffer_to_buffer(child_level.node_count.view(), node_count_readback.view());
+            encoder = encoder.copy_buffer_to_buffer(
+                child_level.edge_ref_count.view(),
+                edge_ref_count_readback.view(),
+            );
+
+            if config.profile {
+                encoder = self.match_profiler.resolve(encoder);
+                encoder = self.coarsen_profiler.resolve(encoder);
+            }
+
+            device.queue().submit(encoder.finish());
+
+            timestamps_readback.map_read().await?;
+            node_count_readback.map_read().await?;
+            edge_ref_count_readback.map_read().await?;
+
+            let (match_ns, coarsen_ns) = {
+                let timestamps = timestamps_readback.mapped();
+
+                (
+                    timestamps[1].saturating_sub(timestamps[0]),
+                    timestamps[2].saturating_sub(timestamps[1]),
+                )
+            };
+            let coarse_node_count = *node_count_readback.mapped();
+            let coarse_edge_ref_count = *edge_ref_count_readback.mapped();
+
+            timestamps_readback.unmap();
+            node_count_readback.unmap();
+            edge_ref_count_readback.unmap();
+
+            let (match_stages, coarsen_stages) = if config.profile {
+                let match_profile = self.match_profiler.read().await?;
+                let coarsen_profile = self.coarsen_profiler.read().await?;
+
+                (
+                    match_profile.totals_by_label(),
+                    coarsen_profile.totals_by_label(),
+                )
+            } else {
+                (Vec::new(), Vec::new())
+            };
+
+            measurements.push(LevelMeasurement {
+                level,
+                node_count,
+                edge_ref_count,
+                coarse_node_count,
+                coarse_edge_ref_count,
+                match_ns,
+                coarsen_ns,
+                match_encode_ns,
+                coarsen_encode_ns,
+                match_stages,
+                coarsen_stages,
+            });
+
+            // Stop if the matching could not find any more pairs to collapse, or if the coarse
+            // graph has become small enough.
+            if coarse_node_count == node_count || coarse_node_count < config.min_node_count {
+                break;
+            }
+
+            mem::swap(&mut parent_level, &mut child_level);
+
+            node_count = coarse_node_count;
+            edge_ref_count = coarse_edge_ref_count;
+        }
+
+        Ok(measurements)
+    }
+}
@@ -320,6 +320,7 @@ async fn render() -> Result<(), Box<dyn Error>> {
                 fine_nodes_matching: nodes_matching.view(),
                 temporary_storage_0: temporary_storage_0.view(),
                 temporary_storage_1: temporary_storage_1.view(),
+                edge_channels: &[],
                 counts: Some(CoarsenCounts {
                     node_count: parent_level.node_count.uniform(),
                     edge_ref_count: parent_level.edge_ref_count.uniform(),
@@ -334,6 +335,7 @@ async fn render() -> Result<(), Box<dyn Error>> {
                 coarse_nodes_edge_offset: child_level.nodes_edge_offset.view(),
                 coarse_nodes_edges: child_level.nodes_edges.view(),
                 coarse_nodes_edge_weights: child_level.nodes_edge_weights.view(),
+                fine_edges_coarse_edge: None,
             },
         );
 
@@ -16,6 +16,7 @@ use empa::device::DeviceDescriptor;
 use empa::texture::format::rgba8unorm;
 use empa::{abi, buffer, texture};
 use futures::{FutureExt, StreamExt};
+use graco::generators::{grid_2d, Grid2DConfig};
 use graco::matching::{
     MatchPairsByEdgeWeight, MatchPairsByEdgeWeightConfig, MatchPairsByEdgeWeightInput,
     MatchPairsByEdgeWeightsCounts,
@@ -35,88 +36,25 @@ fn main() {
 }
 
 fn generate_regular_graph_state(grid_size: u32, perturbation_factor: f32) -> GraphState {
-    let mut nodes_edge_offset = Vec::new();
-    let mut nodes_position = Vec::new();
-    let mut nodes_edges = Vec::new();
-    let mut nodes_edge_weights = Vec::new();
-
-    let mut rng = oorandom::Rand32::new(1);
-
-    let spacing = 1.0 / (grid_size as f32 + 1.0);
-    let max_perturbation = perturbation_factor * spacing;
-
-    let compute_node_index = |col, row| -> u32 { row * grid_size + col };
-
-    let mut compute_node_position = |col, row| -> abi::Vec2<f32> {
-        let perturbation_x = rng.rand_float() * max_perturbation * 2.0 - max_perturbation;
-        let perturbation_y = rng.rand_float() * max_perturbation * 2.0 - max_perturbation;
-
-        abi::Vec2(
-            spacing + col as f32 * spacing + perturbation_x,
-            spacing + row as f32 * spacing + perturbation_y,
-        )
-    };
-
-    fn compute_edge_weight(pos_a: abi::Vec2<f32>, pos_b: abi::Vec2<f32>) -> u32 {
-        let d_x = pos_b.0 - pos_a.0;
-        let d_y = pos_b.1 - pos_a.1;
-
-        let distance = (d_x * d_x + d_y * d_y).sqrt();
-        let distance_inv = 1.0 / distance;
-
-        (distance_inv * 1000.0) as u32
-    }
-
-    for row in 0..grid_size {
-        for col in 0..grid_size {
-            nodes_position.push(compute_node_position(col, row));
-        }
-    }
-
-    let mut current_edge_offset = 0;
-
-    for row in 0..grid_size {
-        for col in 0..grid_size {
-            let current_index = compute_node_index(col, row);
-            let current_pos = nodes_position[current_index as usize];
-
-            nodes_edge_offset.push(current_edge_offset);
-
-            let mut add_edge = |col, row| {
-                let other_index = compute_node_index(col, row);
-                let other_pos = nodes_position[other_index as usize];
-
-                let weight = compute_edge_weight(current_pos, other_pos);
-
-                nodes_edges.push(other_index);
-                nodes_edge_weights.push(weight);
-
-                current_edge_offset += 1;
-            };
-
-            if col > 0 {
-                add_edge(col - 1, row);
-            }
-
-            if col < grid_size - 1 {
-                add_edge(col + 1, row);
-            }
-
-            if row > 0 {
-                add_edge(col, row - 1);
-            }
+    let graph = grid_2d(&Grid2DConfig {
+        width: grid_size,
+        height: grid_size,
+        jitter: perturbation_factor,
+        ..Default::default()
+    });
 
-            if row < grid_size - 1 {
-                add_edge(col, row + 1);
-            }
-        }
-    }
+    let nodes_position = graph
+        .nodes_position
+        .as_ref()
+        .and_then(|positions| positions.as_2d())
+        .expect("2D grid graph should have 2D positions")
+        .to_vec();
 
     GraphState {
-        nodes_edge_offset,
+        nodes_edge_offset: graph.nodes_edge_offset,
         nodes_position,
-        nodes_edges,
-        nodes_edge_weights,
+        nodes_edges: graph.nodes_edges,
+        nodes_edge_weights: graph.nodes_edge_weights,
     }
 }
 
@@ -188,6 +126,7 @@ async fn render() -> Result<(), Box<dyn Error>> {
             MatchPairsByEdgeWeightConfig {
                 rounds,
                 prng_seed: 1,
+                ..Default::default()
             },
         )
         .await;
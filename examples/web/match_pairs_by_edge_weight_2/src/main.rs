@@ -152,6 +152,7 @@ async fn render() -> Result<(), Box<dyn Error>> {
             MatchPairsByEdgeWeightConfig {
                 rounds,
                 prng_seed: 1,
+                ..Default::default()
             },
         )
         .await;
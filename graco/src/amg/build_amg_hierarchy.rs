@@ -0,0 +1,488 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::Buffer;
+use empa::device::Device;
+use empa::type_flag::{O, X};
+use empa_tk::prefix_sum::{PrefixSum, PrefixSumInput};
+
+use crate::amg::compact_strong_connections::{
+    CompactStrongConnections, CompactStrongConnectionsResources,
+};
+use crate::amg::galerkin_product::{GalerkinProduct, GalerkinProductResources};
+use crate::amg::mark_strong_connections::{MarkStrongConnections, MarkStrongConnectionsResources};
+use crate::amg::split_diagonal::{SplitDiagonal, SplitDiagonalResources};
+use crate::context::Context;
+use crate::hierarchy::{create_count_buffer, read_counts, MIN_COARSENING_RATIO};
+use crate::matching::{
+    MatchPairsByEdgeWeight, MatchPairsByEdgeWeightConfig, MatchPairsByEdgeWeightInput,
+    MatchPairsByEdgeWeightsCounts,
+};
+use crate::{CoarsenCounts, CoarsenGraph, CoarsenGraphInput, CoarsenGraphOutput};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AmgConfig {
+    /// An off-diagonal entry `a_ij` is a strong connection if
+    /// `|a_ij| >= strength_threshold * sqrt(|a_ii * a_jj|)`; only strong connections are
+    /// considered for aggregation.
+    pub strength_threshold: f32,
+    /// Coarsening stops once a level has no more than this many rows.
+    pub coarsest_row_count: u32,
+    /// The maximum number of levels, including the base level.
+    pub max_levels: usize,
+    /// The configuration of the matching that is used to aggregate the rows.
+    pub matching: MatchPairsByEdgeWeightConfig,
+}
+
+impl Default for AmgConfig {
+    fn default() -> Self {
+        AmgConfig {
+            strength_threshold: 0.25,
+            coarsest_row_count: 64,
+            max_levels: 32,
+            matching: Default::default(),
+        }
+    }
+}
+
+/// A square sparse matrix in compressed sparse row (CSR) form.
+///
+/// The buffers must be exactly sized to the matrix's row count and entry count. The matrix must be
+/// symmetric; diagonal entries may appear anywhere in their row.
+pub struct AmgMatrix<'a, U0, U1, U2> {
+    pub row_offsets: buffer::View<'a, [u32], U0>,
+    pub column_indices: buffer::View<'a, [u32], U1>,
+    pub values: buffer::View<'a, [f32], U2>,
+}
+
+/// A level in an [AmgHierarchy].
+///
+/// The matrix is stored with its diagonal split off: `values` holds the off-diagonal entries (on
+/// the base level, the original diagonal entries remain in the pattern with a value of `0`) and
+/// `diagonal` holds the diagonal. As in [HierarchyLevel](crate::hierarchy::HierarchyLevel), the
+/// buffers of a coarse level are sized for the next finer level.
+pub(crate) struct AmgLevel {
+    pub(crate) row_count: u32,
+    pub(crate) entry_count: u32,
+    pub(crate) row_count_buffer: Buffer<u32, buffer::Usages<O, O, X, X, O, O, X, X, O, O>>,
+    pub(crate) entry_count_buffer: Buffer<u32, buffer::Usages<O, O, X, X, O, O, X, X, O, O>>,
+    pub(crate) row_offsets: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    pub(crate) column_indices: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    pub(crate) values: Buffer<[f32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    pub(crate) diagonal: Buffer<[f32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    /// Maps the rows of the next finer level to the rows of this level (the piecewise constant
+    /// prolongator); unused for the base level.
+    pub(crate) fine_nodes_mapping: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    /// Together with the `coarse_nodes_mapping`, maps the rows of this level to the rows of the
+    /// next finer level that were aggregated into it; unused for the base level.
+    pub(crate) coarse_nodes_mapping_offset:
+        Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    pub(crate) coarse_nodes_mapping: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    pub(crate) x: Buffer<[f32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    pub(crate) x_next: Buffer<[f32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    pub(crate) b: Buffer<[f32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    pub(crate) residual: Buffer<[f32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+}
+
+impl AmgLevel {
+    fn new(device: &Device, row_capacity: usize, entry_capacity: usize) -> Self {
+        let slice_usages = buffer::Usages::storage_binding()
+            .and_copy_dst()
+            .and_copy_src();
+
+        AmgLevel {
+            row_count: row_capacity as u32,
+            entry_count: entry_capacity as u32,
+            row_count_buffer: create_count_buffer(device, row_capacity as u32),
+            entry_count_buffer: create_count_buffer(device, entry_capacity as u32),
+            row_offsets: device.create_slice_buffer_zeroed(row_capacity, slice_usages),
+            column_indices: device.create_slice_buffer_zeroed(entry_capacity, slice_usages),
+            values: device.create_slice_buffer_zeroed(entry_capacity, slice_usages),
+            diagonal: device.create_slice_buffer_zeroed(row_capacity, slice_usages),
+            fine_nodes_mapping: device
+                .create_slice_buffer_zeroed(row_capacity, buffer::Usages::storage_binding()),
+            coarse_nodes_mapping_offset: device
+                .create_slice_buffer_zeroed(row_capacity, buffer::Usages::storage_binding()),
+            coarse_nodes_mapping: device
+                .create_slice_buffer_zeroed(row_capacity, buffer::Usages::storage_binding()),
+            x: device.create_slice_buffer_zeroed(row_capacity, slice_usages),
+            x_next: device.create_slice_buffer_zeroed(row_capacity, slice_usages),
+            b: device.create_slice_buffer_zeroed(row_capacity, slice_usages),
+            residual: device.create_slice_buffer_zeroed(row_capacity, slice_usages),
+        }
+    }
+}
+
+/// The levels of an aggregation-based algebraic multigrid (AMG) hierarchy, as built by
+/// [BuildAmgHierarchy]; apply it with a [VCycle](crate::amg::VCycle).
+pub struct AmgHierarchy {
+    pub(crate) levels: Vec<AmgLevel>,
+}
+
+impl AmgHierarchy {
+    /// The number of levels in the hierarchy, including the base level.
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The number of rows of the operator on the given `level`.
+    pub fn row_count(&self, level: usize) -> u32 {
+        self.levels[level].row_count
+    }
+
+    /// The number of off-diagonal entries of the operator on the given `level`.
+    ///
+    /// For the base level, this is the entry count of the input matrix, including its diagonal
+    /// entries.
+    pub fn entry_count(&self, level: usize) -> u32 {
+        self.levels[level].entry_count
+    }
+}
+
+/// Builds an aggregation-based algebraic multigrid (AMG) hierarchy for a sparse matrix; this is
+/// the setup phase of an AMG solver.
+///
+/// For each level, this builds a strength-of-connection graph from the level's matrix, aggregates
+/// pairs of strongly connected rows with [MatchPairsByEdgeWeight], and coarsens the matrix's
+/// sparsity pattern with [CoarsenGraph] according to these aggregates. The aggregates define a
+/// piecewise constant prolongator `P`; the coarse operator `P^T A P` (the Galerkin product) sums
+/// the entries that [CoarsenGraph] merged into each coarse entry, and retains the entries within
+/// an aggregate in the coarse diagonal.
+///
+/// Submits its own command buffers, as the number of levels in the hierarchy is only known after
+/// reading back the row count of each coarse level.
+pub struct BuildAmgHierarchy {
+    device: Device,
+    config: AmgConfig,
+    matcher: MatchPairsByEdgeWeight,
+    coarsen_graph: CoarsenGraph,
+    split_diagonal: Rc<SplitDiagonal>,
+    mark_strong_connections: Rc<MarkStrongConnections>,
+    compact_strong_connections: Rc<CompactStrongConnections>,
+    galerkin_product: Rc<GalerkinProduct>,
+    prefix_sum_inclusive: Rc<RefCell<PrefixSum<u32>>>,
+    strength_threshold: Buffer<f32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+}
+
+impl BuildAmgHierarchy {
+    pub async fn init(device: Device, config: AmgConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](BuildAmgHierarchy::init), but obtains the sub-pipelines from the given
+    /// `context`, so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context, config: AmgConfig) -> Self {
+        let device = context.device().clone();
+
+        let (
+            matcher,
+            coarsen_graph,
+            split_diagonal,
+            mark_strong_connections,
+            compact_strong_connections,
+            galerkin_product,
+            prefix_sum_inclusive,
+        ) = join!(
+            MatchPairsByEdgeWeight::init_with_context(context, config.matching),
+            CoarsenGraph::init_with_context(context),
+            context.get_or_init_kernel(SplitDiagonal::init),
+            context.get_or_init_kernel(MarkStrongConnections::init),
+            context.get_or_init_kernel(CompactStrongConnections::init),
+            context.get_or_init_kernel(GalerkinProduct::init),
+            context.get_or_init_mut("init_inclusive_u32", PrefixSum::init_inclusive_u32),
+        )
+        .await;
+
+        let strength_threshold =
+            device.create_buffer(config.strength_threshold, buffer::Usages::uniform_binding());
+
+        BuildAmgHierarchy {
+            device,
+            config,
+            matcher,
+            coarsen_graph,
+            split_diagonal,
+            mark_strong_connections,
+            compact_strong_connections,
+            galerkin_product,
+            prefix_sum_inclusive,
+            strength_threshold,
+        }
+    }
+
+    /// Builds the hierarchy for the given `matrix`.
+    pub async fn build<U0, U1, U2>(
+        &mut self,
+        matrix: AmgMatrix<'_, U0, U1, U2>,
+    ) -> Result<AmgHierarchy, Box<dyn Error>>
+    where
+        U0: buffer::CopySrc,
+        U1: buffer::CopySrc,
+        U2: buffer::CopySrc,
+    {
+        let AmgMatrix {
+            row_offsets,
+            column_indices,
+            values,
+        } = matrix;
+
+        let row_count = row_offsets.len();
+        let entry_count = column_indices.len();
+
+        assert_eq!(
+            values.len(),
+            entry_count,
+            "`values` must have the same length as `column_indices`"
+        );
+
+        // Copy the matrix into the base level and split off its diagonal, so that all levels can
+        // be treated uniformly.
+        let base_level = AmgLevel::new(&self.device, row_count, entry_count);
+
+        let mut encoder = self.device.create_command_encoder();
+
+        encoder = encoder.copy_buffer_to_buffer_slice(row_offsets, base_level.row_offsets.view());
+        encoder =
+            encoder.copy_buffer_to_buffer_slice(column_indices, base_level.column_indices.view());
+        encoder = encoder.copy_buffer_to_buffer_slice(values, base_level.values.view());
+        encoder = self.split_diagonal.encode(
+            encoder,
+            SplitDiagonalResources {
+                row_count: base_level.row_count_buffer.uniform(),
+                entry_count: base_level.entry_count_buffer.uniform(),
+                row_offsets: base_level.row_offsets.storage(),
+                column_indices: base_level.column_indices.storage(),
+                values: base_level.values.storage(),
+                diagonal: base_level.diagonal.storage(),
+            },
+            row_count as u32,
+        );
+
+        self.device.queue().submit(encoder.finish());
+
+        let mut levels = vec![base_level];
+
+        self.coarsen(&mut levels).await?;
+
+        Ok(AmgHierarchy { levels })
+    }
+
+    async fn coarsen(&mut self, levels: &mut Vec<AmgLevel>) -> Result<(), Box<dyn Error>> {
+        let base_row_count = levels[0].row_count as usize;
+        let base_entry_count = levels[0].entry_count as usize;
+
+        // Scratch buffers that are only needed while coarsening a level; coarse levels are never
+        // larger than the base level.
+        let workspace = Workspace::new(&self.device, base_row_count, base_entry_count);
+
+        while levels.len() < self.config.max_levels {
+            let fine_level = levels.last().unwrap();
+
+            if fine_level.row_count <= self.config.coarsest_row_count {
+                break;
+            }
+
+            let fine_row_count = fine_level.row_count;
+            let mut coarse_level = AmgLevel::new(
+                &self.device,
+                fine_row_count as usize,
+                fine_level.entry_count as usize,
+            );
+
+            let mut encoder = self.device.create_command_encoder();
+
+            // Build the strength-of-connection graph: mark the strong entries, compute their
+            // positions in the compacted edge list with a prefix sum, and then compact them.
+            encoder = self.mark_strong_connections.encode(
+                encoder,
+                MarkStrongConnectionsResources {
+                    row_count: fine_level.row_count_buffer.uniform(),
+                    entry_count: fine_level.entry_count_buffer.uniform(),
+                    strength_threshold: self.strength_threshold.uniform(),
+                    row_offsets: fine_level.row_offsets.storage(),
+                    column_indices: fine_level.column_indices.storage(),
+                    values: fine_level.values.storage(),
+                    diagonal: fine_level.diagonal.storage(),
+                    entries_strong: workspace.entries_strong.storage(),
+                    entries_strength: workspace.entries_strength.storage(),
+                },
+                fine_row_count,
+            );
+            encoder = self.prefix_sum_inclusive.borrow_mut().encode(
+                encoder,
+                PrefixSumInput {
+                    data: workspace.entries_strong.view(),
+                    count: Some(fine_level.entry_count_buffer.uniform()),
+                },
+            );
+            encoder = self.compact_strong_connections.encode(
+                encoder,
+                CompactStrongConnectionsResources {
+                    row_count: fine_level.row_count_buffer.uniform(),
+                    entry_count: fine_level.entry_count_buffer.uniform(),
+                    row_offsets: fine_level.row_offsets.storage(),
+                    column_indices: fine_level.column_indices.storage(),
+                    entries_strong_prefix_sum: workspace.entries_strong.storage(),
+                    entries_strength: workspace.entries_strength.storage(),
+                    nodes_edge_offset: workspace.strength_nodes_edge_offset.storage(),
+                    nodes_edges: workspace.strength_nodes_edges.storage(),
+                    nodes_edge_weights: workspace.strength_nodes_edge_weights.storage(),
+                    edge_ref_count: workspace.strength_edge_ref_count.storage(),
+                },
+                fine_row_count,
+            );
+
+            // Aggregate pairs of strongly connected rows.
+            encoder = encoder.clear_buffer_slice(workspace.nodes_matching.view());
+            encoder = self.matcher.encode(
+                encoder,
+                MatchPairsByEdgeWeightInput {
+                    nodes_edge_offset: workspace.strength_nodes_edge_offset.view(),
+                    nodes_edges: workspace.strength_nodes_edges.view(),
+                    nodes_edge_weights: workspace.strength_nodes_edge_weights.view(),
+                    count: Some(MatchPairsByEdgeWeightsCounts {
+                        node_count: fine_level.row_count_buffer.uniform(),
+                        edge_ref_count: workspace.strength_edge_ref_count.uniform(),
+                    }),
+                },
+                workspace.nodes_matching.view(),
+            );
+
+            // Coarsen the matrix's sparsity pattern as a directed graph, which also produces the
+            // aggregation mapping. Only the mapping from fine entries to coarse entries is used
+            // to compute the coarse values; the coarse edge weights that `CoarsenGraph` computes
+            // from the (meaningless) column indices we pass as the fine edge weights are
+            // discarded.
+            encoder = self.coarsen_graph.encode_directed(
+                encoder,
+                CoarsenGraphInput {
+                    fine_nodes_edge_offset: fine_level.row_offsets.view(),
+                    fine_nodes_edges: fine_level.column_indices.view(),
+                    fine_nodes_edge_weights: fine_level.column_indices.view(),
+                    fine_nodes_matching: workspace.nodes_matching.view(),
+                    temporary_storage_0: workspace.temporary_storage_0.view(),
+                    temporary_storage_1: workspace.temporary_storage_1.view(),
+                    edge_channels: &[],
+                    counts: Some(CoarsenCounts {
+                        node_count: fine_level.row_count_buffer.uniform(),
+                        edge_ref_count: fine_level.entry_count_buffer.uniform(),
+                    }),
+                },
+                CoarsenGraphOutput {
+                    fine_nodes_mapping: coarse_level.fine_nodes_mapping.view(),
+                    coarse_nodes_mapping_offset: coarse_level.coarse_nodes_mapping_offset.view(),
+                    coarse_nodes_mapping: coarse_level.coarse_nodes_mapping.view(),
+                    coarse_node_count: coarse_level.row_count_buffer.view(),
+                    coarse_edge_ref_count: coarse_level.entry_count_buffer.view(),
+                    coarse_nodes_edge_offset: coarse_level.row_offsets.view(),
+                    coarse_nodes_edges: coarse_level.column_indices.view(),
+                    coarse_nodes_edge_weights: workspace.coarse_entries_weight.view(),
+                    fine_edges_coarse_edge: Some(workspace.fine_entries_coarse_entry.storage()),
+                },
+            );
+
+            encoder = encoder.clear_buffer_slice(coarse_level.values.view());
+            encoder = encoder.clear_buffer_slice(coarse_level.diagonal.view());
+            encoder = self.galerkin_product.encode(
+                encoder,
+                GalerkinProductResources {
+                    fine_row_count: fine_level.row_count_buffer.uniform(),
+                    fine_entry_count: fine_level.entry_count_buffer.uniform(),
+                    fine_row_offsets: fine_level.row_offsets.storage(),
+                    fine_values: fine_level.values.storage(),
+                    fine_diagonal: fine_level.diagonal.storage(),
+                    fine_nodes_mapping: coarse_level.fine_nodes_mapping.storage(),
+                    fine_entries_coarse_entry: workspace.fine_entries_coarse_entry.storage(),
+                    coarse_values: coarse_level.values.storage(),
+                    coarse_diagonal: coarse_level.diagonal.storage(),
+                },
+                fine_row_count,
+            );
+
+            self.device.queue().submit(encoder.finish());
+
+            let (coarse_row_count, coarse_entry_count) = read_counts(
+                &self.device,
+                coarse_level.row_count_buffer.view(),
+                coarse_level.entry_count_buffer.view(),
+            )
+            .await?;
+
+            // If the matching could not find any pairs to aggregate, then the coarse level is a
+            // copy of the fine level; discard it.
+            if coarse_row_count == fine_row_count {
+                break;
+            }
+
+            coarse_level.row_count = coarse_row_count;
+            coarse_level.entry_count = coarse_entry_count;
+
+            levels.push(coarse_level);
+
+            if coarse_row_count as f32 > MIN_COARSENING_RATIO * fine_row_count as f32 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct Workspace {
+    entries_strong: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    entries_strength: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    strength_nodes_edge_offset: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    strength_nodes_edges: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    strength_nodes_edge_weights: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    strength_edge_ref_count: Buffer<u32, buffer::Usages<O, O, X, X, O, O, O, O, O, O>>,
+    nodes_matching: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    temporary_storage_0: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    temporary_storage_1: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    coarse_entries_weight: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    fine_entries_coarse_entry: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+}
+
+impl Workspace {
+    fn new(device: &Device, row_capacity: usize, entry_capacity: usize) -> Self {
+        let temporary_capacity =
+            CoarsenGraph::required_temporary_storage(row_capacity, entry_capacity);
+
+        Workspace {
+            entries_strong: device
+                .create_slice_buffer_zeroed(entry_capacity, buffer::Usages::storage_binding()),
+            entries_strength: device
+                .create_slice_buffer_zeroed(entry_capacity, buffer::Usages::storage_binding()),
+            strength_nodes_edge_offset: device
+                .create_slice_buffer_zeroed(row_capacity, buffer::Usages::storage_binding()),
+            strength_nodes_edges: device
+                .create_slice_buffer_zeroed(entry_capacity, buffer::Usages::storage_binding()),
+            strength_nodes_edge_weights: device
+                .create_slice_buffer_zeroed(entry_capacity, buffer::Usages::storage_binding()),
+            strength_edge_ref_count: device.create_buffer(
+                0u32,
+                buffer::Usages::storage_binding().and_uniform_binding(),
+            ),
+            nodes_matching: device.create_slice_buffer_zeroed(
+                row_capacity,
+                buffer::Usages::storage_binding().and_copy_dst(),
+            ),
+            temporary_storage_0: device.create_slice_buffer_zeroed(
+                temporary_capacity,
+                buffer::Usages::storage_binding().and_copy_dst(),
+            ),
+            temporary_storage_1: device.create_slice_buffer_zeroed(
+                temporary_capacity,
+                buffer::Usages::storage_binding().and_copy_dst(),
+            ),
+            coarse_entries_weight: device.create_slice_buffer_zeroed(
+                entry_capacity,
+                buffer::Usages::storage_binding().and_copy_dst(),
+            ),
+            fine_entries_coarse_entry: device
+                .create_slice_buffer_zeroed(entry_capacity, buffer::Usages::storage_binding()),
+        }
+    }
+}
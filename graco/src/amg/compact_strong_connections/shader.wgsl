@@ -0,0 +1,75 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> row_count: u32;
+
+@group(0) @binding(1)
+var<uniform> entry_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> row_offsets: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> column_indices: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> entries_strong_prefix_sum: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> entries_strength: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read_write> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read_write> nodes_edges: array<u32>;
+
+@group(0) @binding(8)
+var<storage, read_write> nodes_edge_weights: array<u32>;
+
+@group(0) @binding(9)
+var<storage, read_write> edge_ref_count: u32;
+
+fn strong_count_before(entry: u32) -> u32 {
+    if entry == 0 {
+        return 0u;
+    } else {
+        return entries_strong_prefix_sum[entry - 1];
+    }
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= row_count {
+        return;
+    }
+
+    let entries_start = row_offsets[index];
+    var entries_end = entry_count;
+
+    if index < row_count - 1 {
+        entries_end = row_offsets[index + 1];
+    }
+
+    nodes_edge_offset[index] = strong_count_before(entries_start);
+
+    // An entry is strong if the inclusive prefix sum increases at its position; the prefix sum minus one is then its
+    // position in the compacted edge list.
+    for (var i = entries_start; i < entries_end; i += 1u) {
+        let position = entries_strong_prefix_sum[i];
+
+        if position > strong_count_before(i) {
+            nodes_edges[position - 1] = column_indices[i];
+            nodes_edge_weights[position - 1] = entries_strength[i];
+        }
+    }
+
+    if index == row_count - 1 {
+        edge_ref_count = strong_count_before(entry_count);
+    }
+}
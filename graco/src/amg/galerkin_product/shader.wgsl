@@ -0,0 +1,96 @@
+#include <src/dispatch.wgsl>
+
+// The value `CoarsenGraph` writes to `fine_edges_coarse_edge` for fine edges that became self-references.
+const COARSE_ENTRY_DIAGONAL = 0xFFFFFFFFu;
+
+@group(0) @binding(0)
+var<uniform> fine_row_count: u32;
+
+@group(0) @binding(1)
+var<uniform> fine_entry_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> fine_row_offsets: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> fine_values: array<f32>;
+
+@group(0) @binding(4)
+var<storage, read> fine_diagonal: array<f32>;
+
+@group(0) @binding(5)
+var<storage, read> fine_nodes_mapping: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> fine_entries_coarse_entry: array<u32>;
+
+// Hold `f32` values; WGSL has no floating point atomics, so we add with a compare-exchange loop on the bit patterns.
+@group(0) @binding(7)
+var<storage, read_write> coarse_values: array<atomic<u32>>;
+
+@group(0) @binding(8)
+var<storage, read_write> coarse_diagonal: array<atomic<u32>>;
+
+fn add_coarse_value(index: u32, value: f32) {
+    var old = atomicLoad(&coarse_values[index]);
+
+    loop {
+        let result = atomicCompareExchangeWeak(&coarse_values[index], old, bitcast<u32>(bitcast<f32>(old) + value));
+
+        if result.exchanged {
+            break;
+        }
+
+        old = result.old_value;
+    }
+}
+
+fn add_coarse_diagonal(index: u32, value: f32) {
+    var old = atomicLoad(&coarse_diagonal[index]);
+
+    loop {
+        let result = atomicCompareExchangeWeak(&coarse_diagonal[index], old, bitcast<u32>(bitcast<f32>(old) + value));
+
+        if result.exchanged {
+            break;
+        }
+
+        old = result.old_value;
+    }
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= fine_row_count {
+        return;
+    }
+
+    let entries_start = fine_row_offsets[index];
+    var entries_end = fine_entry_count;
+
+    if index < fine_row_count - 1 {
+        entries_end = fine_row_offsets[index + 1];
+    }
+
+    // With a piecewise constant prolongator `P`, entry `(I, J)` of `P^T A P` is the sum of the entries `a_ij` for which
+    // `i` is aggregated into `I` and `j` into `J`. Entries within an aggregate become self-references when the pattern
+    // is coarsened, but rather than dropping them, we retain them in the coarse diagonal.
+    var diagonal = fine_diagonal[index];
+
+    for (var i = entries_start; i < entries_end; i += 1u) {
+        let coarse_entry = fine_entries_coarse_entry[i];
+
+        if coarse_entry == COARSE_ENTRY_DIAGONAL {
+            diagonal += fine_values[i];
+        } else {
+            add_coarse_value(coarse_entry, fine_values[i]);
+        }
+    }
+
+    add_coarse_diagonal(fine_nodes_mapping[index], diagonal);
+}
@@ -0,0 +1,109 @@
+use empa::access_mode::ReadWrite;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct JacobiSweepResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub row_count: Uniform<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub entry_count: Uniform<'a, u32>,
+    #[resource(binding = 2, visibility = "COMPUTE")]
+    pub jacobi_weight: Uniform<'a, f32>,
+    #[resource(binding = 3, visibility = "COMPUTE")]
+    pub row_offsets: Storage<'a, [u32]>,
+    #[resource(binding = 4, visibility = "COMPUTE")]
+    pub column_indices: Storage<'a, [u32]>,
+    #[resource(binding = 5, visibility = "COMPUTE")]
+    pub values: Storage<'a, [f32]>,
+    #[resource(binding = 6, visibility = "COMPUTE")]
+    pub diagonal: Storage<'a, [f32]>,
+    #[resource(binding = 7, visibility = "COMPUTE")]
+    pub b: Storage<'a, [f32]>,
+    #[resource(binding = 8, visibility = "COMPUTE")]
+    pub x: Storage<'a, [f32]>,
+    #[resource(binding = 9, visibility = "COMPUTE")]
+    pub x_next: Storage<'a, [f32], ReadWrite>,
+}
+
+type ResourcesLayout = <JacobiSweepResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct JacobiSweep {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+    dispatch_config: DispatchConfig,
+}
+
+impl JacobiSweep {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        JacobiSweep {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+            dispatch_config,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        encoder: CommandEncoder,
+        resources: JacobiSweepResources,
+        row_count: u32,
+    ) -> CommandEncoder {
+        let key = [
+            resources.row_count.binding_key(),
+            resources.entry_count.binding_key(),
+            resources.jacobi_weight.binding_key(),
+            resources.row_offsets.binding_key(),
+            resources.column_indices.binding_key(),
+            resources.values.binding_key(),
+            resources.diagonal.binding_key(),
+            resources.b.binding_key(),
+            resources.x.binding_key(),
+            resources.x_next.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group)
+            .dispatch_workgroups(self.dispatch_config.workgroups(row_count))
+            .end()
+    }
+}
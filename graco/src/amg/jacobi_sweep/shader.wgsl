@@ -0,0 +1,67 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> row_count: u32;
+
+@group(0) @binding(1)
+var<uniform> entry_count: u32;
+
+@group(0) @binding(2)
+var<uniform> jacobi_weight: f32;
+
+@group(0) @binding(3)
+var<storage, read> row_offsets: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> column_indices: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> values: array<f32>;
+
+@group(0) @binding(6)
+var<storage, read> diagonal: array<f32>;
+
+@group(0) @binding(7)
+var<storage, read> b: array<f32>;
+
+@group(0) @binding(8)
+var<storage, read> x: array<f32>;
+
+@group(0) @binding(9)
+var<storage, read_write> x_next: array<f32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= row_count {
+        return;
+    }
+
+    let entries_start = row_offsets[index];
+    var entries_end = entry_count;
+
+    if index < row_count - 1 {
+        entries_end = row_offsets[index + 1];
+    }
+
+    let row_diagonal = diagonal[index];
+
+    var product = row_diagonal * x[index];
+
+    for (var i = entries_start; i < entries_end; i += 1u) {
+        product += values[i] * x[column_indices[i]];
+    }
+
+    var value = x[index];
+
+    // Rows without a diagonal cannot be relaxed; leave them unchanged.
+    if row_diagonal != 0.0 {
+        value += jacobi_weight * (b[index] - product) / row_diagonal;
+    }
+
+    x_next[index] = value;
+}
@@ -0,0 +1,75 @@
+#include <src/dispatch.wgsl>
+
+// Strengths are in the range `[0, 1]` and are scaled by this factor to obtain the integer edge weights of the strength
+// graph.
+const STRENGTH_SCALE = 65535.0;
+
+@group(0) @binding(0)
+var<uniform> row_count: u32;
+
+@group(0) @binding(1)
+var<uniform> entry_count: u32;
+
+@group(0) @binding(2)
+var<uniform> strength_threshold: f32;
+
+@group(0) @binding(3)
+var<storage, read> row_offsets: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> column_indices: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> values: array<f32>;
+
+@group(0) @binding(6)
+var<storage, read> diagonal: array<f32>;
+
+@group(0) @binding(7)
+var<storage, read_write> entries_strong: array<u32>;
+
+@group(0) @binding(8)
+var<storage, read_write> entries_strength: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= row_count {
+        return;
+    }
+
+    let entries_start = row_offsets[index];
+    var entries_end = entry_count;
+
+    if index < row_count - 1 {
+        entries_end = row_offsets[index + 1];
+    }
+
+    let row_diagonal = abs(diagonal[index]);
+
+    for (var i = entries_start; i < entries_end; i += 1u) {
+        let column = column_indices[i];
+
+        // Row `i` strongly depends on column `j` if `|a_ij| >= threshold * sqrt(|a_ii * a_jj|)`. This measure is
+        // symmetric for symmetric matrices, so the strength graph is undirected.
+        let scale = sqrt(row_diagonal * abs(diagonal[column]));
+
+        var strength = 0.0;
+
+        if column != index && scale > 0.0 {
+            strength = min(abs(values[i]) / scale, 1.0);
+        }
+
+        if strength > 0.0 && strength >= strength_threshold {
+            entries_strong[i] = 1u;
+            entries_strength[i] = max(u32(strength * STRENGTH_SCALE), 1u);
+        } else {
+            entries_strong[i] = 0u;
+            entries_strength[i] = 0u;
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! Aggregation-based algebraic multigrid (AMG).
+//!
+//! Use [BuildAmgHierarchy] for the setup phase, which aggregates the rows of a sparse matrix with
+//! the matching and coarsening pipelines of this crate, and [VCycle] to apply the resulting
+//! hierarchy, either as a stand-alone iterative solver or as a preconditioner.
+
+mod compact_strong_connections;
+mod compute_residual;
+mod galerkin_product;
+mod jacobi_sweep;
+mod mark_strong_connections;
+mod prolong_correction;
+mod restrict_residual;
+mod split_diagonal;
+
+mod build_amg_hierarchy;
+pub use self::build_amg_hierarchy::{AmgConfig, AmgHierarchy, AmgMatrix, BuildAmgHierarchy};
+
+mod v_cycle;
+pub use self::v_cycle::{VCycle, VCycleConfig};
@@ -0,0 +1,27 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> fine_row_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> fine_nodes_mapping: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> coarse_x: array<f32>;
+
+@group(0) @binding(3)
+var<storage, read_write> fine_x: array<f32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= fine_row_count {
+        return;
+    }
+
+    fine_x[index] += coarse_x[fine_nodes_mapping[index]];
+}
@@ -0,0 +1,48 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> coarse_row_count: u32;
+
+@group(0) @binding(1)
+var<uniform> fine_row_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> coarse_nodes_mapping_offset: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> coarse_nodes_mapping: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> fine_residual: array<f32>;
+
+@group(0) @binding(5)
+var<storage, read_write> coarse_b: array<f32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= coarse_row_count {
+        return;
+    }
+
+    let start = coarse_nodes_mapping_offset[index];
+    var end = fine_row_count;
+
+    if index < coarse_row_count - 1 {
+        end = coarse_nodes_mapping_offset[index + 1];
+    }
+
+    // The restriction is the transpose of the piecewise constant prolongator: each coarse row sums the residuals of the
+    // fine rows in its aggregate.
+    var sum = 0.0;
+
+    for (var i = start; i < end; i += 1u) {
+        sum += fine_residual[coarse_nodes_mapping[i]];
+    }
+
+    coarse_b[index] = sum;
+}
@@ -0,0 +1,51 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> row_count: u32;
+
+@group(0) @binding(1)
+var<uniform> entry_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> row_offsets: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> column_indices: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> values: array<f32>;
+
+@group(0) @binding(5)
+var<storage, read_write> diagonal: array<f32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= row_count {
+        return;
+    }
+
+    let entries_start = row_offsets[index];
+    var entries_end = entry_count;
+
+    if index < row_count - 1 {
+        entries_end = row_offsets[index + 1];
+    }
+
+    // Move the diagonal entries out of the row and into the separate diagonal. The entries stay in place with a value
+    // of zero, so that the sparsity pattern does not have to be compacted.
+    var value = 0.0;
+
+    for (var i = entries_start; i < entries_end; i += 1u) {
+        if column_indices[i] == index {
+            value += values[i];
+            values[i] = 0.0;
+        }
+    }
+
+    diagonal[index] = value;
+}
@@ -0,0 +1,217 @@
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::Buffer;
+use empa::command::CommandEncoder;
+use empa::device::Device;
+use empa::type_flag::{O, X};
+
+use crate::amg::build_amg_hierarchy::AmgLevel;
+use crate::amg::compute_residual::{ComputeResidual, ComputeResidualResources};
+use crate::amg::jacobi_sweep::{JacobiSweep, JacobiSweepResources};
+use crate::amg::prolong_correction::{ProlongCorrection, ProlongCorrectionResources};
+use crate::amg::restrict_residual::{RestrictResidual, RestrictResidualResources};
+use crate::amg::AmgHierarchy;
+use crate::context::Context;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VCycleConfig {
+    /// The number of Jacobi sweeps on each level before restricting the residual to the next
+    /// coarser level.
+    pub pre_smoothing_sweeps: usize,
+    /// The number of Jacobi sweeps on each level after adding the correction from the next
+    /// coarser level.
+    pub post_smoothing_sweeps: usize,
+    /// The number of Jacobi sweeps that approximate the solve on the coarsest level.
+    pub coarsest_sweeps: usize,
+    /// The damping factor of the weighted Jacobi smoother.
+    pub jacobi_weight: f32,
+}
+
+impl Default for VCycleConfig {
+    fn default() -> Self {
+        VCycleConfig {
+            pre_smoothing_sweeps: 2,
+            post_smoothing_sweeps: 2,
+            coarsest_sweeps: 32,
+            jacobi_weight: 2.0 / 3.0,
+        }
+    }
+}
+
+/// Applies a multigrid V-cycle for an [AmgHierarchy] on the GPU, which improves an approximate
+/// solution `x` of the system `A x = b`.
+///
+/// On each level, the V-cycle smooths the level's approximation with weighted Jacobi sweeps,
+/// restricts the residual to the next coarser level, recurses, and then adds the prolongated
+/// coarse correction before smoothing again. The coarsest level is solved approximately with
+/// additional Jacobi sweeps. As the smoother is symmetric, the V-cycle may be used as a
+/// preconditioner for the conjugate gradient method.
+pub struct VCycle {
+    config: VCycleConfig,
+    jacobi_sweep: Rc<JacobiSweep>,
+    compute_residual: Rc<ComputeResidual>,
+    restrict_residual: Rc<RestrictResidual>,
+    prolong_correction: Rc<ProlongCorrection>,
+    jacobi_weight: Buffer<f32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+}
+
+impl VCycle {
+    pub async fn init(device: Device, config: VCycleConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](VCycle::init), but obtains the sub-pipelines from the given `context`, so
+    /// that they may be shared with other components.
+    pub async fn init_with_context(context: &Context, config: VCycleConfig) -> Self {
+        let (jacobi_sweep, compute_residual, restrict_residual, prolong_correction) = join!(
+            context.get_or_init_kernel(JacobiSweep::init),
+            context.get_or_init_kernel(ComputeResidual::init),
+            context.get_or_init_kernel(RestrictResidual::init),
+            context.get_or_init_kernel(ProlongCorrection::init),
+        )
+        .await;
+
+        let jacobi_weight = context
+            .device()
+            .create_buffer(config.jacobi_weight, buffer::Usages::uniform_binding());
+
+        VCycle {
+            config,
+            jacobi_sweep,
+            compute_residual,
+            restrict_residual,
+            prolong_correction,
+            jacobi_weight,
+        }
+    }
+
+    /// Encodes a single V-cycle for the `hierarchy`, which updates the approximate solution `x`
+    /// for the right-hand side `b` in place.
+    ///
+    /// Both `b` and `x` must have a length equal to the row count of the hierarchy's base level.
+    pub fn encode<U0, U1>(
+        &self,
+        mut encoder: CommandEncoder,
+        hierarchy: &AmgHierarchy,
+        b: buffer::View<[f32], U0>,
+        x: buffer::View<[f32], U1>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::CopySrc,
+        U1: buffer::CopySrc + buffer::CopyDst,
+    {
+        let levels = &hierarchy.levels;
+        let base_level = &levels[0];
+
+        assert_eq!(
+            b.len(),
+            base_level.row_count as usize,
+            "`b` must have a length equal to the base level's row count"
+        );
+        assert_eq!(
+            x.len(),
+            base_level.row_count as usize,
+            "`x` must have a length equal to the base level's row count"
+        );
+
+        encoder = encoder.copy_buffer_to_buffer_slice(b, base_level.b.view());
+        encoder = encoder.copy_buffer_to_buffer_slice(x, base_level.x.view());
+
+        let coarsest = levels.len() - 1;
+
+        // Descend: smooth, then restrict the residual to become the right-hand side of the next
+        // coarser level, which starts from a zero initial guess.
+        for index in 0..coarsest {
+            let level = &levels[index];
+            let coarse_level = &levels[index + 1];
+
+            encoder = self.encode_smoothing(encoder, level, self.config.pre_smoothing_sweeps);
+            encoder = self.compute_residual.encode(
+                encoder,
+                ComputeResidualResources {
+                    row_count: level.row_count_buffer.uniform(),
+                    entry_count: level.entry_count_buffer.uniform(),
+                    row_offsets: level.row_offsets.storage(),
+                    column_indices: level.column_indices.storage(),
+                    values: level.values.storage(),
+                    diagonal: level.diagonal.storage(),
+                    b: level.b.storage(),
+                    x: level.x.storage(),
+                    residual: level.residual.storage(),
+                },
+                level.row_count,
+            );
+            encoder = self.restrict_residual.encode(
+                encoder,
+                RestrictResidualResources {
+                    coarse_row_count: coarse_level.row_count_buffer.uniform(),
+                    fine_row_count: level.row_count_buffer.uniform(),
+                    coarse_nodes_mapping_offset: coarse_level.coarse_nodes_mapping_offset.storage(),
+                    coarse_nodes_mapping: coarse_level.coarse_nodes_mapping.storage(),
+                    fine_residual: level.residual.storage(),
+                    coarse_b: coarse_level.b.storage(),
+                },
+                coarse_level.row_count,
+            );
+            encoder = encoder.clear_buffer_slice(coarse_level.x.view());
+        }
+
+        encoder = self.encode_smoothing(encoder, &levels[coarsest], self.config.coarsest_sweeps);
+
+        // Ascend: add the coarse correction to each finer level, then smooth.
+        for index in (0..coarsest).rev() {
+            let level = &levels[index];
+            let coarse_level = &levels[index + 1];
+
+            encoder = self.prolong_correction.encode(
+                encoder,
+                ProlongCorrectionResources {
+                    fine_row_count: level.row_count_buffer.uniform(),
+                    fine_nodes_mapping: coarse_level.fine_nodes_mapping.storage(),
+                    coarse_x: coarse_level.x.storage(),
+                    fine_x: level.x.storage(),
+                },
+                level.row_count,
+            );
+            encoder = self.encode_smoothing(encoder, level, self.config.post_smoothing_sweeps);
+        }
+
+        encoder.copy_buffer_to_buffer_slice(base_level.x.view(), x)
+    }
+
+    fn encode_smoothing(
+        &self,
+        mut encoder: CommandEncoder,
+        level: &AmgLevel,
+        sweeps: usize,
+    ) -> CommandEncoder {
+        let row_count = level.row_count as usize;
+
+        for _ in 0..sweeps {
+            encoder = self.jacobi_sweep.encode(
+                encoder,
+                JacobiSweepResources {
+                    row_count: level.row_count_buffer.uniform(),
+                    entry_count: level.entry_count_buffer.uniform(),
+                    jacobi_weight: self.jacobi_weight.uniform(),
+                    row_offsets: level.row_offsets.storage(),
+                    column_indices: level.column_indices.storage(),
+                    values: level.values.storage(),
+                    diagonal: level.diagonal.storage(),
+                    b: level.b.storage(),
+                    x: level.x.storage(),
+                    x_next: level.x_next.storage(),
+                },
+                level.row_count,
+            );
+            encoder = encoder.copy_buffer_to_buffer_slice(
+                level.x_next.view().get(..row_count).unwrap(),
+                level.x.view().get(..row_count).unwrap(),
+            );
+        }
+
+        encoder
+    }
+}
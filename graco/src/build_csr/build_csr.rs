@@ -0,0 +1,457 @@
+use std::cell::RefCell;
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::{Buffer, Uniform};
+use empa::command::{CommandEncoder, DispatchWorkgroups};
+use empa::device::Device;
+use empa::type_flag::{O, X};
+use empa_tk::gather_by::{GatherBy, GatherByInput};
+use empa_tk::prefix_sum::{PrefixSum, PrefixSumInput};
+use empa_tk::radix_sort::{RadixSortBy, RadixSortByInput};
+
+use crate::build_csr::compact_edges::{CompactEdges, CompactEdgesResources};
+use crate::build_csr::generate_dispatches::{GenerateDispatches, GenerateDispatchesResources};
+use crate::build_csr::mark_edge_validity::{MarkEdgeValidity, MarkEdgeValidityResources};
+use crate::build_csr::merge_edge_weights::{MergeEdgeWeights, MergeEdgeWeightsResources};
+use crate::build_csr::resolve_edge_ref_count::{ResolveEdgeRefCount, ResolveEdgeRefCountResources};
+use crate::build_csr::scatter_nodes_edge_offset::{
+    ScatterNodesEdgeOffset, ScatterNodesEdgeOffsetResources,
+};
+use crate::build_csr::symmetrize_edges::{SymmetrizeEdges, SymmetrizeEdgesResources};
+use crate::coarsen_graph::generate_index_list::{GenerateIndexList, GenerateIndexListResources};
+use crate::context::Context;
+use crate::counts_fallback::FallbackCountsCache;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BuildCsrConfig {
+    /// Whether to add a reversed copy of each input edge, which turns a list that stores each
+    /// undirected edge once into a list that stores it once for each of the nodes it connects.
+    ///
+    /// Every input edge is copied, including self-loops and edges whose reverse is already part
+    /// of the input. If `merge_duplicates` is also enabled, this doubles the weight of each
+    /// self-loop and of each edge that was already present in both directions. Input that already
+    /// stores both directions of its edges should therefore not be symmetrized.
+    pub symmetrize: bool,
+    /// Whether to merge edges that share both their source and their target into a single edge,
+    /// whose weight is the sum of the merged edges' weights.
+    pub merge_duplicates: bool,
+}
+
+impl Default for BuildCsrConfig {
+    fn default() -> Self {
+        BuildCsrConfig {
+            symmetrize: false,
+            merge_duplicates: true,
+        }
+    }
+}
+
+pub struct BuildCsrCounts<'a> {
+    pub node_count: Uniform<'a, u32>,
+    pub edge_count: Uniform<'a, u32>,
+}
+
+pub struct BuildCsrInput<'a, U0, U1, U2, U3, U4> {
+    pub edge_sources: buffer::View<'a, [u32], U0>,
+    pub edge_targets: buffer::View<'a, [u32], U1>,
+    pub edge_weights: buffer::View<'a, [u32], U2>,
+    pub temporary_storage_0: buffer::View<'a, [u32], U3>,
+    pub temporary_storage_1: buffer::View<'a, [u32], U4>,
+    pub counts: Option<BuildCsrCounts<'a>>,
+}
+
+pub struct BuildCsrOutput<'a, U0, U1, U2, U3> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    pub edge_ref_count: buffer::View<'a, u32, U3>,
+}
+
+pub struct BuildCsr {
+    device: Device,
+    counts_cache: FallbackCountsCache,
+    config: BuildCsrConfig,
+    generate_dispatches: Rc<GenerateDispatches>,
+    generate_index_list: Rc<GenerateIndexList>,
+    symmetrize_edges: Rc<SymmetrizeEdges>,
+    mark_edge_validity: Rc<MarkEdgeValidity>,
+    compact_edges: Rc<CompactEdges>,
+    merge_edge_weights: Rc<MergeEdgeWeights>,
+    resolve_edge_ref_count: Rc<ResolveEdgeRefCount>,
+    scatter_nodes_edge_offset: Rc<ScatterNodesEdgeOffset>,
+    sort_by: Rc<RefCell<RadixSortBy<u32, u32>>>,
+    gather_by: Rc<RefCell<GatherBy<u32, u32>>>,
+    prefix_sum_inclusive: Rc<RefCell<PrefixSum<u32>>>,
+    edge_count_factor: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+    merge_duplicates: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+    edge_count: Buffer<u32, buffer::Usages<O, O, X, X, O, O, O, O, O, O>>,
+    edge_count_dispatch: Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+}
+
+impl BuildCsr {
+    pub async fn init(device: Device, config: BuildCsrConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](BuildCsr::init), but obtains the sub-pipelines from the given `context`,
+    /// so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context, config: BuildCsrConfig) -> Self {
+        let device = context.device().clone();
+
+        let (
+            generate_dispatches,
+            generate_index_list,
+            symmetrize_edges,
+            mark_edge_validity,
+            compact_edges,
+            merge_edge_weights,
+            resolve_edge_ref_count,
+            scatter_nodes_edge_offset,
+            sort_by,
+            gather_by,
+            prefix_sum_inclusive,
+        ) = join!(
+            context.get_or_init_kernel(GenerateDispatches::init),
+            context.get_or_init_kernel(GenerateIndexList::init),
+            context.get_or_init_kernel(SymmetrizeEdges::init),
+            context.get_or_init_kernel(MarkEdgeValidity::init),
+            context.get_or_init_kernel(CompactEdges::init),
+            context.get_or_init_kernel(MergeEdgeWeights::init),
+            context.get_or_init("init", ResolveEdgeRefCount::init),
+            context.get_or_init_kernel(ScatterNodesEdgeOffset::init),
+            context.get_or_init_mut("init_u32", RadixSortBy::init_u32),
+            context.get_or_init_mut("init_u32", GatherBy::init_u32),
+            context.get_or_init_mut("init_inclusive_u32", PrefixSum::init_inclusive_u32),
+        )
+        .await;
+
+        let edge_count_factor = device.create_buffer(
+            if config.symmetrize { 2 } else { 1 },
+            buffer::Usages::uniform_binding(),
+        );
+        let merge_duplicates = device.create_buffer(
+            config.merge_duplicates as u32,
+            buffer::Usages::uniform_binding(),
+        );
+        let edge_count =
+            device.create_buffer(0, buffer::Usages::storage_binding().and_uniform_binding());
+        let edge_count_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
+
+        let counts_cache = FallbackCountsCache::new(device.clone());
+
+        BuildCsr {
+            device,
+            counts_cache,
+            config,
+            generate_dispatches,
+            generate_index_list,
+            symmetrize_edges,
+            mark_edge_validity,
+            compact_edges,
+            merge_edge_weights,
+            resolve_edge_ref_count,
+            scatter_nodes_edge_offset,
+            sort_by,
+            gather_by,
+            prefix_sum_inclusive,
+            edge_count_factor,
+            merge_duplicates,
+            edge_count,
+            edge_count_dispatch,
+        }
+    }
+
+    pub fn encode<U0, U1, U2, U3, U4, U5, U6, U7, U8>(
+        &mut self,
+        mut encoder: CommandEncoder,
+        input: BuildCsrInput<U0, U1, U2, U3, U4>,
+        output: BuildCsrOutput<U5, U6, U7, U8>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U4: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U5: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U6: buffer::StorageBinding,
+        U7: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U8: buffer::StorageBinding,
+    {
+        // Builds a CSR representation from an unsorted COO edge list, using the same "compound
+        // sort" technique `CoarsenGraph` uses to construct the coarse edge lists: we sort an index
+        // list by edge target and then (stably) by edge source, which groups the edges by source
+        // node, with each group sorted by target node. Duplicate edges then end up stored
+        // consecutively, which allows us to filter them out with a validity prefix-sum.
+        //
+        // The input edge buffers double as temporary storage; their contents are undefined after
+        // this operation. If the edge list is to be symmetrized, then the edge buffers (as well as
+        // the temporary storage buffers and the `nodes_edges` and `nodes_edge_weights` output
+        // buffers) must have the capacity to store twice the input edge count: the reversed edges
+        // are appended to the input edges in-place.
+
+        let BuildCsrInput {
+            edge_sources,
+            edge_targets,
+            edge_weights,
+            temporary_storage_0,
+            temporary_storage_1,
+            counts,
+        } = input;
+
+        let BuildCsrOutput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            edge_ref_count,
+        } = output;
+
+        let dispatch_indirect = counts.is_some();
+
+        if let Some(counts) = counts.as_ref() {
+            encoder = self.generate_dispatches.encode(
+                encoder,
+                GenerateDispatchesResources {
+                    edge_count_factor: self.edge_count_factor.uniform(),
+                    edge_count: counts.edge_count.clone(),
+                    resolved_edge_count: self.edge_count.storage(),
+                    edge_count_dispatch: self.edge_count_dispatch.storage(),
+                },
+            );
+        }
+
+        // If no counts are provided and the edge list is to be symmetrized, then we assume that
+        // the first half of the edge buffers holds the input edges.
+        let fallback_node_count = nodes_edge_offset.len() as u32;
+        let fallback_edge_count = if self.config.symmetrize {
+            (edge_sources.len() as u32 / 2) * 2
+        } else {
+            edge_sources.len() as u32
+        };
+        let counts_fallback = self.counts_cache.get(
+            counts.map(|c| (c.node_count, self.edge_count.uniform())),
+            (fallback_node_count, fallback_edge_count),
+        );
+
+        if self.config.symmetrize {
+            encoder = self.symmetrize_edges.encode(
+                encoder,
+                SymmetrizeEdgesResources {
+                    edge_count: counts_fallback.edge_ref_count(),
+                    edge_sources: edge_sources.storage(),
+                    edge_targets: edge_targets.storage(),
+                    edge_weights: edge_weights.storage(),
+                },
+                dispatch_indirect,
+                self.edge_count_dispatch.view(),
+                fallback_edge_count,
+            );
+        }
+
+        // We'll use `temporary_storage_0` to store the index list that will "store the sort", and
+        // we'll use the `nodes_edges` buffer to store the sort keys.
+        let index_list = temporary_storage_0;
+        let sort_keys = nodes_edges;
+
+        encoder = self.generate_index_list.encode(
+            encoder,
+            GenerateIndexListResources {
+                count: counts_fallback.edge_ref_count(),
+                data: index_list.storage(),
+            },
+            dispatch_indirect,
+            self.edge_count_dispatch.view(),
+            fallback_edge_count,
+        );
+
+        // Copy the edge targets into the sort keys buffer (gathering by the unsorted index list is
+        // an identity operation), then sort the index list by edge target.
+        encoder = self.gather_by.borrow_mut().encode(
+            encoder,
+            GatherByInput {
+                gather_by: index_list,
+                data: edge_targets,
+                count: Some(counts_fallback.edge_ref_count()),
+            },
+            sort_keys,
+        );
+
+        encoder = self.sort_by.borrow_mut().encode(
+            encoder,
+            RadixSortByInput {
+                keys: sort_keys,
+                values: index_list,
+                temporary_key_storage: nodes_edge_weights,
+                temporary_value_storage: temporary_storage_1,
+                count: Some(counts_fallback.edge_ref_count()),
+            },
+        );
+
+        // Apply the sort to the edge sources and sort again, now by edge source. Radix sort is
+        // stable, so within each run of edges that share a source, the edges remain sorted by
+        // target.
+        encoder = self.gather_by.borrow_mut().encode(
+            encoder,
+            GatherByInput {
+                gather_by: index_list,
+                data: edge_sources,
+                count: Some(counts_fallback.edge_ref_count()),
+            },
+            sort_keys,
+        );
+
+        encoder = self.sort_by.borrow_mut().encode(
+            encoder,
+            RadixSortByInput {
+                keys: sort_keys,
+                values: index_list,
+                temporary_key_storage: nodes_edge_weights,
+                temporary_value_storage: temporary_storage_1,
+                count: Some(counts_fallback.edge_ref_count()),
+            },
+        );
+
+        // The sort keys buffer now holds the sorted edge sources. Apply the compound sort to the
+        // edge targets and weights. At this point we no longer need the original edge targets, so
+        // we reuse that buffer to store the sorted edge weights.
+        let sorted_sources = sort_keys;
+        let sorted_targets = temporary_storage_1;
+        let sorted_weights = edge_targets;
+
+        encoder = self.gather_by.borrow_mut().encode(
+            encoder,
+            GatherByInput {
+                gather_by: index_list,
+                data: edge_targets,
+                count: Some(counts_fallback.edge_ref_count()),
+            },
+            sorted_targets,
+        );
+
+        encoder = self.gather_by.borrow_mut().encode(
+            encoder,
+            GatherByInput {
+                gather_by: index_list,
+                data: edge_weights,
+                count: Some(counts_fallback.edge_ref_count()),
+            },
+            sorted_weights,
+        );
+
+        // We no longer need the index list; we reuse its buffer to store the validity list. An
+        // edge is valid if it is not a duplicate of the edge that precedes it (or if we're not
+        // merging duplicates, every edge is valid).
+        let validity = index_list;
+
+        encoder = self.mark_edge_validity.encode(
+            encoder,
+            MarkEdgeValidityResources {
+                edge_count: counts_fallback.edge_ref_count(),
+                merge_duplicates: self.merge_duplicates.uniform(),
+                sorted_sources: sorted_sources.storage(),
+                sorted_targets: sorted_targets.storage(),
+                validity: validity.storage(),
+            },
+            dispatch_indirect,
+            self.edge_count_dispatch.view(),
+            fallback_edge_count,
+        );
+
+        // After an inclusive prefix-sum over the validity list, subtracting `1` from the value for
+        // a valid edge gives its position in the compacted edge list. For a duplicate edge, it
+        // gives the position of the edge it duplicates, which is where its weight will be merged.
+        encoder = self.prefix_sum_inclusive.borrow_mut().encode(
+            encoder,
+            PrefixSumInput {
+                data: validity,
+                count: Some(counts_fallback.edge_ref_count()),
+            },
+        );
+
+        let validity_prefix_sum = validity;
+
+        // Compact the sorted edge sources into the `edge_sources` buffer (the original edge sources
+        // are no longer needed); we'll need these to find the node edge offsets later.
+        let compacted_sources = edge_sources;
+
+        encoder = self.compact_edges.encode(
+            encoder,
+            CompactEdgesResources {
+                edge_count: counts_fallback.edge_ref_count(),
+                validity_prefix_sum: validity_prefix_sum.storage(),
+                data: sorted_sources.storage(),
+                compacted_data: compacted_sources.storage(),
+            },
+            dispatch_indirect,
+            self.edge_count_dispatch.view(),
+            fallback_edge_count,
+        );
+
+        // The sorted edge sources are now no longer needed, so we can compact the sorted edge
+        // targets into their final destination.
+        encoder = self.compact_edges.encode(
+            encoder,
+            CompactEdgesResources {
+                edge_count: counts_fallback.edge_ref_count(),
+                validity_prefix_sum: validity_prefix_sum.storage(),
+                data: sorted_targets.storage(),
+                compacted_data: nodes_edges.storage(),
+            },
+            dispatch_indirect,
+            self.edge_count_dispatch.view(),
+            fallback_edge_count,
+        );
+
+        encoder = encoder.clear_buffer_slice(nodes_edge_weights);
+        encoder = self.merge_edge_weights.encode(
+            encoder,
+            MergeEdgeWeightsResources {
+                edge_count: counts_fallback.edge_ref_count(),
+                validity_prefix_sum: validity_prefix_sum.storage(),
+                edge_weights: sorted_weights.storage(),
+                merged_edge_weights: nodes_edge_weights.storage(),
+            },
+            dispatch_indirect,
+            self.edge_count_dispatch.view(),
+            fallback_edge_count,
+        );
+
+        encoder = self.resolve_edge_ref_count.encode(
+            encoder,
+            ResolveEdgeRefCountResources {
+                edge_count: counts_fallback.edge_ref_count(),
+                validity_prefix_sum: validity_prefix_sum.storage(),
+                edge_ref_count: edge_ref_count.storage(),
+            },
+        );
+
+        // Finally, find the edge offset for each node from the compacted edge sources. Nodes
+        // without any edges will not be found in the compacted edge sources; clearing the offsets
+        // first takes care of graphs without any edges.
+        encoder = encoder.clear_buffer_slice(nodes_edge_offset);
+        encoder = self.scatter_nodes_edge_offset.encode(
+            encoder,
+            ScatterNodesEdgeOffsetResources {
+                node_count: counts_fallback.node_count(),
+                edge_count: counts_fallback.edge_ref_count(),
+                validity_prefix_sum: validity_prefix_sum.storage(),
+                compacted_sources: compacted_sources.storage(),
+                nodes_edge_offset: nodes_edge_offset.storage(),
+            },
+            dispatch_indirect,
+            self.edge_count_dispatch.view(),
+            fallback_edge_count,
+        );
+
+        encoder
+    }
+}
@@ -0,0 +1,104 @@
+use empa::access_mode::ReadWrite;
+use empa::buffer;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, DispatchWorkgroups, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct CompactEdgesResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub edge_count: Uniform<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub validity_prefix_sum: Storage<'a, [u32]>,
+    #[resource(binding = 2, visibility = "COMPUTE")]
+    pub data: Storage<'a, [u32]>,
+    #[resource(binding = 3, visibility = "COMPUTE")]
+    pub compacted_data: Storage<'a, [u32], ReadWrite>,
+}
+
+type ResourcesLayout =
+    <CompactEdgesResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct CompactEdges {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+    dispatch_config: DispatchConfig,
+}
+
+impl CompactEdges {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        CompactEdges {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+            dispatch_config,
+        }
+    }
+
+    pub fn encode<U>(
+        &self,
+        encoder: CommandEncoder,
+        resources: CompactEdgesResources,
+        dispatch_indirect: bool,
+        dispatch: buffer::View<DispatchWorkgroups, U>,
+        fallback_count: u32,
+    ) -> CommandEncoder
+    where
+        U: buffer::Indirect,
+    {
+        let key = [
+            resources.edge_count.binding_key(),
+            resources.validity_prefix_sum.binding_key(),
+            resources.data.binding_key(),
+            resources.compacted_data.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        let encoder = encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group);
+
+        if dispatch_indirect {
+            encoder.dispatch_workgroups_indirect(dispatch).end()
+        } else {
+            encoder
+                .dispatch_workgroups(self.dispatch_config.workgroups(fallback_count))
+                .end()
+        }
+    }
+}
@@ -0,0 +1,39 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> edge_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> validity_prefix_sum: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> data: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> compacted_data: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= edge_count {
+        return;
+    }
+
+    let prefix = validity_prefix_sum[index];
+
+    var previous_prefix = 0u;
+
+    if index > 0 {
+        previous_prefix = validity_prefix_sum[index - 1];
+    }
+
+    // The validity list was destroyed by the prefix-sum, but we can recover it: an edge is valid if (and only if) the
+    // inclusive prefix-sum increased at its index.
+    if prefix > previous_prefix {
+        compacted_data[prefix - 1] = data[index];
+    }
+}
@@ -0,0 +1,93 @@
+use empa::access_mode::ReadWrite;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, DispatchWorkgroups, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct GenerateDispatchesResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub edge_count_factor: Uniform<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub edge_count: Uniform<'a, u32>,
+    #[resource(binding = 2, visibility = "COMPUTE")]
+    pub resolved_edge_count: Storage<'a, u32, ReadWrite>,
+    #[resource(binding = 3, visibility = "COMPUTE")]
+    pub edge_count_dispatch: Storage<'a, DispatchWorkgroups, ReadWrite>,
+}
+
+type ResourcesLayout =
+    <GenerateDispatchesResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct GenerateDispatches {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+}
+
+impl GenerateDispatches {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        GenerateDispatches {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        encoder: CommandEncoder,
+        resources: GenerateDispatchesResources,
+    ) -> CommandEncoder {
+        let key = [
+            resources.edge_count_factor.binding_key(),
+            resources.edge_count.binding_key(),
+            resources.resolved_edge_count.binding_key(),
+            resources.edge_count_dispatch.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group)
+            .dispatch_workgroups(DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            })
+            .end()
+    }
+}
@@ -0,0 +1,23 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> edge_count_factor: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_count: u32;
+
+@group(0) @binding(2)
+var<storage, read_write> resolved_edge_count: u32;
+
+@group(0) @binding(3)
+var<storage, read_write> edge_count_dispatch: DispatchWorkgroups;
+
+@compute @workgroup_size(1, 1, 1)
+fn main() {
+    // If the edge list is to be symmetrized, then the edge count will double.
+    let edge_count = edge_count * edge_count_factor;
+
+    resolved_edge_count = edge_count;
+
+    edge_count_dispatch = dispatch_workgroups(edge_count);
+}
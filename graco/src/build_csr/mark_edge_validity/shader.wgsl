@@ -0,0 +1,41 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> edge_count: u32;
+
+@group(0) @binding(1)
+var<uniform> merge_duplicates: u32;
+
+@group(0) @binding(2)
+var<storage, read> sorted_sources: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> sorted_targets: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> validity: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= edge_count {
+        return;
+    }
+
+    // The edge list is sorted by `(source, target)` pairs, so duplicate edges are always stored consecutively; we only
+    // need to compare against the previous edge.
+    let is_duplicate = merge_duplicates != 0
+        && index > 0
+        && sorted_sources[index] == sorted_sources[index - 1]
+        && sorted_targets[index] == sorted_targets[index - 1];
+
+    if is_duplicate {
+        validity[index] = 0u;
+    } else {
+        validity[index] = 1u;
+    }
+}
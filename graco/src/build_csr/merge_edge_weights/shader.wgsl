@@ -0,0 +1,31 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> edge_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> validity_prefix_sum: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> edge_weights: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> merged_edge_weights: array<atomic<u32>>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= edge_count {
+        return;
+    }
+
+    // The first edge is always valid, so the inclusive validity prefix-sum is always greater than `0`. For a duplicate
+    // edge, the prefix-sum value points to the position of the valid edge it duplicates.
+    let dest_index = validity_prefix_sum[index] - 1;
+
+    atomicAdd(&merged_edge_weights[dest_index], edge_weights[index]);
+}
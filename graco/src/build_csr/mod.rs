@@ -0,0 +1,12 @@
+mod compact_edges;
+mod generate_dispatches;
+mod mark_edge_validity;
+mod merge_edge_weights;
+mod resolve_edge_ref_count;
+mod scatter_nodes_edge_offset;
+mod symmetrize_edges;
+
+mod build_csr;
+pub use self::build_csr::{
+    BuildCsr, BuildCsrConfig, BuildCsrCounts, BuildCsrInput, BuildCsrOutput,
+};
@@ -0,0 +1,85 @@
+use empa::access_mode::ReadWrite;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, DispatchWorkgroups, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct ResolveEdgeRefCountResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub edge_count: Uniform<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub validity_prefix_sum: Storage<'a, [u32]>,
+    #[resource(binding = 2, visibility = "COMPUTE")]
+    pub edge_ref_count: Storage<'a, u32, ReadWrite>,
+}
+
+type ResourcesLayout =
+    <ResolveEdgeRefCountResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct ResolveEdgeRefCount {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+}
+
+impl ResolveEdgeRefCount {
+    pub async fn init(device: Device) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(ComputeStageBuilder::begin(&shader, "main").finish())
+                    .finish(),
+            )
+            .await;
+
+        ResolveEdgeRefCount {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        encoder: CommandEncoder,
+        resources: ResolveEdgeRefCountResources,
+    ) -> CommandEncoder {
+        let key = [
+            resources.edge_count.binding_key(),
+            resources.validity_prefix_sum.binding_key(),
+            resources.edge_ref_count.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group)
+            .dispatch_workgroups(DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            })
+            .end()
+    }
+}
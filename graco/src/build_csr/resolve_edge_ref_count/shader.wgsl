@@ -0,0 +1,17 @@
+@group(0) @binding(0)
+var<uniform> edge_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> validity_prefix_sum: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> edge_ref_count: u32;
+
+@compute @workgroup_size(1, 1, 1)
+fn main() {
+    if edge_count == 0 {
+        edge_ref_count = 0u;
+    } else {
+        edge_ref_count = validity_prefix_sum[edge_count - 1];
+    }
+}
@@ -0,0 +1,58 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> validity_prefix_sum: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> compacted_sources: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> nodes_edge_offset: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if edge_count == 0 {
+        return;
+    }
+
+    let edge_ref_count = validity_prefix_sum[edge_count - 1];
+
+    if index >= edge_ref_count {
+        return;
+    }
+
+    let source = compacted_sources[index];
+
+    // Each thread that sees the start of a new run of source nodes is responsible for writing the offset for that
+    // node, as well as for any nodes without edges that precede it (nodes with an index in between the previous
+    // source node and the current source node).
+    var range_start = 0u;
+
+    if index > 0 {
+        range_start = compacted_sources[index - 1] + 1;
+    }
+
+    let range_end = min(source + 1, node_count);
+
+    for (var node = range_start; node < range_end; node += 1u) {
+        nodes_edge_offset[node] = index;
+    }
+
+    // The thread for the last edge also takes care of any trailing nodes without edges.
+    if index == edge_ref_count - 1 {
+        for (var node = source + 1; node < node_count; node += 1u) {
+            nodes_edge_offset[node] = edge_ref_count;
+        }
+    }
+}
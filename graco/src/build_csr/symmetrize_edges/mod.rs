@@ -0,0 +1,104 @@
+use empa::access_mode::ReadWrite;
+use empa::buffer;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, DispatchWorkgroups, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct SymmetrizeEdgesResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub edge_count: Uniform<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub edge_sources: Storage<'a, [u32], ReadWrite>,
+    #[resource(binding = 2, visibility = "COMPUTE")]
+    pub edge_targets: Storage<'a, [u32], ReadWrite>,
+    #[resource(binding = 3, visibility = "COMPUTE")]
+    pub edge_weights: Storage<'a, [u32], ReadWrite>,
+}
+
+type ResourcesLayout =
+    <SymmetrizeEdgesResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct SymmetrizeEdges {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+    dispatch_config: DispatchConfig,
+}
+
+impl SymmetrizeEdges {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        SymmetrizeEdges {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+            dispatch_config,
+        }
+    }
+
+    pub fn encode<U>(
+        &self,
+        encoder: CommandEncoder,
+        resources: SymmetrizeEdgesResources,
+        dispatch_indirect: bool,
+        dispatch: buffer::View<DispatchWorkgroups, U>,
+        fallback_count: u32,
+    ) -> CommandEncoder
+    where
+        U: buffer::Indirect,
+    {
+        let key = [
+            resources.edge_count.binding_key(),
+            resources.edge_sources.binding_key(),
+            resources.edge_targets.binding_key(),
+            resources.edge_weights.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        let encoder = encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group);
+
+        if dispatch_indirect {
+            encoder.dispatch_workgroups_indirect(dispatch).end()
+        } else {
+            encoder
+                .dispatch_workgroups(self.dispatch_config.workgroups(fallback_count))
+                .end()
+        }
+    }
+}
@@ -0,0 +1,38 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> edge_count: u32;
+
+@group(0) @binding(1)
+var<storage, read_write> edge_sources: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> edge_targets: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> edge_weights: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    // Note that `edge_count` is the edge count *after* symmetrization; the first half of the edge list holds the input
+    // edges, the second half will receive the reversed copies.
+    let input_edge_count = edge_count / 2;
+
+    if index >= input_edge_count {
+        return;
+    }
+
+    let reversed_index = input_edge_count + index;
+
+    // Every edge is copied, including self-loops and edges whose reverse is already part of the input; when duplicates
+    // are merged, the weights of these edges are therefore doubled (see `BuildCsrConfig::symmetrize`).
+
+    edge_sources[reversed_index] = edge_targets[index];
+    edge_targets[reversed_index] = edge_sources[index];
+    edge_weights[reversed_index] = edge_weights[index];
+}
@@ -0,0 +1,367 @@
+use std::cell::RefCell;
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::Buffer;
+use empa::command::{CommandEncoder, DispatchWorkgroups};
+use empa::device::Device;
+use empa::type_flag::{O, X};
+use empa_tk::prefix_sum::{PrefixSum, PrefixSumInput};
+
+use crate::coarsen_batch::count_graph_elements::{CountGraphElements, CountGraphElementsResources};
+use crate::coarsen_batch::finalize_graph_offsets::{
+    FinalizeGraphOffsets, FinalizeGraphOffsetsResources,
+};
+use crate::coarsen_batch::gather_coarse_graph_ids::{
+    GatherCoarseGraphIds, GatherCoarseGraphIdsResources,
+};
+use crate::coarsen_graph::generate_dispatches::{GenerateDispatches, GenerateDispatchesResources};
+use crate::context::Context;
+use crate::matching::{
+    MatchPairsByEdgeWeight, MatchPairsByEdgeWeightConfig, MatchPairsByEdgeWeightInput,
+    MatchPairsByEdgeWeightsCounts,
+};
+use crate::{CoarsenCounts, CoarsenGraph, CoarsenGraphOutput, CoarsenGraphWorkspaceInput};
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct CoarsenBatchConfig {
+    pub matching: MatchPairsByEdgeWeightConfig,
+}
+
+/// Input for [CoarsenBatch::encode].
+///
+/// The graph is the disjoint union of all graphs in the batch: no edge may connect nodes that
+/// belong to different graphs. `nodes_graph_id` stores the index of the graph each node belongs
+/// to; the nodes must be ordered by graph (graph IDs must be non-decreasing), e.g. by
+/// concatenating the node lists of the individual graphs.
+pub struct CoarsenBatchInput<'a, U0, U1, U2, U3> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    pub nodes_graph_id: buffer::View<'a, [u32], U3>,
+    pub counts: Option<CoarsenCounts<'a>>,
+}
+
+/// Output for [CoarsenBatch::encode].
+///
+/// The first group of buffers is identical to [CoarsenGraphOutput]. Coarse nodes (and their
+/// edges) remain ordered by graph, so `coarse_nodes_graph_id` may be used as the
+/// `nodes_graph_id` input for coarsening the next level.
+///
+/// The `graphs_*` buffers store a value for each graph in the batch; the length of
+/// `graphs_node_count` determines the number of graphs. A graph's coarse nodes occupy the range
+/// `graphs_node_offset[g]..graphs_node_offset[g] + graphs_node_count[g]` of the coarse node
+/// list, its coarse edge references occupy the range
+/// `graphs_edge_ref_offset[g]..graphs_edge_ref_offset[g] + graphs_edge_ref_count[g]` of the
+/// coarse edge list.
+pub struct CoarsenBatchOutput<'a, U0, U1, U2, U3, U4, U5, U6, U7, U8, U9, U10, U11, U12> {
+    pub fine_nodes_mapping: buffer::View<'a, [u32], U0>,
+    pub coarse_nodes_mapping_offset: buffer::View<'a, [u32], U1>,
+    pub coarse_nodes_mapping: buffer::View<'a, [u32], U2>,
+    pub coarse_node_count: buffer::View<'a, u32, U3>,
+    pub coarse_edge_ref_count: buffer::View<'a, u32, U4>,
+    pub coarse_nodes_edge_offset: buffer::View<'a, [u32], U5>,
+    pub coarse_nodes_edges: buffer::View<'a, [u32], U6>,
+    pub coarse_nodes_edge_weights: buffer::View<'a, [u32], U7>,
+    pub coarse_nodes_graph_id: buffer::View<'a, [u32], U8>,
+    pub graphs_node_count: buffer::View<'a, [u32], U9>,
+    pub graphs_node_offset: buffer::View<'a, [u32], U10>,
+    pub graphs_edge_ref_count: buffer::View<'a, [u32], U11>,
+    pub graphs_edge_ref_offset: buffer::View<'a, [u32], U12>,
+}
+
+/// Matches and coarsens a batch of graphs in a single dispatch sequence.
+///
+/// Encoding [MatchPairsByEdgeWeight] and [CoarsenGraph] separately for each of many small graphs
+/// is dominated by per-dispatch overhead. Since matching only pairs adjacent nodes, matching and
+/// coarsening the disjoint union of the graphs produces the same result as processing each graph
+/// individually. This additionally resolves the per-graph coarse node and edge counts and offsets,
+/// so that the coarse batch can be split into the individual coarse graphs without a round-trip
+/// to the host.
+pub struct CoarsenBatch {
+    device: Device,
+    matcher: MatchPairsByEdgeWeight,
+    coarsen_graph: CoarsenGraph,
+    generate_dispatches: Rc<GenerateDispatches>,
+    gather_coarse_graph_ids: Rc<GatherCoarseGraphIds>,
+    count_graph_elements: Rc<CountGraphElements>,
+    finalize_graph_offsets: Rc<FinalizeGraphOffsets>,
+    prefix_sum_inclusive: Rc<RefCell<PrefixSum<u32>>>,
+    node_count_dispatch: Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+    edge_ref_count_dispatch:
+        Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+    nodes_match: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+}
+
+impl CoarsenBatch {
+    pub async fn init(device: Device, config: CoarsenBatchConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](CoarsenBatch::init), but obtains the sub-pipelines from the given
+    /// `context`, so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context, config: CoarsenBatchConfig) -> Self {
+        let device = context.device().clone();
+
+        let (
+            matcher,
+            coarsen_graph,
+            generate_dispatches,
+            gather_coarse_graph_ids,
+            count_graph_elements,
+            finalize_graph_offsets,
+            prefix_sum_inclusive,
+        ) = join!(
+            MatchPairsByEdgeWeight::init_with_context(context, config.matching),
+            CoarsenGraph::init_with_context(context),
+            context.get_or_init_kernel(GenerateDispatches::init),
+            context.get_or_init_kernel(GatherCoarseGraphIds::init),
+            context.get_or_init_kernel(CountGraphElements::init),
+            context.get_or_init_kernel(FinalizeGraphOffsets::init),
+            context.get_or_init_mut("init_inclusive_u32", PrefixSum::init_inclusive_u32),
+        )
+        .await;
+
+        let node_count_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
+        let edge_ref_count_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
+        let nodes_match =
+            device.create_slice_buffer_zeroed(1, buffer::Usages::storage_binding().and_copy_dst());
+
+        CoarsenBatch {
+            device,
+            matcher,
+            coarsen_graph,
+            generate_dispatches,
+            gather_coarse_graph_ids,
+            count_graph_elements,
+            finalize_graph_offsets,
+            prefix_sum_inclusive,
+            node_count_dispatch,
+            edge_ref_count_dispatch,
+            nodes_match,
+        }
+    }
+
+    pub fn encode<U0, U1, U2, U3, U4, U5, U6, U7, U8, U9, U10, U11, U12, U13, U14, U15, U16>(
+        &mut self,
+        mut encoder: CommandEncoder,
+        input: CoarsenBatchInput<U0, U1, U2, U3>,
+        output: CoarsenBatchOutput<U4, U5, U6, U7, U8, U9, U10, U11, U12, U13, U14, U15, U16>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+        U4: buffer::StorageBinding,
+        U5: buffer::StorageBinding,
+        U6: buffer::StorageBinding,
+        U7: buffer::StorageBinding,
+        U8: buffer::StorageBinding,
+        U9: buffer::StorageBinding,
+        U10: buffer::StorageBinding,
+        U11: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U12: buffer::StorageBinding,
+        U13: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U14: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U15: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U16: buffer::StorageBinding + buffer::CopyDst + 'static,
+    {
+        let CoarsenBatchInput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            nodes_graph_id,
+            counts,
+        } = input;
+
+        let CoarsenBatchOutput {
+            fine_nodes_mapping,
+            coarse_nodes_mapping_offset,
+            coarse_nodes_mapping,
+            coarse_node_count,
+            coarse_edge_ref_count,
+            coarse_nodes_edge_offset,
+            coarse_nodes_edges,
+            coarse_nodes_edge_weights,
+            coarse_nodes_graph_id,
+            graphs_node_count,
+            graphs_node_offset,
+            graphs_edge_ref_count,
+            graphs_edge_ref_offset,
+        } = output;
+
+        let graph_count = graphs_node_count.len();
+
+        assert!(
+            graphs_node_offset.len() == graph_count
+                && graphs_edge_ref_count.len() == graph_count
+                && graphs_edge_ref_offset.len() == graph_count,
+            "the `graphs_*` buffers must all have the same length"
+        );
+
+        if counts.is_none() {
+            assert!(
+                nodes_graph_id.len() >= nodes_edge_offset.len(),
+                "`nodes_graph_id` must have a length of at least `{}`, but has a length of `{}`",
+                nodes_edge_offset.len(),
+                nodes_graph_id.len()
+            );
+            assert!(
+                coarse_nodes_graph_id.len() >= nodes_edge_offset.len(),
+                "`coarse_nodes_graph_id` must have a length of at least `{}`, but has a length \
+                of `{}`",
+                nodes_edge_offset.len(),
+                coarse_nodes_graph_id.len()
+            );
+        }
+
+        if self.nodes_match.len() < nodes_edge_offset.len() {
+            self.nodes_match = self
+                .device
+                .create_slice_buffer_zeroed(nodes_edge_offset.len(), self.nodes_match.usage());
+        }
+
+        let dispatch_indirect = counts.is_some();
+
+        // Because no edge connects nodes in different graphs, the matching never pairs nodes from
+        // different graphs, and therefore each coarse node also belongs to exactly 1 graph.
+        encoder = self.matcher.encode(
+            encoder,
+            MatchPairsByEdgeWeightInput {
+                nodes_edge_offset,
+                nodes_edges,
+                nodes_edge_weights,
+                count: counts.as_ref().map(|c| MatchPairsByEdgeWeightsCounts {
+                    node_count: c.node_count.clone(),
+                    edge_ref_count: c.edge_ref_count.clone(),
+                }),
+            },
+            self.nodes_match.view(),
+        );
+
+        // The coarse node index is determined by the lowest fine node index in a match, so if the
+        // fine nodes are ordered by graph, then the coarse nodes are also ordered by graph.
+        encoder = self.coarsen_graph.encode_with_workspace(
+            encoder,
+            CoarsenGraphWorkspaceInput {
+                fine_nodes_edge_offset: nodes_edge_offset,
+                fine_nodes_edges: nodes_edges,
+                fine_nodes_edge_weights: nodes_edge_weights,
+                fine_nodes_matching: self.nodes_match.view(),
+                edge_channels: &[],
+                counts: counts.as_ref().map(|c| CoarsenCounts {
+                    node_count: c.node_count.clone(),
+                    edge_ref_count: c.edge_ref_count.clone(),
+                }),
+            },
+            CoarsenGraphOutput {
+                fine_nodes_mapping,
+                coarse_nodes_mapping_offset,
+                coarse_nodes_mapping,
+                coarse_node_count,
+                coarse_edge_ref_count,
+                coarse_nodes_edge_offset,
+                coarse_nodes_edges,
+                coarse_nodes_edge_weights,
+                fine_edges_coarse_edge: None,
+            },
+        );
+
+        if let Some(counts) = counts.as_ref() {
+            encoder = self.generate_dispatches.encode(
+                encoder,
+                GenerateDispatchesResources {
+                    node_count: counts.node_count.clone(),
+                    edge_ref_count: counts.edge_ref_count.clone(),
+                    node_count_dispatch: self.node_count_dispatch.storage(),
+                    edge_ref_count_dispatch: self.edge_ref_count_dispatch.storage(),
+                },
+            );
+        }
+
+        let fallback_node_count = nodes_edge_offset.len() as u32;
+
+        // The coarse node count is only known on the device, but it is never greater than the fine
+        // node count; the following 2 stages are therefore dispatched for the fine node count and
+        // the shaders discard any invocations beyond the coarse node count.
+        encoder = self.gather_coarse_graph_ids.encode(
+            encoder,
+            GatherCoarseGraphIdsResources {
+                coarse_node_count: coarse_node_count.storage(),
+                nodes_graph_id: nodes_graph_id.storage(),
+                coarse_nodes_mapping_offset: coarse_nodes_mapping_offset.storage(),
+                coarse_nodes_mapping: coarse_nodes_mapping.storage(),
+                coarse_nodes_graph_id: coarse_nodes_graph_id.storage(),
+            },
+            dispatch_indirect,
+            self.node_count_dispatch.view(),
+            fallback_node_count,
+        );
+
+        encoder = encoder.clear_buffer_slice(graphs_node_count);
+        encoder = encoder.clear_buffer_slice(graphs_node_offset);
+        encoder = encoder.clear_buffer_slice(graphs_edge_ref_count);
+        encoder = encoder.clear_buffer_slice(graphs_edge_ref_offset);
+        encoder = self.count_graph_elements.encode(
+            encoder,
+            CountGraphElementsResources {
+                coarse_node_count: coarse_node_count.storage(),
+                coarse_edge_ref_count: coarse_edge_ref_count.storage(),
+                coarse_nodes_edge_offset: coarse_nodes_edge_offset.storage(),
+                coarse_nodes_graph_id: coarse_nodes_graph_id.storage(),
+                graphs_node_count: graphs_node_count.storage(),
+                graphs_node_offset: graphs_node_offset.storage(),
+                graphs_edge_ref_count: graphs_edge_ref_count.storage(),
+                graphs_edge_ref_offset: graphs_edge_ref_offset.storage(),
+            },
+            dispatch_indirect,
+            self.node_count_dispatch.view(),
+            fallback_node_count,
+        );
+
+        // The offset buffers now hold copies of the counts. We turn them into offsets with an
+        // inclusive prefix-sum, followed by subtracting each graph's own count.
+        encoder = self.prefix_sum_inclusive.borrow_mut().encode(
+            encoder,
+            PrefixSumInput {
+                data: graphs_node_offset,
+                count: None,
+            },
+        );
+        encoder = self.prefix_sum_inclusive.borrow_mut().encode(
+            encoder,
+            PrefixSumInput {
+                data: graphs_edge_ref_offset,
+                count: None,
+            },
+        );
+        encoder = self.finalize_graph_offsets.encode(
+            encoder,
+            FinalizeGraphOffsetsResources {
+                graphs_node_count: graphs_node_count.storage(),
+                graphs_node_offset: graphs_node_offset.storage(),
+                graphs_edge_ref_count: graphs_edge_ref_count.storage(),
+                graphs_edge_ref_offset: graphs_edge_ref_offset.storage(),
+            },
+            graph_count as u32,
+        );
+
+        encoder
+    }
+}
@@ -0,0 +1,57 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<storage, read> coarse_node_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> coarse_edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> coarse_nodes_edge_offset: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> coarse_nodes_graph_id: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> graphs_node_count: array<atomic<u32>>;
+
+@group(0) @binding(5)
+var<storage, read_write> graphs_node_offset: array<atomic<u32>>;
+
+@group(0) @binding(6)
+var<storage, read_write> graphs_edge_ref_count: array<atomic<u32>>;
+
+@group(0) @binding(7)
+var<storage, read_write> graphs_edge_ref_offset: array<atomic<u32>>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    // Note that this is dispatched for the fine node count, as the coarse node count is only known on the device.
+    if index >= coarse_node_count {
+        return;
+    }
+
+    let graph_id = coarse_nodes_graph_id[index];
+
+    let edges_start = coarse_nodes_edge_offset[index];
+
+    var edges_end = coarse_edge_ref_count;
+
+    if index < coarse_node_count - 1 {
+        edges_end = coarse_nodes_edge_offset[index + 1];
+    }
+
+    let edge_ref_count = edges_end - edges_start;
+
+    // The offset buffers receive a copy of the counts; a prefix-sum over these copies will later turn them into the
+    // offsets.
+    atomicAdd(&graphs_node_count[graph_id], 1u);
+    atomicAdd(&graphs_node_offset[graph_id], 1u);
+    atomicAdd(&graphs_edge_ref_count[graph_id], edge_ref_count);
+    atomicAdd(&graphs_edge_ref_offset[graph_id], edge_ref_count);
+}
@@ -0,0 +1,30 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<storage, read> graphs_node_count: array<u32>;
+
+@group(0) @binding(1)
+var<storage, read_write> graphs_node_offset: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> graphs_edge_ref_count: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> graphs_edge_ref_offset: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= arrayLength(&graphs_node_count) {
+        return;
+    }
+
+    // The offset buffers currently hold an inclusive prefix-sum over the counts; subtracting a graph's own count
+    // results in the offset at which the graph starts.
+    graphs_node_offset[index] -= graphs_node_count[index];
+    graphs_edge_ref_offset[index] -= graphs_edge_ref_count[index];
+}
@@ -0,0 +1,34 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<storage, read> coarse_node_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> nodes_graph_id: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> coarse_nodes_mapping_offset: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> coarse_nodes_mapping: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> coarse_nodes_graph_id: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    // Note that this is dispatched for the fine node count, as the coarse node count is only known on the device.
+    if index >= coarse_node_count {
+        return;
+    }
+
+    // All fine nodes that map to a coarse node belong to the same graph, so we only need to look at the first one.
+    let fine_node = coarse_nodes_mapping[coarse_nodes_mapping_offset[index]];
+
+    coarse_nodes_graph_id[index] = nodes_graph_id[fine_node];
+}
@@ -0,0 +1,8 @@
+mod count_graph_elements;
+mod finalize_graph_offsets;
+mod gather_coarse_graph_ids;
+
+mod coarsen_batch;
+pub use self::coarsen_batch::{
+    CoarsenBatch, CoarsenBatchConfig, CoarsenBatchInput, CoarsenBatchOutput,
+};
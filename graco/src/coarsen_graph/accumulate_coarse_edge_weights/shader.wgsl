@@ -0,0 +1,40 @@
+#include <src/dispatch.wgsl>
+
+// Must match `COARSE_EDGE_SELF_REFERENCE` in `coarsen_graph.rs`.
+const COARSE_EDGE_SELF_REFERENCE = 0xFFFFFFFFu;
+
+@group(0) @binding(0)
+var<uniform> count: u32;
+
+@group(0) @binding(1)
+var<storage, read> fine_nodes_edge_weights: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> fine_edges_coarse_edge: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> coarse_nodes_edge_weights: array<atomic<u32>>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= count {
+        return;
+    }
+
+    let coarse_edge = fine_edges_coarse_edge[index];
+
+    if coarse_edge != COARSE_EDGE_SELF_REFERENCE {
+        let weight = fine_nodes_edge_weights[index];
+        let previous = atomicAdd(&coarse_nodes_edge_weights[coarse_edge], weight);
+
+        // Saturates the summed weight in the same way as `collect_coarse_nodes_edge_weights`.
+        if previous + weight < previous {
+            atomicStore(&coarse_nodes_edge_weights[coarse_edge], 0xFFFFFFFFu);
+        }
+    }
+}
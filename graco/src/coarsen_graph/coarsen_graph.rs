@@ -1,7 +1,10 @@
+use std::cell::RefCell;
 use std::future::join;
+use std::rc::Rc;
 
+use empa::access_mode::ReadWrite;
 use empa::buffer;
-use empa::buffer::{Buffer, Uniform};
+use empa::buffer::{Buffer, Storage, Uniform};
 use empa::command::{CommandEncoder, DispatchWorkgroups};
 use empa::device::Device;
 use empa::type_flag::{O, X};
@@ -11,32 +14,109 @@ use empa_tk::prefix_sum::{PrefixSum, PrefixSumInput};
 use empa_tk::radix_sort::{RadixSortBy, RadixSortByInput};
 use empa_tk::scatter_by::{ScatterBy, ScatterByInput};
 
+use crate::coarsen_graph::accumulate_coarse_edge_weights::{
+    AccumulateCoarseEdgeWeights, AccumulateCoarseEdgeWeightsResources,
+};
 use crate::coarsen_graph::collect_coarse_nodes_edge_weights::{
     CollectCoarseNodesEdgeWeights, CollectCoarseNodesEdgeWeightsResources,
 };
+use crate::coarsen_graph::combine_coarse_edge_channel::{
+    CombineCoarseEdgeChannel, CombineCoarseEdgeChannelResources,
+};
 use crate::coarsen_graph::compact_coarse_edges::{CompactCoarseEdges, CompactCoarseEdgesResources};
 use crate::coarsen_graph::finalize_coarse_nodes_edge_offset::{
     FinalizeCoarseNodesEdgeOffset, FinalizeCoarseNodesEdgeOffsetResources,
 };
+use crate::coarsen_graph::find_coarse_nodes_edge_offset::{
+    FindCoarseNodesEdgeOffset, FindCoarseNodesEdgeOffsetResources,
+};
 use crate::coarsen_graph::gather_edge_owner_list::{
     GatherEdgeOwnerList, GatherEdgeOwnerListResources,
 };
 use crate::coarsen_graph::generate_dispatches::{GenerateDispatches, GenerateDispatchesResources};
 use crate::coarsen_graph::generate_index_list::{GenerateIndexList, GenerateIndexListResources};
+use crate::coarsen_graph::init_coarse_edge_channel::{
+    InitCoarseEdgeChannel, InitCoarseEdgeChannelResources,
+};
 use crate::coarsen_graph::mark_coarse_edge_validity::{
     MarkCoarseEdgeValidity, MarkCoarseEdgeValidityResources,
 };
 use crate::coarsen_graph::resolve_coarse_edge_ref_count::{
     ResolveCoarseEdgeRefCount, ResolveCoarseEdgeRefCountResources,
 };
-use crate::coarsen_graph::DEFAULT_GROUP_SIZE;
-use crate::counts_fallback::FallbackCounts;
+use crate::coarsen_graph::save_edge_permutation::{
+    SaveEdgePermutation, SaveEdgePermutationResources,
+};
+use crate::coarsen_graph::scatter_fine_edges_coarse_edge::{
+    ScatterFineEdgesCoarseEdge, ScatterFineEdgesCoarseEdgeResources,
+};
+use crate::context::Context;
+use crate::counts_fallback::{FallbackCounts, FallbackCountsCache};
+use crate::profiler::{profile_stage, profile_start, Profiler};
+
+/// The value written to [CoarsenGraphOutput::fine_edges_coarse_edge] for fine edges that don't map
+/// onto a coarse edge, because they became self-references.
+pub const COARSE_EDGE_SELF_REFERENCE: u32 = u32::MAX;
 
 pub struct CoarsenCounts<'a> {
     pub node_count: Uniform<'a, u32>,
     pub edge_ref_count: Uniform<'a, u32>,
 }
 
+/// The rule by which the values of a [CoarsenEdgeChannel] are combined when multiple fine edges
+/// (parallel edges) map onto the same coarse edge.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CombineRule {
+    /// The coarse edge value is the sum of the fine edge values.
+    Sum = 0,
+    /// The coarse edge value is the smallest of the fine edge values.
+    Min = 1,
+    /// The coarse edge value is the largest of the fine edge values.
+    Max = 2,
+}
+
+/// An additional per-edge attribute (e.g. a length, a count or a capacity) that is coarsened
+/// alongside the edge weights.
+///
+/// The channel's values are rearranged with the same sort permutation as the edges, and compacted
+/// with the same validity prefix-sum, such that `coarse_edge_values` lines up with the coarse
+/// level's `coarse_nodes_edges`. Values for edges that become self-references are dropped; values
+/// for parallel edges are combined according to the
+/// [combine_rule](CoarsenEdgeChannel::combine_rule).
+///
+/// The values are interpreted as `u32` values. Note that the bit patterns of non-negative `f32`
+/// values order the same as `u32` values, so [CombineRule::Min] and [CombineRule::Max] may also be
+/// used for non-negative `f32` values; [CombineRule::Sum] may not.
+///
+/// The `coarse_edge_values` buffer must be able to hold the fine level's edge ref count.
+pub struct CoarsenEdgeChannel<'a> {
+    fine_edge_values: Storage<'a, [u32]>,
+    coarse_edge_values: Storage<'a, [u32], ReadWrite>,
+    combine_rule: CombineRule,
+}
+
+impl<'a> CoarsenEdgeChannel<'a> {
+    pub fn new<U0, U1>(
+        fine_edge_values: buffer::View<'a, [u32], U0>,
+        coarse_edge_values: buffer::View<'a, [u32], U1>,
+        combine_rule: CombineRule,
+    ) -> Self
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+    {
+        CoarsenEdgeChannel {
+            fine_edge_values: fine_edge_values.storage(),
+            coarse_edge_values: coarse_edge_values.storage(),
+            combine_rule,
+        }
+    }
+
+    pub fn combine_rule(&self) -> CombineRule {
+        self.combine_rule
+    }
+}
+
 pub struct CoarsenGraphInput<'a, U0, U1, U2, U3, U4, U5> {
     pub fine_nodes_edge_offset: buffer::View<'a, [u32], U0>,
     pub fine_nodes_edges: buffer::View<'a, [u32], U1>,
@@ -44,6 +124,18 @@ pub struct CoarsenGraphInput<'a, U0, U1, U2, U3, U4, U5> {
     pub fine_nodes_matching: buffer::View<'a, [u32], U3>,
     pub temporary_storage_0: buffer::View<'a, [u32], U4>,
     pub temporary_storage_1: buffer::View<'a, [u32], U5>,
+    pub edge_channels: &'a [CoarsenEdgeChannel<'a>],
+    pub counts: Option<CoarsenCounts<'a>>,
+}
+
+/// Input for [CoarsenGraph::encode_with_workspace], which is identical to [CoarsenGraphInput],
+/// except that the temporary storage is managed by the [CoarsenGraph] instance.
+pub struct CoarsenGraphWorkspaceInput<'a, U0, U1, U2, U3> {
+    pub fine_nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub fine_nodes_edges: buffer::View<'a, [u32], U1>,
+    pub fine_nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    pub fine_nodes_matching: buffer::View<'a, [u32], U3>,
+    pub edge_channels: &'a [CoarsenEdgeChannel<'a>],
     pub counts: Option<CoarsenCounts<'a>>,
 }
 
@@ -55,33 +147,86 @@ pub struct CoarsenGraphOutput<'a, U0, U1, U2, U3, U4, U5, U6, U7> {
     pub coarse_edge_ref_count: buffer::View<'a, u32, U4>,
     pub coarse_nodes_edge_offset: buffer::View<'a, [u32], U5>,
     pub coarse_nodes_edges: buffer::View<'a, [u32], U6>,
+    /// The weight of each coarse edge is the sum of the weights of the fine edges that map onto
+    /// it, saturated at `u32::MAX`.
     pub coarse_nodes_edge_weights: buffer::View<'a, [u32], U7>,
+    /// Optional output that receives, for each fine edge, the index of the coarse edge it was
+    /// merged into, or [COARSE_EDGE_SELF_REFERENCE] if the edge became a self-reference and was
+    /// dropped. Must be able to hold the fine level's edge ref count.
+    ///
+    /// This may be used to aggregate edge attributes on the user side, or to project per-edge
+    /// results from the coarse level back onto the fine level.
+    pub fine_edges_coarse_edge: Option<Storage<'a, [u32], ReadWrite>>,
+}
+
+/// Input for [CoarsenGraph::encode_directed_companion].
+///
+/// The `fine_nodes_edge_offset`, `fine_nodes_edges` and `fine_nodes_edge_weights` buffers describe
+/// the in-arcs of the fine level graph in compressed sparse column (CSC) form: for each node, the
+/// edge list holds the indices of the nodes that have an arc pointing to that node. The
+/// `fine_nodes_mapping` must be the mapping that was produced by coarsening the out-arcs of the
+/// same graph with [CoarsenGraph::encode_directed].
+pub struct CoarsenCompanionEdgesInput<'a, U0, U1, U2, U3, U4, U5> {
+    pub fine_nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub fine_nodes_edges: buffer::View<'a, [u32], U1>,
+    pub fine_nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    pub fine_nodes_mapping: buffer::View<'a, [u32], U3>,
+    pub temporary_storage_0: buffer::View<'a, [u32], U4>,
+    pub temporary_storage_1: buffer::View<'a, [u32], U5>,
+    pub edge_channels: &'a [CoarsenEdgeChannel<'a>],
+    pub counts: Option<CoarsenCounts<'a>>,
+}
+
+/// Output for [CoarsenGraph::encode_directed_companion], the coarse level's in-arcs in CSC form.
+pub struct CoarsenCompanionEdgesOutput<'a, U0, U1, U2, U3> {
+    pub coarse_edge_ref_count: buffer::View<'a, u32, U0>,
+    pub coarse_nodes_edge_offset: buffer::View<'a, [u32], U1>,
+    pub coarse_nodes_edges: buffer::View<'a, [u32], U2>,
+    pub coarse_nodes_edge_weights: buffer::View<'a, [u32], U3>,
+    /// See [CoarsenGraphOutput::fine_edges_coarse_edge].
+    pub fine_edges_coarse_edge: Option<Storage<'a, [u32], ReadWrite>>,
+}
+
+/// Input for [CoarsenGraph::recompute_weights].
+pub struct RecomputeWeightsInput<'a, U0, U1> {
+    pub fine_nodes_edge_weights: buffer::View<'a, [u32], U0>,
+    /// The [fine_edges_coarse_edge](CoarsenGraphOutput::fine_edges_coarse_edge) mapping that was
+    /// produced by a previous coarsening of the same fine level graph.
+    pub fine_edges_coarse_edge: buffer::View<'a, [u32], U1>,
+    pub counts: Option<CoarsenCounts<'a>>,
 }
 
 pub struct CoarsenGraph {
     device: Device,
-    generate_dispatches: GenerateDispatches,
-    generate_index_list: GenerateIndexList,
-    gather_edge_owner_list: GatherEdgeOwnerList,
-    mark_coarse_edge_validity: MarkCoarseEdgeValidity,
-    collect_coarse_nodes_edge_weights: CollectCoarseNodesEdgeWeights,
-    compact_coarse_edges: CompactCoarseEdges,
-    resolve_coarse_edge_ref_count: ResolveCoarseEdgeRefCount,
-    finalize_coarse_nodes_edge_offset: FinalizeCoarseNodesEdgeOffset,
-    sort_by: RadixSortBy<u32, u32>,
-    find_runs: FindRuns<u32>,
-    scatter_by: ScatterBy<u32, u32>,
-    gather_by: GatherBy<u32, u32>,
-    prefix_sum_inclusive: PrefixSum<u32>,
-    group_size: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
-    edge_run_count: Buffer<u32, buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    counts_cache: FallbackCountsCache,
+    generate_dispatches: Rc<GenerateDispatches>,
+    generate_index_list: Rc<GenerateIndexList>,
+    sort_by: Rc<RefCell<RadixSortBy<u32, u32>>>,
+    find_runs: Rc<RefCell<FindRuns<u32>>>,
+    scatter_by: Rc<RefCell<ScatterBy<u32, u32>>>,
+    accumulate_coarse_edge_weights: Rc<AccumulateCoarseEdgeWeights>,
+    edge_list: EdgeListCoarsening,
     node_count_dispatch: Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
     edge_ref_count_dispatch:
         Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+    workspace: Option<Workspace>,
+}
+
+struct Workspace {
+    temporary_storage_0: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    temporary_storage_1: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
 }
 
 impl CoarsenGraph {
     pub async fn init(device: Device) -> Self {
+        Self::init_with_context(&Context::new(device)).await
+    }
+
+    /// Same as [init](CoarsenGraph::init), but obtains the sub-pipelines from the given `context`,
+    /// so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context) -> Self {
+        let device = context.device().clone();
+
         let (
             generate_dispatches,
             generate_index_list,
@@ -91,32 +236,41 @@ impl CoarsenGraph {
             compact_coarse_edges,
             resolve_coarse_edge_ref_count,
             finalize_coarse_nodes_edge_offset,
+            find_coarse_nodes_edge_offset,
+            save_edge_permutation,
+            init_coarse_edge_channel,
+            combine_coarse_edge_channel,
+            scatter_fine_edges_coarse_edge,
+            accumulate_coarse_edge_weights,
             sort_by,
             find_runs,
             scatter_by,
             gather_by,
             prefix_sum_inclusive,
         ) = join!(
-            GenerateDispatches::init(device.clone()),
-            GenerateIndexList::init(device.clone()),
-            GatherEdgeOwnerList::init(device.clone()),
-            MarkCoarseEdgeValidity::init(device.clone()),
-            CollectCoarseNodesEdgeWeights::init(device.clone()),
-            CompactCoarseEdges::init(device.clone()),
-            ResolveCoarseEdgeRefCount::init(device.clone()),
-            FinalizeCoarseNodesEdgeOffset::init(device.clone()),
-            RadixSortBy::init_u32(device.clone()),
-            FindRuns::init_u32(device.clone()),
-            ScatterBy::init_u32(device.clone()),
-            GatherBy::init_u32(device.clone()),
-            PrefixSum::init_inclusive_u32(device.clone()),
+            context.get_or_init_kernel(GenerateDispatches::init),
+            context.get_or_init_kernel(GenerateIndexList::init),
+            context.get_or_init_kernel(GatherEdgeOwnerList::init),
+            context.get_or_init_kernel(MarkCoarseEdgeValidity::init),
+            context.get_or_init_kernel(CollectCoarseNodesEdgeWeights::init),
+            context.get_or_init_kernel(CompactCoarseEdges::init),
+            context.get_or_init("init", ResolveCoarseEdgeRefCount::init),
+            context.get_or_init_kernel(FinalizeCoarseNodesEdgeOffset::init),
+            context.get_or_init_kernel(FindCoarseNodesEdgeOffset::init),
+            context.get_or_init_kernel(SaveEdgePermutation::init),
+            context.get_or_init_kernel(InitCoarseEdgeChannel::init),
+            context.get_or_init_kernel(CombineCoarseEdgeChannel::init),
+            context.get_or_init_kernel(ScatterFineEdgesCoarseEdge::init),
+            context.get_or_init_kernel(AccumulateCoarseEdgeWeights::init),
+            context.get_or_init_mut("init_u32", RadixSortBy::init_u32),
+            context.get_or_init_mut("init_u32", FindRuns::init_u32),
+            context.get_or_init_mut("init_u32", ScatterBy::init_u32),
+            context.get_or_init_mut("init_u32", GatherBy::init_u32),
+            context.get_or_init_mut("init_inclusive_u32", PrefixSum::init_inclusive_u32),
         )
         .await;
 
-        let group_size =
-            device.create_buffer(DEFAULT_GROUP_SIZE, buffer::Usages::uniform_binding());
-        let edge_run_count =
-            device.create_buffer(DEFAULT_GROUP_SIZE, buffer::Usages::storage_binding());
+        let edge_run_count = device.create_buffer(0, buffer::Usages::storage_binding());
         let node_count_dispatch = device.create_buffer(
             DispatchWorkgroups {
                 count_x: 1,
@@ -134,33 +288,459 @@ impl CoarsenGraph {
             buffer::Usages::storage_binding().and_indirect(),
         );
 
-        CoarsenGraph {
-            device,
-            generate_dispatches,
-            generate_index_list,
+        let counts_cache = FallbackCountsCache::new(device.clone());
+
+        let combine_rules = [CombineRule::Sum, CombineRule::Min, CombineRule::Max]
+            .into_iter()
+            .map(|rule| device.create_buffer(rule as u32, buffer::Usages::uniform_binding()))
+            .collect();
+
+        let edge_list = EdgeListCoarsening {
+            device: device.clone(),
+            generate_index_list: generate_index_list.clone(),
             gather_edge_owner_list,
             mark_coarse_edge_validity,
             collect_coarse_nodes_edge_weights,
             compact_coarse_edges,
             resolve_coarse_edge_ref_count,
             finalize_coarse_nodes_edge_offset,
-            sort_by,
-            find_runs,
-            scatter_by,
+            find_coarse_nodes_edge_offset,
+            save_edge_permutation,
+            init_coarse_edge_channel,
+            combine_coarse_edge_channel,
+            scatter_fine_edges_coarse_edge,
+            sort_by: sort_by.clone(),
+            find_runs: find_runs.clone(),
             gather_by,
             prefix_sum_inclusive,
-            group_size,
             edge_run_count,
+            combine_rules,
+            saved_edge_permutation: None,
+        };
+
+        CoarsenGraph {
+            device,
+            counts_cache,
+            generate_dispatches,
+            generate_index_list,
+            sort_by,
+            find_runs,
+            scatter_by,
+            accumulate_coarse_edge_weights,
+            edge_list,
             node_count_dispatch,
             edge_ref_count_dispatch,
+            workspace: None,
         }
     }
 
+    /// Returns the minimum length of each of the
+    /// [temporary_storage_0](CoarsenGraphInput::temporary_storage_0) and
+    /// [temporary_storage_1](CoarsenGraphInput::temporary_storage_1) buffers for a fine level
+    /// graph with the given `node_count` and `edge_ref_count`.
+    ///
+    /// Note that the fine level's node count and edge ref count are derived from the lengths of
+    /// the `fine_nodes_edge_offset` and `fine_nodes_edges` buffers respectively, even if
+    /// [counts](CoarsenGraphInput::counts) are provided.
+    pub fn required_temporary_storage(node_count: usize, edge_ref_count: usize) -> usize {
+        // The temporary storage buffers are first used to sort the nodes and then to sort the
+        // edges.
+        node_count.max(edge_ref_count)
+    }
+
     pub fn encode<U0, U1, U2, U3, U4, U5, U6, U7, U8, U9, U10, U11, U12, U13>(
+        &mut self,
+        encoder: CommandEncoder,
+        input: CoarsenGraphInput<U0, U1, U2, U3, U4, U5>,
+        output: CoarsenGraphOutput<U6, U7, U8, U9, U10, U11, U12, U13>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+        U4: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U5: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U6: buffer::StorageBinding,
+        U7: buffer::StorageBinding,
+        U8: buffer::StorageBinding,
+        U9: buffer::StorageBinding,
+        U10: buffer::StorageBinding,
+        U11: buffer::StorageBinding,
+        U12: buffer::StorageBinding,
+        U13: buffer::StorageBinding + buffer::CopyDst + 'static,
+    {
+        self.encode_internal(encoder, input, output, EdgeMode::Undirected, None)
+    }
+
+    /// Same as [encode](CoarsenGraph::encode), but records a timestamp on the `profiler` after
+    /// each stage of the coarsening algorithm.
+    pub fn encode_profiled<U0, U1, U2, U3, U4, U5, U6, U7, U8, U9, U10, U11, U12, U13>(
+        &mut self,
+        encoder: CommandEncoder,
+        input: CoarsenGraphInput<U0, U1, U2, U3, U4, U5>,
+        output: CoarsenGraphOutput<U6, U7, U8, U9, U10, U11, U12, U13>,
+        profiler: &mut Profiler,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+        U4: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U5: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U6: buffer::StorageBinding,
+        U7: buffer::StorageBinding,
+        U8: buffer::StorageBinding,
+        U9: buffer::StorageBinding,
+        U10: buffer::StorageBinding,
+        U11: buffer::StorageBinding,
+        U12: buffer::StorageBinding,
+        U13: buffer::StorageBinding + buffer::CopyDst + 'static,
+    {
+        self.encode_internal(encoder, input, output, EdgeMode::Undirected, Some(profiler))
+    }
+
+    /// Same as [encode](CoarsenGraph::encode), but rather than requiring the caller to provide
+    /// temporary storage, uses temporary storage owned by this [CoarsenGraph] instance.
+    ///
+    /// The temporary storage is allocated on first use and grows as required; it is retained for
+    /// subsequent calls.
+    pub fn encode_with_workspace<U0, U1, U2, U3, U4, U5, U6, U7, U8, U9, U10, U11>(
+        &mut self,
+        encoder: CommandEncoder,
+        input: CoarsenGraphWorkspaceInput<U0, U1, U2, U3>,
+        output: CoarsenGraphOutput<U4, U5, U6, U7, U8, U9, U10, U11>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+        U4: buffer::StorageBinding,
+        U5: buffer::StorageBinding,
+        U6: buffer::StorageBinding,
+        U7: buffer::StorageBinding,
+        U8: buffer::StorageBinding,
+        U9: buffer::StorageBinding,
+        U10: buffer::StorageBinding,
+        U11: buffer::StorageBinding + buffer::CopyDst + 'static,
+    {
+        let CoarsenGraphWorkspaceInput {
+            fine_nodes_edge_offset,
+            fine_nodes_edges,
+            fine_nodes_edge_weights,
+            fine_nodes_matching,
+            edge_channels,
+            counts,
+        } = input;
+
+        let required =
+            Self::required_temporary_storage(fine_nodes_edge_offset.len(), fine_nodes_edges.len());
+
+        // Take the workspace out of `self` for the duration of the encoding, so that we can
+        // borrow its buffers while also borrowing `self` mutably.
+        let workspace = match self.workspace.take() {
+            Some(workspace) if workspace.temporary_storage_0.len() >= required => workspace,
+            _ => Workspace {
+                temporary_storage_0: self.device.create_slice_buffer_zeroed(
+                    required,
+                    buffer::Usages::storage_binding().and_copy_dst(),
+                ),
+                temporary_storage_1: self.device.create_slice_buffer_zeroed(
+                    required,
+                    buffer::Usages::storage_binding().and_copy_dst(),
+                ),
+            },
+        };
+
+        let encoder = self.encode_internal(
+            encoder,
+            CoarsenGraphInput {
+                fine_nodes_edge_offset,
+                fine_nodes_edges,
+                fine_nodes_edge_weights,
+                fine_nodes_matching,
+                temporary_storage_0: workspace.temporary_storage_0.view(),
+                temporary_storage_1: workspace.temporary_storage_1.view(),
+                edge_channels,
+                counts,
+            },
+            output,
+            EdgeMode::Undirected,
+            None,
+        );
+
+        self.workspace = Some(workspace);
+
+        encoder
+    }
+
+    /// Same as [encode](CoarsenGraph::encode), but treats the graph as a directed graph, where the
+    /// edge lists hold each node's out-arcs.
+    ///
+    /// Unlike the undirected mode, this does not assume that each arc is also stored in the
+    /// reverse direction: parallel arcs between 2 coarse nodes are combined into a single coarse
+    /// arc (summing their weights), arcs that become self-references are dropped, and coarse nodes
+    /// without out-arcs are allowed. The direction of the arcs is preserved in the coarse graph.
+    ///
+    /// The `fine_nodes_matching` should be obtained by matching on a symmetrised view of the
+    /// directed graph (e.g. by building it with [BuildCsr](crate::BuildCsr) with
+    /// [symmetrize](crate::BuildCsrConfig::symmetrize) enabled), as the matching algorithms require
+    /// that matched nodes are adjacent in both directions. If the in-arcs are also required on the
+    /// coarse level, use [encode_directed_companion](CoarsenGraph::encode_directed_companion)
+    /// afterwards to coarsen the graph's compressed sparse column (CSC) companion.
+    pub fn encode_directed<U0, U1, U2, U3, U4, U5, U6, U7, U8, U9, U10, U11, U12, U13>(
+        &mut self,
+        encoder: CommandEncoder,
+        input: CoarsenGraphInput<U0, U1, U2, U3, U4, U5>,
+        output: CoarsenGraphOutput<U6, U7, U8, U9, U10, U11, U12, U13>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+        U4: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U5: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U6: buffer::StorageBinding,
+        U7: buffer::StorageBinding,
+        U8: buffer::StorageBinding,
+        U9: buffer::StorageBinding,
+        U10: buffer::StorageBinding,
+        U11: buffer::StorageBinding,
+        U12: buffer::StorageBinding,
+        U13: buffer::StorageBinding + buffer::CopyDst + 'static,
+    {
+        self.encode_internal(encoder, input, output, EdgeMode::Directed, None)
+    }
+
+    /// Coarsens the in-arcs of a directed graph, given in compressed sparse column (CSC) form,
+    /// using the `fine_nodes_mapping` that was produced by a prior call to
+    /// [encode_directed](CoarsenGraph::encode_directed) for the out-arcs of the same graph.
+    ///
+    /// Parallel in-arcs are combined and in-arcs that become self-references are dropped, in the
+    /// same way as for the out-arcs, such that the output is the CSC companion of the coarse graph
+    /// produced by [encode_directed](CoarsenGraph::encode_directed).
+    ///
+    /// The temporary storage requirements are the same as for
+    /// [encode](CoarsenGraph::encode); the `coarse_nodes_edges` and `coarse_nodes_edge_weights`
+    /// buffers must be able to hold the fine in-arc count.
+    pub fn encode_directed_companion<U0, U1, U2, U3, U4, U5, U6, U7, U8, U9>(
+        &mut self,
+        mut encoder: CommandEncoder,
+        input: CoarsenCompanionEdgesInput<U0, U1, U2, U3, U4, U5>,
+        output: CoarsenCompanionEdgesOutput<U6, U7, U8, U9>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+        U4: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U5: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U6: buffer::StorageBinding,
+        U7: buffer::StorageBinding,
+        U8: buffer::StorageBinding,
+        U9: buffer::StorageBinding + buffer::CopyDst + 'static,
+    {
+        let CoarsenCompanionEdgesInput {
+            fine_nodes_edge_offset,
+            fine_nodes_edges,
+            fine_nodes_edge_weights,
+            fine_nodes_mapping,
+            temporary_storage_0,
+            temporary_storage_1,
+            edge_channels,
+            counts,
+        } = input;
+
+        let CoarsenCompanionEdgesOutput {
+            coarse_edge_ref_count,
+            coarse_nodes_edge_offset,
+            coarse_nodes_edges,
+            coarse_nodes_edge_weights,
+            fine_edges_coarse_edge,
+        } = output;
+
+        let node_capacity = fine_nodes_edge_offset.len();
+        let edge_ref_capacity = fine_nodes_edges.len();
+        let temporary_capacity = Self::required_temporary_storage(node_capacity, edge_ref_capacity);
+
+        assert_capacity(
+            "temporary_storage_0",
+            temporary_storage_0.len(),
+            temporary_capacity,
+        );
+        assert_capacity(
+            "temporary_storage_1",
+            temporary_storage_1.len(),
+            temporary_capacity,
+        );
+
+        if counts.is_none() {
+            assert_capacity(
+                "fine_nodes_edge_weights",
+                fine_nodes_edge_weights.len(),
+                edge_ref_capacity,
+            );
+            assert_capacity(
+                "fine_nodes_mapping",
+                fine_nodes_mapping.len(),
+                node_capacity,
+            );
+            assert_capacity(
+                "coarse_nodes_edge_offset",
+                coarse_nodes_edge_offset.len(),
+                node_capacity,
+            );
+            assert_capacity(
+                "coarse_nodes_edges",
+                coarse_nodes_edges.len(),
+                edge_ref_capacity,
+            );
+            assert_capacity(
+                "coarse_nodes_edge_weights",
+                coarse_nodes_edge_weights.len(),
+                edge_ref_capacity,
+            );
+        }
+
+        let dispatch_indirect = counts.is_some();
+
+        if let Some(counts) = counts.as_ref() {
+            encoder = self.generate_dispatches.encode(
+                encoder,
+                GenerateDispatchesResources {
+                    node_count: counts.node_count.clone(),
+                    edge_ref_count: counts.edge_ref_count.clone(),
+                    node_count_dispatch: self.node_count_dispatch.storage(),
+                    edge_ref_count_dispatch: self.edge_ref_count_dispatch.storage(),
+                },
+            );
+        }
+
+        let fallback_node_count = fine_nodes_edge_offset.len() as u32;
+        let fallback_edge_ref_count = fine_nodes_edges.len() as u32;
+        let counts_fallback = self.counts_cache.get(
+            counts.map(|c| (c.node_count, c.edge_ref_count)),
+            (fallback_node_count, fallback_edge_ref_count),
+        );
+
+        self.edge_list.encode(
+            encoder,
+            EdgeListInput {
+                fine_nodes_edge_offset,
+                fine_nodes_edges,
+                fine_nodes_edge_weights,
+                fine_nodes_mapping,
+                temporary_storage_0,
+                temporary_storage_1,
+                edge_channels,
+            },
+            EdgeListOutput {
+                coarse_edge_ref_count,
+                coarse_nodes_edge_offset,
+                coarse_nodes_edges,
+                coarse_nodes_edge_weights,
+                fine_edges_coarse_edge,
+            },
+            EdgeListCounts {
+                fallback: &counts_fallback,
+                dispatch_indirect,
+                node_count_dispatch: &self.node_count_dispatch,
+                edge_ref_count_dispatch: &self.edge_ref_count_dispatch,
+                fallback_node_count,
+                fallback_edge_ref_count,
+            },
+            EdgeMode::Directed,
+            &mut None,
+        )
+    }
+
+    /// Recomputes the coarse level's edge weights for updated fine level edge weights, reusing the
+    /// coarse graph structure from a previous coarsening of the same fine level graph.
+    ///
+    /// This is a fast path for when only the fine edge weights have changed since the graph was
+    /// coarsened: rather than rerunning the full coarsening (which includes 2 device-wide radix
+    /// sorts), it only re-accumulates the fine edge weights into the coarse edge weights, using
+    /// the [fine_edges_coarse_edge](CoarsenGraphOutput::fine_edges_coarse_edge) mapping that was
+    /// output by the previous coarsening. Note that this does not recompute the matching, so the
+    /// coarse graph structure may differ from what a full rerun with the updated weights would
+    /// produce.
+    ///
+    /// The `counts`, if provided, must be the fine level counts. The `coarse_nodes_edge_weights`
+    /// buffer is cleared before the weights are accumulated.
+    pub fn recompute_weights<U0, U1, U2>(
+        &mut self,
+        mut encoder: CommandEncoder,
+        input: RecomputeWeightsInput<U0, U1>,
+        coarse_nodes_edge_weights: buffer::View<[u32], U2>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding + buffer::CopyDst + 'static,
+    {
+        let RecomputeWeightsInput {
+            fine_nodes_edge_weights,
+            fine_edges_coarse_edge,
+            counts,
+        } = input;
+
+        if counts.is_none() {
+            assert_capacity(
+                "fine_nodes_edge_weights",
+                fine_nodes_edge_weights.len(),
+                fine_edges_coarse_edge.len(),
+            );
+        }
+
+        let dispatch_indirect = counts.is_some();
+
+        if let Some(counts) = counts.as_ref() {
+            encoder = self.generate_dispatches.encode(
+                encoder,
+                GenerateDispatchesResources {
+                    node_count: counts.node_count.clone(),
+                    edge_ref_count: counts.edge_ref_count.clone(),
+                    node_count_dispatch: self.node_count_dispatch.storage(),
+                    edge_ref_count_dispatch: self.edge_ref_count_dispatch.storage(),
+                },
+            );
+        }
+
+        // We don't know the fine node count here, but we only use the edge ref count.
+        let fallback_edge_ref_count = fine_edges_coarse_edge.len() as u32;
+        let counts_fallback = self.counts_cache.get(
+            counts.map(|c| (c.node_count, c.edge_ref_count)),
+            (0, fallback_edge_ref_count),
+        );
+
+        encoder = encoder.clear_buffer_slice(coarse_nodes_edge_weights);
+        encoder = self.accumulate_coarse_edge_weights.encode(
+            encoder,
+            AccumulateCoarseEdgeWeightsResources {
+                count: counts_fallback.edge_ref_count(),
+                fine_nodes_edge_weights: fine_nodes_edge_weights.storage(),
+                fine_edges_coarse_edge: fine_edges_coarse_edge.storage(),
+                coarse_nodes_edge_weights: coarse_nodes_edge_weights.storage(),
+            },
+            dispatch_indirect,
+            self.edge_ref_count_dispatch.view(),
+            fallback_edge_ref_count,
+        );
+
+        encoder
+    }
+
+    fn encode_internal<U0, U1, U2, U3, U4, U5, U6, U7, U8, U9, U10, U11, U12, U13>(
         &mut self,
         mut encoder: CommandEncoder,
         input: CoarsenGraphInput<U0, U1, U2, U3, U4, U5>,
         output: CoarsenGraphOutput<U6, U7, U8, U9, U10, U11, U12, U13>,
+        mode: EdgeMode,
+        mut profiler: Option<&mut Profiler>,
     ) -> CommandEncoder
     where
         U0: buffer::StorageBinding,
@@ -199,6 +779,7 @@ impl CoarsenGraph {
             fine_nodes_matching,
             temporary_storage_0,
             temporary_storage_1,
+            edge_channels,
             counts,
         } = input;
 
@@ -211,28 +792,95 @@ impl CoarsenGraph {
             coarse_nodes_edge_offset,
             coarse_nodes_edges,
             coarse_nodes_edge_weights,
+            fine_edges_coarse_edge,
         } = output;
 
+        // The temporary storage requirement is based on the lengths of the fine level buffers,
+        // regardless of whether the counts are provided (see `required_temporary_storage`).
+        let node_capacity = fine_nodes_edge_offset.len();
+        let edge_ref_capacity = fine_nodes_edges.len();
+        let temporary_capacity = Self::required_temporary_storage(node_capacity, edge_ref_capacity);
+
+        assert_capacity(
+            "temporary_storage_0",
+            temporary_storage_0.len(),
+            temporary_capacity,
+        );
+        assert_capacity(
+            "temporary_storage_1",
+            temporary_storage_1.len(),
+            temporary_capacity,
+        );
+
+        // If the counts are provided, then the actual node count and edge ref count are only
+        // known on the device and may be smaller than the lengths of the fine level buffers. We
+        // can therefore only validate the other buffers if the counts are not provided.
+        if counts.is_none() {
+            assert_capacity(
+                "fine_nodes_matching",
+                fine_nodes_matching.len(),
+                node_capacity,
+            );
+            assert_capacity(
+                "fine_nodes_edge_weights",
+                fine_nodes_edge_weights.len(),
+                edge_ref_capacity,
+            );
+            assert_capacity(
+                "fine_nodes_mapping",
+                fine_nodes_mapping.len(),
+                node_capacity,
+            );
+            assert_capacity(
+                "coarse_nodes_mapping_offset",
+                coarse_nodes_mapping_offset.len(),
+                node_capacity,
+            );
+            assert_capacity(
+                "coarse_nodes_mapping",
+                coarse_nodes_mapping.len(),
+                node_capacity,
+            );
+            assert_capacity(
+                "coarse_nodes_edge_offset",
+                coarse_nodes_edge_offset.len(),
+                node_capacity,
+            );
+            // The coarse edge buffers double as temporary storage during coarsening, so they must
+            // be able to hold the fine edge ref count, not just the coarse edge ref count.
+            assert_capacity(
+                "coarse_nodes_edges",
+                coarse_nodes_edges.len(),
+                edge_ref_capacity,
+            );
+            assert_capacity(
+                "coarse_nodes_edge_weights",
+                coarse_nodes_edge_weights.len(),
+                edge_ref_capacity,
+            );
+        }
+
         let dispatch_indirect = counts.is_some();
 
+        encoder = profile_start(&mut profiler, encoder);
+
         if let Some(counts) = counts.as_ref() {
             encoder = self.generate_dispatches.encode(
                 encoder,
                 GenerateDispatchesResources {
-                    group_size: self.group_size.uniform(),
                     node_count: counts.node_count.clone(),
                     edge_ref_count: counts.edge_ref_count.clone(),
                     node_count_dispatch: self.node_count_dispatch.storage(),
                     edge_ref_count_dispatch: self.edge_ref_count_dispatch.storage(),
                 },
             );
+            encoder = profile_stage(&mut profiler, encoder, "generate dispatches");
         }
 
         let fallback_node_count = fine_nodes_edge_offset.len() as u32;
         let fallback_edge_ref_count = fine_nodes_edges.len() as u32;
-        let counts_fallback = FallbackCounts::new(
+        let counts_fallback = self.counts_cache.get(
             counts.map(|c| (c.node_count, c.edge_ref_count)),
-            &self.device,
             (fallback_node_count, fallback_edge_ref_count),
         );
 
@@ -247,7 +895,7 @@ impl CoarsenGraph {
             fallback_node_count,
         );
 
-        encoder = self.sort_by.encode(
+        encoder = self.sort_by.borrow_mut().encode(
             encoder,
             RadixSortByInput {
                 keys: fine_nodes_matching,
@@ -257,10 +905,11 @@ impl CoarsenGraph {
                 count: Some(counts_fallback.node_count()),
             },
         );
+        encoder = profile_stage(&mut profiler, encoder, "sort nodes by matching");
 
         let run_mapping = temporary_storage_0;
 
-        encoder = self.find_runs.encode(
+        encoder = self.find_runs.borrow_mut().encode(
             encoder,
             FindRunsInput {
                 data: fine_nodes_matching,
@@ -272,8 +921,9 @@ impl CoarsenGraph {
                 run_mapping,
             },
         );
+        encoder = profile_stage(&mut profiler, encoder, "find coarse nodes");
 
-        encoder = self.scatter_by.encode(
+        encoder = self.scatter_by.borrow_mut().encode(
             encoder,
             ScatterByInput {
                 scatter_by: coarse_nodes_mapping,
@@ -282,11 +932,176 @@ impl CoarsenGraph {
             },
             fine_nodes_mapping,
         );
+        encoder = profile_stage(&mut profiler, encoder, "scatter fine nodes mapping");
 
         // We now have both a mapping from fine nodes to coarse nodes (`fine_nodes_mapping`) and
         // a mapping from coarse nodes to fine nodes (`coarse_nodes_mapping_offset` in combination
         // with `coarse_nodes_mapping`); we're now ready to construct the edge lists.
 
+        encoder = self.edge_list.encode(
+            encoder,
+            EdgeListInput {
+                fine_nodes_edge_offset,
+                fine_nodes_edges,
+                fine_nodes_edge_weights,
+                fine_nodes_mapping,
+                temporary_storage_0,
+                temporary_storage_1,
+                edge_channels,
+            },
+            EdgeListOutput {
+                coarse_edge_ref_count,
+                coarse_nodes_edge_offset,
+                coarse_nodes_edges,
+                coarse_nodes_edge_weights,
+                fine_edges_coarse_edge,
+            },
+            EdgeListCounts {
+                fallback: &counts_fallback,
+                dispatch_indirect,
+                node_count_dispatch: &self.node_count_dispatch,
+                edge_ref_count_dispatch: &self.edge_ref_count_dispatch,
+                fallback_node_count,
+                fallback_edge_ref_count,
+            },
+            mode,
+            &mut profiler,
+        );
+
+        // And we're done!
+
+        encoder
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EdgeMode {
+    Undirected,
+    Directed,
+}
+
+struct EdgeListInput<'a, U0, U1, U2, U3, U4, U5> {
+    fine_nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    fine_nodes_edges: buffer::View<'a, [u32], U1>,
+    fine_nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    fine_nodes_mapping: buffer::View<'a, [u32], U3>,
+    temporary_storage_0: buffer::View<'a, [u32], U4>,
+    temporary_storage_1: buffer::View<'a, [u32], U5>,
+    edge_channels: &'a [CoarsenEdgeChannel<'a>],
+}
+
+struct EdgeListOutput<'a, U0, U1, U2, U3> {
+    coarse_edge_ref_count: buffer::View<'a, u32, U0>,
+    coarse_nodes_edge_offset: buffer::View<'a, [u32], U1>,
+    coarse_nodes_edges: buffer::View<'a, [u32], U2>,
+    coarse_nodes_edge_weights: buffer::View<'a, [u32], U3>,
+    fine_edges_coarse_edge: Option<Storage<'a, [u32], ReadWrite>>,
+}
+
+struct EdgeListCounts<'a> {
+    fallback: &'a FallbackCounts<'a>,
+    dispatch_indirect: bool,
+    node_count_dispatch:
+        &'a Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+    edge_ref_count_dispatch:
+        &'a Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+    fallback_node_count: u32,
+    fallback_edge_ref_count: u32,
+}
+
+/// The stages that construct the coarse edge lists from the fine edge lists, given a mapping from
+/// fine nodes to coarse nodes.
+///
+/// These are kept separate from the [CoarsenGraph] stages that construct the node mapping, so that
+/// the edge lists of a graph's CSC companion can be coarsened with a node mapping that was
+/// previously obtained for the CSR edge lists.
+struct EdgeListCoarsening {
+    device: Device,
+    generate_index_list: Rc<GenerateIndexList>,
+    gather_edge_owner_list: Rc<GatherEdgeOwnerList>,
+    mark_coarse_edge_validity: Rc<MarkCoarseEdgeValidity>,
+    collect_coarse_nodes_edge_weights: Rc<CollectCoarseNodesEdgeWeights>,
+    compact_coarse_edges: Rc<CompactCoarseEdges>,
+    resolve_coarse_edge_ref_count: Rc<ResolveCoarseEdgeRefCount>,
+    finalize_coarse_nodes_edge_offset: Rc<FinalizeCoarseNodesEdgeOffset>,
+    find_coarse_nodes_edge_offset: Rc<FindCoarseNodesEdgeOffset>,
+    save_edge_permutation: Rc<SaveEdgePermutation>,
+    init_coarse_edge_channel: Rc<InitCoarseEdgeChannel>,
+    combine_coarse_edge_channel: Rc<CombineCoarseEdgeChannel>,
+    scatter_fine_edges_coarse_edge: Rc<ScatterFineEdgesCoarseEdge>,
+    sort_by: Rc<RefCell<RadixSortBy<u32, u32>>>,
+    find_runs: Rc<RefCell<FindRuns<u32>>>,
+    gather_by: Rc<RefCell<GatherBy<u32, u32>>>,
+    prefix_sum_inclusive: Rc<RefCell<PrefixSum<u32>>>,
+    edge_run_count: Buffer<u32, buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    // Indexed by `CombineRule as usize`.
+    combine_rules: Vec<Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>>,
+    // Allocated on first use with edge channels or a `fine_edges_coarse_edge` output; grows as
+    // required.
+    saved_edge_permutation: Option<Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>>,
+}
+
+impl EdgeListCoarsening {
+    fn reserve_saved_edge_permutation(&mut self, capacity: usize) {
+        let sufficient = self
+            .saved_edge_permutation
+            .as_ref()
+            .map(|buffer| buffer.len() >= capacity)
+            .unwrap_or(false);
+
+        if !sufficient {
+            self.saved_edge_permutation = Some(
+                self.device
+                    .create_slice_buffer_zeroed(capacity, buffer::Usages::storage_binding()),
+            );
+        }
+    }
+
+    fn encode<U0, U1, U2, U3, U4, U5, U6, U7, U8, U9>(
+        &mut self,
+        mut encoder: CommandEncoder,
+        input: EdgeListInput<U0, U1, U2, U3, U4, U5>,
+        output: EdgeListOutput<U6, U7, U8, U9>,
+        counts: EdgeListCounts,
+        mode: EdgeMode,
+        profiler: &mut Option<&mut Profiler>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+        U4: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U5: buffer::StorageBinding + buffer::CopyDst + 'static,
+        U6: buffer::StorageBinding,
+        U7: buffer::StorageBinding,
+        U8: buffer::StorageBinding,
+        U9: buffer::StorageBinding + buffer::CopyDst + 'static,
+    {
+        let EdgeListInput {
+            fine_nodes_edge_offset,
+            fine_nodes_edges,
+            fine_nodes_edge_weights,
+            fine_nodes_mapping,
+            temporary_storage_0,
+            temporary_storage_1,
+            edge_channels,
+        } = input;
+
+        let EdgeListOutput {
+            coarse_edge_ref_count,
+            coarse_nodes_edge_offset,
+            coarse_nodes_edges,
+            coarse_nodes_edge_weights,
+            fine_edges_coarse_edge,
+        } = output;
+
+        let needs_edge_permutation = !edge_channels.is_empty() || fine_edges_coarse_edge.is_some();
+
+        if needs_edge_permutation {
+            self.reserve_saved_edge_permutation(fine_nodes_edges.len());
+        }
+
         // We'll need 4 `u32` slice buffers for this section that each have the capacity to store
         // the coarse edge count. To save the user a buffer allocation, we'll use the
         // `coarse_nodes_edge_weights` buffer as one of these buffers. These buffers will end up
@@ -304,15 +1119,16 @@ impl CoarsenGraph {
         // As a first step, we'll take the edge pointers from the fine level, and replace all node
         // indices pointed to with the indices of the nodes they map to in the coarse level, using
         // the `fine_nodes_mapping`.
-        encoder = self.gather_by.encode(
+        encoder = self.gather_by.borrow_mut().encode(
             encoder,
             GatherByInput {
                 gather_by: fine_nodes_edges,
                 data: fine_nodes_mapping,
-                count: Some(counts_fallback.edge_ref_count()),
+                count: Some(counts.fallback.edge_ref_count()),
             },
             storage_0,
         );
+        encoder = profile_stage(profiler, encoder, "map edges");
 
         // We now generate a new list of consecutive indices `0, 1, 2, ...`. We'll use this list to
         // do a "compound sort":
@@ -320,27 +1136,28 @@ impl CoarsenGraph {
         encoder = self.generate_index_list.encode(
             encoder,
             GenerateIndexListResources {
-                count: counts_fallback.edge_ref_count(),
+                count: counts.fallback.edge_ref_count(),
                 data: storage_1.storage(),
             },
-            dispatch_indirect,
-            self.edge_ref_count_dispatch.view(),
-            fallback_edge_ref_count,
+            counts.dispatch_indirect,
+            counts.edge_ref_count_dispatch.view(),
+            counts.fallback_edge_ref_count,
         );
 
         //  We'll first radix-sort this index list using mapped edge list we produced in the
         //  previous step. The index list now "stores the sort".
 
-        encoder = self.sort_by.encode(
+        encoder = self.sort_by.borrow_mut().encode(
             encoder,
             RadixSortByInput {
                 keys: storage_0,
                 values: storage_1,
                 temporary_key_storage: storage_2,
                 temporary_value_storage: storage_3,
-                count: Some(counts_fallback.edge_ref_count()),
+                count: Some(counts.fallback.edge_ref_count()),
             },
         );
+        encoder = profile_stage(profiler, encoder, "first edge sort");
 
         // We generate a new list based on the edge list from the fine level, where for each
         // fine level edge we store the index of the would-be "owner node" in the coarse level.
@@ -350,49 +1167,51 @@ impl CoarsenGraph {
         encoder = self.gather_edge_owner_list.encode(
             encoder,
             GatherEdgeOwnerListResources {
-                fine_node_count: counts_fallback.node_count(),
-                fine_edge_count: counts_fallback.edge_ref_count(),
+                fine_node_count: counts.fallback.node_count(),
+                fine_edge_count: counts.fallback.edge_ref_count(),
                 fine_nodes_edge_offset: fine_nodes_edge_offset.storage(),
                 fine_nodes_mapping: fine_nodes_mapping.storage(),
                 coarsened_edge_owner_list: storage_2.storage(),
             },
-            dispatch_indirect,
-            self.node_count_dispatch.view(),
-            fallback_node_count,
+            counts.dispatch_indirect,
+            counts.node_count_dispatch.view(),
+            counts.fallback_node_count,
         );
 
-        encoder = self.gather_by.encode(
+        encoder = self.gather_by.borrow_mut().encode(
             encoder,
             GatherByInput {
                 gather_by: storage_1,
                 data: storage_2,
-                count: Some(counts_fallback.edge_ref_count()),
+                count: Some(counts.fallback.edge_ref_count()),
             },
             storage_0,
         );
+        encoder = profile_stage(profiler, encoder, "gather edge owner list");
 
         // We radix-sort the index list again, now using the gathered owner list we created in the
         // previous step as the sort keys.
 
-        encoder = self.sort_by.encode(
+        encoder = self.sort_by.borrow_mut().encode(
             encoder,
             RadixSortByInput {
                 keys: storage_0,
                 values: storage_1,
                 temporary_key_storage: storage_2,
                 temporary_value_storage: storage_3,
-                count: Some(counts_fallback.edge_ref_count()),
+                count: Some(counts.fallback.edge_ref_count()),
             },
         );
+        encoder = profile_stage(profiler, encoder, "second edge sort");
 
         // Recreate the mapped edge list (it got "destroyed" by the first sort operation).
 
-        encoder = self.gather_by.encode(
+        encoder = self.gather_by.borrow_mut().encode(
             encoder,
             GatherByInput {
                 gather_by: fine_nodes_edges,
                 data: fine_nodes_mapping,
-                count: Some(counts_fallback.edge_ref_count()),
+                count: Some(counts.fallback.edge_ref_count()),
             },
             storage_2,
         );
@@ -400,12 +1219,12 @@ impl CoarsenGraph {
         // We now apply the "compound sort" stored in the index list to this mapped edge list with
         // another gather-by operation.
 
-        encoder = self.gather_by.encode(
+        encoder = self.gather_by.borrow_mut().encode(
             encoder,
             GatherByInput {
                 gather_by: storage_1,
                 data: storage_2,
-                count: Some(counts_fallback.edge_ref_count()),
+                count: Some(counts.fallback.edge_ref_count()),
             },
             storage_3,
         );
@@ -413,15 +1232,38 @@ impl CoarsenGraph {
         // We'll also apply it to the `fine_nodes_edge_weights` list to create a corresponding edge
         // weight list.
 
-        encoder = self.gather_by.encode(
+        encoder = self.gather_by.borrow_mut().encode(
             encoder,
             GatherByInput {
                 gather_by: storage_1,
                 data: fine_nodes_edge_weights,
-                count: Some(counts_fallback.edge_ref_count()),
+                count: Some(counts.fallback.edge_ref_count()),
             },
             storage_2,
         );
+        encoder = profile_stage(profiler, encoder, "gather sorted edges");
+
+        // The index list now stores the permutation that maps each position in the sorted edge
+        // list to the position of the corresponding edge in the fine level edge list. If there are
+        // any additional edge channels, then we'll need this permutation again after the
+        // validity prefix-sum, but its buffer gets reused before then, so we save a copy. The same
+        // goes for if the `fine_edges_coarse_edge` output was requested.
+        if let Some(saved_edge_permutation) = self.saved_edge_permutation.as_ref() {
+            if needs_edge_permutation {
+                encoder = self.save_edge_permutation.encode(
+                    encoder,
+                    SaveEdgePermutationResources {
+                        count: counts.fallback.edge_ref_count(),
+                        edge_permutation: storage_1.storage(),
+                        saved_edge_permutation: saved_edge_permutation.storage(),
+                    },
+                    counts.dispatch_indirect,
+                    counts.edge_ref_count_dispatch.view(),
+                    counts.fallback_edge_ref_count,
+                );
+                encoder = profile_stage(profiler, encoder, "save edge permutation");
+            }
+        }
 
         // This first phase of constructing the coarsened edge list leverages the fact that radix
         // sort is a stable, order preserving sort. We have essentially applied a "compound sort" to
@@ -465,20 +1307,28 @@ impl CoarsenGraph {
         //   produce the same `(owner node, referenced node)` pair as the current edge and its
         //   owner node.
 
-        // We'll first "find the runs" in the owner node list, these run offsets are not the final
-        // edge offsets for the coarse level, but we'll convert them later.
-        encoder = self.find_runs.encode(
-            encoder,
-            FindRunsInput {
-                data: storage_0,
-                count: Some(counts_fallback.edge_ref_count()),
-            },
-            FindRunsOutput {
-                run_count: self.edge_run_count.view(),
-                run_starts: coarse_nodes_edge_offset,
-                run_mapping: storage_1,
-            },
-        );
+        // For undirected graphs, every coarse node owns at least 1 (possibly invalid) edge, as
+        // every fine node is adjacent to at least 1 other node. For directed graphs this is not
+        // the case: a coarse node that has no out-arcs won't appear in the owner node list at
+        // all. We therefore only use the run-finding approach below for undirected graphs; for
+        // directed graphs we resolve the offsets separately after the validity prefix-sum.
+        if mode == EdgeMode::Undirected {
+            // We'll first "find the runs" in the owner node list, these run offsets are not the
+            // final edge offsets for the coarse level, but we'll convert them later.
+            encoder = self.find_runs.borrow_mut().encode(
+                encoder,
+                FindRunsInput {
+                    data: storage_0,
+                    count: Some(counts.fallback.edge_ref_count()),
+                },
+                FindRunsOutput {
+                    run_count: self.edge_run_count.view(),
+                    run_starts: coarse_nodes_edge_offset,
+                    run_mapping: storage_1,
+                },
+            );
+            encoder = profile_stage(profiler, encoder, "find edge runs");
+        }
 
         // Now construct the validity list. In addition to storing the validity state for each edge
         // in a separate validity list, will also store it in the 2 most significant bits of the
@@ -499,15 +1349,16 @@ impl CoarsenGraph {
         encoder = self.mark_coarse_edge_validity.encode(
             encoder,
             MarkCoarseEdgeValidityResources {
-                count: counts_fallback.edge_ref_count(),
+                count: counts.fallback.edge_ref_count(),
                 owner_nodes: storage_0.storage(),
                 mapped_edges: storage_3.storage(),
                 validity: storage_1.storage(),
             },
-            dispatch_indirect,
-            self.edge_ref_count_dispatch.view(),
-            fallback_edge_ref_count,
+            counts.dispatch_indirect,
+            counts.edge_ref_count_dispatch.view(),
+            counts.fallback_edge_ref_count,
         );
+        encoder = profile_stage(profiler, encoder, "mark edge validity");
 
         // We now perform an inclusive prefix-sum operation over the validity list. After this, for
         // each valid edge, subtracting `1` from the value in this list will give the index of the
@@ -515,13 +1366,35 @@ impl CoarsenGraph {
         //
         // This index also identifies the coarse edge weight position to sum edge weights into, both
         // for valid edges and invalid-but-not-self-referencing edges.
-        encoder = self.prefix_sum_inclusive.encode(
+        encoder = self.prefix_sum_inclusive.borrow_mut().encode(
             encoder,
             PrefixSumInput {
                 data: storage_1,
-                count: Some(counts_fallback.edge_ref_count()),
+                count: Some(counts.fallback.edge_ref_count()),
             },
         );
+        encoder = profile_stage(profiler, encoder, "validity prefix sum");
+
+        // For directed graphs we resolve the coarse edge offsets now, while the sorted owner node
+        // list is still available: for each coarse node, we search the owner node list for the
+        // start of the node's uncompacted edge range, and then map it to the compacted range with
+        // the validity prefix-sum.
+        if mode == EdgeMode::Directed {
+            encoder = self.find_coarse_nodes_edge_offset.encode(
+                encoder,
+                FindCoarseNodesEdgeOffsetResources {
+                    node_count: counts.fallback.node_count(),
+                    edge_ref_count: counts.fallback.edge_ref_count(),
+                    owner_nodes: storage_0.storage(),
+                    validity_prefix_sum: storage_1.storage(),
+                    coarse_nodes_edge_offset: coarse_nodes_edge_offset.storage(),
+                },
+                counts.dispatch_indirect,
+                counts.node_count_dispatch.view(),
+                counts.fallback_node_count,
+            );
+            encoder = profile_stage(profiler, encoder, "find edge offsets");
+        }
 
         // Compute the final `coarse_nodes_edge_weights` by (atomically) adding together the
         // weights for the mapped edges to the index provided by the validity prefix-sum, except
@@ -530,16 +1403,17 @@ impl CoarsenGraph {
         encoder = self.collect_coarse_nodes_edge_weights.encode(
             encoder,
             CollectCoarseNodesEdgeWeightsResources {
-                count: counts_fallback.edge_ref_count(),
+                count: counts.fallback.edge_ref_count(),
                 mapped_edges: storage_3.storage(),
                 mapped_edge_weights: storage_2.storage(),
                 validity_prefix_sum: storage_1.storage(),
                 coarse_nodes_edge_weights: storage_0.storage(),
             },
-            dispatch_indirect,
-            self.edge_ref_count_dispatch.view(),
-            fallback_edge_ref_count,
+            counts.dispatch_indirect,
+            counts.edge_ref_count_dispatch.view(),
+            counts.fallback_edge_ref_count,
         );
+        encoder = profile_stage(profiler, encoder, "collect edge weights");
 
         // Copy the edges from the uncompacted mapped edge list to their final positions in the
         // compacted `coarse_nodes_edges` list, if the edge is marked as "valid". Take care to not
@@ -547,47 +1421,120 @@ impl CoarsenGraph {
         encoder = self.compact_coarse_edges.encode(
             encoder,
             CompactCoarseEdgesResources {
-                count: counts_fallback.edge_ref_count(),
+                count: counts.fallback.edge_ref_count(),
                 mapped_edges: storage_3.storage(),
                 validity_prefix_sum: storage_1.storage(),
                 coarse_nodes_edges: storage_2.storage(),
             },
-            dispatch_indirect,
-            self.edge_ref_count_dispatch.view(),
-            fallback_edge_ref_count,
+            counts.dispatch_indirect,
+            counts.edge_ref_count_dispatch.view(),
+            counts.fallback_edge_ref_count,
         );
+        encoder = profile_stage(profiler, encoder, "compact edges");
+
+        // Coarsen any additional edge channels. Each coarse edge value is first initialized with
+        // the value of the valid edge that starts its run of parallel edges, after which the
+        // values of the duplicates in the run are folded in with atomics.
+        if let Some(saved_edge_permutation) = self.saved_edge_permutation.as_ref() {
+            for channel in edge_channels {
+                encoder = self.init_coarse_edge_channel.encode(
+                    encoder,
+                    InitCoarseEdgeChannelResources {
+                        count: counts.fallback.edge_ref_count(),
+                        mapped_edges: storage_3.storage(),
+                        validity_prefix_sum: storage_1.storage(),
+                        edge_permutation: saved_edge_permutation.storage(),
+                        fine_edge_values: channel.fine_edge_values.clone(),
+                        coarse_edge_values: channel.coarse_edge_values.clone(),
+                    },
+                    counts.dispatch_indirect,
+                    counts.edge_ref_count_dispatch.view(),
+                    counts.fallback_edge_ref_count,
+                );
+                encoder = self.combine_coarse_edge_channel.encode(
+                    encoder,
+                    CombineCoarseEdgeChannelResources {
+                        count: counts.fallback.edge_ref_count(),
+                        combine_rule: self.combine_rules[channel.combine_rule as usize].uniform(),
+                        mapped_edges: storage_3.storage(),
+                        validity_prefix_sum: storage_1.storage(),
+                        edge_permutation: saved_edge_permutation.storage(),
+                        fine_edge_values: channel.fine_edge_values.clone(),
+                        coarse_edge_values: channel.coarse_edge_values.clone(),
+                    },
+                    counts.dispatch_indirect,
+                    counts.edge_ref_count_dispatch.view(),
+                    counts.fallback_edge_ref_count,
+                );
+            }
+
+            if !edge_channels.is_empty() {
+                encoder = profile_stage(profiler, encoder, "combine edge channels");
+            }
+
+            // Record the coarse edge that each fine edge maps onto by scattering the validity
+            // prefix-sum positions through the edge permutation.
+            if let Some(fine_edges_coarse_edge) = fine_edges_coarse_edge {
+                encoder = self.scatter_fine_edges_coarse_edge.encode(
+                    encoder,
+                    ScatterFineEdgesCoarseEdgeResources {
+                        count: counts.fallback.edge_ref_count(),
+                        mapped_edges: storage_3.storage(),
+                        validity_prefix_sum: storage_1.storage(),
+                        edge_permutation: saved_edge_permutation.storage(),
+                        fine_edges_coarse_edge,
+                    },
+                    counts.dispatch_indirect,
+                    counts.edge_ref_count_dispatch.view(),
+                    counts.fallback_edge_ref_count,
+                );
+                encoder = profile_stage(profiler, encoder, "scatter fine edges coarse edge");
+            }
+        }
 
         // Resolve the edge ref count for the coarse level by copying the last number in the
         // validity prefix-sum list
         encoder = self.resolve_coarse_edge_ref_count.encode(
             encoder,
             ResolveCoarseEdgeRefCountResources {
-                fine_level_edge_ref_count: counts_fallback.edge_ref_count(),
+                fine_level_edge_ref_count: counts.fallback.edge_ref_count(),
                 validity_prefix_sum: storage_1.storage(),
                 coarse_level_edge_ref_count: coarse_edge_ref_count.storage(),
             },
         );
 
-        // Finally, we collected a preliminary version of `coarse_nodes_edge_offset` earlier, but
-        // the offsets it currently contains point to the starts of the uncompacted node edge
-        // ranges. We can adjust it by using the validity prefix-sum as a mapping to find the
-        // offsets of the compacted node edge ranges. Note that the first of a node's edges in the
-        // uncompacted range can be an invalid edge reference, in which case we add `1`.
-        encoder = self.finalize_coarse_nodes_edge_offset.encode(
-            encoder,
-            FinalizeCoarseNodesEdgeOffsetResources {
-                count: counts_fallback.node_count(),
-                mapped_edges: storage_3.storage(),
-                validity_prefix_sum: storage_1.storage(),
-                coarse_nodes_edge_offset: coarse_nodes_edge_offset.storage(),
-            },
-            dispatch_indirect,
-            self.node_count_dispatch.view(),
-            fallback_node_count,
-        );
-
-        // And we're done!
+        if mode == EdgeMode::Undirected {
+            // Finally, for undirected graphs we collected a preliminary version of
+            // `coarse_nodes_edge_offset` earlier, but the offsets it currently contains point to
+            // the starts of the uncompacted node edge ranges. We can adjust it by using the
+            // validity prefix-sum as a mapping to find the offsets of the compacted node edge
+            // ranges. Note that the first of a node's edges in the uncompacted range can be an
+            // invalid edge reference, in which case we add `1`.
+            encoder = self.finalize_coarse_nodes_edge_offset.encode(
+                encoder,
+                FinalizeCoarseNodesEdgeOffsetResources {
+                    count: counts.fallback.node_count(),
+                    mapped_edges: storage_3.storage(),
+                    validity_prefix_sum: storage_1.storage(),
+                    coarse_nodes_edge_offset: coarse_nodes_edge_offset.storage(),
+                },
+                counts.dispatch_indirect,
+                counts.node_count_dispatch.view(),
+                counts.fallback_node_count,
+            );
+            encoder = profile_stage(profiler, encoder, "finalize edge offsets");
+        }
 
         encoder
     }
 }
+
+fn assert_capacity(name: &str, len: usize, required: usize) {
+    assert!(
+        len >= required,
+        "`{}` must have a length of at least `{}`, but has a length of `{}`",
+        name,
+        required,
+        len
+    );
+}
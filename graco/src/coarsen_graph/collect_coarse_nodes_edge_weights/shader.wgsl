@@ -1,3 +1,4 @@
+#include <src/dispatch.wgsl>
 #include <src/coarsen_graph/validity.wgsl>
 
 @group(0) @binding(0)
@@ -15,9 +16,12 @@ var<storage, read> validity_prefix_sum: array<u32>;
 @group(0) @binding(4)
 var<storage, read_write> coarse_nodes_edge_weights: array<atomic<u32>>;
 
-@compute @workgroup_size(256, 1, 1)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let index = global_id.x;
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
 
     if index >= count {
         return;
@@ -33,6 +37,14 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         // always greater than 0 not just for valid edges, but also for invalid-but-not-self-referencing duplicate
         let dest_index = validity_prefix_sum[index] - 1;
 
-        atomicAdd(&coarse_nodes_edge_weights[dest_index], mapped_edge_weights[index]);
+        let weight = mapped_edge_weights[index];
+        let previous = atomicAdd(&coarse_nodes_edge_weights[dest_index], weight);
+
+        // Saturate rather than wrap around if the summed weight does not fit in a `u32`. Every addition that wraps
+        // around is followed by a store of the maximum value, and once the weight is saturated, any further non-zero
+        // addition wraps around again, so the final weight is the maximum value whenever the exact sum exceeds it.
+        if previous + weight < previous {
+            atomicStore(&coarse_nodes_edge_weights[dest_index], 0xFFFFFFFFu);
+        }
     }
 }
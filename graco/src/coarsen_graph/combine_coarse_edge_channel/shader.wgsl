@@ -0,0 +1,62 @@
+#include <src/dispatch.wgsl>
+#include <src/coarsen_graph/validity.wgsl>
+
+const COMBINE_RULE_SUM = 0u;
+const COMBINE_RULE_MIN = 1u;
+const COMBINE_RULE_MAX = 2u;
+
+@group(0) @binding(0)
+var<uniform> count: u32;
+
+@group(0) @binding(1)
+var<uniform> combine_rule: u32;
+
+@group(0) @binding(2)
+var<storage, read> mapped_edges: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> validity_prefix_sum: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> edge_permutation: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> fine_edge_values: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read_write> coarse_edge_values: array<atomic<u32>>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= count {
+        return;
+    }
+
+    // The coarse edge values were initialized with the values of the valid edges that start each run of parallel edges
+    // by the `init_coarse_edge_channel` pass, so we only need to fold in the values of the duplicates.
+    if (mapped_edges[index] >> 30) != VALIDITY_INVALID_DUPLICATE {
+        return;
+    }
+
+    // Note that due to the construction of the inclusive validity prefix-sum, `validity_prefix_sum[index]` points to
+    // the coarse edge of the valid edge that starts the run.
+    let dest_index = validity_prefix_sum[index] - 1;
+    let value = fine_edge_values[edge_permutation[index]];
+
+    switch combine_rule {
+        case COMBINE_RULE_MIN: {
+            atomicMin(&coarse_edge_values[dest_index], value);
+        }
+        case COMBINE_RULE_MAX: {
+            atomicMax(&coarse_edge_values[dest_index], value);
+        }
+        default: {
+            atomicAdd(&coarse_edge_values[dest_index], value);
+        }
+    }
+}
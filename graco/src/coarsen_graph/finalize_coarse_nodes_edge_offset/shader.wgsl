@@ -1,3 +1,4 @@
+#include <src/dispatch.wgsl>
 #include <src/coarsen_graph/validity.wgsl>
 
 @group(0) @binding(0)
@@ -12,9 +13,12 @@ var<storage, read> validity_prefix_sum: array<u32>;
 @group(0) @binding(3)
 var<storage, read_write> coarse_nodes_edge_offset: array<u32>;
 
-@compute @workgroup_size(256, 1, 1)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let index = global_id.x;
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
 
     if index >= count {
         return;
@@ -0,0 +1,55 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> owner_nodes: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> validity_prefix_sum: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> coarse_nodes_edge_offset: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    // The owner node list is sorted, so we can find the start of this node's uncompacted edge range
+    // with a binary search for the first edge whose owner is not smaller than this node. This also
+    // works for nodes that don't own any edges, in which case the search ends at the start of the
+    // next node's range.
+    var low = 0u;
+    var high = edge_ref_count;
+
+    while low < high {
+        let mid = low + (high - low) / 2u;
+
+        if owner_nodes[mid] < index {
+            low = mid + 1u;
+        } else {
+            high = mid;
+        }
+    }
+
+    // The inclusive validity prefix-sum at the preceding position gives the number of valid edges
+    // that precede this node's range in the compacted edge list.
+    var offset = 0u;
+
+    if low > 0u {
+        offset = validity_prefix_sum[low - 1u];
+    }
+
+    coarse_nodes_edge_offset[index] = offset;
+}
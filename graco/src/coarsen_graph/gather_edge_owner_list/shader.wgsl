@@ -1,3 +1,5 @@
+#include <src/dispatch.wgsl>
+
 @group(0) @binding(0)
 var<uniform> fine_node_count: u32;
 
@@ -13,9 +15,12 @@ var<storage, read> fine_nodes_mapping: array<u32>;
 @group(0) @binding(4)
 var<storage, read_write> coarsened_edge_owner_list: array<u32>;
 
-@compute @workgroup_size(256, 1, 1)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let index = global_id.x;
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
 
     if index < fine_node_count {
         let start = fine_nodes_edge_offset[index];
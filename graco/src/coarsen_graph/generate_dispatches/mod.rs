@@ -8,19 +8,20 @@ use empa::device::Device;
 use empa::resource_binding::BindGroupLayout;
 use empa::shader_module::{shader_source, ShaderSource};
 
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
 const SHADER: ShaderSource = shader_source!("shader.wgsl");
 
 #[derive(empa::resource_binding::Resources)]
 pub struct GenerateDispatchesResources<'a> {
     #[resource(binding = 0, visibility = "COMPUTE")]
-    pub group_size: Uniform<'a, u32>,
-    #[resource(binding = 1, visibility = "COMPUTE")]
     pub node_count: Uniform<'a, u32>,
-    #[resource(binding = 2, visibility = "COMPUTE")]
+    #[resource(binding = 1, visibility = "COMPUTE")]
     pub edge_ref_count: Uniform<'a, u32>,
-    #[resource(binding = 3, visibility = "COMPUTE")]
+    #[resource(binding = 2, visibility = "COMPUTE")]
     pub node_count_dispatch: Storage<'a, DispatchWorkgroups, ReadWrite>,
-    #[resource(binding = 4, visibility = "COMPUTE")]
+    #[resource(binding = 3, visibility = "COMPUTE")]
     pub edge_ref_count_dispatch: Storage<'a, DispatchWorkgroups, ReadWrite>,
 }
 
@@ -30,11 +31,12 @@ type ResourcesLayout =
 pub struct GenerateDispatches {
     device: Device,
     bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
     pipeline: ComputePipeline<(ResourcesLayout,)>,
 }
 
 impl GenerateDispatches {
-    pub async fn init(device: Device) -> Self {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
         let shader = device.create_shader_module(&SHADER);
 
         let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
@@ -44,7 +46,11 @@ impl GenerateDispatches {
             .create_compute_pipeline(
                 &ComputePipelineDescriptorBuilder::begin()
                     .layout(&pipeline_layout)
-                    .compute(ComputeStageBuilder::begin(&shader, "main").finish())
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
                     .finish(),
             )
             .await;
@@ -52,6 +58,7 @@ impl GenerateDispatches {
         GenerateDispatches {
             device,
             bind_group_layout,
+            bind_groups: Default::default(),
             pipeline,
         }
     }
@@ -61,14 +68,21 @@ impl GenerateDispatches {
         encoder: CommandEncoder,
         resources: GenerateDispatchesResources,
     ) -> CommandEncoder {
-        let bind_group = self
-            .device
-            .create_bind_group(&self.bind_group_layout, resources);
+        let key = [
+            resources.node_count.binding_key(),
+            resources.edge_ref_count.binding_key(),
+            resources.node_count_dispatch.binding_key(),
+            resources.edge_ref_count_dispatch.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
 
         encoder
             .begin_compute_pass()
             .set_pipeline(&self.pipeline)
-            .set_bind_groups(&bind_group)
+            .set_bind_groups(&*bind_group)
             .dispatch_workgroups(DispatchWorkgroups {
                 count_x: 1,
                 count_y: 1,
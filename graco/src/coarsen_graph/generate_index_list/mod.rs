@@ -9,7 +9,8 @@ use empa::device::Device;
 use empa::resource_binding::BindGroupLayout;
 use empa::shader_module::{shader_source, ShaderSource};
 
-use crate::coarsen_graph::DEFAULT_GROUP_SIZE;
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
 
 const SHADER: ShaderSource = shader_source!("shader.wgsl");
 
@@ -27,11 +28,13 @@ type ResourcesLayout =
 pub struct GenerateIndexList {
     device: Device,
     bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
     pipeline: ComputePipeline<(ResourcesLayout,)>,
+    dispatch_config: DispatchConfig,
 }
 
 impl GenerateIndexList {
-    pub async fn init(device: Device) -> Self {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
         let shader = device.create_shader_module(&SHADER);
 
         let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
@@ -41,7 +44,11 @@ impl GenerateIndexList {
             .create_compute_pipeline(
                 &ComputePipelineDescriptorBuilder::begin()
                     .layout(&pipeline_layout)
-                    .compute(ComputeStageBuilder::begin(&shader, "main").finish())
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
                     .finish(),
             )
             .await;
@@ -49,7 +56,9 @@ impl GenerateIndexList {
         GenerateIndexList {
             device,
             bind_group_layout,
+            bind_groups: Default::default(),
             pipeline,
+            dispatch_config,
         }
     }
 
@@ -64,24 +73,22 @@ impl GenerateIndexList {
     where
         U: buffer::Indirect,
     {
-        let bind_group = self
-            .device
-            .create_bind_group(&self.bind_group_layout, resources);
+        let key = [resources.count.binding_key(), resources.data.binding_key()];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
 
         let encoder = encoder
             .begin_compute_pass()
             .set_pipeline(&self.pipeline)
-            .set_bind_groups(&bind_group);
+            .set_bind_groups(&*bind_group);
 
         if dispatch_indirect {
             encoder.dispatch_workgroups_indirect(dispatch).end()
         } else {
             encoder
-                .dispatch_workgroups(DispatchWorkgroups {
-                    count_x: fallback_count.div_ceil(DEFAULT_GROUP_SIZE),
-                    count_y: 1,
-                    count_z: 1,
-                })
+                .dispatch_workgroups(self.dispatch_config.workgroups(fallback_count))
                 .end()
         }
     }
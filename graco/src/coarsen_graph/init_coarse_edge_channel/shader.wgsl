@@ -0,0 +1,40 @@
+#include <src/dispatch.wgsl>
+#include <src/coarsen_graph/validity.wgsl>
+
+@group(0) @binding(0)
+var<uniform> count: u32;
+
+@group(0) @binding(1)
+var<storage, read> mapped_edges: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> validity_prefix_sum: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> edge_permutation: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> fine_edge_values: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read_write> coarse_edge_values: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= count {
+        return;
+    }
+
+    // Every run of parallel edges starts with a valid edge. We initialize each coarse edge value with the value of the
+    // valid edge in its run; the `combine_coarse_edge_channel` pass then folds in the values of the duplicates.
+    if (mapped_edges[index] >> 30) == VALIDITY_VALID {
+        let dest_index = validity_prefix_sum[index] - 1;
+
+        coarse_edge_values[dest_index] = fine_edge_values[edge_permutation[index]];
+    }
+}
@@ -1,13 +1,21 @@
+mod accumulate_coarse_edge_weights;
 mod collect_coarse_nodes_edge_weights;
+mod combine_coarse_edge_channel;
 mod compact_coarse_edges;
 mod finalize_coarse_nodes_edge_offset;
+mod find_coarse_nodes_edge_offset;
 mod gather_edge_owner_list;
-mod generate_dispatches;
-mod generate_index_list;
+pub(crate) mod generate_dispatches;
+pub(crate) mod generate_index_list;
+mod init_coarse_edge_channel;
 mod mark_coarse_edge_validity;
 mod resolve_coarse_edge_ref_count;
+mod save_edge_permutation;
+mod scatter_fine_edges_coarse_edge;
 
 mod coarsen_graph;
-pub use self::coarsen_graph::{CoarsenCounts, CoarsenGraph, CoarsenGraphInput, CoarsenGraphOutput};
-
-const DEFAULT_GROUP_SIZE: u32 = 256;
+pub use self::coarsen_graph::{
+    CoarsenCompanionEdgesInput, CoarsenCompanionEdgesOutput, CoarsenCounts, CoarsenEdgeChannel,
+    CoarsenGraph, CoarsenGraphInput, CoarsenGraphOutput, CoarsenGraphWorkspaceInput, CombineRule,
+    RecomputeWeightsInput, COARSE_EDGE_SELF_REFERENCE,
+};
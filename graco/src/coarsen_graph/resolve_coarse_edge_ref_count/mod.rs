@@ -8,6 +8,8 @@ use empa::device::Device;
 use empa::resource_binding::BindGroupLayout;
 use empa::shader_module::{shader_source, ShaderSource};
 
+use crate::counts_fallback::{BindGroupCache, Binding};
+
 const SHADER: ShaderSource = shader_source!("shader.wgsl");
 
 #[derive(empa::resource_binding::Resources)]
@@ -26,6 +28,7 @@ type ResourcesLayout =
 pub struct ResolveCoarseEdgeRefCount {
     device: Device,
     bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
     pipeline: ComputePipeline<(ResourcesLayout,)>,
 }
 
@@ -48,6 +51,7 @@ impl ResolveCoarseEdgeRefCount {
         ResolveCoarseEdgeRefCount {
             device,
             bind_group_layout,
+            bind_groups: Default::default(),
             pipeline,
         }
     }
@@ -57,14 +61,20 @@ impl ResolveCoarseEdgeRefCount {
         encoder: CommandEncoder,
         resources: ResolveCoarseEdgeRefCountResources,
     ) -> CommandEncoder {
-        let bind_group = self
-            .device
-            .create_bind_group(&self.bind_group_layout, resources);
+        let key = [
+            resources.fine_level_edge_ref_count.binding_key(),
+            resources.validity_prefix_sum.binding_key(),
+            resources.coarse_level_edge_ref_count.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
 
         encoder
             .begin_compute_pass()
             .set_pipeline(&self.pipeline)
-            .set_bind_groups(&bind_group)
+            .set_bind_groups(&*bind_group)
             .dispatch_workgroups(DispatchWorkgroups {
                 count_x: 1,
                 count_y: 1,
@@ -0,0 +1,22 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> count: u32;
+
+@group(0) @binding(1)
+var<storage, read> edge_permutation: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> saved_edge_permutation: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index < count {
+        saved_edge_permutation[index] = edge_permutation[index];
+    }
+}
@@ -0,0 +1,44 @@
+#include <src/dispatch.wgsl>
+#include <src/coarsen_graph/validity.wgsl>
+
+// Must match `COARSE_EDGE_SELF_REFERENCE` in `coarsen_graph.rs`.
+const COARSE_EDGE_SELF_REFERENCE = 0xFFFFFFFFu;
+
+@group(0) @binding(0)
+var<uniform> count: u32;
+
+@group(0) @binding(1)
+var<storage, read> mapped_edges: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> validity_prefix_sum: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> edge_permutation: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> fine_edges_coarse_edge: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= count {
+        return;
+    }
+
+    let validity = mapped_edges[index] >> 30;
+
+    var coarse_edge = COARSE_EDGE_SELF_REFERENCE;
+
+    // Both valid edges and duplicate edges map onto the coarse edge at the position provided by the validity
+    // prefix-sum (see also `collect_coarse_nodes_edge_weights`).
+    if validity != VALIDITY_INVALID_SELF_REFERENCE {
+        coarse_edge = validity_prefix_sum[index] - 1;
+    }
+
+    fine_edges_coarse_edge[edge_permutation[index]] = coarse_edge;
+}
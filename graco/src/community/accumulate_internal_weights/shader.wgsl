@@ -0,0 +1,69 @@
+#include <src/dispatch.wgsl>
+#include <src/u64.wgsl>
+
+@group(0) @binding(0)
+var<uniform> fine_node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> fine_edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> fine_nodes_edge_offset: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> fine_nodes_edges: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> fine_nodes_edge_weights: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> fine_nodes_internal_weight: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> fine_nodes_mapping: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read_write> coarse_nodes_internal_weight: array<atomic<u32>>;
+
+fn coarse_node_internal_weight_add(coarse_node: u32, weight: vec2<u32>) {
+    let previous = atomicAdd(&coarse_nodes_internal_weight[2u * coarse_node], weight.x);
+    let high = weight.y + u64_carry(previous, weight.x);
+
+    if high > 0 {
+        atomicAdd(&coarse_nodes_internal_weight[2u * coarse_node + 1u], high);
+    }
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= fine_node_count {
+        return;
+    }
+
+    let coarse_node = fine_nodes_mapping[index];
+
+    let edges_start = fine_nodes_edge_offset[index];
+    var edges_end = fine_edge_ref_count;
+
+    if index < fine_node_count - 1 {
+        edges_end = fine_nodes_edge_offset[index + 1];
+    }
+
+    // Edges between fine nodes that map onto the same coarse node become self-references, which `CoarsenGraph` drops.
+    // We keep their weight as internal weight instead. As each edge is stored once for each of the nodes it connects,
+    // it is counted twice, which matches how the edge contributes to the strength of its nodes.
+    var internal = vec2(fine_nodes_internal_weight[2u * index], fine_nodes_internal_weight[2u * index + 1u]);
+
+    for (var i = edges_start; i < edges_end; i += 1u) {
+        if fine_nodes_mapping[fine_nodes_edges[i]] == coarse_node {
+            internal = u64_add_u32(internal, fine_nodes_edge_weights[i]);
+        }
+    }
+
+    coarse_node_internal_weight_add(coarse_node, internal);
+}
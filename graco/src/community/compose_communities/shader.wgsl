@@ -0,0 +1,26 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> fine_nodes_mapping: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> nodes_community: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    // Each original node's community is a node on the current fine level; replace it with the coarse node that fine
+    // node was aggregated into.
+    nodes_community[index] = fine_nodes_mapping[nodes_community[index]];
+}
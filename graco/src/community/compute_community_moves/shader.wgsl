@@ -0,0 +1,139 @@
+#include <src/dispatch.wgsl>
+#include <src/u64.wgsl>
+
+// The maximum number of distinct neighboring communities that are considered as move targets for a node.
+const MAX_CANDIDATE_COMMUNITIES = 16u;
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> nodes_edges: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> nodes_edge_weights: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> nodes_strength: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> nodes_community: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read> communities_strength: array<u32>;
+
+@group(0) @binding(8)
+var<storage, read> communities_size: array<u32>;
+
+@group(0) @binding(9)
+var<storage, read> total_strength: array<u32>;
+
+@group(0) @binding(10)
+var<storage, read_write> nodes_next_community: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let current_community = nodes_community[index];
+    let strength = u64_to_f32(vec2(nodes_strength[2u * index], nodes_strength[2u * index + 1u]));
+    let total = u64_to_f32(vec2(total_strength[0], total_strength[1]));
+
+    let edges_start = nodes_edge_offset[index];
+    var edges_end = edge_ref_count;
+
+    if index < node_count - 1 {
+        edges_end = nodes_edge_offset[index + 1];
+    }
+
+    // The modularity gain of moving this node (after removing it from its current community) into community `c` is
+    // proportional to `w_c - strength * total_c / total`, where `w_c` is the weight of the node's edges into `c` and
+    // `total_c` is the summed strength of the nodes in `c`. We compute this score for the current community (without
+    // this node) and for each neighboring community, and move to the community with the highest score.
+    //
+    // The weight into each distinct neighboring community is accumulated in a table that holds the first
+    // `MAX_CANDIDATE_COMMUNITIES` communities the node's edges point to; edges into further communities are ignored.
+    // Early on, every node is its own community, so a hub node would otherwise have to track a candidate for each of
+    // its neighbors. Ignoring some candidates only delays the hub's move: once its neighbors have merged into larger
+    // communities in earlier iterations, they fit the table. The weights are accumulated as `f32`, as the scores are
+    // computed in `f32` anyway and a node's summed edge weight may not fit in 32 bits on coarse levels.
+    var current_weight = 0.0;
+    var candidate_communities: array<u32, MAX_CANDIDATE_COMMUNITIES>;
+    var candidate_weights: array<f32, MAX_CANDIDATE_COMMUNITIES>;
+    var candidate_count = 0u;
+
+    for (var i = edges_start; i < edges_end; i += 1u) {
+        let community = nodes_community[nodes_edges[i]];
+        let weight = f32(nodes_edge_weights[i]);
+
+        if community == current_community {
+            current_weight += weight;
+
+            continue;
+        }
+
+        var slot = 0u;
+
+        while slot < candidate_count && candidate_communities[slot] != community {
+            slot += 1u;
+        }
+
+        if slot == candidate_count {
+            if candidate_count == MAX_CANDIDATE_COMMUNITIES {
+                continue;
+            }
+
+            candidate_communities[slot] = community;
+            candidate_weights[slot] = 0.0;
+            candidate_count += 1u;
+        }
+
+        candidate_weights[slot] += weight;
+    }
+
+    let current_community_strength = vec2(
+        communities_strength[2u * current_community],
+        communities_strength[2u * current_community + 1u],
+    );
+    let current_total = u64_to_f32(current_community_strength) - strength;
+    let current_score = current_weight - strength * current_total / total;
+
+    var best_community = current_community;
+    var best_score = current_score;
+
+    for (var slot = 0u; slot < candidate_count; slot += 1u) {
+        let community = candidate_communities[slot];
+        let community_strength = vec2(communities_strength[2u * community], communities_strength[2u * community + 1u]);
+        let score = candidate_weights[slot] - strength * u64_to_f32(community_strength) / total;
+
+        let is_tie = score == best_score && best_community != current_community && community < best_community;
+
+        if score > best_score || is_tie {
+            best_community = community;
+            best_score = score;
+        }
+    }
+
+    // All nodes move simultaneously, so 2 singleton nodes that are attracted to each other would swap communities
+    // rather than merge. To prevent this, a singleton node only moves into another singleton community if that
+    // community has a lower id (Lu et al., "Parallel heuristics for scalable community detection").
+    if best_community != current_community && communities_size[current_community] == 1u
+        && communities_size[best_community] == 1u && best_community > current_community {
+        best_community = current_community;
+    }
+
+    nodes_next_community[index] = best_community;
+}
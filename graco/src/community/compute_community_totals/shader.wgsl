@@ -0,0 +1,44 @@
+#include <src/dispatch.wgsl>
+#include <src/u64.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> nodes_community: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> nodes_strength: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> communities_strength: array<atomic<u32>>;
+
+@group(0) @binding(4)
+var<storage, read_write> communities_size: array<atomic<u32>>;
+
+fn community_strength_add(community: u32, strength: vec2<u32>) {
+    let previous = atomicAdd(&communities_strength[2u * community], strength.x);
+    let high = strength.y + u64_carry(previous, strength.x);
+
+    if high > 0 {
+        atomicAdd(&communities_strength[2u * community + 1u], high);
+    }
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let community = nodes_community[index];
+
+    // Strengths are 64-bit values that are split over 2 entries (low word first); see `src/u64.wgsl`.
+    community_strength_add(community, vec2(nodes_strength[2u * index], nodes_strength[2u * index + 1u]));
+    atomicAdd(&communities_size[community], 1u);
+}
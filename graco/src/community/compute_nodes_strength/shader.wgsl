@@ -0,0 +1,63 @@
+#include <src/dispatch.wgsl>
+#include <src/u64.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> nodes_edge_weights: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> nodes_internal_weight: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read_write> nodes_strength: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read_write> total_strength: array<atomic<u32>>;
+
+fn total_strength_add(strength: vec2<u32>) {
+    let previous = atomicAdd(&total_strength[0], strength.x);
+    let high = strength.y + u64_carry(previous, strength.x);
+
+    if high > 0 {
+        atomicAdd(&total_strength[1], high);
+    }
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let edges_start = nodes_edge_offset[index];
+    var edges_end = edge_ref_count;
+
+    if index < node_count - 1 {
+        edges_end = nodes_edge_offset[index + 1];
+    }
+
+    // The internal weight accounts for the edges that were collapsed into this node on finer levels, so that the
+    // strength of a node equals the summed strength of the fine nodes it represents.
+    var strength = vec2(nodes_internal_weight[2u * index], nodes_internal_weight[2u * index + 1u]);
+
+    for (var i = edges_start; i < edges_end; i += 1u) {
+        strength = u64_add_u32(strength, nodes_edge_weights[i]);
+    }
+
+    nodes_strength[2u * index] = strength.x;
+    nodes_strength[2u * index + 1u] = strength.y;
+    total_strength_add(strength);
+}
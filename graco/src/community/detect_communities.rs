@@ -0,0 +1,636 @@
+use std::error::Error;
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::Buffer;
+use empa::command::CommandEncoder;
+use empa::device::Device;
+use empa::type_flag::{O, X};
+
+use crate::community::accumulate_internal_weights::{
+    AccumulateInternalWeights, AccumulateInternalWeightsResources,
+};
+use crate::community::compose_communities::{ComposeCommunities, ComposeCommunitiesResources};
+use crate::community::compute_community_moves::{
+    ComputeCommunityMoves, ComputeCommunityMovesResources,
+};
+use crate::community::compute_community_totals::{
+    ComputeCommunityTotals, ComputeCommunityTotalsResources,
+};
+use crate::community::compute_nodes_strength::{
+    ComputeNodesStrength, ComputeNodesStrengthResources,
+};
+use crate::context::Context;
+use crate::hierarchy::HierarchyLevel;
+use crate::{CoarsenCounts, CoarsenGraph, CoarsenGraphOutput, CoarsenGraphWorkspaceInput};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CommunityConfig {
+    /// The number of local moving rounds that are run on each level before it is aggregated.
+    pub local_moving_rounds: usize,
+    /// Aggregation stops once a level improves the modularity by less than this amount.
+    pub min_modularity_gain: f64,
+    /// The maximum number of levels to aggregate.
+    pub max_levels: usize,
+}
+
+impl Default for CommunityConfig {
+    fn default() -> Self {
+        CommunityConfig {
+            local_moving_rounds: 16,
+            min_modularity_gain: 1e-6,
+            max_levels: 32,
+        }
+    }
+}
+
+pub struct CommunityInput<'a, U0, U1, U2> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+}
+
+/// The result of aggregating a single level during [DetectCommunities::detect].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CommunityLevel {
+    /// The number of communities after this level, which is the node count of the aggregated
+    /// graph.
+    pub community_count: u32,
+    /// The modularity of the community assignment after this level, with respect to the original
+    /// graph.
+    pub modularity: f64,
+}
+
+/// The communities found by [DetectCommunities::detect].
+#[derive(Clone, PartialEq, Debug)]
+pub struct CommunitySummary {
+    /// The modularity of the original graph's singleton communities, where each node is its own
+    /// community.
+    pub initial_modularity: f64,
+    /// The levels that were aggregated, from the finest to the coarsest; the last level describes
+    /// the final community assignment. Empty if no level improved the modularity.
+    pub levels: Vec<CommunityLevel>,
+}
+
+impl CommunitySummary {
+    /// The modularity of the final community assignment.
+    pub fn modularity(&self) -> f64 {
+        self.levels
+            .last()
+            .map(|level| level.modularity)
+            .unwrap_or(self.initial_modularity)
+    }
+}
+
+/// Detects communities in a graph by greedily optimizing modularity with the Louvain method.
+///
+/// Each level starts with every node in its own community. A local moving phase then repeatedly
+/// moves all nodes in parallel to the neighboring community that yields the largest modularity
+/// gain, after which the communities are aggregated into the nodes of a coarser graph with
+/// [CoarsenGraph], by using the community ids as the matching. Edges inside a community become
+/// self-references on the coarse level, which [CoarsenGraph] drops, so their weight is tracked
+/// separately as each coarse node's internal weight. This repeats until a level no longer improves
+/// the modularity.
+///
+/// The graph must be undirected (each edge is stored once for each of the nodes it connects) and
+/// is described by buffers that are exactly sized to its node count and edge ref count.
+pub struct DetectCommunities {
+    device: Device,
+    config: CommunityConfig,
+    coarsen_graph: CoarsenGraph,
+    compute_nodes_strength: Rc<ComputeNodesStrength>,
+    compute_community_totals: Rc<ComputeCommunityTotals>,
+    compute_community_moves: Rc<ComputeCommunityMoves>,
+    accumulate_internal_weights: Rc<AccumulateInternalWeights>,
+    compose_communities: Rc<ComposeCommunities>,
+}
+
+impl DetectCommunities {
+    pub async fn init(device: Device, config: CommunityConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](DetectCommunities::init), but obtains the sub-pipelines from the given
+    /// `context`, so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context, config: CommunityConfig) -> Self {
+        let device = context.device().clone();
+
+        let (
+            coarsen_graph,
+            compute_nodes_strength,
+            compute_community_totals,
+            compute_community_moves,
+            accumulate_internal_weights,
+            compose_communities,
+        ) = join!(
+            CoarsenGraph::init_with_context(context),
+            context.get_or_init_kernel(ComputeNodesStrength::init),
+            context.get_or_init_kernel(ComputeCommunityTotals::init),
+            context.get_or_init_kernel(ComputeCommunityMoves::init),
+            context.get_or_init_kernel(AccumulateInternalWeights::init),
+            context.get_or_init_kernel(ComposeCommunities::init),
+        )
+        .await;
+
+        DetectCommunities {
+            device,
+            config,
+            coarsen_graph,
+            compute_nodes_strength,
+            compute_community_totals,
+            compute_community_moves,
+            accumulate_internal_weights,
+            compose_communities,
+        }
+    }
+
+    /// Detects communities in the graph described by the `input` and writes the community id for
+    /// each node to `nodes_community`.
+    ///
+    /// The community ids are contiguous, starting at `0`. Submits its own command buffers, as the
+    /// number of levels is only known after reading back the node count and modularity of each
+    /// aggregated level.
+    pub async fn detect<U0, U1, U2, U3>(
+        &mut self,
+        input: CommunityInput<'_, U0, U1, U2>,
+        nodes_community: buffer::View<'_, [u32], U3>,
+    ) -> Result<CommunitySummary, Box<dyn Error>>
+    where
+        U0: buffer::CopySrc,
+        U1: buffer::CopySrc,
+        U2: buffer::CopySrc,
+        U3: buffer::CopyDst,
+    {
+        let CommunityInput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+        } = input;
+
+        let node_count = nodes_edge_offset.len();
+        let edge_ref_count = nodes_edges.len();
+
+        assert_eq!(
+            nodes_edge_weights.len(),
+            edge_ref_count,
+            "`nodes_edge_weights` must have the same length as `nodes_edges`"
+        );
+        assert_eq!(
+            nodes_community.len(),
+            node_count,
+            "`nodes_community` must have the same length as `nodes_edge_offset`"
+        );
+
+        let workspace = Workspace::new(&self.device, node_count);
+
+        // Copy the input graph into the base level, so that all levels can be treated uniformly.
+        // The base level has no internal weight.
+        let mut fine_level = Level::new(&self.device, node_count, edge_ref_count);
+
+        let mut encoder = self.device.create_command_encoder();
+
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            nodes_edge_offset,
+            fine_level.graph.nodes_edge_offset.view(),
+        );
+        encoder =
+            encoder.copy_buffer_to_buffer_slice(nodes_edges, fine_level.graph.nodes_edges.view());
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            nodes_edge_weights,
+            fine_level.graph.nodes_edge_weights.view(),
+        );
+        encoder = self.encode_nodes_strength(encoder, &fine_level, &workspace, node_count as u32);
+
+        self.device.queue().submit(encoder.finish());
+
+        let initial_modularity = self.read_modularity(&fine_level).await?;
+
+        let mut modularity = initial_modularity;
+        let mut levels = Vec::new();
+
+        while levels.len() < self.config.max_levels {
+            let fine_node_count = fine_level.graph.node_count;
+            let mut coarse_level = Level::new(
+                &self.device,
+                fine_node_count as usize,
+                fine_level.graph.edge_ref_count as usize,
+            );
+
+            let mut encoder = self.encode_local_moving(
+                self.device.create_command_encoder(),
+                &fine_level,
+                &workspace,
+            );
+
+            // `CoarsenGraph` sorts the matching in place, so we aggregate a copy of the community
+            // ids.
+            encoder = encoder.copy_buffer_to_buffer_slice(
+                workspace
+                    .nodes_community
+                    .view()
+                    .get(..fine_node_count as usize)
+                    .unwrap(),
+                workspace
+                    .nodes_matching
+                    .view()
+                    .get(..fine_node_count as usize)
+                    .unwrap(),
+            );
+            encoder = self.coarsen_graph.encode_with_workspace(
+                encoder,
+                CoarsenGraphWorkspaceInput {
+                    fine_nodes_edge_offset: fine_level.graph.nodes_edge_offset.view(),
+                    fine_nodes_edges: fine_level.graph.nodes_edges.view(),
+                    fine_nodes_edge_weights: fine_level.graph.nodes_edge_weights.view(),
+                    fine_nodes_matching: workspace.nodes_matching.view(),
+                    edge_channels: &[],
+                    counts: Some(CoarsenCounts {
+                        node_count: fine_level.graph.node_count_buffer.uniform(),
+                        edge_ref_count: fine_level.graph.edge_ref_count_buffer.uniform(),
+                    }),
+                },
+                CoarsenGraphOutput {
+                    fine_nodes_mapping: coarse_level.graph.fine_nodes_mapping.view(),
+                    coarse_nodes_mapping_offset: coarse_level
+                        .graph
+                        .coarse_nodes_mapping_offset
+                        .view(),
+                    coarse_nodes_mapping: coarse_level.graph.coarse_nodes_mapping.view(),
+                    coarse_node_count: coarse_level.graph.node_count_buffer.view(),
+                    coarse_edge_ref_count: coarse_level.graph.edge_ref_count_buffer.view(),
+                    coarse_nodes_edge_offset: coarse_level.graph.nodes_edge_offset.view(),
+                    coarse_nodes_edges: coarse_level.graph.nodes_edges.view(),
+                    coarse_nodes_edge_weights: coarse_level.graph.nodes_edge_weights.view(),
+                    fine_edges_coarse_edge: None,
+                },
+            );
+
+            encoder = encoder.clear_buffer_slice(coarse_level.nodes_internal_weight.view());
+            encoder = self.accumulate_internal_weights.encode(
+                encoder,
+                AccumulateInternalWeightsResources {
+                    fine_node_count: fine_level.graph.node_count_buffer.uniform(),
+                    fine_edge_ref_count: fine_level.graph.edge_ref_count_buffer.uniform(),
+                    fine_nodes_edge_offset: fine_level.graph.nodes_edge_offset.storage(),
+                    fine_nodes_edges: fine_level.graph.nodes_edges.storage(),
+                    fine_nodes_edge_weights: fine_level.graph.nodes_edge_weights.storage(),
+                    fine_nodes_internal_weight: fine_level.nodes_internal_weight.storage(),
+                    fine_nodes_mapping: coarse_level.graph.fine_nodes_mapping.storage(),
+                    coarse_nodes_internal_weight: coarse_level.nodes_internal_weight.storage(),
+                },
+                fine_node_count,
+            );
+
+            // The coarse node count is not yet known on the host, but it is no larger than the
+            // fine node count; the kernel checks the actual count on the device.
+            encoder =
+                self.encode_nodes_strength(encoder, &coarse_level, &workspace, fine_node_count);
+
+            self.device.queue().submit(encoder.finish());
+
+            coarse_level.graph.read_counts(&self.device).await?;
+
+            // If no nodes changed communities, then aggregation would not change the graph.
+            if coarse_level.graph.node_count == fine_node_count {
+                break;
+            }
+
+            let coarse_modularity = self.read_modularity(&coarse_level).await?;
+            let gain = coarse_modularity - modularity;
+
+            // The parallel local moving phase does not guarantee that the modularity improves;
+            // only keep the level if it did.
+            if gain <= 0.0 {
+                break;
+            }
+
+            let mut encoder = self.device.create_command_encoder();
+
+            encoder = self.compose_communities.encode(
+                encoder,
+                ComposeCommunitiesResources {
+                    node_count: workspace.node_count.uniform(),
+                    fine_nodes_mapping: coarse_level.graph.fine_nodes_mapping.storage(),
+                    nodes_community: workspace.result.storage(),
+                },
+                node_count as u32,
+            );
+
+            self.device.queue().submit(encoder.finish());
+
+            levels.push(CommunityLevel {
+                community_count: coarse_level.graph.node_count,
+                modularity: coarse_modularity,
+            });
+
+            modularity = coarse_modularity;
+            fine_level = coarse_level;
+
+            if gain < self.config.min_modularity_gain {
+                break;
+            }
+        }
+
+        let mut encoder = self.device.create_command_encoder();
+
+        encoder = encoder.copy_buffer_to_buffer_slice(workspace.result.view(), nodes_community);
+
+        self.device.queue().submit(encoder.finish());
+
+        Ok(CommunitySummary {
+            initial_modularity,
+            levels,
+        })
+    }
+
+    fn encode_nodes_strength(
+        &self,
+        mut encoder: CommandEncoder,
+        level: &Level,
+        workspace: &Workspace,
+        node_count: u32,
+    ) -> CommandEncoder {
+        encoder = encoder.clear_buffer_slice(workspace.total_strength.view());
+        encoder = self.compute_nodes_strength.encode(
+            encoder,
+            ComputeNodesStrengthResources {
+                node_count: level.graph.node_count_buffer.uniform(),
+                edge_ref_count: level.graph.edge_ref_count_buffer.uniform(),
+                nodes_edge_offset: level.graph.nodes_edge_offset.storage(),
+                nodes_edge_weights: level.graph.nodes_edge_weights.storage(),
+                nodes_internal_weight: level.nodes_internal_weight.storage(),
+                nodes_strength: level.nodes_strength.storage(),
+                total_strength: workspace.total_strength.storage(),
+            },
+            node_count,
+        );
+
+        encoder
+    }
+
+    fn encode_local_moving(
+        &self,
+        mut encoder: CommandEncoder,
+        level: &Level,
+        workspace: &Workspace,
+    ) -> CommandEncoder {
+        let node_count = level.graph.node_count as usize;
+
+        // Start with each node in its own community.
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            workspace.identity.view().get(..node_count).unwrap(),
+            workspace.nodes_community.view().get(..node_count).unwrap(),
+        );
+
+        for _ in 0..self.config.local_moving_rounds {
+            encoder = encoder.clear_buffer_slice(workspace.communities_strength.view());
+            encoder = encoder.clear_buffer_slice(workspace.communities_size.view());
+            encoder = self.compute_community_totals.encode(
+                encoder,
+                ComputeCommunityTotalsResources {
+                    node_count: level.graph.node_count_buffer.uniform(),
+                    nodes_community: workspace.nodes_community.storage(),
+                    nodes_strength: level.nodes_strength.storage(),
+                    communities_strength: workspace.communities_strength.storage(),
+                    communities_size: workspace.communities_size.storage(),
+                },
+                level.graph.node_count,
+            );
+            encoder = self.compute_community_moves.encode(
+                encoder,
+                ComputeCommunityMovesResources {
+                    node_count: level.graph.node_count_buffer.uniform(),
+                    edge_ref_count: level.graph.edge_ref_count_buffer.uniform(),
+                    nodes_edge_offset: level.graph.nodes_edge_offset.storage(),
+                    nodes_edges: level.graph.nodes_edges.storage(),
+                    nodes_edge_weights: level.graph.nodes_edge_weights.storage(),
+                    nodes_strength: level.nodes_strength.storage(),
+                    nodes_community: workspace.nodes_community.storage(),
+                    communities_strength: workspace.communities_strength.storage(),
+                    communities_size: workspace.communities_size.storage(),
+                    total_strength: workspace.total_strength.storage(),
+                    nodes_next_community: workspace.nodes_next_community.storage(),
+                },
+                level.graph.node_count,
+            );
+            encoder = encoder.copy_buffer_to_buffer_slice(
+                workspace
+                    .nodes_next_community
+                    .view()
+                    .get(..node_count)
+                    .unwrap(),
+                workspace.nodes_community.view().get(..node_count).unwrap(),
+            );
+        }
+
+        encoder
+    }
+
+    /// Reads back the internal weights and strengths of the `level`'s nodes and computes the
+    /// modularity of the community assignment in which each of the level's nodes is a community.
+    async fn read_modularity(&self, level: &Level) -> Result<f64, Box<dyn Error>> {
+        let len = 2 * level.graph.node_count as usize;
+
+        let internal_weight_readback = self
+            .device
+            .create_slice_buffer_zeroed(len, buffer::Usages::copy_dst().and_map_read());
+        let strength_readback = self
+            .device
+            .create_slice_buffer_zeroed(len, buffer::Usages::copy_dst().and_map_read());
+
+        let mut encoder = self.device.create_command_encoder();
+
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            level.nodes_internal_weight.view().get(..len).unwrap(),
+            internal_weight_readback.view(),
+        );
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            level.nodes_strength.view().get(..len).unwrap(),
+            strength_readback.view(),
+        );
+
+        self.device.queue().submit(encoder.finish());
+
+        let (r0, r1) = join!(
+            internal_weight_readback.map_read(),
+            strength_readback.map_read()
+        )
+        .await;
+
+        r0?;
+        r1?;
+
+        let modularity = modularity(
+            &decode_weights(&internal_weight_readback.mapped()),
+            &decode_weights(&strength_readback.mapped()),
+        );
+
+        internal_weight_readback.unmap();
+        strength_readback.unmap();
+
+        Ok(modularity)
+    }
+}
+
+/// Decodes 64-bit weights that are split over 2 consecutive `u32` values (low word first).
+fn decode_weights(raw: &[u32]) -> Vec<u64> {
+    raw.chunks_exact(2)
+        .map(|pair| pair[0] as u64 | ((pair[1] as u64) << 32))
+        .collect()
+}
+
+/// Computes the modularity of a community assignment from the total internal weight and strength of
+/// each community, where each edge inside a community counts towards its internal weight once for
+/// each of the nodes it connects.
+fn modularity(communities_internal_weight: &[u64], communities_strength: &[u64]) -> f64 {
+    let total: f64 = communities_strength.iter().map(|s| *s as f64).sum();
+
+    if total == 0.0 {
+        return 0.0;
+    }
+
+    communities_internal_weight
+        .iter()
+        .zip(communities_strength)
+        .map(|(internal, strength)| {
+            let fraction = *strength as f64 / total;
+
+            *internal as f64 / total - fraction * fraction
+        })
+        .sum()
+}
+
+/// A level in the aggregation hierarchy: the level's graph, along with the internal weight and
+/// strength of each of its nodes.
+///
+/// The internal weights and strengths are 64-bit values that are split over 2 consecutive `u32`
+/// entries (low word first), as sums of edge weights may not fit in 32 bits.
+struct Level {
+    graph: HierarchyLevel,
+    /// The summed weight of the edges between the fine nodes that a coarse node represents.
+    nodes_internal_weight: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    /// The internal weight of a node plus the weights of its edges.
+    nodes_strength: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+}
+
+impl Level {
+    fn new(device: &Device, node_capacity: usize, edge_ref_capacity: usize) -> Self {
+        let slice_usages = buffer::Usages::storage_binding()
+            .and_copy_dst()
+            .and_copy_src();
+
+        Level {
+            graph: HierarchyLevel::new(device, node_capacity, edge_ref_capacity),
+            nodes_internal_weight: device
+                .create_slice_buffer_zeroed(2 * node_capacity, slice_usages),
+            nodes_strength: device.create_slice_buffer_zeroed(2 * node_capacity, slice_usages),
+        }
+    }
+}
+
+/// Buffers that are shared by all levels; coarse levels are never larger than the base level.
+struct Workspace {
+    node_count: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+    identity: Buffer<[u32], buffer::Usages<O, O, O, O, O, O, O, X, O, O>>,
+    result: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, X, O, O>>,
+    nodes_community: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    nodes_next_community: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, X, O, O>>,
+    nodes_matching: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    communities_strength: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    communities_size: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    total_strength: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+}
+
+impl Workspace {
+    fn new(device: &Device, node_capacity: usize) -> Self {
+        let identity: Vec<u32> = (0..node_capacity as u32).collect();
+
+        Workspace {
+            node_count: device
+                .create_buffer(node_capacity as u32, buffer::Usages::uniform_binding()),
+            identity: device.create_buffer(identity.clone(), buffer::Usages::copy_src()),
+            // The community of each original node, which starts out as the node itself and is
+            // composed with the fine-to-coarse mapping of each level that is kept.
+            result: device
+                .create_buffer(identity, buffer::Usages::storage_binding().and_copy_src()),
+            nodes_community: device.create_slice_buffer_zeroed(
+                node_capacity,
+                buffer::Usages::storage_binding()
+                    .and_copy_dst()
+                    .and_copy_src(),
+            ),
+            nodes_next_community: device.create_slice_buffer_zeroed(
+                node_capacity,
+                buffer::Usages::storage_binding().and_copy_src(),
+            ),
+            nodes_matching: device.create_slice_buffer_zeroed(
+                node_capacity,
+                buffer::Usages::storage_binding().and_copy_dst(),
+            ),
+            communities_strength: device.create_slice_buffer_zeroed(
+                2 * node_capacity,
+                buffer::Usages::storage_binding().and_copy_dst(),
+            ),
+            communities_size: device.create_slice_buffer_zeroed(
+                node_capacity,
+                buffer::Usages::storage_binding().and_copy_dst(),
+            ),
+            total_strength: device
+                .create_slice_buffer_zeroed(2, buffer::Usages::storage_binding().and_copy_dst()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_weights() {
+        assert_eq!(
+            decode_weights(&[7, 0, u32::MAX, 1, 0, u32::MAX]),
+            vec![7, (1 << 32) | u32::MAX as u64, (u32::MAX as u64) << 32]
+        );
+    }
+
+    #[test]
+    fn test_modularity_two_triangles() {
+        // Two triangles (with unit edge weights) that are connected by a single edge; each triangle
+        // is a community. Each community has 3 internal edges, which count twice towards its
+        // internal weight, and a strength of 7 (its 6 internal edge refs and the connecting edge).
+        let modularity = modularity(&[6, 6], &[7, 7]);
+
+        assert!((modularity - 5.0 / 14.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_modularity_singletons() {
+        // The same graph as above, with each node in its own community.
+        let modularity = modularity(&[0; 6], &[2, 2, 3, 3, 2, 2]);
+
+        assert!((modularity + 34.0 / 196.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_modularity_single_community() {
+        let modularity = modularity(&[14], &[14]);
+
+        assert!(modularity.abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_modularity_large_weights() {
+        // Strengths that do not fit in 32 bits.
+        let weight = 3 << 32;
+        let modularity = modularity(&[weight, weight], &[weight, weight]);
+
+        assert!((modularity - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_modularity_zero_total() {
+        assert_eq!(modularity(&[0, 0], &[0, 0]), 0.0);
+        assert_eq!(modularity(&[], &[]), 0.0);
+    }
+}
@@ -0,0 +1,14 @@
+//! Modularity-based community detection.
+//!
+//! See [DetectCommunities] for an overview of the algorithm.
+
+mod accumulate_internal_weights;
+mod compose_communities;
+mod compute_community_moves;
+mod compute_community_totals;
+mod compute_nodes_strength;
+
+mod detect_communities;
+pub use self::detect_communities::{
+    CommunityConfig, CommunityInput, CommunityLevel, CommunitySummary, DetectCommunities,
+};
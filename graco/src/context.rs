@@ -0,0 +1,127 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::rc::Rc;
+
+use empa::device::Device;
+use futures::future::{LocalBoxFuture, Shared};
+use futures::FutureExt;
+
+use crate::dispatch::DispatchConfig;
+
+// Caches the (possibly still in-flight) initialization of each instance, so that components that
+// request the same instance concurrently (e.g. inside a `join!`) await the same initialization,
+// rather than each compiling their own pipelines.
+type Cache = HashMap<(TypeId, &'static str), Shared<LocalBoxFuture<'static, Rc<dyn Any>>>>;
+
+/// Shares compiled pipelines between graco components that run on the same device.
+///
+/// Components created with an `init_with_context` constructor (e.g.
+/// [CoarsenGraph::init_with_context](crate::CoarsenGraph::init_with_context)) obtain their
+/// sub-pipelines from the context, so that each distinct shader (including the empa-tk sort, scan
+/// and gather pipelines) is only compiled once per context, regardless of how many components use
+/// it. Cloning a context is cheap; clones share the same cache.
+///
+/// Components created with a plain `init` constructor use a private context.
+///
+/// All shaders created through a context use the context's [DispatchConfig].
+#[derive(Clone)]
+pub struct Context {
+    device: Device,
+    dispatch_config: DispatchConfig,
+    cache: Rc<RefCell<Cache>>,
+}
+
+impl Context {
+    /// Creates a new context for the `device`, with a [DispatchConfig] derived from the device's
+    /// limits (see [DispatchConfig::for_device]).
+    pub fn new(device: Device) -> Self {
+        let dispatch_config = DispatchConfig::for_device(&device);
+
+        Self::with_dispatch_config(device, dispatch_config)
+    }
+
+    /// Creates a new context for the `device` that uses the given `dispatch_config`.
+    pub fn with_dispatch_config(device: Device, dispatch_config: DispatchConfig) -> Self {
+        Context {
+            device,
+            dispatch_config,
+            cache: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn dispatch_config(&self) -> DispatchConfig {
+        self.dispatch_config
+    }
+
+    /// Returns the cached instance of `T` for the given `key`, or initializes (and caches) a new
+    /// instance with `init` if there is no such instance yet. If another caller is still
+    /// initializing the instance, this awaits that initialization rather than starting a new one.
+    ///
+    /// The `key` distinguishes between different instances of the same type (e.g. an inclusive
+    /// and an exclusive prefix-sum); by convention, it is the name of the constructor that is used
+    /// to initialize the instance.
+    ///
+    /// This may be used to share the pipelines of components outside of graco.
+    pub async fn get_or_init<T, F, Fut>(&self, key: &'static str, init: F) -> Rc<T>
+    where
+        T: 'static,
+        F: FnOnce(Device) -> Fut,
+        Fut: Future<Output = T> + 'static,
+    {
+        let key = (TypeId::of::<T>(), key);
+
+        // Clone the shared future out of the cache before awaiting it, so that we don't hold on to
+        // the borrow while other components access the cache.
+        let value = self
+            .cache
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| {
+                let init = init(self.device.clone());
+
+                async move { Rc::new(init.await) as Rc<dyn Any> }
+                    .boxed_local()
+                    .shared()
+            })
+            .clone();
+
+        value.await.downcast().unwrap()
+    }
+
+    /// Same as [get_or_init](Context::get_or_init), but for instances that need to be borrowed
+    /// mutably to be used.
+    pub async fn get_or_init_mut<T, F, Fut>(&self, key: &'static str, init: F) -> Rc<RefCell<T>>
+    where
+        T: 'static,
+        F: FnOnce(Device) -> Fut,
+        Fut: Future<Output = T> + 'static,
+    {
+        self.get_or_init(key, |device| {
+            let init = init(device);
+
+            async move { RefCell::new(init.await) }
+        })
+        .await
+    }
+
+    /// Same as [get_or_init](Context::get_or_init), but for kernels that are initialized with the
+    /// context's [DispatchConfig], such as graco's own kernels and
+    /// [GenerateDispatch](crate::GenerateDispatch).
+    pub async fn get_or_init_kernel<T, F, Fut>(&self, init: F) -> Rc<T>
+    where
+        T: 'static,
+        F: FnOnce(Device, DispatchConfig) -> Fut,
+        Fut: Future<Output = T> + 'static,
+    {
+        let dispatch_config = self.dispatch_config;
+
+        self.get_or_init("init", |device| init(device, dispatch_config))
+            .await
+    }
+}
@@ -1,42 +1,186 @@
-use empa::buffer::{Buffer, Uniform, Usages};
+use std::sync::{Arc, Mutex};
+
+use empa::buffer::{Buffer, Storage, Uniform, Usages};
 use empa::device::Device;
+use empa::resource_binding::BindGroup;
 use empa::type_flag::{O, X};
 
-pub enum FallbackCounts<'a> {
+// The number of distinct fallback count pairs and bind groups to retain. Interactive use typically
+// re-encodes the same graph (1 entry), multi-level use encodes 1 graph per level; this comfortably
+// covers typical hierarchy depths without retaining an unbounded number of buffers.
+const CACHE_CAPACITY: usize = 16;
+
+pub struct FallbackCounts<'a> {
+    inner: FallbackCountsInner<'a>,
+}
+
+enum FallbackCountsInner<'a> {
     Binding(Uniform<'a, u32>, Uniform<'a, u32>),
-    Buffer(
-        Buffer<u32, Usages<O, O, O, X, O, O, O, O, O, O>>,
-        Buffer<u32, Usages<O, O, O, X, O, O, O, O, O, O>>,
-    ),
+    Cached(Arc<CacheEntry>),
+}
+
+impl FallbackCounts<'_> {
+    pub fn node_count(&self) -> Uniform<u32> {
+        match &self.inner {
+            FallbackCountsInner::Binding(b0, _) => b0.clone(),
+            FallbackCountsInner::Cached(entry) => entry.b0.uniform(),
+        }
+    }
+
+    pub fn edge_ref_count(&self) -> Uniform<u32> {
+        match &self.inner {
+            FallbackCountsInner::Binding(_, b1) => b1.clone(),
+            FallbackCountsInner::Cached(entry) => entry.b1.uniform(),
+        }
+    }
 }
 
-impl<'a> FallbackCounts<'a> {
-    pub fn new(
+struct CacheEntry {
+    counts: (u32, u32),
+    b0: Buffer<u32, Usages<O, O, O, X, O, O, O, O, O, O>>,
+    b1: Buffer<u32, Usages<O, O, O, X, O, O, O, O, O, O>>,
+}
+
+/// Provides uniform buffers for the fallback counts that are used if the caller of a pipeline
+/// does not provide count bindings, reusing previously created buffers if the same counts are
+/// requested again.
+///
+/// The cache is not observable by the pipelines' callers, so it uses interior mutability; this
+/// allows pipelines to encode through a shared reference.
+pub struct FallbackCountsCache {
+    device: Device,
+    // Ordered from least recently used to most recently used.
+    entries: Mutex<Vec<Arc<CacheEntry>>>,
+}
+
+impl FallbackCountsCache {
+    pub fn new(device: Device) -> Self {
+        FallbackCountsCache {
+            device,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn get<'a>(
+        &self,
         binding: Option<(Uniform<'a, u32>, Uniform<'a, u32>)>,
-        device: &Device,
         fallback_counts: (u32, u32),
-    ) -> Self {
+    ) -> FallbackCounts<'a> {
         if let Some((b0, b1)) = binding {
-            Self::Binding(b0, b1)
+            return FallbackCounts {
+                inner: FallbackCountsInner::Binding(b0, b1),
+            };
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+
+        let entry = if let Some(index) = entries
+            .iter()
+            .position(|entry| entry.counts == fallback_counts)
+        {
+            entries.remove(index)
         } else {
-            let b0 = device.create_buffer(fallback_counts.0, Usages::uniform_binding());
-            let b1 = device.create_buffer(fallback_counts.1, Usages::uniform_binding());
+            if entries.len() == CACHE_CAPACITY {
+                entries.remove(0);
+            }
 
-            Self::Buffer(b0, b1)
+            Arc::new(CacheEntry {
+                counts: fallback_counts,
+                b0: self
+                    .device
+                    .create_buffer(fallback_counts.0, Usages::uniform_binding()),
+                b1: self
+                    .device
+                    .create_buffer(fallback_counts.1, Usages::uniform_binding()),
+            })
+        };
+
+        entries.push(entry.clone());
+
+        FallbackCounts {
+            inner: FallbackCountsInner::Cached(entry),
         }
     }
+}
 
-    pub fn node_count(&self) -> Uniform<u32> {
-        match self {
-            FallbackCounts::Binding(b0, _) => b0.clone(),
-            FallbackCounts::Buffer(b0, _) => b0.uniform(),
+/// Identifies the range of a buffer that a binding refers to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BindingKey {
+    buffer: usize,
+    offset: usize,
+    size: usize,
+}
+
+/// A resource binding that can be used to key a [BindGroupCache].
+pub trait Binding {
+    fn binding_key(&self) -> BindingKey;
+}
+
+impl<T: ?Sized> Binding for Uniform<'_, T> {
+    fn binding_key(&self) -> BindingKey {
+        BindingKey {
+            buffer: self.buffer_id(),
+            offset: self.offset_in_bytes(),
+            size: self.size_in_bytes(),
         }
     }
+}
 
-    pub fn edge_ref_count(&self) -> Uniform<u32> {
-        match self {
-            FallbackCounts::Binding(_, b1) => b1.clone(),
-            FallbackCounts::Buffer(_, b1) => b1.uniform(),
+impl<T: ?Sized, A> Binding for Storage<'_, T, A> {
+    fn binding_key(&self) -> BindingKey {
+        BindingKey {
+            buffer: self.buffer_id(),
+            offset: self.offset_in_bytes(),
+            size: self.size_in_bytes(),
+        }
+    }
+}
+
+/// Reuses the bind groups of a pipeline if it is encoded again with the same buffer ranges (which
+/// includes the count buffers provided by a [FallbackCountsCache]), rather than creating new bind
+/// groups on every `encode` call.
+///
+/// A cached bind group keeps the buffers it binds alive, so a buffer's identity can not be reused
+/// for a different buffer while the cache holds a bind group for it.
+pub struct BindGroupCache<L> {
+    // Ordered from least recently used to most recently used.
+    entries: Mutex<Vec<(Vec<BindingKey>, Arc<BindGroup<L>>)>>,
+}
+
+impl<L> BindGroupCache<L> {
+    /// Returns the cached bind group for the bindings identified by the `key`, or creates it with
+    /// `create` if there is none.
+    pub fn get_or_create<F>(&self, key: &[BindingKey], create: F) -> Arc<BindGroup<L>>
+    where
+        F: FnOnce() -> BindGroup<L>,
+    {
+        let mut entries = self.entries.lock().unwrap();
+
+        if let Some(index) = entries.iter().position(|(k, _)| k.as_slice() == key) {
+            let entry = entries.remove(index);
+            let bind_group = entry.1.clone();
+
+            entries.push(entry);
+
+            return bind_group;
+        }
+
+        if entries.len() == CACHE_CAPACITY {
+            entries.remove(0);
+        }
+
+        let bind_group = Arc::new(create());
+
+        entries.push((key.to_vec(), bind_group.clone()));
+
+        bind_group
+    }
+}
+
+impl<L> Default for BindGroupCache<L> {
+    fn default() -> Self {
+        BindGroupCache {
+            entries: Mutex::new(Vec::new()),
         }
     }
 }
@@ -0,0 +1,91 @@
+use empa::command::DispatchWorkgroups;
+use empa::device::Device;
+
+/// The preferred workgroup size; the actual workgroup size may be smaller if the device does not
+/// support workgroups of this size.
+const PREFERRED_GROUP_SIZE: u32 = 256;
+
+/// Controls the workgroup size of graco's compute shaders and how work is spread over
+/// workgroups.
+///
+/// The workgroup size is applied to the shaders as a pipeline-overridable constant, so changing
+/// it does not require recompiling the shader source. If the number of workgroups needed to
+/// process a buffer exceeds `max_workgroups_per_dimension`, the workgroups are dispatched as a 2D
+/// grid instead, which allows graphs with hundreds of millions of edge references to be
+/// processed.
+///
+/// A [DispatchConfig] may be provided to a [Context](crate::Context) with
+/// [Context::with_dispatch_config](crate::Context::with_dispatch_config); components that are
+/// initialized without a context use [DispatchConfig::for_device].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DispatchConfig {
+    /// The number of invocations per workgroup.
+    ///
+    /// Must be a power of 2 and must not exceed the device's `max_compute_workgroup_size_x` and
+    /// `max_compute_invocations_per_workgroup` limits.
+    pub group_size: u32,
+
+    /// The maximum number of workgroups to dispatch along a single dimension.
+    ///
+    /// Must not exceed the device's `max_compute_workgroups_per_dimension` limit.
+    pub max_workgroups_per_dimension: u32,
+}
+
+impl DispatchConfig {
+    /// Derives a dispatch configuration from the limits of the given `device`.
+    pub fn for_device(device: &Device) -> Self {
+        let limits = device.limits();
+
+        let max_group_size = limits
+            .max_compute_workgroup_size_x
+            .min(limits.max_compute_invocations_per_workgroup);
+
+        // Round down to a power of 2, so that all invocations in a workgroup map neatly onto
+        // subgroups.
+        let group_size = PREFERRED_GROUP_SIZE.min(1 << max_group_size.ilog2());
+
+        DispatchConfig {
+            group_size,
+            max_workgroups_per_dimension: limits.max_compute_workgroups_per_dimension,
+        }
+    }
+
+    /// Returns the workgroups to dispatch to run at least `count` invocations.
+    ///
+    /// Mirrors `dispatch_workgroups` in `dispatch.wgsl`.
+    pub(crate) fn workgroups(&self, count: u32) -> DispatchWorkgroups {
+        let workgroups = count.div_ceil(self.group_size);
+
+        if workgroups <= self.max_workgroups_per_dimension {
+            DispatchWorkgroups {
+                count_x: workgroups,
+                count_y: 1,
+                count_z: 1,
+            }
+        } else {
+            let count_y = workgroups.div_ceil(self.max_workgroups_per_dimension);
+
+            DispatchWorkgroups {
+                count_x: workgroups.div_ceil(count_y),
+                count_y,
+                count_z: 1,
+            }
+        }
+    }
+
+    /// Returns the pipeline-overridable constants declared in `dispatch.wgsl`, which shaders that
+    /// include it must be created with.
+    pub fn constants(&self) -> DispatchConstants {
+        DispatchConstants {
+            group_size: self.group_size,
+            max_workgroups_per_dimension: self.max_workgroups_per_dimension,
+        }
+    }
+}
+
+/// The pipeline-overridable constants declared in `dispatch.wgsl`.
+#[derive(empa::compute_pipeline::PipelineConstants)]
+pub struct DispatchConstants {
+    group_size: u32,
+    max_workgroups_per_dimension: u32,
+}
@@ -0,0 +1,37 @@
+#pragma once
+
+// These are overridden with the values of the `DispatchConfig` that the pipeline was created with.
+override group_size: u32 = 256;
+override max_workgroups_per_dimension: u32 = 65535;
+
+struct DispatchWorkgroups {
+    x: u32,
+    y: u32,
+    z: u32
+}
+
+fn div_ceil(a: u32, b: u32) -> u32 {
+    return (a + b - 1) / b;
+}
+
+// Returns the dispatch that covers `count` invocations. If a 1D dispatch would exceed the maximum number of
+// workgroups per dimension, then this spreads the workgroups over 2 dimensions; use `global_index` to recover the
+// linear invocation index. This may dispatch more invocations than requested, so shaders must still check the index
+// against the count.
+fn dispatch_workgroups(count: u32) -> DispatchWorkgroups {
+    let workgroups = div_ceil(count, group_size);
+
+    if workgroups <= max_workgroups_per_dimension {
+        return DispatchWorkgroups(workgroups, 1, 1);
+    }
+
+    let count_y = div_ceil(workgroups, max_workgroups_per_dimension);
+    let count_x = div_ceil(workgroups, count_y);
+
+    return DispatchWorkgroups(count_x, count_y, 1);
+}
+
+// Returns the linear invocation index for a (potentially 2D) dispatch produced by `dispatch_workgroups`.
+fn global_index(global_id: vec3<u32>, num_workgroups: vec3<u32>) -> u32 {
+    return global_id.x + global_id.y * num_workgroups.x * group_size;
+}
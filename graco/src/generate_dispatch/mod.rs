@@ -0,0 +1,99 @@
+use empa::access_mode::ReadWrite;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, DispatchWorkgroups, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct GenerateDispatchResources<'a> {
+    /// The number of invocations the dispatch must cover.
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub count: Uniform<'a, u32>,
+    /// Receives the dispatch, for use with `dispatch_workgroups_indirect`.
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub dispatch: Storage<'a, DispatchWorkgroups, ReadWrite>,
+}
+
+type ResourcesLayout =
+    <GenerateDispatchResources<'static> as empa::resource_binding::Resources>::Layout;
+
+/// Generates an indirect dispatch that covers a GPU-side invocation count.
+///
+/// The dispatch uses the workgroup size of the [DispatchConfig] the kernel was initialized with,
+/// and falls back to a 2D grid of workgroups if a 1D dispatch would exceed the config's
+/// `max_workgroups_per_dimension`. Shaders that are dispatched with it should be created with the
+/// same [DispatchConfig] and recover the linear invocation index with `global_index` (see
+/// `dispatch.wgsl`).
+///
+/// Obtain a shared instance with
+/// [Context::get_or_init_kernel](crate::Context::get_or_init_kernel).
+pub struct GenerateDispatch {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+}
+
+impl GenerateDispatch {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        GenerateDispatch {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        encoder: CommandEncoder,
+        resources: GenerateDispatchResources,
+    ) -> CommandEncoder {
+        let key = [
+            resources.count.binding_key(),
+            resources.dispatch.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group)
+            .dispatch_workgroups(DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            })
+            .end()
+    }
+}
@@ -0,0 +1,12 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> count: u32;
+
+@group(0) @binding(1)
+var<storage, read_write> dispatch: DispatchWorkgroups;
+
+@compute @workgroup_size(1, 1, 1)
+fn main() {
+    dispatch = dispatch_workgroups(count);
+}
@@ -0,0 +1,141 @@
+use crate::generators::generated_graph::build_weighted;
+use crate::generators::{EdgeWeighting, GeneratedGraph, NodePositions};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BarabasiAlbertConfig {
+    pub node_count: u32,
+    /// The number of edges each newly added node attaches to existing nodes.
+    pub edges_per_node: u32,
+    pub seed: u64,
+    pub weighting: EdgeWeighting,
+    /// If enabled, nodes are assigned random positions in the unit square.
+    pub output_positions: bool,
+}
+
+impl Default for BarabasiAlbertConfig {
+    fn default() -> Self {
+        BarabasiAlbertConfig {
+            node_count: 1024,
+            edges_per_node: 3,
+            seed: 1,
+            weighting: EdgeWeighting::Random { min: 1, max: 1000 },
+            output_positions: false,
+        }
+    }
+}
+
+/// Generates a Barabási–Albert preferential attachment graph, which has a scale-free (power-law)
+/// degree distribution.
+///
+/// Starts from a clique of `edges_per_node + 1` nodes; each subsequent node attaches to
+/// `edges_per_node` distinct existing nodes, chosen with a probability proportional to their
+/// degree.
+pub fn barabasi_albert(config: &BarabasiAlbertConfig) -> GeneratedGraph {
+    let BarabasiAlbertConfig {
+        node_count,
+        edges_per_node,
+        seed,
+        weighting,
+        output_positions,
+    } = *config;
+
+    assert!(
+        edges_per_node > 0,
+        "`edges_per_node` must be greater than `0`"
+    );
+
+    let mut rng = oorandom::Rand32::new(seed);
+
+    let initial_count = (edges_per_node + 1).min(node_count);
+
+    let mut edges = Vec::new();
+
+    // Every edge end-point is recorded in this list; sampling uniformly from this list is
+    // equivalent to sampling nodes with a probability proportional to their degree.
+    let mut endpoints = Vec::new();
+
+    for b in 0..initial_count {
+        for a in 0..b {
+            edges.push((a, b));
+            endpoints.push(a);
+            endpoints.push(b);
+        }
+    }
+
+    let mut targets = Vec::with_capacity(edges_per_node as usize);
+
+    for node in initial_count..node_count {
+        targets.clear();
+
+        while targets.len() < edges_per_node as usize {
+            let candidate = endpoints[rng.rand_range(0..endpoints.len() as u32) as usize];
+
+            if !targets.contains(&candidate) {
+                targets.push(candidate);
+            }
+        }
+
+        for &target in &targets {
+            edges.push((target, node));
+            endpoints.push(target);
+            endpoints.push(node);
+        }
+    }
+
+    let positions = NodePositions::random_2d(&mut rng, node_count as usize);
+
+    build_weighted(
+        &mut rng,
+        node_count as usize,
+        edges,
+        weighting,
+        positions,
+        output_positions,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::generated_graph::tests::assert_csr_invariants;
+
+    #[test]
+    fn test_barabasi_albert() {
+        let config = BarabasiAlbertConfig {
+            node_count: 50,
+            edges_per_node: 3,
+            ..Default::default()
+        };
+        let graph = barabasi_albert(&config);
+
+        assert_csr_invariants(&graph);
+        assert_eq!(graph.node_count(), 50);
+        // The initial clique of 4 nodes has 6 edges, every other node adds 3 distinct edges.
+        assert_eq!(graph.edge_ref_count(), 2 * (6 + 46 * 3));
+
+        for node in 0..50 {
+            let degree = graph
+                .nodes_edge_offset
+                .get(node + 1)
+                .copied()
+                .unwrap_or(graph.edge_ref_count() as u32)
+                - graph.nodes_edge_offset[node];
+
+            assert!(degree >= 3);
+        }
+
+        assert_eq!(barabasi_albert(&config), graph);
+    }
+
+    #[test]
+    fn test_fewer_nodes_than_clique() {
+        let graph = barabasi_albert(&BarabasiAlbertConfig {
+            node_count: 3,
+            edges_per_node: 4,
+            ..Default::default()
+        });
+
+        assert_csr_invariants(&graph);
+        assert_eq!(graph.edge_ref_count(), 6);
+    }
+}
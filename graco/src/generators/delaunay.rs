@@ -0,0 +1,376 @@
+use crate::generators::generated_graph::build_weighted;
+use crate::generators::{EdgeWeighting, GeneratedGraph, NodePositions};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DelaunayConfig {
+    pub node_count: u32,
+    pub seed: u64,
+    pub weighting: EdgeWeighting,
+    pub output_positions: bool,
+}
+
+impl Default for DelaunayConfig {
+    fn default() -> Self {
+        DelaunayConfig {
+            node_count: 1024,
+            seed: 1,
+            weighting: EdgeWeighting::InverseDistance { scale: 1000.0 },
+            output_positions: true,
+        }
+    }
+}
+
+/// Generates the Delaunay triangulation of points placed uniformly at random in the unit square.
+///
+/// The resulting graphs are planar, with an average degree just under `6`, which makes them a good
+/// model for mesh-like inputs.
+pub fn delaunay(config: &DelaunayConfig) -> GeneratedGraph {
+    let DelaunayConfig {
+        node_count,
+        seed,
+        weighting,
+        output_positions,
+    } = *config;
+
+    let mut rng = oorandom::Rand32::new(seed);
+
+    let positions = NodePositions::random_2d(&mut rng, node_count as usize);
+    let points: Vec<[f64; 2]> = positions
+        .as_2d()
+        .unwrap()
+        .iter()
+        .map(|p| [p.0 as f64, p.1 as f64])
+        .collect();
+
+    let edges = triangulate(&points, &mut rng);
+
+    build_weighted(
+        &mut rng,
+        node_count as usize,
+        edges,
+        weighting,
+        positions,
+        output_positions,
+    )
+}
+
+const NONE: u32 = u32::MAX;
+
+struct Triangle {
+    // The vertices in counter-clockwise order.
+    vertices: [u32; 3],
+    // The neighbour opposite to each vertex (the neighbour that shares the edge that does not
+    // include the vertex), or `NONE` if there is no such neighbour.
+    neighbours: [u32; 3],
+    alive: bool,
+}
+
+struct BoundaryEdge {
+    a: u32,
+    b: u32,
+    outside: u32,
+    cavity_triangle: u32,
+}
+
+fn orient(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+// Positive if `d` lies inside the circumcircle of the counter-clockwise triangle `a, b, c`.
+fn in_circle(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> f64 {
+    let a_x = a[0] - d[0];
+    let a_y = a[1] - d[1];
+    let b_x = b[0] - d[0];
+    let b_y = b[1] - d[1];
+    let c_x = c[0] - d[0];
+    let c_y = c[1] - d[1];
+
+    let a_l = a_x * a_x + a_y * a_y;
+    let b_l = b_x * b_x + b_y * b_y;
+    let c_l = c_x * c_x + c_y * c_y;
+
+    a_x * (b_y * c_l - b_l * c_y) - a_y * (b_x * c_l - b_l * c_x) + a_l * (b_x * c_y - b_y * c_x)
+}
+
+// Incremental Bowyer-Watson triangulation. Points are inserted in a spatially coherent order, so
+// that the point location walk from the previously inserted triangle stays short.
+fn triangulate(points: &[[f64; 2]], rng: &mut oorandom::Rand32) -> Vec<(u32, u32)> {
+    let point_count = points.len();
+
+    if point_count < 2 {
+        return Vec::new();
+    }
+
+    // Add the vertices of a "super triangle" that encloses the unit square by a wide margin.
+    let mut vertices = points.to_vec();
+
+    vertices.push([-100.0, -100.0]);
+    vertices.push([300.0, -100.0]);
+    vertices.push([-100.0, 300.0]);
+
+    let super_base = point_count as u32;
+
+    let mut triangles = vec![Triangle {
+        vertices: [super_base, super_base + 1, super_base + 2],
+        neighbours: [NONE; 3],
+        alive: true,
+    }];
+
+    // Order the points along a "snake" through a grid of cells.
+    let cells_per_side = ((point_count as f64).sqrt() as usize).max(1);
+    let cell_of = |p: [f64; 2]| -> (usize, usize) {
+        let x = ((p[0] * cells_per_side as f64) as usize).min(cells_per_side - 1);
+        let y = ((p[1] * cells_per_side as f64) as usize).min(cells_per_side - 1);
+
+        (x, y)
+    };
+
+    let mut insertion_order: Vec<u32> = (0..point_count as u32).collect();
+
+    insertion_order.sort_by_key(|&index| {
+        let (x, y) = cell_of(points[index as usize]);
+        let x = if y % 2 == 0 {
+            x
+        } else {
+            cells_per_side - 1 - x
+        };
+
+        (y, x)
+    });
+
+    let mut cavity_stamp: Vec<u32> = vec![0];
+    let mut cavity = Vec::new();
+    let mut stack = Vec::new();
+    let mut boundary = Vec::new();
+    let mut last_triangle = 0u32;
+
+    for (stamp, &point_index) in insertion_order.iter().enumerate() {
+        let stamp = stamp as u32 + 1;
+        let p = vertices[point_index as usize];
+
+        // Locate the triangle that contains the point by walking from the last triangle towards
+        // the point. We start testing the edges at a random offset to avoid cycling.
+        let mut current = last_triangle;
+
+        'walk: loop {
+            let triangle = &triangles[current as usize];
+            let offset = rng.rand_range(0..3) as usize;
+
+            for k in 0..3 {
+                let i = (k + offset) % 3;
+                let a = vertices[triangle.vertices[(i + 1) % 3] as usize];
+                let b = vertices[triangle.vertices[(i + 2) % 3] as usize];
+
+                if orient(a, b, p) < 0.0 && triangle.neighbours[i] != NONE {
+                    current = triangle.neighbours[i];
+
+                    continue 'walk;
+                }
+            }
+
+            break;
+        }
+
+        // Skip exact duplicates of existing vertices, they would produce degenerate triangles.
+        if triangles[current as usize]
+            .vertices
+            .iter()
+            .any(|&v| vertices[v as usize] == p)
+        {
+            continue;
+        }
+
+        // Find the cavity: the connected set of triangles whose circumcircle contains the point.
+        cavity.clear();
+        boundary.clear();
+        stack.clear();
+
+        cavity_stamp[current as usize] = stamp;
+        cavity.push(current);
+        stack.push(current);
+
+        while let Some(t) = stack.pop() {
+            for i in 0..3 {
+                let triangle = &triangles[t as usize];
+                let a = triangle.vertices[(i + 1) % 3];
+                let b = triangle.vertices[(i + 2) % 3];
+                let neighbour = triangle.neighbours[i];
+
+                if neighbour != NONE {
+                    if cavity_stamp[neighbour as usize] == stamp {
+                        continue;
+                    }
+
+                    let [n_a, n_b, n_c] = triangles[neighbour as usize].vertices;
+                    let in_cavity = in_circle(
+                        vertices[n_a as usize],
+                        vertices[n_b as usize],
+                        vertices[n_c as usize],
+                        p,
+                    ) > 0.0;
+
+                    if in_cavity {
+                        cavity_stamp[neighbour as usize] = stamp;
+                        cavity.push(neighbour);
+                        stack.push(neighbour);
+
+                        continue;
+                    }
+                }
+
+                boundary.push(BoundaryEdge {
+                    a,
+                    b,
+                    outside: neighbour,
+                    cavity_triangle: t,
+                });
+            }
+        }
+
+        for &t in &cavity {
+            triangles[t as usize].alive = false;
+        }
+
+        // Fill the cavity with a fan of new triangles that connect the point to the cavity's
+        // boundary edges.
+        let first_new = triangles.len() as u32;
+
+        for edge in &boundary {
+            let new_index = triangles.len() as u32;
+
+            triangles.push(Triangle {
+                vertices: [edge.a, edge.b, point_index],
+                neighbours: [NONE, NONE, edge.outside],
+                alive: true,
+            });
+            cavity_stamp.push(0);
+
+            if edge.outside != NONE {
+                let outside = &mut triangles[edge.outside as usize];
+
+                for neighbour in outside.neighbours.iter_mut() {
+                    if *neighbour == edge.cavity_triangle {
+                        *neighbour = new_index;
+                    }
+                }
+            }
+        }
+
+        // Link the new triangles to each other: the new triangle `[a, b, p]` shares its edge
+        // `(b, p)` with the new triangle that starts at `b`, and its edge `(p, a)` with the new
+        // triangle that ends at `a`.
+        for i in 0..boundary.len() {
+            let a = boundary[i].a;
+            let b = boundary[i].b;
+
+            let starts_at_b = boundary.iter().position(|edge| edge.a == b);
+            let ends_at_a = boundary.iter().position(|edge| edge.b == a);
+
+            let triangle = &mut triangles[first_new as usize + i];
+
+            if let Some(j) = starts_at_b {
+                triangle.neighbours[0] = first_new + j as u32;
+            }
+
+            if let Some(j) = ends_at_a {
+                triangle.neighbours[1] = first_new + j as u32;
+            }
+        }
+
+        last_triangle = first_new;
+    }
+
+    let mut edges = Vec::with_capacity(point_count * 3);
+
+    for triangle in triangles.iter().filter(|t| t.alive) {
+        for i in 0..3 {
+            let a = triangle.vertices[i];
+            let b = triangle.vertices[(i + 1) % 3];
+
+            // The super triangle encloses all points, so every edge between 2 points is shared by 2
+            // alive triangles (with opposite orientations); only emit it once.
+            if a < super_base && b < super_base && a < b {
+                edges.push((a, b));
+            }
+        }
+    }
+
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::generated_graph::tests::assert_csr_invariants;
+
+    // Returns the number of points on the convex hull (Andrew's monotone chain).
+    fn hull_size(points: &[[f64; 2]]) -> usize {
+        let mut sorted = points.to_vec();
+
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let mut hull: Vec<[f64; 2]> = Vec::new();
+
+        for pass in 0..2 {
+            let start = hull.len();
+
+            for &p in &sorted {
+                while hull.len() >= start + 2
+                    && orient(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0
+                {
+                    hull.pop();
+                }
+
+                hull.push(p);
+            }
+
+            // The last point of each chain is the first point of the other chain.
+            hull.pop();
+
+            if pass == 0 {
+                sorted.reverse();
+            }
+        }
+
+        hull.len()
+    }
+
+    #[test]
+    fn test_delaunay() {
+        let config = DelaunayConfig {
+            node_count: 200,
+            ..Default::default()
+        };
+        let graph = delaunay(&config);
+
+        assert_csr_invariants(&graph);
+
+        let points: Vec<[f64; 2]> = graph
+            .nodes_position
+            .as_ref()
+            .unwrap()
+            .as_2d()
+            .unwrap()
+            .iter()
+            .map(|p| [p.0 as f64, p.1 as f64])
+            .collect();
+
+        // A triangulation of `n` points (in general position) of which `h` lie on the convex hull
+        // has `3n - 3 - h` edges.
+        let expected_edge_count = 3 * 200 - 3 - hull_size(&points);
+
+        assert_eq!(graph.edge_ref_count(), 2 * expected_edge_count);
+        assert_eq!(delaunay(&config), graph);
+    }
+
+    #[test]
+    fn test_triangle() {
+        let graph = delaunay(&DelaunayConfig {
+            node_count: 3,
+            ..Default::default()
+        });
+
+        assert_csr_invariants(&graph);
+        assert_eq!(graph.nodes_edges, vec![1, 2, 0, 2, 0, 1]);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::generators::generated_graph::build_weighted;
+use crate::generators::{EdgeWeighting, GeneratedGraph, NodePositions};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ErdosRenyiConfig {
+    pub node_count: u32,
+    /// The probability with which any pair of nodes is connected.
+    pub edge_probability: f64,
+    pub seed: u64,
+    pub weighting: EdgeWeighting,
+    /// If enabled, nodes are assigned random positions in the unit square.
+    pub output_positions: bool,
+}
+
+impl Default for ErdosRenyiConfig {
+    fn default() -> Self {
+        ErdosRenyiConfig {
+            node_count: 1024,
+            edge_probability: 0.005,
+            seed: 1,
+            weighting: EdgeWeighting::Random { min: 1, max: 1000 },
+            output_positions: false,
+        }
+    }
+}
+
+/// Generates an Erdős–Rényi `G(n, p)` random graph, in which each pair of nodes is connected
+/// independently with probability `p`.
+pub fn erdos_renyi(config: &ErdosRenyiConfig) -> GeneratedGraph {
+    let ErdosRenyiConfig {
+        node_count,
+        edge_probability,
+        seed,
+        weighting,
+        output_positions,
+    } = *config;
+
+    let mut rng = oorandom::Rand32::new(seed);
+
+    let positions = NodePositions::random_2d(&mut rng, node_count as usize);
+
+    let mut edges = Vec::new();
+
+    if edge_probability >= 1.0 {
+        for b in 0..node_count {
+            for a in 0..b {
+                edges.push((a, b));
+            }
+        }
+    } else if edge_probability > 0.0 {
+        // Rather than flipping a coin for every pair of nodes, we sample the gaps between
+        // consecutive edges from a geometric distribution, see Batagelj and Brandes "Efficient
+        // generation of large random networks". This runs in time linear in the number of edges,
+        // rather than quadratic in the number of nodes.
+        let log_q = (1.0 - edge_probability).ln();
+
+        let mut a: i64 = -1;
+        let mut b: i64 = 1;
+
+        while b < node_count as i64 {
+            let r = 1.0 - rng.rand_float() as f64;
+
+            a += 1 + (r.ln() / log_q).floor() as i64;
+
+            while a >= b && b < node_count as i64 {
+                a -= b;
+                b += 1;
+            }
+
+            if b < node_count as i64 {
+                edges.push((a as u32, b as u32));
+            }
+        }
+    }
+
+    build_weighted(
+        &mut rng,
+        node_count as usize,
+        edges,
+        weighting,
+        positions,
+        output_positions,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::generated_graph::tests::assert_csr_invariants;
+
+    #[test]
+    fn test_complete_graph() {
+        let graph = erdos_renyi(&ErdosRenyiConfig {
+            node_count: 6,
+            edge_probability: 1.0,
+            ..Default::default()
+        });
+
+        assert_csr_invariants(&graph);
+        assert_eq!(graph.edge_ref_count(), 6 * 5);
+    }
+
+    #[test]
+    fn test_empty_graph() {
+        let graph = erdos_renyi(&ErdosRenyiConfig {
+            node_count: 6,
+            edge_probability: 0.0,
+            ..Default::default()
+        });
+
+        assert_eq!(graph.nodes_edge_offset, vec![0; 6]);
+        assert_eq!(graph.edge_ref_count(), 0);
+    }
+
+    #[test]
+    fn test_random_graph() {
+        let config = ErdosRenyiConfig {
+            node_count: 200,
+            edge_probability: 0.1,
+            ..Default::default()
+        };
+        let graph = erdos_renyi(&config);
+
+        assert_csr_invariants(&graph);
+
+        // The expected number of edges is `0.1 * 200 * 199 / 2 = 1990`.
+        let edge_count = graph.edge_ref_count() / 2;
+
+        assert!((1700..2300).contains(&edge_count), "{}", edge_count);
+        assert_eq!(erdos_renyi(&config), graph);
+    }
+}
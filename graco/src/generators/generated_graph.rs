@@ -0,0 +1,336 @@
+use empa::abi;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum NodePositions {
+    Dim2(Vec<abi::Vec2<f32>>),
+    Dim3(Vec<abi::Vec3<f32>>),
+}
+
+/// Determines how edge weights are assigned by the generators.
+///
+/// Note that the matching pipeline never matches along edges with a weight of `0`; the
+/// generators will therefore never produce edge weights smaller than `1`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EdgeWeighting {
+    /// All edges get the same weight.
+    Constant(u32),
+    /// Each edge gets a weight chosen uniformly at random from the range `min..=max`.
+    Random { min: u32, max: u32 },
+    /// Edges get a weight of `scale / distance`, where `distance` is the distance between the
+    /// positions of the edge's end-points.
+    ///
+    /// The weight is capped at `65535`, so that the summed weights of the coarse edges that
+    /// coarsening produces do not overflow.
+    InverseDistance { scale: f32 },
+}
+
+// Coarsening sums the weights of the fine edges that collapse into a coarse edge in `u32`; capping
+// the weights at `2^16 - 1` leaves room for summing at least `2^16` of them.
+const MAX_INVERSE_DISTANCE_WEIGHT: f32 = 65535.0;
+
+const MIN_INVERSE_DISTANCE: f32 = 1e-6;
+
+impl EdgeWeighting {
+    pub(crate) fn weight(
+        &self,
+        rng: &mut oorandom::Rand32,
+        positions: &NodePositions,
+        a: u32,
+        b: u32,
+    ) -> u32 {
+        let weight = match *self {
+            EdgeWeighting::Constant(weight) => weight,
+            EdgeWeighting::Random { min, max } => {
+                if max > min {
+                    min + rng.rand_range(0..(max - min).saturating_add(1))
+                } else {
+                    min
+                }
+            }
+            EdgeWeighting::InverseDistance { scale } => {
+                // Keep coincident end-points from producing an infinite weight.
+                let distance = positions
+                    .distance(a as usize, b as usize)
+                    .max(MIN_INVERSE_DISTANCE);
+
+                (scale / distance).min(MAX_INVERSE_DISTANCE_WEIGHT) as u32
+            }
+        };
+
+        weight.max(1)
+    }
+}
+
+impl NodePositions {
+    pub(crate) fn random_2d(rng: &mut oorandom::Rand32, node_count: usize) -> Self {
+        let positions = (0..node_count)
+            .map(|_| abi::Vec2(rng.rand_float(), rng.rand_float()))
+            .collect();
+
+        NodePositions::Dim2(positions)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            NodePositions::Dim2(positions) => positions.len(),
+            NodePositions::Dim3(positions) => positions.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_2d(&self) -> Option<&[abi::Vec2<f32>]> {
+        if let NodePositions::Dim2(positions) = self {
+            Some(positions)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_3d(&self) -> Option<&[abi::Vec3<f32>]> {
+        if let NodePositions::Dim3(positions) = self {
+            Some(positions)
+        } else {
+            None
+        }
+    }
+
+    fn distance(&self, a: usize, b: usize) -> f32 {
+        match self {
+            NodePositions::Dim2(positions) => {
+                let abi::Vec2(a_x, a_y) = positions[a];
+                let abi::Vec2(b_x, b_y) = positions[b];
+
+                let d_x = b_x - a_x;
+                let d_y = b_y - a_y;
+
+                (d_x * d_x + d_y * d_y).sqrt()
+            }
+            NodePositions::Dim3(positions) => {
+                let abi::Vec3(a_x, a_y, a_z) = positions[a];
+                let abi::Vec3(b_x, b_y, b_z) = positions[b];
+
+                let d_x = b_x - a_x;
+                let d_y = b_y - a_y;
+                let d_z = b_z - a_z;
+
+                (d_x * d_x + d_y * d_y + d_z * d_z).sqrt()
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct GeneratedGraph {
+    pub nodes_edge_offset: Vec<u32>,
+    pub nodes_edges: Vec<u32>,
+    pub nodes_edge_weights: Vec<u32>,
+    pub nodes_position: Option<NodePositions>,
+}
+
+impl GeneratedGraph {
+    /// Builds a graph from an undirected edge list.
+    ///
+    /// Each `(a, b, weight)` triple is interpreted as an undirected edge; it does not matter in
+    /// which order the end-points are specified and the reverse edge should not be specified
+    /// separately. Self-referencing edges are dropped. If an edge is specified more than once, only
+    /// the first occurrence is kept.
+    ///
+    /// Panics if an edge references a node index that is not smaller than `node_count`.
+    pub fn from_edges<I>(node_count: usize, edges: I) -> Self
+    where
+        I: IntoIterator<Item = (u32, u32, u32)>,
+    {
+        let mut directed_edges = Vec::new();
+
+        for (index, (a, b, weight)) in edges.into_iter().enumerate() {
+            assert!(
+                (a as usize) < node_count && (b as usize) < node_count,
+                "edge ({}, {}) references a node outside of the node range",
+                a,
+                b
+            );
+
+            if a != b {
+                directed_edges.push((a, b, index, weight));
+                directed_edges.push((b, a, index, weight));
+            }
+        }
+
+        // Sort by `(source, target)`, and then by the order in which the edges were specified, so
+        // that deduplication keeps the first occurrence.
+        directed_edges.sort_unstable_by_key(|&(a, b, index, _)| (a, b, index));
+        directed_edges.dedup_by_key(|&mut (a, b, _, _)| (a, b));
+
+        let mut nodes_edge_offset = Vec::with_capacity(node_count);
+        let mut nodes_edges = Vec::with_capacity(directed_edges.len());
+        let mut nodes_edge_weights = Vec::with_capacity(directed_edges.len());
+
+        let mut edges = directed_edges.into_iter().peekable();
+
+        for node in 0..node_count as u32 {
+            nodes_edge_offset.push(nodes_edges.len() as u32);
+
+            while let Some((_, b, _, weight)) = edges.next_if(|&(a, ..)| a == node) {
+                nodes_edges.push(b);
+                nodes_edge_weights.push(weight);
+            }
+        }
+
+        GeneratedGraph {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            nodes_position: None,
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes_edge_offset.len()
+    }
+
+    pub fn edge_ref_count(&self) -> usize {
+        self.nodes_edges.len()
+    }
+
+    pub(crate) fn with_positions(mut self, positions: NodePositions, output: bool) -> Self {
+        if output {
+            self.nodes_position = Some(positions);
+        }
+
+        self
+    }
+}
+
+/// Helper for generators that produce their edge list before assigning edge weights.
+pub(crate) fn build_weighted<I>(
+    rng: &mut oorandom::Rand32,
+    node_count: usize,
+    edges: I,
+    weighting: EdgeWeighting,
+    positions: NodePositions,
+    output_positions: bool,
+) -> GeneratedGraph
+where
+    I: IntoIterator<Item = (u32, u32)>,
+{
+    let edges: Vec<(u32, u32, u32)> = edges
+        .into_iter()
+        .map(|(a, b)| (a, b, weighting.weight(rng, &positions, a, b)))
+        .collect();
+
+    GeneratedGraph::from_edges(node_count, edges).with_positions(positions, output_positions)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Asserts that the `graph` has the CSR layout described in the [generators](crate::generators)
+    /// module documentation.
+    pub(crate) fn assert_csr_invariants(graph: &GeneratedGraph) {
+        let node_count = graph.node_count();
+        let edge_ref_count = graph.edge_ref_count();
+
+        assert_eq!(graph.nodes_edge_weights.len(), edge_ref_count);
+
+        if node_count > 0 {
+            assert_eq!(graph.nodes_edge_offset[0], 0);
+        }
+
+        for node in 0..node_count {
+            let start = graph.nodes_edge_offset[node] as usize;
+            let end = graph
+                .nodes_edge_offset
+                .get(node + 1)
+                .map(|&end| end as usize)
+                .unwrap_or(edge_ref_count);
+
+            assert!(start <= end && end <= edge_ref_count, "node {}", node);
+
+            let edges = &graph.nodes_edges[start..end];
+
+            // Sorted by target without duplicates.
+            assert!(edges.windows(2).all(|w| w[0] < w[1]), "node {}", node);
+
+            for (edge, &target) in (start..end).zip(edges) {
+                assert!((target as usize) < node_count);
+                assert_ne!(target as usize, node, "self-reference");
+
+                let weight = graph.nodes_edge_weights[edge];
+
+                assert!(weight >= 1);
+                assert_eq!(
+                    reverse_edge_weight(graph, target, node as u32),
+                    Some(weight),
+                    "edge {} -> {}",
+                    node,
+                    target
+                );
+            }
+        }
+    }
+
+    fn reverse_edge_weight(graph: &GeneratedGraph, node: u32, target: u32) -> Option<u32> {
+        let start = graph.nodes_edge_offset[node as usize] as usize;
+        let end = graph
+            .nodes_edge_offset
+            .get(node as usize + 1)
+            .map(|&end| end as usize)
+            .unwrap_or(graph.edge_ref_count());
+
+        graph.nodes_edges[start..end]
+            .binary_search(&target)
+            .ok()
+            .map(|index| graph.nodes_edge_weights[start + index])
+    }
+
+    #[test]
+    fn test_from_edges() {
+        // Includes a self-reference, a duplicate (in reverse order) and an isolated node.
+        let graph =
+            GeneratedGraph::from_edges(5, [(0, 1, 3), (2, 0, 5), (1, 1, 7), (1, 0, 9), (3, 1, 2)]);
+
+        assert_csr_invariants(&graph);
+        assert_eq!(graph.nodes_edge_offset, vec![0, 2, 4, 5, 6]);
+        assert_eq!(graph.nodes_edges, vec![1, 2, 0, 3, 0, 1]);
+        // The duplicate keeps the weight of the first occurrence.
+        assert_eq!(graph.nodes_edge_weights, vec![3, 5, 3, 2, 5, 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_edges_out_of_range() {
+        GeneratedGraph::from_edges(2, [(0, 2, 1)]);
+    }
+
+    #[test]
+    fn test_edge_weighting() {
+        let mut rng = oorandom::Rand32::new(1);
+        let positions = NodePositions::Dim2(vec![
+            abi::Vec2(0.0, 0.0),
+            abi::Vec2(0.5, 0.0),
+            abi::Vec2(0.5, 0.0),
+        ]);
+
+        assert_eq!(
+            EdgeWeighting::Constant(0).weight(&mut rng, &positions, 0, 1),
+            1
+        );
+
+        for _ in 0..100 {
+            let weight =
+                EdgeWeighting::Random { min: 3, max: 5 }.weight(&mut rng, &positions, 0, 1);
+
+            assert!((3..=5).contains(&weight));
+        }
+
+        let inverse_distance = EdgeWeighting::InverseDistance { scale: 10.0 };
+
+        assert_eq!(inverse_distance.weight(&mut rng, &positions, 0, 1), 20);
+        // Coincident end-points get the capped weight.
+        assert_eq!(inverse_distance.weight(&mut rng, &positions, 1, 2), 65535);
+    }
+}
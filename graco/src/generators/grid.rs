@@ -0,0 +1,280 @@
+use empa::abi;
+
+use crate::generators::generated_graph::build_weighted;
+use crate::generators::{EdgeWeighting, GeneratedGraph, NodePositions};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Grid2DConfig {
+    pub width: u32,
+    pub height: u32,
+    /// The maximum random perturbation applied to each node position, as a fraction of the grid
+    /// spacing.
+    pub jitter: f32,
+    pub seed: u64,
+    pub weighting: EdgeWeighting,
+    pub output_positions: bool,
+}
+
+impl Default for Grid2DConfig {
+    fn default() -> Self {
+        Grid2DConfig {
+            width: 16,
+            height: 16,
+            jitter: 0.0,
+            seed: 1,
+            weighting: EdgeWeighting::InverseDistance { scale: 1000.0 },
+            output_positions: true,
+        }
+    }
+}
+
+/// Generates a regular 2D grid graph in which each node connects to its (up to 4) horizontal and
+/// vertical neighbours.
+///
+/// Node positions are distributed over the unit square, with a margin of 1 grid spacing on each
+/// side. Nodes are numbered in row-major order.
+pub fn grid_2d(config: &Grid2DConfig) -> GeneratedGraph {
+    let Grid2DConfig {
+        width,
+        height,
+        jitter,
+        seed,
+        weighting,
+        output_positions,
+    } = *config;
+
+    let mut rng = oorandom::Rand32::new(seed);
+
+    let spacing_x = 1.0 / (width as f32 + 1.0);
+    let spacing_y = 1.0 / (height as f32 + 1.0);
+    let max_perturbation_x = jitter * spacing_x;
+    let max_perturbation_y = jitter * spacing_y;
+
+    let compute_node_index = |col: u32, row: u32| -> u32 { row * width + col };
+
+    let mut nodes_position = Vec::with_capacity((width * height) as usize);
+
+    for row in 0..height {
+        for col in 0..width {
+            let perturbation_x = rng.rand_float() * max_perturbation_x * 2.0 - max_perturbation_x;
+            let perturbation_y = rng.rand_float() * max_perturbation_y * 2.0 - max_perturbation_y;
+
+            nodes_position.push(abi::Vec2(
+                spacing_x + col as f32 * spacing_x + perturbation_x,
+                spacing_y + row as f32 * spacing_y + perturbation_y,
+            ));
+        }
+    }
+
+    let positions = NodePositions::Dim2(nodes_position);
+
+    let mut edges = Vec::with_capacity((width * height * 2) as usize);
+
+    for row in 0..height {
+        for col in 0..width {
+            let current_index = compute_node_index(col, row);
+
+            if col < width - 1 {
+                edges.push((current_index, compute_node_index(col + 1, row)));
+            }
+
+            if row < height - 1 {
+                edges.push((current_index, compute_node_index(col, row + 1)));
+            }
+        }
+    }
+
+    build_weighted(
+        &mut rng,
+        (width * height) as usize,
+        edges,
+        weighting,
+        positions,
+        output_positions,
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Grid3DConfig {
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    /// The maximum random perturbation applied to each node position, as a fraction of the grid
+    /// spacing.
+    pub jitter: f32,
+    pub seed: u64,
+    pub weighting: EdgeWeighting,
+    pub output_positions: bool,
+}
+
+impl Default for Grid3DConfig {
+    fn default() -> Self {
+        Grid3DConfig {
+            width: 16,
+            height: 16,
+            depth: 16,
+            jitter: 0.0,
+            seed: 1,
+            weighting: EdgeWeighting::InverseDistance { scale: 1000.0 },
+            output_positions: true,
+        }
+    }
+}
+
+/// Generates a regular 3D grid graph in which each node connects to its (up to 6) axis-aligned
+/// neighbours.
+///
+/// Node positions are distributed over the unit cube, with a margin of 1 grid spacing on each
+/// side. Nodes are numbered in layer-major, then row-major order.
+pub fn grid_3d(config: &Grid3DConfig) -> GeneratedGraph {
+    let Grid3DConfig {
+        width,
+        height,
+        depth,
+        jitter,
+        seed,
+        weighting,
+        output_positions,
+    } = *config;
+
+    let mut rng = oorandom::Rand32::new(seed);
+
+    let spacing = [
+        1.0 / (width as f32 + 1.0),
+        1.0 / (height as f32 + 1.0),
+        1.0 / (depth as f32 + 1.0),
+    ];
+
+    let node_count = (width * height * depth) as usize;
+
+    let mut nodes_position = Vec::with_capacity(node_count);
+
+    for layer in 0..depth {
+        for row in 0..height {
+            for col in 0..width {
+                let mut coordinate = |i: usize, cell: u32| {
+                    let max_perturbation = jitter * spacing[i];
+                    let perturbation = rng.rand_float() * max_perturbation * 2.0 - max_perturbation;
+
+                    spacing[i] + cell as f32 * spacing[i] + perturbation
+                };
+
+                let x = coordinate(0, col);
+                let y = coordinate(1, row);
+                let z = coordinate(2, layer);
+
+                nodes_position.push(abi::Vec3(x, y, z));
+            }
+        }
+    }
+
+    let positions = NodePositions::Dim3(nodes_position);
+
+    let compute_node_index =
+        |col: u32, row: u32, layer: u32| -> u32 { (layer * height + row) * width + col };
+
+    let mut edges = Vec::with_capacity(node_count * 3);
+
+    for layer in 0..depth {
+        for row in 0..height {
+            for col in 0..width {
+                let current_index = compute_node_index(col, row, layer);
+
+                if col < width - 1 {
+                    edges.push((current_index, compute_node_index(col + 1, row, layer)));
+                }
+
+                if row < height - 1 {
+                    edges.push((current_index, compute_node_index(col, row + 1, layer)));
+                }
+
+                if layer < depth - 1 {
+                    edges.push((current_index, compute_node_index(col, row, layer + 1)));
+                }
+            }
+        }
+    }
+
+    build_weighted(
+        &mut rng,
+        node_count,
+        edges,
+        weighting,
+        positions,
+        output_positions,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::generated_graph::tests::assert_csr_invariants;
+
+    #[test]
+    fn test_grid_2d() {
+        let graph = grid_2d(&Grid2DConfig {
+            width: 3,
+            height: 2,
+            weighting: EdgeWeighting::Constant(1),
+            ..Default::default()
+        });
+
+        assert_csr_invariants(&graph);
+        assert_eq!(graph.node_count(), 6);
+        // 2 horizontal edges in each of the 2 rows, and 3 vertical edges, stored twice.
+        assert_eq!(graph.edge_ref_count(), 14);
+        assert_eq!(graph.nodes_edge_offset, vec![0, 2, 5, 7, 9, 12]);
+        assert_eq!(
+            graph.nodes_edges,
+            vec![1, 3, 0, 2, 4, 1, 5, 0, 4, 1, 3, 5, 2, 4]
+        );
+        assert_eq!(graph.nodes_position.as_ref().map(|p| p.len()), Some(6));
+    }
+
+    #[test]
+    fn test_grid_2d_inverse_distance() {
+        let graph = grid_2d(&Grid2DConfig {
+            width: 4,
+            height: 4,
+            jitter: 0.25,
+            ..Default::default()
+        });
+
+        assert_csr_invariants(&graph);
+        assert_eq!(graph.edge_ref_count(), 2 * 2 * 3 * 4);
+    }
+
+    #[test]
+    fn test_grid_3d() {
+        let graph = grid_3d(&Grid3DConfig {
+            width: 2,
+            height: 2,
+            depth: 2,
+            ..Default::default()
+        });
+
+        assert_csr_invariants(&graph);
+        assert_eq!(graph.node_count(), 8);
+        // The 12 edges of a cube; every corner connects to 3 others.
+        assert_eq!(graph.edge_ref_count(), 24);
+        assert_eq!(graph.nodes_edge_offset, vec![0, 3, 6, 9, 12, 15, 18, 21]);
+        assert_eq!(&graph.nodes_edges[..3], &[1, 2, 4]);
+    }
+
+    #[test]
+    fn test_grid_3d_edge_count() {
+        let graph = grid_3d(&Grid3DConfig {
+            width: 4,
+            height: 3,
+            depth: 2,
+            output_positions: false,
+            ..Default::default()
+        });
+
+        assert_csr_invariants(&graph);
+        assert_eq!(graph.node_count(), 24);
+        // The edges along the x, y and z axes, each stored twice.
+        assert_eq!(graph.edge_ref_count(), 2 * (18 + 16 + 12));
+        assert!(graph.nodes_position.is_none());
+    }
+}
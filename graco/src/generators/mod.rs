@@ -0,0 +1,26 @@
+//! Seeded, reproducible synthetic graph generators.
+//!
+//! All generators produce undirected graphs in the CSR layout the matching and coarsening
+//! pipelines expect: every edge is stored twice (once for each of its end-points), a node's edges
+//! are sorted by target node index, and there are no self-referencing or duplicate edges.
+
+mod barabasi_albert;
+pub use self::barabasi_albert::{barabasi_albert, BarabasiAlbertConfig};
+
+mod delaunay;
+pub use self::delaunay::{delaunay, DelaunayConfig};
+
+mod erdos_renyi;
+pub use self::erdos_renyi::{erdos_renyi, ErdosRenyiConfig};
+
+mod generated_graph;
+pub use self::generated_graph::{EdgeWeighting, GeneratedGraph, NodePositions};
+
+mod grid;
+pub use self::grid::{grid_2d, grid_3d, Grid2DConfig, Grid3DConfig};
+
+mod random_geometric;
+pub use self::random_geometric::{random_geometric, RandomGeometricConfig};
+
+mod rmat;
+pub use self::rmat::{rmat, RmatConfig};
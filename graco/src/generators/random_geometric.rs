@@ -0,0 +1,132 @@
+use empa::abi;
+
+use crate::generators::generated_graph::build_weighted;
+use crate::generators::{EdgeWeighting, GeneratedGraph, NodePositions};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RandomGeometricConfig {
+    pub node_count: u32,
+    /// Two nodes are connected if the distance between them is smaller than this radius.
+    pub radius: f32,
+    pub seed: u64,
+    pub weighting: EdgeWeighting,
+    pub output_positions: bool,
+}
+
+impl Default for RandomGeometricConfig {
+    fn default() -> Self {
+        RandomGeometricConfig {
+            node_count: 1024,
+            radius: 0.05,
+            seed: 1,
+            weighting: EdgeWeighting::InverseDistance { scale: 1000.0 },
+            output_positions: true,
+        }
+    }
+}
+
+/// Generates a random geometric graph: nodes are placed uniformly at random in the unit square,
+/// and any two nodes closer together than the configured radius are connected.
+pub fn random_geometric(config: &RandomGeometricConfig) -> GeneratedGraph {
+    let RandomGeometricConfig {
+        node_count,
+        radius,
+        seed,
+        weighting,
+        output_positions,
+    } = *config;
+
+    let mut rng = oorandom::Rand32::new(seed);
+
+    let positions = NodePositions::random_2d(&mut rng, node_count as usize);
+    let points = positions.as_2d().unwrap();
+
+    // Bin the points into a uniform grid with a cell size of (at least) the radius, so that we
+    // only need to compare each point against the points in the 9 surrounding cells.
+    let cells_per_side = ((1.0 / radius.max(f32::EPSILON)) as usize).clamp(1, 4096);
+    let cell_of = |p: abi::Vec2<f32>| -> (usize, usize) {
+        let x = ((p.0 * cells_per_side as f32) as usize).min(cells_per_side - 1);
+        let y = ((p.1 * cells_per_side as f32) as usize).min(cells_per_side - 1);
+
+        (x, y)
+    };
+
+    let mut cells: Vec<Vec<u32>> = vec![Vec::new(); cells_per_side * cells_per_side];
+
+    for (index, point) in points.iter().enumerate() {
+        let (x, y) = cell_of(*point);
+
+        cells[y * cells_per_side + x].push(index as u32);
+    }
+
+    let radius_squared = radius * radius;
+    let mut edges = Vec::new();
+
+    for (a, point_a) in points.iter().enumerate() {
+        let (x, y) = cell_of(*point_a);
+
+        for cell_y in y.saturating_sub(1)..(y + 2).min(cells_per_side) {
+            for cell_x in x.saturating_sub(1)..(x + 2).min(cells_per_side) {
+                for &b in &cells[cell_y * cells_per_side + cell_x] {
+                    // Only emit each undirected edge once.
+                    if b as usize <= a {
+                        continue;
+                    }
+
+                    let point_b = points[b as usize];
+                    let d_x = point_b.0 - point_a.0;
+                    let d_y = point_b.1 - point_a.1;
+
+                    if d_x * d_x + d_y * d_y < radius_squared {
+                        edges.push((a as u32, b));
+                    }
+                }
+            }
+        }
+    }
+
+    build_weighted(
+        &mut rng,
+        node_count as usize,
+        edges,
+        weighting,
+        positions,
+        output_positions,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::generated_graph::tests::assert_csr_invariants;
+
+    #[test]
+    fn test_random_geometric() {
+        let radius = 0.15;
+        let graph = random_geometric(&RandomGeometricConfig {
+            node_count: 100,
+            radius,
+            ..Default::default()
+        });
+
+        assert_csr_invariants(&graph);
+
+        // Compare against the pairs found by brute force.
+        let points = graph.nodes_position.as_ref().unwrap().as_2d().unwrap();
+        let mut expected_edge_count = 0;
+
+        for (a, point_a) in points.iter().enumerate() {
+            for point_b in &points[a + 1..] {
+                let d_x = point_b.0 - point_a.0;
+                let d_y = point_b.1 - point_a.1;
+
+                if d_x * d_x + d_y * d_y < radius * radius {
+                    expected_edge_count += 1;
+                }
+            }
+        }
+
+        assert!(expected_edge_count > 0);
+        assert_eq!(graph.edge_ref_count(), 2 * expected_edge_count);
+    }
+}
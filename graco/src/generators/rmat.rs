@@ -0,0 +1,138 @@
+use crate::generators::generated_graph::build_weighted;
+use crate::generators::{EdgeWeighting, GeneratedGraph, NodePositions};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RmatConfig {
+    /// The graph will have `2^scale` nodes.
+    pub scale: u32,
+    /// The number of edges to sample, as a multiple of the node count. Because duplicate and
+    /// self-referencing edges are dropped, the final edge count will be somewhat lower.
+    pub edge_factor: u32,
+    /// The probabilities of recursing into the top-left, top-right and bottom-left quadrants of the
+    /// adjacency matrix; the probability of recursing into the bottom-right quadrant is
+    /// `1 - a - b - c`.
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub seed: u64,
+    pub weighting: EdgeWeighting,
+    /// If enabled, nodes are assigned random positions in the unit square.
+    pub output_positions: bool,
+}
+
+impl Default for RmatConfig {
+    fn default() -> Self {
+        // The Graph500 parameters.
+        RmatConfig {
+            scale: 14,
+            edge_factor: 16,
+            a: 0.57,
+            b: 0.19,
+            c: 0.19,
+            seed: 1,
+            weighting: EdgeWeighting::Random { min: 1, max: 1000 },
+            output_positions: false,
+        }
+    }
+}
+
+/// Generates an R-MAT (recursive matrix) graph, a Kronecker graph variant with a skewed,
+/// power-law-like degree distribution. See Chakrabarti et al. "R-MAT: A Recursive Model for Graph
+/// Mining".
+///
+/// Node indices are randomly permuted after generation, so that the high-degree nodes are not all
+/// clustered at the start of the node range.
+pub fn rmat(config: &RmatConfig) -> GeneratedGraph {
+    let RmatConfig {
+        scale,
+        edge_factor,
+        a,
+        b,
+        c,
+        seed,
+        weighting,
+        output_positions,
+    } = *config;
+
+    assert!(scale < 30, "scale must be smaller than 30");
+
+    let mut rng = oorandom::Rand32::new(seed);
+
+    let node_count = 1usize << scale;
+    let sample_count = node_count * edge_factor as usize;
+
+    let ab = a + b;
+    let abc = a + b + c;
+
+    let mut edges = Vec::with_capacity(sample_count);
+
+    for _ in 0..sample_count {
+        let mut row = 0u32;
+        let mut col = 0u32;
+
+        for level in (0..scale).rev() {
+            let r = rng.rand_float();
+            let bit = 1 << level;
+
+            if r < a {
+                // Top-left quadrant, nothing to add.
+            } else if r < ab {
+                col |= bit;
+            } else if r < abc {
+                row |= bit;
+            } else {
+                row |= bit;
+                col |= bit;
+            }
+        }
+
+        edges.push((row, col));
+    }
+
+    // Fisher-Yates shuffle to obtain a random node permutation.
+    let mut permutation: Vec<u32> = (0..node_count as u32).collect();
+
+    for i in (1..node_count).rev() {
+        let j = rng.rand_range(0..(i as u32 + 1)) as usize;
+
+        permutation.swap(i, j);
+    }
+
+    let edges = edges
+        .into_iter()
+        .map(|(a, b)| (permutation[a as usize], permutation[b as usize]));
+
+    let positions = NodePositions::random_2d(&mut rng, node_count);
+
+    build_weighted(
+        &mut rng,
+        node_count,
+        edges,
+        weighting,
+        positions,
+        output_positions,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::generated_graph::tests::assert_csr_invariants;
+
+    #[test]
+    fn test_rmat() {
+        let config = RmatConfig {
+            scale: 8,
+            edge_factor: 4,
+            ..Default::default()
+        };
+        let graph = rmat(&config);
+
+        assert_csr_invariants(&graph);
+        assert_eq!(graph.node_count(), 256);
+        // Each sampled edge is stored at most twice; duplicates and self-references are dropped.
+        assert!(graph.edge_ref_count() > 0);
+        assert!(graph.edge_ref_count() <= 2 * 256 * 4);
+        assert_eq!(rmat(&config), graph);
+    }
+}
@@ -0,0 +1,387 @@
+use std::error::Error;
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::{Buffer, Storage};
+use empa::device::Device;
+use empa::type_flag::{O, X};
+
+use crate::context::Context;
+use crate::hierarchy::accumulate_coarse_nodes_weight::{
+    AccumulateCoarseNodesWeight, AccumulateCoarseNodesWeightResources,
+};
+use crate::hierarchy::copy_nodes_weight::{CopyNodesWeight, CopyNodesWeightResources};
+use crate::matching::{
+    MatchPairsByEdgeWeight, MatchPairsByEdgeWeightConfig, MatchPairsByEdgeWeightInput,
+    MatchPairsByEdgeWeightsCounts,
+};
+use crate::{CoarsenCounts, CoarsenGraph, CoarsenGraphOutput, CoarsenGraphWorkspaceInput};
+
+// If a round of coarsening shrinks the node count by less than this factor, then the matching has
+// (nearly) stalled and further coarsening is not worth the cost of another level. Also used by the
+// AMG hierarchy, where the nodes are the rows of the matrix.
+pub(crate) const MIN_COARSENING_RATIO: f32 = 0.95;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct HierarchyConfig {
+    /// Coarsening stops once a level has no more than this many nodes.
+    pub coarsest_node_count: u32,
+    /// The maximum number of levels, including the base level.
+    pub max_levels: usize,
+}
+
+pub struct HierarchyInput<'a, U0, U1, U2> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    /// The weight of each node, or `None` if all nodes have a weight of `1`.
+    pub nodes_weight: Option<Storage<'a, [u32]>>,
+}
+
+/// A level in a coarsening hierarchy.
+///
+/// The buffers of a coarse level are sized for the next finer level, as the coarse level's size is
+/// only known after it has been coarsened; the `node_count` and `edge_ref_count` are the actual
+/// counts. The buffers of the base level are exactly sized.
+pub struct HierarchyLevel {
+    pub node_count: u32,
+    pub edge_ref_count: u32,
+    pub node_count_buffer: Buffer<u32, buffer::Usages<O, O, X, X, O, O, X, X, O, O>>,
+    pub edge_ref_count_buffer: Buffer<u32, buffer::Usages<O, O, X, X, O, O, X, X, O, O>>,
+    pub nodes_edge_offset: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    pub nodes_edges: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    pub nodes_edge_weights: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    /// The weight of a coarse node is the sum of the weights of the fine nodes it represents.
+    pub nodes_weight: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    /// Maps the nodes of the next finer level to the nodes of this level; unused for the base
+    /// level.
+    pub fine_nodes_mapping: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    /// For each node of this level, the offset of the range in `coarse_nodes_mapping` that lists
+    /// the nodes of the next finer level it represents; unused for the base level.
+    pub coarse_nodes_mapping_offset: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, X, O, O>>,
+    /// The nodes of the next finer level, grouped by the node of this level they were merged into;
+    /// unused for the base level.
+    pub coarse_nodes_mapping: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, X, O, O>>,
+}
+
+impl HierarchyLevel {
+    pub(crate) fn new(device: &Device, node_capacity: usize, edge_ref_capacity: usize) -> Self {
+        let slice_usages = buffer::Usages::storage_binding()
+            .and_copy_dst()
+            .and_copy_src();
+
+        HierarchyLevel {
+            node_count: node_capacity as u32,
+            edge_ref_count: edge_ref_capacity as u32,
+            node_count_buffer: create_count_buffer(device, node_capacity as u32),
+            edge_ref_count_buffer: create_count_buffer(device, edge_ref_capacity as u32),
+            nodes_edge_offset: device.create_slice_buffer_zeroed(node_capacity, slice_usages),
+            nodes_edges: device.create_slice_buffer_zeroed(edge_ref_capacity, slice_usages),
+            nodes_edge_weights: device.create_slice_buffer_zeroed(edge_ref_capacity, slice_usages),
+            nodes_weight: device.create_slice_buffer_zeroed(node_capacity, slice_usages),
+            fine_nodes_mapping: device
+                .create_slice_buffer_zeroed(node_capacity, buffer::Usages::storage_binding()),
+            coarse_nodes_mapping_offset: device.create_slice_buffer_zeroed(
+                node_capacity,
+                buffer::Usages::storage_binding().and_copy_src(),
+            ),
+            coarse_nodes_mapping: device.create_slice_buffer_zeroed(
+                node_capacity,
+                buffer::Usages::storage_binding().and_copy_src(),
+            ),
+        }
+    }
+
+    /// Reads back the counts that were written to the `node_count_buffer` and
+    /// `edge_ref_count_buffer` (e.g. by [CoarsenGraph]) and updates the `node_count` and
+    /// `edge_ref_count`.
+    pub(crate) async fn read_counts(&mut self, device: &Device) -> Result<(), Box<dyn Error>> {
+        let (node_count, edge_ref_count) = read_counts(
+            device,
+            self.node_count_buffer.view(),
+            self.edge_ref_count_buffer.view(),
+        )
+        .await?;
+
+        self.node_count = node_count;
+        self.edge_ref_count = edge_ref_count;
+
+        Ok(())
+    }
+}
+
+/// Creates a buffer for the node count or edge ref count of a level, initialized to the given
+/// `count`.
+///
+/// The count of a coarse level is written by [CoarsenGraph] and bound as a uniform by the
+/// pipelines that process the level.
+pub(crate) fn create_count_buffer(
+    device: &Device,
+    count: u32,
+) -> Buffer<u32, buffer::Usages<O, O, X, X, O, O, X, X, O, O>> {
+    device.create_buffer(
+        count,
+        buffer::Usages::storage_binding()
+            .and_uniform_binding()
+            .and_copy_dst()
+            .and_copy_src(),
+    )
+}
+
+/// Reads back a node count and an edge ref count from device memory.
+pub(crate) async fn read_counts<U0, U1>(
+    device: &Device,
+    node_count: buffer::View<'_, u32, U0>,
+    edge_ref_count: buffer::View<'_, u32, U1>,
+) -> Result<(u32, u32), Box<dyn Error>>
+where
+    U0: buffer::CopySrc,
+    U1: buffer::CopySrc,
+{
+    let node_count_readback = device.create_buffer(0u32, buffer::Usages::copy_dst().and_map_read());
+    let edge_ref_count_readback =
+        device.create_buffer(0u32, buffer::Usages::copy_dst().and_map_read());
+
+    let mut encoder = device.create_command_encoder();
+
+    encoder = encoder.copy_buffer_to_buffer(node_count, node_count_readback.view());
+    encoder = encoder.copy_buffer_to_buffer(edge_ref_count, edge_ref_count_readback.view());
+
+    device.queue().submit(encoder.finish());
+
+    let (r0, r1) = join!(
+        node_count_readback.map_read(),
+        edge_ref_count_readback.map_read()
+    )
+    .await;
+
+    r0?;
+    r1?;
+
+    let counts = (
+        *node_count_readback.mapped(),
+        *edge_ref_count_readback.mapped(),
+    );
+
+    node_count_readback.unmap();
+    edge_ref_count_readback.unmap();
+
+    Ok(counts)
+}
+
+/// Builds a coarsening hierarchy for a graph by repeatedly matching and coarsening it, for the
+/// multilevel algorithms in this crate.
+///
+/// Submits its own command buffers, as the number of levels in the hierarchy is only known after
+/// reading back the node count of each coarse level.
+pub struct BuildHierarchy {
+    device: Device,
+    matcher: MatchPairsByEdgeWeight,
+    coarsen_graph: CoarsenGraph,
+    accumulate_coarse_nodes_weight: Rc<AccumulateCoarseNodesWeight>,
+    copy_nodes_weight: Rc<CopyNodesWeight>,
+}
+
+impl BuildHierarchy {
+    pub async fn init_with_context(
+        context: &Context,
+        matching: MatchPairsByEdgeWeightConfig,
+    ) -> Self {
+        let device = context.device().clone();
+
+        let (matcher, coarsen_graph, accumulate_coarse_nodes_weight, copy_nodes_weight) = join!(
+            MatchPairsByEdgeWeight::init_with_context(context, matching),
+            CoarsenGraph::init_with_context(context),
+            context.get_or_init_kernel(AccumulateCoarseNodesWeight::init),
+            context.get_or_init_kernel(CopyNodesWeight::init),
+        )
+        .await;
+
+        BuildHierarchy {
+            device,
+            matcher,
+            coarsen_graph,
+            accumulate_coarse_nodes_weight,
+            copy_nodes_weight,
+        }
+    }
+
+    /// Builds the hierarchy for the graph described by the `input`, which must be described by
+    /// buffers that are exactly sized to its node count and edge ref count.
+    ///
+    /// Returns the levels of the hierarchy, starting with a copy of the input graph as the base
+    /// level, followed by progressively coarser levels.
+    pub async fn build<U0, U1, U2>(
+        &mut self,
+        input: HierarchyInput<'_, U0, U1, U2>,
+        config: HierarchyConfig,
+    ) -> Result<Vec<HierarchyLevel>, Box<dyn Error>>
+    where
+        U0: buffer::CopySrc,
+        U1: buffer::CopySrc,
+        U2: buffer::CopySrc,
+    {
+        let HierarchyInput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            nodes_weight,
+        } = input;
+
+        let node_count = nodes_edge_offset.len();
+        let edge_ref_count = nodes_edges.len();
+
+        assert_eq!(
+            nodes_edge_weights.len(),
+            edge_ref_count,
+            "`nodes_edge_weights` must have the same length as `nodes_edges`"
+        );
+
+        // Copy the input graph into the base level of the hierarchy, so that all levels can be
+        // treated uniformly.
+        let base_level = HierarchyLevel::new(&self.device, node_count, edge_ref_count);
+
+        let mut encoder = self.device.create_command_encoder();
+
+        encoder = encoder
+            .copy_buffer_to_buffer_slice(nodes_edge_offset, base_level.nodes_edge_offset.view());
+        encoder = encoder.copy_buffer_to_buffer_slice(nodes_edges, base_level.nodes_edges.view());
+        encoder = encoder
+            .copy_buffer_to_buffer_slice(nodes_edge_weights, base_level.nodes_edge_weights.view());
+
+        let unit_weights: Buffer<[u32], _>;
+
+        let source_weights = if let Some(nodes_weight) = nodes_weight {
+            nodes_weight
+        } else {
+            unit_weights = self
+                .device
+                .create_buffer(vec![1u32; node_count], buffer::Usages::storage_binding());
+
+            unit_weights.storage()
+        };
+
+        encoder = self.copy_nodes_weight.encode(
+            encoder,
+            CopyNodesWeightResources {
+                node_count: base_level.node_count_buffer.uniform(),
+                source: source_weights,
+                destination: base_level.nodes_weight.storage(),
+            },
+            node_count as u32,
+        );
+
+        self.device.queue().submit(encoder.finish());
+
+        let mut levels = vec![base_level];
+
+        self.coarsen(&mut levels, config).await?;
+
+        Ok(levels)
+    }
+
+    async fn coarsen(
+        &mut self,
+        levels: &mut Vec<HierarchyLevel>,
+        config: HierarchyConfig,
+    ) -> Result<(), Box<dyn Error>> {
+        let base_node_count = levels[0].node_count as usize;
+
+        // A scratch buffer that is only needed while coarsening a level; coarse levels are never
+        // larger than the base level.
+        let nodes_matching = self.device.create_slice_buffer_zeroed(
+            base_node_count,
+            buffer::Usages::storage_binding().and_copy_dst(),
+        );
+
+        while levels.len() < config.max_levels {
+            let fine_level = levels.last().unwrap();
+
+            if fine_level.node_count <= config.coarsest_node_count {
+                break;
+            }
+
+            let fine_node_count = fine_level.node_count;
+            let mut coarse_level = HierarchyLevel::new(
+                &self.device,
+                fine_node_count as usize,
+                fine_level.edge_ref_count as usize,
+            );
+
+            let mut encoder = self.device.create_command_encoder();
+
+            encoder = encoder.clear_buffer_slice(nodes_matching.view());
+            encoder = self.matcher.encode(
+                encoder,
+                MatchPairsByEdgeWeightInput {
+                    nodes_edge_offset: fine_level.nodes_edge_offset.view(),
+                    nodes_edges: fine_level.nodes_edges.view(),
+                    nodes_edge_weights: fine_level.nodes_edge_weights.view(),
+                    count: Some(MatchPairsByEdgeWeightsCounts {
+                        node_count: fine_level.node_count_buffer.uniform(),
+                        edge_ref_count: fine_level.edge_ref_count_buffer.uniform(),
+                    }),
+                },
+                nodes_matching.view(),
+            );
+            encoder = self.coarsen_graph.encode_with_workspace(
+                encoder,
+                CoarsenGraphWorkspaceInput {
+                    fine_nodes_edge_offset: fine_level.nodes_edge_offset.view(),
+                    fine_nodes_edges: fine_level.nodes_edges.view(),
+                    fine_nodes_edge_weights: fine_level.nodes_edge_weights.view(),
+                    fine_nodes_matching: nodes_matching.view(),
+                    edge_channels: &[],
+                    counts: Some(CoarsenCounts {
+                        node_count: fine_level.node_count_buffer.uniform(),
+                        edge_ref_count: fine_level.edge_ref_count_buffer.uniform(),
+                    }),
+                },
+                CoarsenGraphOutput {
+                    fine_nodes_mapping: coarse_level.fine_nodes_mapping.view(),
+                    coarse_nodes_mapping_offset: coarse_level.coarse_nodes_mapping_offset.view(),
+                    coarse_nodes_mapping: coarse_level.coarse_nodes_mapping.view(),
+                    coarse_node_count: coarse_level.node_count_buffer.view(),
+                    coarse_edge_ref_count: coarse_level.edge_ref_count_buffer.view(),
+                    coarse_nodes_edge_offset: coarse_level.nodes_edge_offset.view(),
+                    coarse_nodes_edges: coarse_level.nodes_edges.view(),
+                    coarse_nodes_edge_weights: coarse_level.nodes_edge_weights.view(),
+                    fine_edges_coarse_edge: None,
+                },
+            );
+
+            encoder = encoder.clear_buffer_slice(coarse_level.nodes_weight.view());
+            encoder = self.accumulate_coarse_nodes_weight.encode(
+                encoder,
+                AccumulateCoarseNodesWeightResources {
+                    fine_node_count: fine_level.node_count_buffer.uniform(),
+                    fine_nodes_mapping: coarse_level.fine_nodes_mapping.storage(),
+                    fine_nodes_weight: fine_level.nodes_weight.storage(),
+                    coarse_nodes_weight: coarse_level.nodes_weight.storage(),
+                },
+                fine_level.node_count,
+            );
+
+            self.device.queue().submit(encoder.finish());
+
+            coarse_level.read_counts(&self.device).await?;
+
+            let coarse_node_count = coarse_level.node_count;
+
+            // If the matching could not find any pairs to collapse, then the coarse level is a
+            // copy of the fine level; discard it.
+            if coarse_node_count == fine_node_count {
+                break;
+            }
+
+            levels.push(coarse_level);
+
+            if coarse_node_count as f32 > MIN_COARSENING_RATIO * fine_node_count as f32 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> source: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> destination: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index < node_count {
+        destination[index] = source[index];
+    }
+}
@@ -0,0 +1,449 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::future::join;
+
+use empa::buffer;
+use empa::device::Device;
+
+use crate::hierarchy::HierarchyLevel;
+
+/// A node in a [Dendrogram].
+#[derive(Clone, PartialEq, Debug)]
+pub struct DendrogramNode {
+    /// The index of the cluster this cluster was merged into, or `None` if it is a root.
+    pub parent: Option<usize>,
+    /// The indices of the clusters that were merged to form this cluster; empty for a leaf.
+    pub children: Vec<usize>,
+    /// The hierarchy level on which the cluster was formed; `0` for a leaf.
+    pub level: usize,
+    /// The number of nodes of the original graph in this cluster.
+    pub member_count: u32,
+    /// The summed weight of the edges between the children that were merged to form this
+    /// cluster; `0` for a leaf.
+    pub merge_weight: u64,
+}
+
+/// A hierarchical clustering of the nodes of a graph, derived from a coarsening hierarchy.
+///
+/// Each coarse node that represents more than one node of the next finer level merges the clusters
+/// of those nodes into a new cluster. A coarse node that represents a single (unmatched) finer node
+/// continues that node's cluster without adding a node to the dendrogram.
+///
+/// The first nodes of the dendrogram are the leaves, one for each node of the original graph, in
+/// the same order; the merged clusters follow in the order of the levels on which they were
+/// formed. Unless the hierarchy was coarsened down to a single node, the dendrogram is a forest
+/// with more than one root.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Dendrogram {
+    nodes: Vec<DendrogramNode>,
+    roots: Vec<usize>,
+    leaf_count: usize,
+}
+
+impl Dendrogram {
+    /// Reads back the `levels` of a hierarchy (see
+    /// [BuildHierarchy](crate::hierarchy::BuildHierarchy)) and derives the dendrogram from the node
+    /// mappings of each coarse level.
+    pub async fn from_hierarchy(
+        device: &Device,
+        levels: &[HierarchyLevel],
+    ) -> Result<Self, Box<dyn Error>> {
+        let leaf_count = levels.first().map(|level| level.node_count).unwrap_or(0) as usize;
+
+        let mut nodes: Vec<DendrogramNode> = (0..leaf_count)
+            .map(|_| DendrogramNode {
+                parent: None,
+                children: Vec::new(),
+                level: 0,
+                member_count: 1,
+                merge_weight: 0,
+            })
+            .collect();
+
+        // The dendrogram node that represents the cluster of each node of the current level.
+        let mut nodes_cluster: Vec<usize> = (0..leaf_count).collect();
+
+        for (index, window) in levels.windows(2).enumerate() {
+            let fine_level = &window[0];
+            let coarse_level = &window[1];
+            let readback = LevelReadback::read(device, fine_level, coarse_level).await?;
+
+            let coarse_node_count = coarse_level.node_count as usize;
+            let fine_node_count = fine_level.node_count as usize;
+
+            let mut coarse_nodes_cluster = Vec::with_capacity(coarse_node_count);
+
+            for coarse_node in 0..coarse_node_count {
+                let start = readback.coarse_nodes_mapping_offset[coarse_node] as usize;
+                let end = readback
+                    .coarse_nodes_mapping_offset
+                    .get(coarse_node + 1)
+                    .map(|offset| *offset as usize)
+                    .unwrap_or(fine_node_count);
+                let members = &readback.coarse_nodes_mapping[start..end];
+
+                if let [member] = members {
+                    coarse_nodes_cluster.push(nodes_cluster[*member as usize]);
+
+                    continue;
+                }
+
+                let cluster = nodes.len();
+                let children: Vec<usize> = members
+                    .iter()
+                    .map(|member| nodes_cluster[*member as usize])
+                    .collect();
+                let member_count = children.iter().map(|c| nodes[*c].member_count).sum();
+
+                for child in &children {
+                    nodes[*child].parent = Some(cluster);
+                }
+
+                nodes.push(DendrogramNode {
+                    parent: None,
+                    children,
+                    level: index + 1,
+                    member_count,
+                    merge_weight: readback.internal_weight(members),
+                });
+
+                coarse_nodes_cluster.push(cluster);
+            }
+
+            nodes_cluster = coarse_nodes_cluster;
+        }
+
+        let roots = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(index, _)| index)
+            .collect();
+
+        Ok(Dendrogram {
+            nodes,
+            roots,
+            leaf_count,
+        })
+    }
+
+    /// The nodes of the dendrogram; the first [leaf_count](Dendrogram::leaf_count) nodes are the
+    /// leaves.
+    pub fn nodes(&self) -> &[DendrogramNode] {
+        &self.nodes
+    }
+
+    /// The indices of the clusters that were not merged into another cluster.
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// The number of leaves, which is the node count of the original graph.
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Formats the dendrogram in the Newick format, with one tree per root, each on its own line.
+    ///
+    /// Leaves are labeled with the index of their node in the original graph, and branch lengths
+    /// are the number of levels between a cluster and its parent.
+    pub fn to_newick(&self) -> String {
+        let mut output = String::new();
+
+        for root in &self.roots {
+            self.write_newick_node(&mut output, *root);
+            output.push_str(";\n");
+        }
+
+        output
+    }
+
+    fn write_newick_node(&self, output: &mut String, index: usize) {
+        let node = &self.nodes[index];
+
+        if node.children.is_empty() {
+            write!(output, "{}", index).unwrap();
+        } else {
+            output.push('(');
+
+            for (i, child) in node.children.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+
+                self.write_newick_node(output, *child);
+                write!(output, ":{}", node.level - self.nodes[*child].level).unwrap();
+            }
+
+            output.push(')');
+        }
+    }
+
+    /// Formats the dendrogram as JSON: an object with a `nodes` array that holds the `id`,
+    /// `parent` (`null` for a root), `children`, `level`, `member_count` and `merge_weight` of
+    /// each node, and a `roots` array that holds the ids of the roots.
+    pub fn to_json(&self) -> String {
+        let mut output = String::from("{\"nodes\":[");
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            if index > 0 {
+                output.push(',');
+            }
+
+            write!(output, "{{\"id\":{},\"parent\":", index).unwrap();
+
+            match node.parent {
+                Some(parent) => write!(output, "{}", parent).unwrap(),
+                None => output.push_str("null"),
+            }
+
+            output.push_str(",\"children\":");
+            write_json_array(&mut output, &node.children);
+
+            write!(
+                output,
+                ",\"level\":{},\"member_count\":{},\"merge_weight\":{}}}",
+                node.level, node.member_count, node.merge_weight
+            )
+            .unwrap();
+        }
+
+        output.push_str("],\"roots\":");
+        write_json_array(&mut output, &self.roots);
+        output.push('}');
+
+        output
+    }
+}
+
+fn write_json_array(output: &mut String, values: &[usize]) {
+    output.push('[');
+
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+
+        write!(output, "{}", value).unwrap();
+    }
+
+    output.push(']');
+}
+
+/// The data of a fine level and the next coarser level that is needed to derive the merges the
+/// coarse level performed.
+struct LevelReadback {
+    coarse_nodes_mapping_offset: Vec<u32>,
+    coarse_nodes_mapping: Vec<u32>,
+    fine_nodes_edge_offset: Vec<u32>,
+    fine_nodes_edges: Vec<u32>,
+    fine_nodes_edge_weights: Vec<u32>,
+}
+
+impl LevelReadback {
+    async fn read(
+        device: &Device,
+        fine_level: &HierarchyLevel,
+        coarse_level: &HierarchyLevel,
+    ) -> Result<Self, Box<dyn Error>> {
+        let coarse_node_count = coarse_level.node_count as usize;
+        let fine_node_count = fine_level.node_count as usize;
+        let fine_edge_ref_count = fine_level.edge_ref_count as usize;
+
+        let (
+            coarse_nodes_mapping_offset,
+            coarse_nodes_mapping,
+            fine_nodes_edge_offset,
+            fine_nodes_edges,
+            fine_nodes_edge_weights,
+        ) = join!(
+            read_slice(
+                device,
+                coarse_level
+                    .coarse_nodes_mapping_offset
+                    .view()
+                    .get(..coarse_node_count)
+                    .unwrap()
+            ),
+            read_slice(
+                device,
+                coarse_level
+                    .coarse_nodes_mapping
+                    .view()
+                    .get(..fine_node_count)
+                    .unwrap()
+            ),
+            read_slice(
+                device,
+                fine_level
+                    .nodes_edge_offset
+                    .view()
+                    .get(..fine_node_count)
+                    .unwrap()
+            ),
+            read_slice(
+                device,
+                fine_level
+                    .nodes_edges
+                    .view()
+                    .get(..fine_edge_ref_count)
+                    .unwrap()
+            ),
+            read_slice(
+                device,
+                fine_level
+                    .nodes_edge_weights
+                    .view()
+                    .get(..fine_edge_ref_count)
+                    .unwrap()
+            ),
+        )
+        .await;
+
+        Ok(LevelReadback {
+            coarse_nodes_mapping_offset: coarse_nodes_mapping_offset?,
+            coarse_nodes_mapping: coarse_nodes_mapping?,
+            fine_nodes_edge_offset: fine_nodes_edge_offset?,
+            fine_nodes_edges: fine_nodes_edges?,
+            fine_nodes_edge_weights: fine_nodes_edge_weights?,
+        })
+    }
+
+    /// The summed weight of the fine edges between the `members`, counting each undirected edge
+    /// once.
+    fn internal_weight(&self, members: &[u32]) -> u64 {
+        let mut weight = 0;
+
+        for member in members {
+            let start = self.fine_nodes_edge_offset[*member as usize] as usize;
+            let end = self
+                .fine_nodes_edge_offset
+                .get(*member as usize + 1)
+                .map(|offset| *offset as usize)
+                .unwrap_or(self.fine_nodes_edges.len());
+
+            let edges = &self.fine_nodes_edges[start..end];
+            let edge_weights = &self.fine_nodes_edge_weights[start..end];
+
+            for (target, edge_weight) in edges.iter().zip(edge_weights) {
+                if target > member && members.contains(target) {
+                    weight += *edge_weight as u64;
+                }
+            }
+        }
+
+        weight
+    }
+}
+
+async fn read_slice<U>(
+    device: &Device,
+    view: buffer::View<'_, [u32], U>,
+) -> Result<Vec<u32>, Box<dyn Error>>
+where
+    U: buffer::CopySrc,
+{
+    if view.len() == 0 {
+        return Ok(Vec::new());
+    }
+
+    let readback =
+        device.create_slice_buffer_zeroed(view.len(), buffer::Usages::copy_dst().and_map_read());
+
+    let mut encoder = device.create_command_encoder();
+
+    encoder = encoder.copy_buffer_to_buffer_slice(view, readback.view());
+
+    device.queue().submit(encoder.finish());
+
+    readback.map_read().await?;
+
+    let data = readback.mapped().to_vec();
+
+    readback.unmap();
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(parent: Option<usize>) -> DendrogramNode {
+        DendrogramNode {
+            parent,
+            children: Vec::new(),
+            level: 0,
+            member_count: 1,
+            merge_weight: 0,
+        }
+    }
+
+    /// A dendrogram over 4 leaves, in which leaves `0` and `1` merge on level 1, that cluster
+    /// merges with leaf `2` on level 2, and leaf `3` is never merged.
+    fn example() -> Dendrogram {
+        Dendrogram {
+            nodes: vec![
+                leaf(Some(4)),
+                leaf(Some(4)),
+                leaf(Some(5)),
+                leaf(None),
+                DendrogramNode {
+                    parent: Some(5),
+                    children: vec![0, 1],
+                    level: 1,
+                    member_count: 2,
+                    merge_weight: 5,
+                },
+                DendrogramNode {
+                    parent: None,
+                    children: vec![4, 2],
+                    level: 2,
+                    member_count: 3,
+                    merge_weight: 7,
+                },
+            ],
+            roots: vec![3, 5],
+            leaf_count: 4,
+        }
+    }
+
+    #[test]
+    fn test_to_newick() {
+        assert_eq!(example().to_newick(), "3;\n((0:1,1:1):1,2:2);\n");
+    }
+
+    #[test]
+    fn test_to_json() {
+        let expected = concat!(
+            "{\"nodes\":[",
+            "{\"id\":0,\"parent\":4,\"children\":[],\"level\":0,\"member_count\":1,",
+            "\"merge_weight\":0},",
+            "{\"id\":1,\"parent\":4,\"children\":[],\"level\":0,\"member_count\":1,",
+            "\"merge_weight\":0},",
+            "{\"id\":2,\"parent\":5,\"children\":[],\"level\":0,\"member_count\":1,",
+            "\"merge_weight\":0},",
+            "{\"id\":3,\"parent\":null,\"children\":[],\"level\":0,\"member_count\":1,",
+            "\"merge_weight\":0},",
+            "{\"id\":4,\"parent\":5,\"children\":[0,1],\"level\":1,\"member_count\":2,",
+            "\"merge_weight\":5},",
+            "{\"id\":5,\"parent\":null,\"children\":[4,2],\"level\":2,\"member_count\":3,",
+            "\"merge_weight\":7}",
+            "],\"roots\":[3,5]}"
+        );
+
+        assert_eq!(example().to_json(), expected);
+    }
+
+    #[test]
+    fn test_empty() {
+        let dendrogram = Dendrogram {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            leaf_count: 0,
+        };
+
+        assert_eq!(dendrogram.to_newick(), "");
+        assert_eq!(dendrogram.to_json(), "{\"nodes\":[],\"roots\":[]}");
+    }
+}
@@ -0,0 +1,9 @@
+mod accumulate_coarse_nodes_weight;
+mod copy_nodes_weight;
+
+mod build_hierarchy;
+pub(crate) use self::build_hierarchy::{create_count_buffer, read_counts, MIN_COARSENING_RATIO};
+pub use self::build_hierarchy::{BuildHierarchy, HierarchyConfig, HierarchyInput, HierarchyLevel};
+
+mod dendrogram;
+pub use self::dendrogram::{Dendrogram, DendrogramNode};
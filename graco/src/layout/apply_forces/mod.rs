@@ -0,0 +1,116 @@
+use empa::abi;
+use empa::access_mode::ReadWrite;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct ApplyForcesResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub node_count: Uniform<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub edge_ref_count: Uniform<'a, u32>,
+    #[resource(binding = 2, visibility = "COMPUTE")]
+    pub ideal_edge_length: Uniform<'a, f32>,
+    #[resource(binding = 3, visibility = "COMPUTE")]
+    pub temperature: Uniform<'a, f32>,
+    #[resource(binding = 4, visibility = "COMPUTE")]
+    pub cell_count: Uniform<'a, u32>,
+    #[resource(binding = 5, visibility = "COMPUTE")]
+    pub nodes_edge_offset: Storage<'a, [u32]>,
+    #[resource(binding = 6, visibility = "COMPUTE")]
+    pub nodes_edges: Storage<'a, [u32]>,
+    #[resource(binding = 7, visibility = "COMPUTE")]
+    pub nodes_position: Storage<'a, [abi::Vec2<f32>]>,
+    #[resource(binding = 8, visibility = "COMPUTE")]
+    pub cells_start: Storage<'a, [u32]>,
+    #[resource(binding = 9, visibility = "COMPUTE")]
+    pub cells_end: Storage<'a, [u32]>,
+    #[resource(binding = 10, visibility = "COMPUTE")]
+    pub cells_nodes: Storage<'a, [u32]>,
+    #[resource(binding = 11, visibility = "COMPUTE")]
+    pub nodes_next_position: Storage<'a, [abi::Vec2<f32>], ReadWrite>,
+}
+
+type ResourcesLayout = <ApplyForcesResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct ApplyForces {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+    dispatch_config: DispatchConfig,
+}
+
+impl ApplyForces {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        ApplyForces {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+            dispatch_config,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        encoder: CommandEncoder,
+        resources: ApplyForcesResources,
+        node_count: u32,
+    ) -> CommandEncoder {
+        let key = [
+            resources.node_count.binding_key(),
+            resources.edge_ref_count.binding_key(),
+            resources.ideal_edge_length.binding_key(),
+            resources.temperature.binding_key(),
+            resources.cell_count.binding_key(),
+            resources.nodes_edge_offset.binding_key(),
+            resources.nodes_edges.binding_key(),
+            resources.nodes_position.binding_key(),
+            resources.cells_start.binding_key(),
+            resources.cells_end.binding_key(),
+            resources.cells_nodes.binding_key(),
+            resources.nodes_next_position.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group)
+            .dispatch_workgroups(self.dispatch_config.workgroups(node_count))
+            .end()
+    }
+}
@@ -0,0 +1,131 @@
+#include <src/dispatch.wgsl>
+#include <src/layout/grid.wgsl>
+
+// Below this distance, relative to the ideal edge length, two nodes are considered to coincide; the repulsive force
+// between them is then applied along an arbitrary (but deterministic) direction at this minimum distance.
+const MIN_DISTANCE_FACTOR = 0.01;
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<uniform> ideal_edge_length: f32;
+
+@group(0) @binding(3)
+var<uniform> temperature: f32;
+
+@group(0) @binding(4)
+var<uniform> cell_count: u32;
+
+@group(0) @binding(5)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> nodes_edges: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read> nodes_position: array<vec2<f32>>;
+
+@group(0) @binding(8)
+var<storage, read> cells_start: array<u32>;
+
+@group(0) @binding(9)
+var<storage, read> cells_end: array<u32>;
+
+@group(0) @binding(10)
+var<storage, read> cells_nodes: array<u32>;
+
+@group(0) @binding(11)
+var<storage, read_write> nodes_next_position: array<vec2<f32>>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let k = ideal_edge_length;
+    let min_distance = MIN_DISTANCE_FACTOR * k;
+    let range = REPULSION_RANGE_FACTOR * k;
+    let position = nodes_position[index];
+    let cell = grid_cell(position, k);
+
+    var displacement = vec2(0.0, 0.0);
+
+    // Repulsion (Fruchterman-Reingold: `k^2 / d`) from all nodes within range, which are all found in the 3x3 block of
+    // grid cells around this node's cell.
+    for (var y = -1; y <= 1; y += 1) {
+        for (var x = -1; x <= 1; x += 1) {
+            let neighbor_cell = cell + vec2(x, y);
+            let bucket = grid_cell_bucket(neighbor_cell, cell_count);
+            let end = cells_end[bucket];
+
+            for (var i = cells_start[bucket]; i < end; i += 1u) {
+                let other = cells_nodes[i];
+
+                if other == index {
+                    continue;
+                }
+
+                let other_position = nodes_position[other];
+
+                // Skip nodes that share the bucket but lie in a different cell, so that no node is visited twice.
+                if any(grid_cell(other_position, k) != neighbor_cell) {
+                    continue;
+                }
+
+                var delta = position - other_position;
+                var distance = length(delta);
+
+                if distance >= range {
+                    continue;
+                }
+
+                if distance < min_distance {
+                    // Separate coinciding nodes along a direction that is opposite for the two nodes.
+                    let sign = select(-1.0, 1.0, index < other);
+
+                    delta = vec2(sign * min_distance, 0.0);
+                    distance = min_distance;
+                }
+
+                displacement += delta / distance * (k * k / distance);
+            }
+        }
+    }
+
+    // Attraction (Fruchterman-Reingold: `d^2 / k`) along the node's edges.
+    let edges_start = nodes_edge_offset[index];
+
+    var edges_end = edge_ref_count;
+
+    if index < node_count - 1 {
+        edges_end = nodes_edge_offset[index + 1];
+    }
+
+    for (var i = edges_start; i < edges_end; i += 1u) {
+        let delta = nodes_position[nodes_edges[i]] - position;
+        let distance = length(delta);
+
+        displacement += delta * (distance / k);
+    }
+
+    // Limit the displacement to the current temperature.
+    let displacement_length = length(displacement);
+
+    var next_position = position;
+
+    if displacement_length > 0.0 {
+        next_position += displacement / displacement_length * min(displacement_length, temperature);
+    }
+
+    nodes_next_position[index] = next_position;
+}
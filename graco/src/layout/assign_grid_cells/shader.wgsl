@@ -0,0 +1,37 @@
+#include <src/dispatch.wgsl>
+#include <src/layout/grid.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> ideal_edge_length: f32;
+
+@group(0) @binding(2)
+var<uniform> cell_count: u32;
+
+@group(0) @binding(3)
+var<storage, read> nodes_position: array<vec2<f32>>;
+
+@group(0) @binding(4)
+var<storage, read_write> nodes_cell: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read_write> cells_node_count: array<atomic<u32>>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let cell = grid_cell_bucket(grid_cell(nodes_position[index], ideal_edge_length), cell_count);
+
+    nodes_cell[index] = cell;
+    atomicAdd(&cells_node_count[cell], 1u);
+}
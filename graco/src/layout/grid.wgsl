@@ -0,0 +1,18 @@
+#pragma once
+
+// Repulsive forces are only applied between nodes that are closer than this multiple of the ideal edge length. The
+// grid cells have this size, so that all nodes within range of a node are found in the 3x3 block of cells around it.
+const REPULSION_RANGE_FACTOR = 2.0;
+
+// Returns the coordinates of the grid cell that contains the given `position`.
+fn grid_cell(position: vec2<f32>, ideal_edge_length: f32) -> vec2<i32> {
+    return vec2<i32>(floor(position / (REPULSION_RANGE_FACTOR * ideal_edge_length)));
+}
+
+// Maps the (unbounded) grid cell coordinates onto one of `cell_count` buckets. Distinct cells may map onto the same
+// bucket, so consumers must compare a node's actual cell against the cell they are looking for.
+fn grid_cell_bucket(cell: vec2<i32>, cell_count: u32) -> u32 {
+    let hash = (bitcast<u32>(cell.x) * 73856093u) ^ (bitcast<u32>(cell.y) * 19349663u);
+
+    return hash % cell_count;
+}
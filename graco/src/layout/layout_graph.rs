@@ -0,0 +1,410 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer::Buffer;
+use empa::command::CommandEncoder;
+use empa::device::Device;
+use empa::type_flag::{O, X};
+use empa::{abi, buffer};
+use empa_tk::prefix_sum::{PrefixSum, PrefixSumInput};
+
+use crate::context::Context;
+use crate::hierarchy::{BuildHierarchy, HierarchyConfig, HierarchyInput, HierarchyLevel};
+use crate::layout::apply_forces::{ApplyForces, ApplyForcesResources};
+use crate::layout::assign_grid_cells::{AssignGridCells, AssignGridCellsResources};
+use crate::layout::prolong_positions::{ProlongPositions, ProlongPositionsResources};
+use crate::layout::scatter_grid_cells::{ScatterGridCells, ScatterGridCellsResources};
+use crate::matching::MatchPairsByEdgeWeightConfig;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LayoutConfig {
+    /// The desired distance between adjacent nodes.
+    pub ideal_edge_length: f32,
+    /// The number of force-directed iterations that are run on the coarsest level, starting from a
+    /// random layout.
+    pub coarsest_iterations: usize,
+    /// The number of force-directed iterations that are run on each finer level, after placing its
+    /// nodes at the positions of their coarse nodes.
+    pub iterations: usize,
+    /// The maximum distance (relative to the `ideal_edge_length`) that a node may move in the first
+    /// iteration on each level.
+    pub initial_temperature: f32,
+    /// The factor by which the maximum distance a node may move is multiplied after each
+    /// iteration.
+    pub cooling_factor: f32,
+    /// Coarsening stops once a level has no more than this many nodes.
+    pub coarsest_node_count: u32,
+    /// The maximum number of levels to coarsen.
+    pub max_levels: usize,
+    /// The seed for the random initial layout of the coarsest level.
+    pub seed: u64,
+    /// The configuration of the matching that is used to coarsen the graph.
+    pub matching: MatchPairsByEdgeWeightConfig,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            ideal_edge_length: 1.0,
+            coarsest_iterations: 200,
+            iterations: 50,
+            initial_temperature: 1.0,
+            cooling_factor: 0.95,
+            coarsest_node_count: 64,
+            max_levels: 32,
+            seed: 1,
+            matching: Default::default(),
+        }
+    }
+}
+
+pub struct LayoutInput<'a, U0, U1, U2> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    /// The edge weights are only used to guide the coarsening: heavy edges are collapsed first.
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+}
+
+/// Computes a 2D layout for a graph with a multilevel force-directed algorithm in the style of
+/// FM3.
+///
+/// Coarsens the graph into a hierarchy of levels (see [BuildHierarchy]), lays out the coarsest
+/// level starting from random positions, and then places the nodes of each finer level at the
+/// (scaled) position of their coarse node, before refining the layout on that level with
+/// Fruchterman-Reingold forces on the GPU. Repulsion is only applied between nodes that are close
+/// to each other, which are found with a spatial hash grid that is rebuilt for each iteration.
+///
+/// The graph must be undirected (each edge is stored once for each of the nodes it connects) and
+/// is described by buffers that are exactly sized to its node count and edge ref count.
+pub struct LayoutGraph {
+    device: Device,
+    config: LayoutConfig,
+    build_hierarchy: BuildHierarchy,
+    prolong_positions: Rc<ProlongPositions>,
+    assign_grid_cells: Rc<AssignGridCells>,
+    scatter_grid_cells: Rc<ScatterGridCells>,
+    apply_forces: Rc<ApplyForces>,
+    prefix_sum_inclusive: Rc<RefCell<PrefixSum<u32>>>,
+    ideal_edge_length: Buffer<f32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+    temperatures: Vec<Buffer<f32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>>,
+}
+
+impl LayoutGraph {
+    pub async fn init(device: Device, config: LayoutConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](LayoutGraph::init), but obtains the sub-pipelines from the given `context`,
+    /// so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context, config: LayoutConfig) -> Self {
+        assert!(
+            config.ideal_edge_length > 0.0,
+            "`ideal_edge_length` must be greater than `0`"
+        );
+
+        let device = context.device().clone();
+
+        let (
+            build_hierarchy,
+            prolong_positions,
+            assign_grid_cells,
+            scatter_grid_cells,
+            apply_forces,
+            prefix_sum_inclusive,
+        ) = join!(
+            BuildHierarchy::init_with_context(context, config.matching),
+            context.get_or_init_kernel(ProlongPositions::init),
+            context.get_or_init_kernel(AssignGridCells::init),
+            context.get_or_init_kernel(ScatterGridCells::init),
+            context.get_or_init_kernel(ApplyForces::init),
+            context.get_or_init_mut("init_inclusive_u32", PrefixSum::init_inclusive_u32),
+        )
+        .await;
+
+        let ideal_edge_length =
+            device.create_buffer(config.ideal_edge_length, buffer::Usages::uniform_binding());
+
+        // The cooling schedule is the same on every level, so we create the temperature uniforms
+        // once and share them between the levels.
+        let iterations = config.iterations.max(config.coarsest_iterations);
+        let mut temperature = config.initial_temperature * config.ideal_edge_length;
+        let mut temperatures = Vec::with_capacity(iterations);
+
+        for _ in 0..iterations {
+            temperatures.push(device.create_buffer(temperature, buffer::Usages::uniform_binding()));
+
+            temperature *= config.cooling_factor;
+        }
+
+        LayoutGraph {
+            device,
+            config,
+            build_hierarchy,
+            prolong_positions,
+            assign_grid_cells,
+            scatter_grid_cells,
+            apply_forces,
+            prefix_sum_inclusive,
+            ideal_edge_length,
+            temperatures,
+        }
+    }
+
+    /// Computes a layout for the graph described by the `input` and writes the position of each
+    /// node to `nodes_position`.
+    ///
+    /// Submits its own command buffers, as the number of levels in the hierarchy is only known
+    /// after reading back the node count of each coarse level.
+    pub async fn layout<U0, U1, U2, U3>(
+        &mut self,
+        input: LayoutInput<'_, U0, U1, U2>,
+        nodes_position: buffer::View<'_, [abi::Vec2<f32>], U3>,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        U0: buffer::CopySrc,
+        U1: buffer::CopySrc,
+        U2: buffer::CopySrc,
+        U3: buffer::CopyDst,
+    {
+        let LayoutInput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+        } = input;
+
+        assert_eq!(
+            nodes_position.len(),
+            nodes_edge_offset.len(),
+            "`nodes_position` must have the same length as `nodes_edge_offset`"
+        );
+
+        let levels = self
+            .build_hierarchy
+            .build(
+                HierarchyInput {
+                    nodes_edge_offset,
+                    nodes_edges,
+                    nodes_edge_weights,
+                    nodes_weight: None,
+                },
+                HierarchyConfig {
+                    coarsest_node_count: self.config.coarsest_node_count,
+                    max_levels: self.config.max_levels,
+                },
+            )
+            .await?;
+
+        let base_node_count = levels[0].node_count as usize;
+
+        // The positions for each level; unlike the buffers of the hierarchy levels, these are
+        // exactly sized to the level's node count.
+        let levels_position: Vec<Buffer<[abi::Vec2<f32>], _>> = levels
+            .iter()
+            .map(|level| {
+                self.device.create_slice_buffer_zeroed(
+                    level.node_count as usize,
+                    buffer::Usages::storage_binding()
+                        .and_copy_dst()
+                        .and_copy_src(),
+                )
+            })
+            .collect();
+
+        // Scratch buffers for the force-directed iterations, shared by all levels; coarse levels
+        // are never larger than the base level.
+        let workspace = Workspace::new(&self.device, base_node_count);
+
+        // Start the coarsest level from random positions in a square that has roughly the area
+        // the final layout is expected to cover.
+        let coarsest_level = levels.last().unwrap();
+        let initial_position = self.device.create_buffer(
+            self.random_positions(coarsest_level.node_count),
+            buffer::Usages::copy_src(),
+        );
+
+        let mut encoder = self.device.create_command_encoder();
+
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            initial_position.view(),
+            levels_position.last().unwrap().view(),
+        );
+
+        // Uncoarsen: place the nodes of each level at the positions of their coarse nodes and
+        // refine the layout.
+        for index in (0..levels.len()).rev() {
+            let level = &levels[index];
+
+            let iterations = if let Some(coarse_level) = levels.get(index + 1) {
+                // A layout with `n` nodes at the ideal edge length covers an area proportional to
+                // `n`, so we scale the coarse layout to the area the finer layout needs.
+                let scale = (level.node_count as f32 / coarse_level.node_count as f32).sqrt();
+                let scale = self
+                    .device
+                    .create_buffer(scale, buffer::Usages::uniform_binding());
+
+                encoder = self.prolong_positions.encode(
+                    encoder,
+                    ProlongPositionsResources {
+                        fine_node_count: level.node_count_buffer.uniform(),
+                        ideal_edge_length: self.ideal_edge_length.uniform(),
+                        scale: scale.uniform(),
+                        fine_nodes_mapping: coarse_level.fine_nodes_mapping.storage(),
+                        coarse_nodes_position: levels_position[index + 1].storage(),
+                        fine_nodes_position: levels_position[index].storage(),
+                    },
+                    level.node_count,
+                );
+
+                self.config.iterations
+            } else {
+                self.config.coarsest_iterations
+            };
+
+            encoder = self.encode_iterations(
+                encoder,
+                level,
+                &levels_position[index],
+                &workspace,
+                iterations,
+            );
+        }
+
+        encoder = encoder.copy_buffer_to_buffer_slice(levels_position[0].view(), nodes_position);
+
+        self.device.queue().submit(encoder.finish());
+
+        Ok(())
+    }
+
+    fn random_positions(&self, node_count: u32) -> Vec<abi::Vec2<f32>> {
+        let side = (node_count as f32).sqrt() * self.config.ideal_edge_length;
+        let mut rng = oorandom::Rand32::new(self.config.seed);
+
+        (0..node_count)
+            .map(|_| abi::Vec2(rng.rand_float() * side, rng.rand_float() * side))
+            .collect()
+    }
+
+    fn encode_iterations(
+        &self,
+        mut encoder: CommandEncoder,
+        level: &HierarchyLevel,
+        nodes_position: &Buffer<[abi::Vec2<f32>], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+        workspace: &Workspace,
+        iterations: usize,
+    ) -> CommandEncoder {
+        let node_count = level.node_count as usize;
+
+        // Use a hash table with at least as many buckets as there are nodes, so that the expected
+        // number of nodes that share a bucket with a node from a different grid cell stays small.
+        let cell_count = self.device.create_buffer(
+            level.node_count.next_power_of_two(),
+            buffer::Usages::uniform_binding(),
+        );
+
+        for temperature in &self.temperatures[..iterations] {
+            // Sort the nodes into the grid cells: count the nodes in each cell, turn the counts
+            // into ranges with a prefix sum, and then scatter the nodes into their cell's range.
+            encoder = encoder.clear_buffer_slice(workspace.cells_end.view());
+            encoder = self.assign_grid_cells.encode(
+                encoder,
+                AssignGridCellsResources {
+                    node_count: level.node_count_buffer.uniform(),
+                    ideal_edge_length: self.ideal_edge_length.uniform(),
+                    cell_count: cell_count.uniform(),
+                    nodes_position: nodes_position.storage(),
+                    nodes_cell: workspace.nodes_cell.storage(),
+                    cells_node_count: workspace.cells_end.storage(),
+                },
+                level.node_count,
+            );
+            encoder = self.prefix_sum_inclusive.borrow_mut().encode(
+                encoder,
+                PrefixSumInput {
+                    data: workspace.cells_end.view(),
+                    count: Some(cell_count.uniform()),
+                },
+            );
+            encoder = encoder.copy_buffer_to_buffer_slice(
+                workspace.cells_end.view(),
+                workspace.cells_start.view(),
+            );
+            encoder = self.scatter_grid_cells.encode(
+                encoder,
+                ScatterGridCellsResources {
+                    node_count: level.node_count_buffer.uniform(),
+                    nodes_cell: workspace.nodes_cell.storage(),
+                    cells_cursor: workspace.cells_start.storage(),
+                    cells_nodes: workspace.cells_nodes.storage(),
+                },
+                level.node_count,
+            );
+
+            encoder = self.apply_forces.encode(
+                encoder,
+                ApplyForcesResources {
+                    node_count: level.node_count_buffer.uniform(),
+                    edge_ref_count: level.edge_ref_count_buffer.uniform(),
+                    ideal_edge_length: self.ideal_edge_length.uniform(),
+                    temperature: temperature.uniform(),
+                    cell_count: cell_count.uniform(),
+                    nodes_edge_offset: level.nodes_edge_offset.storage(),
+                    nodes_edges: level.nodes_edges.storage(),
+                    nodes_position: nodes_position.storage(),
+                    cells_start: workspace.cells_start.storage(),
+                    cells_end: workspace.cells_end.storage(),
+                    cells_nodes: workspace.cells_nodes.storage(),
+                    nodes_next_position: workspace.nodes_next_position.storage(),
+                },
+                level.node_count,
+            );
+            encoder = encoder.copy_buffer_to_buffer_slice(
+                workspace
+                    .nodes_next_position
+                    .view()
+                    .get(..node_count)
+                    .unwrap(),
+                nodes_position.view(),
+            );
+        }
+
+        encoder
+    }
+}
+
+struct Workspace {
+    nodes_cell: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    nodes_next_position: Buffer<[abi::Vec2<f32>], buffer::Usages<O, O, X, O, O, O, O, X, O, O>>,
+    cells_start: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    cells_end: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+    cells_nodes: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+}
+
+impl Workspace {
+    fn new(device: &Device, node_capacity: usize) -> Self {
+        let cell_capacity = node_capacity.next_power_of_two();
+
+        Workspace {
+            nodes_cell: device
+                .create_slice_buffer_zeroed(node_capacity, buffer::Usages::storage_binding()),
+            nodes_next_position: device.create_slice_buffer_zeroed(
+                node_capacity,
+                buffer::Usages::storage_binding().and_copy_src(),
+            ),
+            cells_start: device.create_slice_buffer_zeroed(
+                cell_capacity,
+                buffer::Usages::storage_binding().and_copy_dst(),
+            ),
+            cells_end: device.create_slice_buffer_zeroed(
+                cell_capacity,
+                buffer::Usages::storage_binding()
+                    .and_copy_dst()
+                    .and_copy_src(),
+            ),
+            cells_nodes: device
+                .create_slice_buffer_zeroed(node_capacity, buffer::Usages::storage_binding()),
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Multilevel force-directed graph layout.
+//!
+//! See [LayoutGraph] for an overview of the algorithm.
+
+mod apply_forces;
+mod assign_grid_cells;
+mod prolong_positions;
+mod scatter_grid_cells;
+
+mod layout_graph;
+pub use self::layout_graph::{LayoutConfig, LayoutGraph, LayoutInput};
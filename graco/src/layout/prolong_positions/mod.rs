@@ -0,0 +1,99 @@
+use empa::abi;
+use empa::access_mode::ReadWrite;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct ProlongPositionsResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub fine_node_count: Uniform<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub ideal_edge_length: Uniform<'a, f32>,
+    #[resource(binding = 2, visibility = "COMPUTE")]
+    pub scale: Uniform<'a, f32>,
+    #[resource(binding = 3, visibility = "COMPUTE")]
+    pub fine_nodes_mapping: Storage<'a, [u32]>,
+    #[resource(binding = 4, visibility = "COMPUTE")]
+    pub coarse_nodes_position: Storage<'a, [abi::Vec2<f32>]>,
+    #[resource(binding = 5, visibility = "COMPUTE")]
+    pub fine_nodes_position: Storage<'a, [abi::Vec2<f32>], ReadWrite>,
+}
+
+type ResourcesLayout =
+    <ProlongPositionsResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct ProlongPositions {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+    dispatch_config: DispatchConfig,
+}
+
+impl ProlongPositions {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        ProlongPositions {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+            dispatch_config,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        encoder: CommandEncoder,
+        resources: ProlongPositionsResources,
+        fine_node_count: u32,
+    ) -> CommandEncoder {
+        let key = [
+            resources.fine_node_count.binding_key(),
+            resources.ideal_edge_length.binding_key(),
+            resources.scale.binding_key(),
+            resources.fine_nodes_mapping.binding_key(),
+            resources.coarse_nodes_position.binding_key(),
+            resources.fine_nodes_position.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group)
+            .dispatch_workgroups(self.dispatch_config.workgroups(fine_node_count))
+            .end()
+    }
+}
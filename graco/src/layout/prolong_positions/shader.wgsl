@@ -0,0 +1,49 @@
+#include <src/dispatch.wgsl>
+
+// The distance, relative to the ideal edge length, by which fine nodes are offset from the position of their coarse
+// node, so that the fine nodes that were merged into the same coarse node do not start out on top of each other.
+const JITTER_FACTOR = 0.1;
+
+@group(0) @binding(0)
+var<uniform> fine_node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> ideal_edge_length: f32;
+
+@group(0) @binding(2)
+var<uniform> scale: f32;
+
+@group(0) @binding(3)
+var<storage, read> fine_nodes_mapping: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> coarse_nodes_position: array<vec2<f32>>;
+
+@group(0) @binding(5)
+var<storage, read_write> fine_nodes_position: array<vec2<f32>>;
+
+// A cheap integer hash (from "Hash Functions for GPU Rendering", Jarzynski and Olano), used to derive a deterministic
+// jitter direction for each node.
+fn pcg_hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+
+    return (word >> 22u) ^ word;
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= fine_node_count {
+        return;
+    }
+
+    let angle = f32(pcg_hash(index)) / 4294967296.0 * 6.28318530718;
+    let jitter = vec2(cos(angle), sin(angle)) * JITTER_FACTOR * ideal_edge_length;
+
+    fine_nodes_position[index] = coarse_nodes_position[fine_nodes_mapping[index]] * scale + jitter;
+}
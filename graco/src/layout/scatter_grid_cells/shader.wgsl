@@ -0,0 +1,31 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> nodes_cell: array<u32>;
+
+// Starts out as the inclusive prefix sum of the cell node counts (the end of each cell's range in `cells_nodes`) and
+// is decremented for each node that is placed in the cell; afterwards, it holds the start of each cell's range.
+@group(0) @binding(2)
+var<storage, read_write> cells_cursor: array<atomic<u32>>;
+
+@group(0) @binding(3)
+var<storage, read_write> cells_nodes: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let position = atomicSub(&cells_cursor[nodes_cell[index]], 1u) - 1;
+
+    cells_nodes[position] = index;
+}
@@ -1,8 +1,43 @@
 #![feature(future_join, int_roundings)]
 
+pub mod amg;
+pub mod community;
+pub mod generators;
+pub mod hierarchy;
+pub mod layout;
 pub mod matching;
+pub mod mesh;
+pub mod partition;
+pub mod spectral;
+pub mod validate;
+
+mod build_csr;
+pub use self::build_csr::{
+    BuildCsr, BuildCsrConfig, BuildCsrCounts, BuildCsrInput, BuildCsrOutput,
+};
+
+mod coarsen_batch;
+pub use self::coarsen_batch::{
+    CoarsenBatch, CoarsenBatchConfig, CoarsenBatchInput, CoarsenBatchOutput,
+};
 
 mod coarsen_graph;
-pub use self::coarsen_graph::{CoarsenCounts, CoarsenGraph, CoarsenGraphInput, CoarsenGraphOutput};
+pub use self::coarsen_graph::{
+    CoarsenCompanionEdgesInput, CoarsenCompanionEdgesOutput, CoarsenCounts, CoarsenEdgeChannel,
+    CoarsenGraph, CoarsenGraphInput, CoarsenGraphOutput, CoarsenGraphWorkspaceInput, CombineRule,
+    RecomputeWeightsInput, COARSE_EDGE_SELF_REFERENCE,
+};
+
+mod context;
+pub use self::context::Context;
 
 mod counts_fallback;
+
+mod dispatch;
+pub use self::dispatch::{DispatchConfig, DispatchConstants};
+
+mod generate_dispatch;
+pub use self::generate_dispatch::{GenerateDispatch, GenerateDispatchResources};
+
+mod profiler;
+pub use self::profiler::{Profile, ProfileStage, Profiler};
@@ -0,0 +1,47 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<uniform> heavy_degree_threshold: u32;
+
+@group(0) @binding(3)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> heavy_node_count: atomic<u32>;
+
+@group(0) @binding(5)
+var<storage, read_write> heavy_nodes: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let edges_start = nodes_edge_offset[index];
+
+    var edges_end = edge_ref_count;
+
+    if index < node_count - 1 {
+        edges_end = nodes_edge_offset[index + 1];
+    }
+
+    // Note that the order of the heavy node list is not deterministic, but that does not affect the matching result,
+    // as every heavy node is processed independently of the other heavy nodes.
+    if edges_end - edges_start > heavy_degree_threshold {
+        let heavy_index = atomicAdd(&heavy_node_count, 1u);
+
+        heavy_nodes[heavy_index] = index;
+    }
+}
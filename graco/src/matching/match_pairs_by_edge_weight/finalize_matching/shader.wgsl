@@ -1,3 +1,4 @@
+#include <src/dispatch.wgsl>
 #include <src/matching/match_pairs_by_edge_weight/match_state.wgsl>
 
 @group(0) @binding(0)
@@ -6,9 +7,12 @@ var<uniform> count: u32;
 @group(0) @binding(1)
 var<storage, read_write> nodes_match_state: array<u32>;
 
-@compute @workgroup_size(256, 1, 1)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let index = global_id.x;
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
 
     if index >= count {
         return;
@@ -1,3 +1,4 @@
+#include <src/dispatch.wgsl>
 #include <src/matching/match_pairs_by_edge_weight/match_state.wgsl>
 
 @group(0) @binding(0)
@@ -29,9 +30,15 @@ var<storage, read> nodes_edge_weights: array<u32>;
 @group(0) @binding(7)
 var<storage, read> nodes_proposal: array<u32>;
 
-@compute @workgroup_size(256, 1, 1)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let index = global_id.x;
+@group(0) @binding(8)
+var<uniform> heavy_degree_threshold: u32;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
 
     if has_live_nodes == 0 || index >= node_count {
         return;
@@ -52,6 +59,11 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
             edges_end = nodes_edge_offset[index + 1];
         }
 
+        // Nodes with a degree above the threshold are processed by `find_matches_heavy` instead.
+        if edges_end - edges_start > heavy_degree_threshold {
+            return;
+        }
+
         // Loop over all adjacent nodes to find the proposing node ("blue" node) with the proposal's edge weight. Also
         // verify that the proposing node did in fact propose to this current node, as there may be multiple adjacent
         // nodes with the same edge weight. If we find such a node, then create a match.
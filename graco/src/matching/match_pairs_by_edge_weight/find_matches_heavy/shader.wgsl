@@ -0,0 +1,113 @@
+#include <src/dispatch.wgsl>
+#include <src/matching/match_pairs_by_edge_weight/match_state.wgsl>
+
+// Finds matches for nodes with a degree above the heavy degree threshold. Rather than scanning a node's adjacency
+// list in a single invocation (see `find_matches`), each heavy node is processed by a whole workgroup: every invocation
+// scans a strided subset of the adjacency list for the proposing node, after which the workgroup selects the proposing
+// node that occurs first in the adjacency list, which is the same node the sequential scan in `find_matches` selects.
+
+const NO_CANDIDATE = 0xFFFFFFFFu;
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<uniform> has_live_nodes: u32;
+
+@group(0) @binding(3)
+var<storage, read_write> nodes_match_state: array<MatchState>;
+
+@group(0) @binding(4)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> nodes_edges: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> nodes_edge_weights: array<u32>;
+
+// See `find_matches` for the dual purpose of this buffer.
+@group(0) @binding(7)
+var<storage, read> nodes_proposal: array<u32>;
+
+@group(0) @binding(8)
+var<storage, read> heavy_node_count: u32;
+
+@group(0) @binding(9)
+var<storage, read> heavy_nodes: array<u32>;
+
+var<workgroup> node_state: u32;
+var<workgroup> match_position: atomic<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    if has_live_nodes == 0 {
+        return;
+    }
+
+    for (var heavy_index = workgroup_id.x; heavy_index < heavy_node_count; heavy_index += num_workgroups.x) {
+        let index = heavy_nodes[heavy_index];
+
+        if local_index == 0 {
+            node_state = nodes_match_state[index].packed_data;
+
+            atomicStore(&match_position, NO_CANDIDATE);
+        }
+
+        let state = MatchState(workgroupUniformLoad(&node_state));
+        let status = match_state_status(state);
+        let proposal = nodes_proposal[index];
+
+        if status == MATCH_STATUS_RED && proposal != 0 {
+            let proposal_weight = proposal;
+
+            let edges_start = nodes_edge_offset[index];
+
+            var edges_end = edge_ref_count;
+
+            if index < node_count - 1 {
+                edges_end = nodes_edge_offset[index + 1];
+            }
+
+            for (var i = edges_start + local_index; i < edges_end; i += group_size) {
+                let other_index = nodes_edges[i];
+                let edge_weight = nodes_edge_weights[i];
+                let other_proposal_target_index = nodes_proposal[other_index];
+                let other_state = nodes_match_state[other_index];
+                let other_status = match_state_status(other_state);
+
+                if other_status == MATCH_STATUS_BLUE && edge_weight == proposal_weight && other_proposal_target_index == index {
+                    atomicMin(&match_position, i);
+
+                    // Positions only increase, so the first position this invocation finds is its best position.
+                    break;
+                }
+            }
+
+            workgroupBarrier();
+
+            if local_index == 0 {
+                let position = atomicLoad(&match_position);
+
+                if position != NO_CANDIDATE {
+                    let other_index = nodes_edges[position];
+                    let match_index = min(index, other_index);
+                    let new_state = match_state_new_matched(match_index);
+
+                    nodes_match_state[index] = new_state;
+                    nodes_match_state[other_index] = new_state;
+                }
+            }
+        }
+
+        // Make sure all invocations are done with the workgroup memory before processing the next heavy node.
+        workgroupBarrier();
+    }
+}
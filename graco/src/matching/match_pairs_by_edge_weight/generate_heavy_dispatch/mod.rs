@@ -0,0 +1,87 @@
+use empa::access_mode::ReadWrite;
+use empa::buffer::Storage;
+use empa::command::{CommandEncoder, DispatchWorkgroups, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct GenerateHeavyDispatchResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub heavy_node_count: Storage<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub dispatch: Storage<'a, DispatchWorkgroups, ReadWrite>,
+}
+
+type ResourcesLayout =
+    <GenerateHeavyDispatchResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct GenerateHeavyDispatch {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+}
+
+impl GenerateHeavyDispatch {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        GenerateHeavyDispatch {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        encoder: CommandEncoder,
+        resources: GenerateHeavyDispatchResources,
+    ) -> CommandEncoder {
+        let key = [
+            resources.heavy_node_count.binding_key(),
+            resources.dispatch.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group)
+            .dispatch_workgroups(DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            })
+            .end()
+    }
+}
@@ -0,0 +1,14 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<storage, read> heavy_node_count: u32;
+
+@group(0) @binding(1)
+var<storage, read_write> dispatch: DispatchWorkgroups;
+
+@compute @workgroup_size(1, 1, 1)
+fn main() {
+    // We dispatch 1 workgroup per heavy node. If there are more heavy nodes than we can dispatch workgroups along a
+    // single dimension, then the workgroups will process multiple heavy nodes in a strided loop.
+    dispatch = DispatchWorkgroups(min(heavy_node_count, max_workgroups_per_dimension), 1, 1);
+}
@@ -9,8 +9,9 @@ use empa::device::Device;
 use empa::resource_binding::BindGroupLayout;
 use empa::shader_module::{shader_source, ShaderSource};
 
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
 use crate::matching::match_pairs_by_edge_weight::match_state::MatchState;
-use crate::matching::match_pairs_by_edge_weight::GROUP_SIZE;
 
 const SHADER: ShaderSource = shader_source!("shader.wgsl");
 
@@ -32,6 +33,8 @@ pub struct MakeProposalsResources<'a> {
     pub nodes_edge_weights: Storage<'a, [u32]>,
     #[resource(binding = 7, visibility = "COMPUTE")]
     pub nodes_proposal: Storage<'a, [u32], ReadWrite>,
+    #[resource(binding = 8, visibility = "COMPUTE")]
+    pub heavy_degree_threshold: Uniform<'a, u32>,
 }
 
 type ResourcesLayout =
@@ -40,11 +43,13 @@ type ResourcesLayout =
 pub struct MakeProposals {
     device: Device,
     bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
     pipeline: ComputePipeline<(ResourcesLayout,)>,
+    dispatch_config: DispatchConfig,
 }
 
 impl MakeProposals {
-    pub async fn init(device: Device) -> Self {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
         let shader = device.create_shader_module(&SHADER);
 
         let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
@@ -54,7 +59,11 @@ impl MakeProposals {
             .create_compute_pipeline(
                 &ComputePipelineDescriptorBuilder::begin()
                     .layout(&pipeline_layout)
-                    .compute(ComputeStageBuilder::begin(&shader, "main").finish())
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
                     .finish(),
             )
             .await;
@@ -62,7 +71,9 @@ impl MakeProposals {
         MakeProposals {
             device,
             bind_group_layout,
+            bind_groups: Default::default(),
             pipeline,
+            dispatch_config,
         }
     }
 
@@ -77,24 +88,32 @@ impl MakeProposals {
     where
         U: buffer::Indirect,
     {
-        let bind_group = self
-            .device
-            .create_bind_group(&self.bind_group_layout, resources);
+        let key = [
+            resources.node_count.binding_key(),
+            resources.edge_ref_count.binding_key(),
+            resources.has_live_nodes.binding_key(),
+            resources.nodes_match_state.binding_key(),
+            resources.nodes_edge_offset.binding_key(),
+            resources.nodes_edges.binding_key(),
+            resources.nodes_edge_weights.binding_key(),
+            resources.nodes_proposal.binding_key(),
+            resources.heavy_degree_threshold.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
 
         let encoder = encoder
             .begin_compute_pass()
             .set_pipeline(&self.pipeline)
-            .set_bind_groups(&bind_group);
+            .set_bind_groups(&*bind_group);
 
         if dispatch_indirect {
             encoder.dispatch_workgroups_indirect(dispatch).end()
         } else {
             encoder
-                .dispatch_workgroups(DispatchWorkgroups {
-                    count_x: fallback_count.div_ceil(GROUP_SIZE),
-                    count_y: 1,
-                    count_z: 1,
-                })
+                .dispatch_workgroups(self.dispatch_config.workgroups(fallback_count))
                 .end()
         }
     }
@@ -0,0 +1,114 @@
+use empa::access_mode::ReadWrite;
+use empa::buffer;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, DispatchWorkgroups, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+use crate::matching::match_pairs_by_edge_weight::match_state::MatchState;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct MakeProposalsHeavyResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub node_count: Uniform<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub edge_ref_count: Uniform<'a, u32>,
+    #[resource(binding = 2, visibility = "COMPUTE")]
+    pub has_live_nodes: Uniform<'a, u32>,
+    #[resource(binding = 3, visibility = "COMPUTE")]
+    pub nodes_match_state: Storage<'a, [MatchState], ReadWrite>,
+    #[resource(binding = 4, visibility = "COMPUTE")]
+    pub nodes_edge_offset: Storage<'a, [u32]>,
+    #[resource(binding = 5, visibility = "COMPUTE")]
+    pub nodes_edges: Storage<'a, [u32]>,
+    #[resource(binding = 6, visibility = "COMPUTE")]
+    pub nodes_edge_weights: Storage<'a, [u32]>,
+    #[resource(binding = 7, visibility = "COMPUTE")]
+    pub nodes_proposal: Storage<'a, [u32], ReadWrite>,
+    #[resource(binding = 8, visibility = "COMPUTE")]
+    pub heavy_node_count: Storage<'a, u32>,
+    #[resource(binding = 9, visibility = "COMPUTE")]
+    pub heavy_nodes: Storage<'a, [u32]>,
+}
+
+type ResourcesLayout =
+    <MakeProposalsHeavyResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct MakeProposalsHeavy {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+}
+
+impl MakeProposalsHeavy {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        MakeProposalsHeavy {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+        }
+    }
+
+    /// Dispatches 1 workgroup per heavy node, see [GenerateHeavyDispatch].
+    pub fn encode<U>(
+        &self,
+        encoder: CommandEncoder,
+        resources: MakeProposalsHeavyResources,
+        dispatch: buffer::View<DispatchWorkgroups, U>,
+    ) -> CommandEncoder
+    where
+        U: buffer::Indirect,
+    {
+        let key = [
+            resources.node_count.binding_key(),
+            resources.edge_ref_count.binding_key(),
+            resources.has_live_nodes.binding_key(),
+            resources.nodes_match_state.binding_key(),
+            resources.nodes_edge_offset.binding_key(),
+            resources.nodes_edges.binding_key(),
+            resources.nodes_edge_weights.binding_key(),
+            resources.nodes_proposal.binding_key(),
+            resources.heavy_node_count.binding_key(),
+            resources.heavy_nodes.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group)
+            .dispatch_workgroups_indirect(dispatch)
+            .end()
+    }
+}
@@ -0,0 +1,159 @@
+#include <src/dispatch.wgsl>
+#include <src/matching/match_pairs_by_edge_weight/match_state.wgsl>
+
+// Makes proposals for nodes with a degree above the heavy degree threshold. Rather than scanning a node's adjacency
+// list in a single invocation (see `make_proposals`), each heavy node is processed by a whole workgroup: every
+// invocation scans a strided subset of the adjacency list, after which the workgroup reduces the partial results.
+//
+// The reduction resolves ties in the same way as the sequential scan in `make_proposals` (the candidate that occurs
+// last in the adjacency list wins), so that the matching result does not depend on which variant processed a node.
+
+const NO_CANDIDATE = 0xFFFFFFFFu;
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<uniform> has_live_nodes: u32;
+
+@group(0) @binding(3)
+var<storage, read_write> nodes_match_state: array<MatchState>;
+
+@group(0) @binding(4)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> nodes_edges: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> nodes_edge_weights: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read_write> nodes_proposal: array<atomic<u32>>;
+
+@group(0) @binding(8)
+var<storage, read> heavy_node_count: u32;
+
+@group(0) @binding(9)
+var<storage, read> heavy_nodes: array<u32>;
+
+var<workgroup> node_state: u32;
+var<workgroup> has_live_neighbour: atomic<u32>;
+var<workgroup> candidate_weights: array<u32, group_size>;
+var<workgroup> candidate_positions: array<u32, group_size>;
+
+// Returns `true` if candidate `b` should be preferred over candidate `a`.
+fn prefer_candidate(a_weight: u32, a_position: u32, b_weight: u32, b_position: u32) -> bool {
+    if b_position == NO_CANDIDATE {
+        return false;
+    }
+
+    if a_position == NO_CANDIDATE {
+        return true;
+    }
+
+    return b_weight > a_weight || (b_weight == a_weight && b_position > a_position);
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    if has_live_nodes == 0 {
+        return;
+    }
+
+    for (var heavy_index = workgroup_id.x; heavy_index < heavy_node_count; heavy_index += num_workgroups.x) {
+        let index = heavy_nodes[heavy_index];
+
+        if local_index == 0 {
+            node_state = nodes_match_state[index].packed_data;
+
+            atomicStore(&has_live_neighbour, 0u);
+        }
+
+        let state = MatchState(workgroupUniformLoad(&node_state));
+        let status = match_state_status(state);
+
+        if status == MATCH_STATUS_BLUE {
+            let edges_start = nodes_edge_offset[index];
+
+            var edges_end = edge_ref_count;
+
+            if index < node_count - 1 {
+                edges_end = nodes_edge_offset[index + 1];
+            }
+
+            var best_candidate_weight = 0u;
+            var best_candidate_position = NO_CANDIDATE;
+
+            for (var i = edges_start + local_index; i < edges_end; i += group_size) {
+                let other_index = nodes_edges[i];
+                let edge_weight = nodes_edge_weights[i];
+                let other_state = nodes_match_state[other_index];
+                let other_status = match_state_status(other_state);
+
+                if match_state_is_live(other_state) {
+                    atomicStore(&has_live_neighbour, 1u);
+                }
+
+                if other_status == MATCH_STATUS_RED {
+                    // Positions only increase, so this always prefers the current edge over the previous candidate
+                    // if the weights are equal.
+                    if prefer_candidate(best_candidate_weight, best_candidate_position, edge_weight, i) {
+                        best_candidate_weight = edge_weight;
+                        best_candidate_position = i;
+                    }
+                }
+            }
+
+            candidate_weights[local_index] = best_candidate_weight;
+            candidate_positions[local_index] = best_candidate_position;
+
+            workgroupBarrier();
+
+            for (var stride = group_size / 2; stride > 0; stride >>= 1u) {
+                if local_index < stride {
+                    let other = local_index + stride;
+
+                    if prefer_candidate(
+                        candidate_weights[local_index],
+                        candidate_positions[local_index],
+                        candidate_weights[other],
+                        candidate_positions[other]
+                    ) {
+                        candidate_weights[local_index] = candidate_weights[other];
+                        candidate_positions[local_index] = candidate_positions[other];
+                    }
+                }
+
+                workgroupBarrier();
+            }
+
+            if local_index == 0 {
+                let best_candidate_position = candidate_positions[0];
+
+                if best_candidate_position != NO_CANDIDATE {
+                    let best_candidate_index = nodes_edges[best_candidate_position];
+
+                    atomicMax(&nodes_proposal[best_candidate_index], candidate_weights[0]);
+
+                    // See `make_proposals` for why this reuses the `nodes_proposal` buffer.
+                    atomicStore(&nodes_proposal[index], best_candidate_index);
+                }
+
+                if atomicLoad(&has_live_neighbour) == 0 {
+                    nodes_match_state[index] = match_state_new_dead();
+                }
+            }
+        }
+
+        // Make sure all invocations are done with the workgroup memory before processing the next heavy node.
+        workgroupBarrier();
+    }
+}
@@ -1,5 +1,6 @@
 use std::future::join;
 use std::mem;
+use std::rc::Rc;
 
 use empa::buffer;
 use empa::buffer::{Buffer, Uniform};
@@ -7,37 +8,57 @@ use empa::command::{CommandEncoder, DispatchWorkgroups};
 use empa::device::Device;
 use empa::type_flag::{O, X};
 
-use crate::counts_fallback::FallbackCounts;
+use crate::context::Context;
+use crate::counts_fallback::FallbackCountsCache;
+use crate::generate_dispatch::{GenerateDispatch, GenerateDispatchResources};
 use crate::matching::match_pairs_by_edge_weight::assign_node_colors::{
     AssignNodeColors, AssignNodeColorsResources,
 };
+use crate::matching::match_pairs_by_edge_weight::collect_heavy_nodes::{
+    CollectHeavyNodes, CollectHeavyNodesResources,
+};
 use crate::matching::match_pairs_by_edge_weight::finalize_matching::{
     FinalizeMatching, FinalizeMatchingResources,
 };
 use crate::matching::match_pairs_by_edge_weight::find_matches::{
     FindMatches, FindMatchesResources,
 };
-use crate::matching::match_pairs_by_edge_weight::generate_dispatch::{
-    GenerateDispatch, GenerateDispatchResources,
+use crate::matching::match_pairs_by_edge_weight::find_matches_heavy::{
+    FindMatchesHeavy, FindMatchesHeavyResources,
+};
+use crate::matching::match_pairs_by_edge_weight::generate_heavy_dispatch::{
+    GenerateHeavyDispatch, GenerateHeavyDispatchResources,
 };
 use crate::matching::match_pairs_by_edge_weight::make_proposals::{
     MakeProposals, MakeProposalsResources,
 };
+use crate::matching::match_pairs_by_edge_weight::make_proposals_heavy::{
+    MakeProposalsHeavy, MakeProposalsHeavyResources,
+};
 use crate::matching::match_pairs_by_edge_weight::match_state::MatchState;
+use crate::profiler::{profile_stage, profile_start, Profiler};
 
 mod assign_node_colors;
+mod collect_heavy_nodes;
 mod finalize_matching;
 mod find_matches;
-mod generate_dispatch;
+mod find_matches_heavy;
+mod generate_heavy_dispatch;
 mod make_proposals;
+mod make_proposals_heavy;
 mod match_state;
 
-pub const GROUP_SIZE: u32 = 256;
-
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct MatchPairsByEdgeWeightConfig {
     pub rounds: usize,
     pub prng_seed: u32,
+    /// Nodes with more edge references than this threshold are processed cooperatively by a
+    /// whole workgroup, rather than by a single invocation.
+    ///
+    /// This avoids a single high-degree node (e.g. a hub in a graph with a skewed degree
+    /// distribution) serializing the workgroup it is processed in. The threshold does not affect
+    /// the matching result.
+    pub heavy_degree_threshold: u32,
 }
 
 impl Default for MatchPairsByEdgeWeightConfig {
@@ -45,6 +66,7 @@ impl Default for MatchPairsByEdgeWeightConfig {
         MatchPairsByEdgeWeightConfig {
             rounds: 8,
             prng_seed: 1,
+            heavy_degree_threshold: 1024,
         }
     }
 }
@@ -63,33 +85,60 @@ pub struct MatchPairsByEdgeWeightInput<'a, U0, U1, U2> {
 
 pub struct MatchPairsByEdgeWeight {
     device: Device,
-    generate_dispatch: GenerateDispatch,
-    assign_node_colors: AssignNodeColors,
-    make_proposals: MakeProposals,
-    find_matches: FindMatches,
-    finalize_matching: FinalizeMatching,
+    counts_cache: FallbackCountsCache,
+    generate_dispatch: Rc<GenerateDispatch>,
+    assign_node_colors: Rc<AssignNodeColors>,
+    make_proposals: Rc<MakeProposals>,
+    find_matches: Rc<FindMatches>,
+    finalize_matching: Rc<FinalizeMatching>,
+    collect_heavy_nodes: Rc<CollectHeavyNodes>,
+    generate_heavy_dispatch: Rc<GenerateHeavyDispatch>,
+    make_proposals_heavy: Rc<MakeProposalsHeavy>,
+    find_matches_heavy: Rc<FindMatchesHeavy>,
     config: MatchPairsByEdgeWeightConfig,
     prng_seeds: Vec<Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>>,
     has_live_nodes: Buffer<u32, buffer::Usages<O, O, X, X, O, O, X, O, O, O>>,
-    group_size: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
     dispatch: Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
     proposals: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    heavy_degree_threshold: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+    heavy_node_count: Buffer<u32, buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    heavy_nodes: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    heavy_dispatch: Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
 }
 
 impl MatchPairsByEdgeWeight {
     pub async fn init(device: Device, config: MatchPairsByEdgeWeightConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](MatchPairsByEdgeWeight::init), but obtains the sub-pipelines from the given
+    /// `context`, so that they may be shared with other components.
+    pub async fn init_with_context(
+        context: &Context,
+        config: MatchPairsByEdgeWeightConfig,
+    ) -> Self {
+        let device = context.device().clone();
+
         let (
             generate_dispatch,
             assign_node_colors,
             make_proposals,
             find_matches,
             finalize_matching,
+            collect_heavy_nodes,
+            generate_heavy_dispatch,
+            make_proposals_heavy,
+            find_matches_heavy,
         ) = join!(
-            GenerateDispatch::init(device.clone()),
-            AssignNodeColors::init(device.clone()),
-            MakeProposals::init(device.clone()),
-            FindMatches::init(device.clone()),
-            FinalizeMatching::init(device.clone()),
+            context.get_or_init_kernel(GenerateDispatch::init),
+            context.get_or_init_kernel(AssignNodeColors::init),
+            context.get_or_init_kernel(MakeProposals::init),
+            context.get_or_init_kernel(FindMatches::init),
+            context.get_or_init_kernel(FinalizeMatching::init),
+            context.get_or_init_kernel(CollectHeavyNodes::init),
+            context.get_or_init_kernel(GenerateHeavyDispatch::init),
+            context.get_or_init_kernel(MakeProposalsHeavy::init),
+            context.get_or_init_kernel(FindMatchesHeavy::init),
         )
         .await;
 
@@ -107,7 +156,6 @@ impl MatchPairsByEdgeWeight {
                 .and_uniform_binding()
                 .and_copy_dst(),
         );
-        let group_size = device.create_buffer(GROUP_SIZE, buffer::Usages::uniform_binding());
         let dispatch = device.create_buffer(
             DispatchWorkgroups {
                 count_x: 1,
@@ -118,28 +166,88 @@ impl MatchPairsByEdgeWeight {
         );
         let proposals =
             device.create_slice_buffer_zeroed(1, buffer::Usages::storage_binding().and_copy_dst());
+        let heavy_degree_threshold = device.create_buffer(
+            config.heavy_degree_threshold,
+            buffer::Usages::uniform_binding(),
+        );
+        let heavy_node_count =
+            device.create_buffer(0, buffer::Usages::storage_binding().and_copy_dst());
+        let heavy_nodes =
+            device.create_slice_buffer_zeroed(1, buffer::Usages::storage_binding().and_copy_dst());
+        let heavy_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 0,
+                count_y: 1,
+                count_z: 1,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
+
+        let counts_cache = FallbackCountsCache::new(device.clone());
 
         MatchPairsByEdgeWeight {
             device,
+            counts_cache,
             generate_dispatch,
             assign_node_colors,
             make_proposals,
             find_matches,
             finalize_matching,
+            collect_heavy_nodes,
+            generate_heavy_dispatch,
+            make_proposals_heavy,
+            find_matches_heavy,
             config,
             prng_seeds,
             has_live_nodes,
-            group_size,
             dispatch,
             proposals,
+            heavy_degree_threshold,
+            heavy_node_count,
+            heavy_nodes,
+            heavy_dispatch,
         }
     }
 
     pub fn encode<U0, U1, U2, U3>(
+        &mut self,
+        encoder: CommandEncoder,
+        input: MatchPairsByEdgeWeightInput<U0, U1, U2>,
+        nodes_match: buffer::View<[u32], U3>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+    {
+        self.encode_internal(encoder, input, nodes_match, None)
+    }
+
+    /// Same as [encode](MatchPairsByEdgeWeight::encode), but records a timestamp on the
+    /// `profiler` after each stage of the matching algorithm.
+    pub fn encode_profiled<U0, U1, U2, U3>(
+        &mut self,
+        encoder: CommandEncoder,
+        input: MatchPairsByEdgeWeightInput<U0, U1, U2>,
+        nodes_match: buffer::View<[u32], U3>,
+        profiler: &mut Profiler,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+    {
+        self.encode_internal(encoder, input, nodes_match, Some(profiler))
+    }
+
+    fn encode_internal<U0, U1, U2, U3>(
         &mut self,
         mut encoder: CommandEncoder,
         input: MatchPairsByEdgeWeightInput<U0, U1, U2>,
         nodes_match: buffer::View<[u32], U3>,
+        mut profiler: Option<&mut Profiler>,
     ) -> CommandEncoder
     where
         U0: buffer::StorageBinding,
@@ -163,28 +271,68 @@ impl MatchPairsByEdgeWeight {
                 .create_slice_buffer_zeroed(nodes_edge_offset.len(), self.proposals.usage());
         }
 
+        // Every heavy node has more than `heavy_degree_threshold` edge references, which bounds
+        // the number of heavy nodes.
+        let heavy_node_capacity = (nodes_edges.len()
+            / (self.config.heavy_degree_threshold as usize + 1))
+            .min(nodes_edge_offset.len())
+            .max(1);
+
+        if self.heavy_nodes.len() < heavy_node_capacity {
+            self.heavy_nodes = self
+                .device
+                .create_slice_buffer_zeroed(heavy_node_capacity, self.heavy_nodes.usage());
+        }
+
         let dispatch_indirect = count.is_some();
 
         let fallback_node_count = nodes_edge_offset.len() as u32;
         let fallback_edge_ref_count = nodes_edges.len() as u32;
-        let counts_fallback = FallbackCounts::new(
+        let counts_fallback = self.counts_cache.get(
             count.map(|c| (c.node_count, c.edge_ref_count)),
-            &self.device,
             (fallback_node_count, fallback_edge_ref_count),
         );
 
+        encoder = profile_start(&mut profiler, encoder);
+
         if dispatch_indirect {
             encoder = self.generate_dispatch.encode(
                 encoder,
                 GenerateDispatchResources {
-                    group_size: self.group_size.uniform(),
                     count: counts_fallback.node_count(),
                     dispatch: self.dispatch.storage(),
                 },
             );
+            encoder = profile_stage(&mut profiler, encoder, "generate dispatch");
         }
 
         encoder = encoder.clear_buffer_slice(self.proposals.view());
+        encoder = profile_stage(&mut profiler, encoder, "clear proposals");
+
+        // The set of heavy nodes does not change between rounds, so we only collect it once.
+        encoder = encoder.clear_buffer(self.heavy_node_count.view());
+        encoder = self.collect_heavy_nodes.encode(
+            encoder,
+            CollectHeavyNodesResources {
+                node_count: counts_fallback.node_count(),
+                edge_ref_count: counts_fallback.edge_ref_count(),
+                heavy_degree_threshold: self.heavy_degree_threshold.uniform(),
+                nodes_edge_offset: nodes_edge_offset.storage(),
+                heavy_node_count: self.heavy_node_count.storage(),
+                heavy_nodes: self.heavy_nodes.storage(),
+            },
+            dispatch_indirect,
+            self.dispatch.view(),
+            fallback_node_count,
+        );
+        encoder = self.generate_heavy_dispatch.encode(
+            encoder,
+            GenerateHeavyDispatchResources {
+                heavy_node_count: self.heavy_node_count.storage(),
+                dispatch: self.heavy_dispatch.storage(),
+            },
+        );
+        encoder = profile_stage(&mut profiler, encoder, "collect heavy nodes");
 
         for round in 0..self.config.rounds {
             encoder = encoder.clear_buffer(self.has_live_nodes.view());
@@ -200,6 +348,7 @@ impl MatchPairsByEdgeWeight {
                 self.dispatch.view(),
                 fallback_node_count,
             );
+            encoder = profile_stage(&mut profiler, encoder, "assign node colors");
             encoder = self.make_proposals.encode(
                 encoder,
                 MakeProposalsResources {
@@ -211,11 +360,29 @@ impl MatchPairsByEdgeWeight {
                     nodes_edges: nodes_edges.storage(),
                     nodes_edge_weights: nodes_edge_weights.storage(),
                     nodes_proposal: self.proposals.storage(),
+                    heavy_degree_threshold: self.heavy_degree_threshold.uniform(),
                 },
                 dispatch_indirect,
                 self.dispatch.view(),
                 fallback_node_count,
             );
+            encoder = self.make_proposals_heavy.encode(
+                encoder,
+                MakeProposalsHeavyResources {
+                    node_count: counts_fallback.node_count(),
+                    edge_ref_count: counts_fallback.edge_ref_count(),
+                    has_live_nodes: self.has_live_nodes.uniform(),
+                    nodes_match_state: nodes_match_state.storage(),
+                    nodes_edge_offset: nodes_edge_offset.storage(),
+                    nodes_edges: nodes_edges.storage(),
+                    nodes_edge_weights: nodes_edge_weights.storage(),
+                    nodes_proposal: self.proposals.storage(),
+                    heavy_node_count: self.heavy_node_count.storage(),
+                    heavy_nodes: self.heavy_nodes.storage(),
+                },
+                self.heavy_dispatch.view(),
+            );
+            encoder = profile_stage(&mut profiler, encoder, "make proposals");
             encoder = self.find_matches.encode(
                 encoder,
                 FindMatchesResources {
@@ -227,11 +394,29 @@ impl MatchPairsByEdgeWeight {
                     nodes_edges: nodes_edges.storage(),
                     nodes_edge_weights: nodes_edge_weights.storage(),
                     nodes_proposal: self.proposals.storage(),
+                    heavy_degree_threshold: self.heavy_degree_threshold.uniform(),
                 },
                 dispatch_indirect,
                 self.dispatch.view(),
                 fallback_node_count,
             );
+            encoder = self.find_matches_heavy.encode(
+                encoder,
+                FindMatchesHeavyResources {
+                    node_count: counts_fallback.node_count(),
+                    edge_ref_count: counts_fallback.edge_ref_count(),
+                    has_live_nodes: self.has_live_nodes.uniform(),
+                    nodes_match_state: nodes_match_state.storage(),
+                    nodes_edge_offset: nodes_edge_offset.storage(),
+                    nodes_edges: nodes_edges.storage(),
+                    nodes_edge_weights: nodes_edge_weights.storage(),
+                    nodes_proposal: self.proposals.storage(),
+                    heavy_node_count: self.heavy_node_count.storage(),
+                    heavy_nodes: self.heavy_nodes.storage(),
+                },
+                self.heavy_dispatch.view(),
+            );
+            encoder = profile_stage(&mut profiler, encoder, "find matches");
         }
 
         let nodes_match: buffer::View<[u32], U3> = unsafe { mem::transmute(nodes_match_state) };
@@ -246,6 +431,7 @@ impl MatchPairsByEdgeWeight {
             self.dispatch.view(),
             fallback_node_count,
         );
+        encoder = profile_stage(&mut profiler, encoder, "finalize matching");
 
         encoder
     }
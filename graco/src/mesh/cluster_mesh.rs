@@ -0,0 +1,513 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::Buffer;
+use empa::device::Device;
+use empa::type_flag::{O, X};
+use empa_tk::gather_by::{GatherBy, GatherByInput};
+use empa_tk::prefix_sum::{PrefixSum, PrefixSumInput};
+use empa_tk::radix_sort::{RadixSortBy, RadixSortByInput};
+
+use crate::context::Context;
+use crate::hierarchy::{BuildHierarchy, HierarchyConfig, HierarchyInput};
+use crate::matching::MatchPairsByEdgeWeightConfig;
+use crate::mesh::compose_face_clusters::{ComposeFaceClusters, ComposeFaceClustersResources};
+use crate::mesh::emit_dual_edges::{EmitDualEdges, EmitDualEdgesResources};
+use crate::mesh::generate_face_edges::{GenerateFaceEdges, GenerateFaceEdgesResources};
+use crate::mesh::mark_shared_edges::{MarkSharedEdges, MarkSharedEdgesResources};
+use crate::{BuildCsr, BuildCsrConfig, BuildCsrCounts, BuildCsrInput, BuildCsrOutput};
+
+/// Determines the weights of the edges of a mesh's dual graph, which connect faces that share a
+/// mesh edge.
+///
+/// The faces connected by heavy dual edges are merged first. As the matching never matches along
+/// edges with a weight of `0`, dual edges never get a weight smaller than `1`. Coarsening sums the
+/// weights of merged dual edges; these sums saturate at `u32::MAX`, so on very large meshes with
+/// a large `scale`, heavily merged clusters may become indistinguishable by weight.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DualEdgeWeighting {
+    /// Dual edges get a weight of `scale * (1 + cos(angle)) / 2`, where `angle` is the angle
+    /// between the normals of the two faces; coplanar faces get the heaviest weight.
+    DihedralAngle { scale: f32 },
+    /// Dual edges get a weight of `scale / area`, where `area` is the combined area of the two
+    /// faces; small faces are merged first.
+    Area { scale: f32 },
+}
+
+impl DualEdgeWeighting {
+    fn mode(&self) -> u32 {
+        match self {
+            DualEdgeWeighting::DihedralAngle { .. } => 0,
+            DualEdgeWeighting::Area { .. } => 1,
+        }
+    }
+
+    fn scale(&self) -> f32 {
+        match *self {
+            DualEdgeWeighting::DihedralAngle { scale } => scale,
+            DualEdgeWeighting::Area { scale } => scale,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ClusterMeshConfig {
+    /// The weighting of the edges of the dual graph.
+    pub weighting: DualEdgeWeighting,
+    /// Coarsening stops once a level has no more than this many clusters.
+    pub coarsest_cluster_count: u32,
+    /// The maximum number of levels, including the base level in which each face is its own
+    /// cluster.
+    pub max_levels: usize,
+    /// The configuration of the matching that is used to coarsen the dual graph.
+    pub matching: MatchPairsByEdgeWeightConfig,
+}
+
+impl Default for ClusterMeshConfig {
+    fn default() -> Self {
+        ClusterMeshConfig {
+            weighting: DualEdgeWeighting::DihedralAngle { scale: 1024.0 },
+            coarsest_cluster_count: 64,
+            max_levels: 32,
+            matching: Default::default(),
+        }
+    }
+}
+
+pub struct ClusterMeshInput<'a, U0, U1> {
+    /// The position of each vertex, stored as 3 consecutive values (`x`, `y` and `z`).
+    pub vertex_positions: buffer::View<'a, [f32], U0>,
+    /// The vertex indices of each triangle, stored as 3 consecutive values.
+    pub triangle_indices: buffer::View<'a, [u32], U1>,
+}
+
+/// A level of face clusters produced by [ClusterMesh::cluster].
+pub struct FaceClusterLevel {
+    /// The number of clusters on this level.
+    pub cluster_count: u32,
+    /// The cluster of each face of the input mesh. The cluster ids are contiguous, starting at
+    /// `0`.
+    pub faces_cluster: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, X, O, O>>,
+    /// The number of faces in each cluster. Only the first `cluster_count` values are valid; the
+    /// buffer is sized for the next finer level.
+    pub clusters_face_count: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+}
+
+/// Clusters the faces of a triangle mesh by coarsening the mesh's dual graph, for example to
+/// drive clustered level-of-detail generation.
+///
+/// The dual graph has a node for each face and connects faces that share a mesh edge. It is built
+/// on the GPU by generating the 3 edges of each face, sorting them by vertex pair (with the same
+/// "compound sort" [BuildCsr] uses), and connecting the faces of consecutive equal edges. The dual
+/// graph is then coarsened into a hierarchy of levels (see [BuildHierarchy]), where each coarse
+/// node represents a cluster of faces.
+///
+/// Faces may only be merged if they share an edge, so the clusters on each level are connected.
+/// For a non-manifold edge, the faces that share it are connected in a chain rather than to each
+/// other pairwise.
+pub struct ClusterMesh {
+    device: Device,
+    config: ClusterMeshConfig,
+    build_csr: BuildCsr,
+    build_hierarchy: BuildHierarchy,
+    generate_face_edges: Rc<GenerateFaceEdges>,
+    mark_shared_edges: Rc<MarkSharedEdges>,
+    emit_dual_edges: Rc<EmitDualEdges>,
+    compose_face_clusters: Rc<ComposeFaceClusters>,
+    sort_by: Rc<RefCell<RadixSortBy<u32, u32>>>,
+    gather_by: Rc<RefCell<GatherBy<u32, u32>>>,
+    prefix_sum_inclusive: Rc<RefCell<PrefixSum<u32>>>,
+    weighting_mode: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+    weighting_scale: Buffer<f32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+}
+
+impl ClusterMesh {
+    pub async fn init(device: Device, config: ClusterMeshConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](ClusterMesh::init), but obtains the sub-pipelines from the given `context`,
+    /// so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context, config: ClusterMeshConfig) -> Self {
+        let device = context.device().clone();
+
+        // The dual edges are emitted once per pair of adjacent faces, so `BuildCsr` adds the
+        // reverse edges. Faces that share more than one edge (which only happens for degenerate
+        // meshes) are connected by a single dual edge with the summed weight.
+        let (
+            build_csr,
+            build_hierarchy,
+            generate_face_edges,
+            mark_shared_edges,
+            emit_dual_edges,
+            compose_face_clusters,
+            sort_by,
+            gather_by,
+            prefix_sum_inclusive,
+        ) = join!(
+            BuildCsr::init_with_context(
+                context,
+                BuildCsrConfig {
+                    symmetrize: true,
+                    merge_duplicates: true,
+                }
+            ),
+            BuildHierarchy::init_with_context(context, config.matching),
+            context.get_or_init_kernel(GenerateFaceEdges::init),
+            context.get_or_init_kernel(MarkSharedEdges::init),
+            context.get_or_init_kernel(EmitDualEdges::init),
+            context.get_or_init_kernel(ComposeFaceClusters::init),
+            context.get_or_init_mut("init_u32", RadixSortBy::init_u32),
+            context.get_or_init_mut("init_u32", GatherBy::init_u32),
+            context.get_or_init_mut("init_inclusive_u32", PrefixSum::init_inclusive_u32),
+        )
+        .await;
+
+        let weighting_mode =
+            device.create_buffer(config.weighting.mode(), buffer::Usages::uniform_binding());
+        let weighting_scale =
+            device.create_buffer(config.weighting.scale(), buffer::Usages::uniform_binding());
+
+        ClusterMesh {
+            device,
+            config,
+            build_csr,
+            build_hierarchy,
+            generate_face_edges,
+            mark_shared_edges,
+            emit_dual_edges,
+            compose_face_clusters,
+            sort_by,
+            gather_by,
+            prefix_sum_inclusive,
+            weighting_mode,
+            weighting_scale,
+        }
+    }
+
+    /// Clusters the faces of the mesh described by the `input`.
+    ///
+    /// Returns the levels of face clusters, starting with the base level in which each face is its
+    /// own cluster, followed by progressively coarser levels. If no faces share an edge, then the
+    /// dual graph has no edges and only the base level is returned; if the mesh has no faces, then
+    /// no levels are returned.
+    ///
+    /// Submits its own command buffers, as the size of the dual graph and the number of levels in
+    /// the hierarchy are only known after reading them back.
+    pub async fn cluster<U0, U1>(
+        &mut self,
+        input: ClusterMeshInput<'_, U0, U1>,
+    ) -> Result<Vec<FaceClusterLevel>, Box<dyn Error>>
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+    {
+        let ClusterMeshInput {
+            vertex_positions,
+            triangle_indices,
+        } = input;
+
+        assert_eq!(
+            triangle_indices.len() % 3,
+            0,
+            "`triangle_indices` must have a length that is a multiple of `3`"
+        );
+        assert_eq!(
+            vertex_positions.len() % 3,
+            0,
+            "`vertex_positions` must have a length that is a multiple of `3`"
+        );
+
+        let face_count = triangle_indices.len() / 3;
+        let mesh_edge_count = face_count * 3;
+
+        if face_count == 0 {
+            return Ok(Vec::new());
+        }
+
+        // Each mesh edge emits at most one dual edge, which `BuildCsr` then symmetrizes in place,
+        // so the edge buffers need the capacity for twice the mesh edge count.
+        let capacity = mesh_edge_count * 2;
+
+        let slice_usages = buffer::Usages::storage_binding()
+            .and_copy_dst()
+            .and_copy_src();
+
+        let face_count_buffer = self
+            .device
+            .create_buffer(face_count as u32, buffer::Usages::uniform_binding());
+        let mesh_edge_count_buffer = self
+            .device
+            .create_buffer(mesh_edge_count as u32, buffer::Usages::uniform_binding());
+        let dual_edge_count = self.device.create_buffer(
+            0u32,
+            buffer::Usages::storage_binding().and_uniform_binding(),
+        );
+        let edge_ref_count_buffer = self
+            .device
+            .create_buffer(0u32, buffer::Usages::storage_binding().and_copy_src());
+
+        let buffer_0 = self
+            .device
+            .create_slice_buffer_zeroed(capacity, slice_usages);
+        let buffer_1 = self
+            .device
+            .create_slice_buffer_zeroed(capacity, slice_usages);
+        let buffer_2 = self
+            .device
+            .create_slice_buffer_zeroed(capacity, slice_usages);
+        let buffer_3 = self
+            .device
+            .create_slice_buffer_zeroed(capacity, slice_usages);
+        let buffer_4 = self
+            .device
+            .create_slice_buffer_zeroed(capacity, slice_usages);
+        let buffer_5 = self
+            .device
+            .create_slice_buffer_zeroed(capacity, slice_usages);
+
+        let nodes_edge_offset = self
+            .device
+            .create_slice_buffer_zeroed(face_count, slice_usages);
+        let nodes_edges = self
+            .device
+            .create_slice_buffer_zeroed(capacity, slice_usages);
+        let nodes_edge_weights = self
+            .device
+            .create_slice_buffer_zeroed(capacity, slice_usages);
+
+        let mut encoder = self.device.create_command_encoder();
+
+        let edges_low_vertex = &buffer_0;
+        let edges_high_vertex = &buffer_1;
+        let index_list = &buffer_2;
+        let sort_keys = &buffer_3;
+
+        encoder = self.generate_face_edges.encode(
+            encoder,
+            GenerateFaceEdgesResources {
+                face_count: face_count_buffer.uniform(),
+                triangle_indices: triangle_indices.storage(),
+                edges_low_vertex: edges_low_vertex.storage(),
+                edges_high_vertex: edges_high_vertex.storage(),
+                edges_index: index_list.storage(),
+            },
+            face_count as u32,
+        );
+
+        // Sort the index list by high vertex and then (stably) by low vertex, so that equal vertex
+        // pairs, which identify the mesh edges shared by multiple faces, are stored consecutively.
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            edges_high_vertex.view().get(..mesh_edge_count).unwrap(),
+            sort_keys.view().get(..mesh_edge_count).unwrap(),
+        );
+        encoder = self.sort_by.borrow_mut().encode(
+            encoder,
+            RadixSortByInput {
+                keys: sort_keys.view(),
+                values: index_list.view(),
+                temporary_key_storage: buffer_4.view(),
+                temporary_value_storage: buffer_5.view(),
+                count: Some(mesh_edge_count_buffer.uniform()),
+            },
+        );
+        encoder = self.gather_by.borrow_mut().encode(
+            encoder,
+            GatherByInput {
+                gather_by: index_list.view(),
+                data: edges_low_vertex.view(),
+                count: Some(mesh_edge_count_buffer.uniform()),
+            },
+            sort_keys.view(),
+        );
+        encoder = self.sort_by.borrow_mut().encode(
+            encoder,
+            RadixSortByInput {
+                keys: sort_keys.view(),
+                values: index_list.view(),
+                temporary_key_storage: buffer_4.view(),
+                temporary_value_storage: buffer_5.view(),
+                count: Some(mesh_edge_count_buffer.uniform()),
+            },
+        );
+
+        // The sort keys buffer now holds the sorted low vertices; apply the sort to the high
+        // vertices as well.
+        let sorted_low_vertex = sort_keys;
+        let sorted_high_vertex = &buffer_4;
+
+        encoder = self.gather_by.borrow_mut().encode(
+            encoder,
+            GatherByInput {
+                gather_by: index_list.view(),
+                data: edges_high_vertex.view(),
+                count: Some(mesh_edge_count_buffer.uniform()),
+            },
+            sorted_high_vertex.view(),
+        );
+
+        // After an inclusive prefix-sum over the shared flags, subtracting `1` from the value for a
+        // shared mesh edge gives the position of its dual edge in the dual edge list.
+        let shared_prefix_sum = &buffer_5;
+
+        encoder = self.mark_shared_edges.encode(
+            encoder,
+            MarkSharedEdgesResources {
+                edge_count: mesh_edge_count_buffer.uniform(),
+                sorted_low_vertex: sorted_low_vertex.storage(),
+                sorted_high_vertex: sorted_high_vertex.storage(),
+                sorted_index: index_list.storage(),
+                edges_shared: shared_prefix_sum.storage(),
+            },
+            mesh_edge_count as u32,
+        );
+        encoder = self.prefix_sum_inclusive.borrow_mut().encode(
+            encoder,
+            PrefixSumInput {
+                data: shared_prefix_sum.view().get(..mesh_edge_count).unwrap(),
+                count: Some(mesh_edge_count_buffer.uniform()),
+            },
+        );
+
+        // The vertex buffers are no longer needed; we reuse them (and the sort keys buffer) to
+        // store the dual edge list.
+        let dual_edge_sources = edges_low_vertex;
+        let dual_edge_targets = edges_high_vertex;
+        let dual_edge_weights = sort_keys;
+
+        encoder = self.emit_dual_edges.encode(
+            encoder,
+            EmitDualEdgesResources {
+                edge_count: mesh_edge_count_buffer.uniform(),
+                weighting_mode: self.weighting_mode.uniform(),
+                weighting_scale: self.weighting_scale.uniform(),
+                vertex_positions: vertex_positions.storage(),
+                triangle_indices: triangle_indices.storage(),
+                sorted_index: index_list.storage(),
+                shared_prefix_sum: shared_prefix_sum.storage(),
+                dual_edge_sources: dual_edge_sources.storage(),
+                dual_edge_targets: dual_edge_targets.storage(),
+                dual_edge_weights: dual_edge_weights.storage(),
+                dual_edge_count: dual_edge_count.storage(),
+            },
+            mesh_edge_count as u32,
+        );
+        encoder = self.build_csr.encode(
+            encoder,
+            BuildCsrInput {
+                edge_sources: dual_edge_sources.view(),
+                edge_targets: dual_edge_targets.view(),
+                edge_weights: dual_edge_weights.view(),
+                temporary_storage_0: index_list.view(),
+                temporary_storage_1: sorted_high_vertex.view(),
+                counts: Some(BuildCsrCounts {
+                    node_count: face_count_buffer.uniform(),
+                    edge_count: dual_edge_count.uniform(),
+                }),
+            },
+            BuildCsrOutput {
+                nodes_edge_offset: nodes_edge_offset.view(),
+                nodes_edges: nodes_edges.view(),
+                nodes_edge_weights: nodes_edge_weights.view(),
+                edge_ref_count: edge_ref_count_buffer.view(),
+            },
+        );
+
+        let edge_ref_count_readback = self
+            .device
+            .create_buffer(0u32, buffer::Usages::copy_dst().and_map_read());
+
+        encoder = encoder
+            .copy_buffer_to_buffer(edge_ref_count_buffer.view(), edge_ref_count_readback.view());
+
+        self.device.queue().submit(encoder.finish());
+
+        edge_ref_count_readback.map_read().await?;
+
+        let edge_ref_count = *edge_ref_count_readback.mapped() as usize;
+
+        edge_ref_count_readback.unmap();
+
+        // The dual graph can not be coarsened if it has no edges (and its empty edge buffers can
+        // not be bound), so each face remains its own cluster.
+        if edge_ref_count == 0 {
+            return Ok(vec![FaceClusterLevel {
+                cluster_count: face_count as u32,
+                faces_cluster: create_identity_faces_cluster(&self.device, face_count),
+                clusters_face_count: self
+                    .device
+                    .create_buffer(vec![1u32; face_count], slice_usages),
+            }]);
+        }
+
+        let levels = self
+            .build_hierarchy
+            .build(
+                HierarchyInput {
+                    nodes_edge_offset: nodes_edge_offset.view(),
+                    nodes_edges: nodes_edges.view().get(..edge_ref_count).unwrap(),
+                    nodes_edge_weights: nodes_edge_weights.view().get(..edge_ref_count).unwrap(),
+                    nodes_weight: None,
+                },
+                HierarchyConfig {
+                    coarsest_node_count: self.config.coarsest_cluster_count,
+                    max_levels: self.config.max_levels,
+                },
+            )
+            .await?;
+
+        let mut encoder = self.device.create_command_encoder();
+        let mut cluster_levels: Vec<FaceClusterLevel> = Vec::with_capacity(levels.len());
+
+        // On the base level each face is its own cluster; each coarser level composes the clusters
+        // of the next finer level with that level's fine-to-coarse node mapping. The hierarchy
+        // accumulates node weights, so starting from unit weights, the weight of a coarse node is
+        // the number of faces in its cluster.
+        for level in levels {
+            let faces_cluster = if let Some(fine_level) = cluster_levels.last() {
+                let faces_cluster = self.device.create_slice_buffer_zeroed(
+                    face_count,
+                    buffer::Usages::storage_binding().and_copy_src(),
+                );
+
+                encoder = self.compose_face_clusters.encode(
+                    encoder,
+                    ComposeFaceClustersResources {
+                        face_count: face_count_buffer.uniform(),
+                        fine_nodes_mapping: level.fine_nodes_mapping.storage(),
+                        fine_faces_cluster: fine_level.faces_cluster.storage(),
+                        faces_cluster: faces_cluster.storage(),
+                    },
+                    face_count as u32,
+                );
+
+                faces_cluster
+            } else {
+                create_identity_faces_cluster(&self.device, face_count)
+            };
+
+            cluster_levels.push(FaceClusterLevel {
+                cluster_count: level.node_count,
+                faces_cluster,
+                clusters_face_count: level.nodes_weight,
+            });
+        }
+
+        self.device.queue().submit(encoder.finish());
+
+        Ok(cluster_levels)
+    }
+}
+
+/// Creates the face clusters of the base level, on which each face is its own cluster.
+fn create_identity_faces_cluster(
+    device: &Device,
+    face_count: usize,
+) -> Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, X, O, O>> {
+    let identity: Vec<u32> = (0..face_count as u32).collect();
+
+    device.create_buffer(identity, buffer::Usages::storage_binding().and_copy_src())
+}
@@ -0,0 +1,29 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> face_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> fine_nodes_mapping: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> fine_faces_cluster: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> faces_cluster: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= face_count {
+        return;
+    }
+
+    // Each face's cluster on the finer level is a node of the finer level's dual graph; its cluster on this level is
+    // the coarse node that node was merged into.
+    faces_cluster[index] = fine_nodes_mapping[fine_faces_cluster[index]];
+}
@@ -0,0 +1,113 @@
+use empa::access_mode::ReadWrite;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct EmitDualEdgesResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub edge_count: Uniform<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub weighting_mode: Uniform<'a, u32>,
+    #[resource(binding = 2, visibility = "COMPUTE")]
+    pub weighting_scale: Uniform<'a, f32>,
+    #[resource(binding = 3, visibility = "COMPUTE")]
+    pub vertex_positions: Storage<'a, [f32]>,
+    #[resource(binding = 4, visibility = "COMPUTE")]
+    pub triangle_indices: Storage<'a, [u32]>,
+    #[resource(binding = 5, visibility = "COMPUTE")]
+    pub sorted_index: Storage<'a, [u32]>,
+    #[resource(binding = 6, visibility = "COMPUTE")]
+    pub shared_prefix_sum: Storage<'a, [u32]>,
+    #[resource(binding = 7, visibility = "COMPUTE")]
+    pub dual_edge_sources: Storage<'a, [u32], ReadWrite>,
+    #[resource(binding = 8, visibility = "COMPUTE")]
+    pub dual_edge_targets: Storage<'a, [u32], ReadWrite>,
+    #[resource(binding = 9, visibility = "COMPUTE")]
+    pub dual_edge_weights: Storage<'a, [u32], ReadWrite>,
+    #[resource(binding = 10, visibility = "COMPUTE")]
+    pub dual_edge_count: Storage<'a, u32, ReadWrite>,
+}
+
+type ResourcesLayout =
+    <EmitDualEdgesResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct EmitDualEdges {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+    dispatch_config: DispatchConfig,
+}
+
+impl EmitDualEdges {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        EmitDualEdges {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+            dispatch_config,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        encoder: CommandEncoder,
+        resources: EmitDualEdgesResources,
+        edge_count: u32,
+    ) -> CommandEncoder {
+        let key = [
+            resources.edge_count.binding_key(),
+            resources.weighting_mode.binding_key(),
+            resources.weighting_scale.binding_key(),
+            resources.vertex_positions.binding_key(),
+            resources.triangle_indices.binding_key(),
+            resources.sorted_index.binding_key(),
+            resources.shared_prefix_sum.binding_key(),
+            resources.dual_edge_sources.binding_key(),
+            resources.dual_edge_targets.binding_key(),
+            resources.dual_edge_weights.binding_key(),
+            resources.dual_edge_count.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group)
+            .dispatch_workgroups(self.dispatch_config.workgroups(edge_count))
+            .end()
+    }
+}
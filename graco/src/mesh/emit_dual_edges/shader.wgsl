@@ -0,0 +1,131 @@
+#include <src/dispatch.wgsl>
+
+const WEIGHTING_DIHEDRAL_ANGLE = 0u;
+const WEIGHTING_AREA = 1u;
+
+// The largest `f32` value that converts to a `u32` without overflowing.
+const MAX_WEIGHT = 4294967040.0;
+
+@group(0) @binding(0)
+var<uniform> edge_count: u32;
+
+@group(0) @binding(1)
+var<uniform> weighting_mode: u32;
+
+@group(0) @binding(2)
+var<uniform> weighting_scale: f32;
+
+@group(0) @binding(3)
+var<storage, read> vertex_positions: array<f32>;
+
+@group(0) @binding(4)
+var<storage, read> triangle_indices: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> sorted_index: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> shared_prefix_sum: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read_write> dual_edge_sources: array<u32>;
+
+@group(0) @binding(8)
+var<storage, read_write> dual_edge_targets: array<u32>;
+
+@group(0) @binding(9)
+var<storage, read_write> dual_edge_weights: array<u32>;
+
+@group(0) @binding(10)
+var<storage, read_write> dual_edge_count: u32;
+
+fn vertex_position(vertex: u32) -> vec3<f32> {
+    let base = vertex * 3;
+
+    return vec3(vertex_positions[base], vertex_positions[base + 1], vertex_positions[base + 2]);
+}
+
+// Returns the cross product of two of the face's edges, which is normal to the face and has a length of twice the
+// face's area.
+fn face_cross(face: u32) -> vec3<f32> {
+    let base = face * 3;
+    let p0 = vertex_position(triangle_indices[base]);
+    let p1 = vertex_position(triangle_indices[base + 1]);
+    let p2 = vertex_position(triangle_indices[base + 2]);
+
+    return cross(p1 - p0, p2 - p0);
+}
+
+fn safe_normalize(v: vec3<f32>) -> vec3<f32> {
+    let l = length(v);
+
+    if l > 0.0 {
+        return v / l;
+    } else {
+        return vec3(0.0);
+    }
+}
+
+fn dual_edge_weight(face_a: u32, face_b: u32) -> u32 {
+    let cross_a = face_cross(face_a);
+    let cross_b = face_cross(face_b);
+
+    var weight = 0.0;
+
+    if weighting_mode == WEIGHTING_DIHEDRAL_ANGLE {
+        // Maps the angle between the face normals to `[0, 1]`, where `1` means the faces are coplanar. Degenerate
+        // faces have no normal and are treated as perpendicular to their neighbors.
+        let cos_angle = dot(safe_normalize(cross_a), safe_normalize(cross_b));
+
+        weight = weighting_scale * (1.0 + cos_angle) * 0.5;
+    } else {
+        // Favors merging small faces, which tends to produce clusters of more uniform area.
+        let area = (length(cross_a) + length(cross_b)) * 0.5;
+
+        if area > 0.0 {
+            weight = weighting_scale / area;
+        } else {
+            weight = MAX_WEIGHT;
+        }
+    }
+
+    // The matching never collapses edges with a weight of `0`, so every dual edge gets a weight of at least `1`.
+    return u32(clamp(weight, 1.0, MAX_WEIGHT));
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= edge_count {
+        return;
+    }
+
+    let count = shared_prefix_sum[index];
+
+    if index == edge_count - 1 {
+        dual_edge_count = count;
+    }
+
+    var previous_count = 0u;
+
+    if index > 0 {
+        previous_count = shared_prefix_sum[index - 1];
+    }
+
+    // Only mesh edges that are shared with the next mesh edge in sorted order emit a dual edge.
+    if count == previous_count {
+        return;
+    }
+
+    let face_a = sorted_index[index] / 3;
+    let face_b = sorted_index[index + 1] / 3;
+    let dual_edge_index = count - 1;
+
+    dual_edge_sources[dual_edge_index] = face_a;
+    dual_edge_targets[dual_edge_index] = face_b;
+    dual_edge_weights[dual_edge_index] = dual_edge_weight(face_a, face_b);
+}
@@ -0,0 +1,95 @@
+use empa::access_mode::ReadWrite;
+use empa::buffer::{Storage, Uniform};
+use empa::command::{CommandEncoder, ResourceBindingCommandEncoder};
+use empa::compute_pipeline::{
+    ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
+};
+use empa::device::Device;
+use empa::resource_binding::BindGroupLayout;
+use empa::shader_module::{shader_source, ShaderSource};
+
+use crate::counts_fallback::{BindGroupCache, Binding};
+use crate::dispatch::DispatchConfig;
+
+const SHADER: ShaderSource = shader_source!("shader.wgsl");
+
+#[derive(empa::resource_binding::Resources)]
+pub struct GenerateFaceEdgesResources<'a> {
+    #[resource(binding = 0, visibility = "COMPUTE")]
+    pub face_count: Uniform<'a, u32>,
+    #[resource(binding = 1, visibility = "COMPUTE")]
+    pub triangle_indices: Storage<'a, [u32]>,
+    #[resource(binding = 2, visibility = "COMPUTE")]
+    pub edges_low_vertex: Storage<'a, [u32], ReadWrite>,
+    #[resource(binding = 3, visibility = "COMPUTE")]
+    pub edges_high_vertex: Storage<'a, [u32], ReadWrite>,
+    #[resource(binding = 4, visibility = "COMPUTE")]
+    pub edges_index: Storage<'a, [u32], ReadWrite>,
+}
+
+type ResourcesLayout =
+    <GenerateFaceEdgesResources<'static> as empa::resource_binding::Resources>::Layout;
+
+pub struct GenerateFaceEdges {
+    device: Device,
+    bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
+    pipeline: ComputePipeline<(ResourcesLayout,)>,
+    dispatch_config: DispatchConfig,
+}
+
+impl GenerateFaceEdges {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
+        let shader = device.create_shader_module(&SHADER);
+
+        let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
+        let pipeline_layout = device.create_pipeline_layout(&bind_group_layout);
+
+        let pipeline = device
+            .create_compute_pipeline(
+                &ComputePipelineDescriptorBuilder::begin()
+                    .layout(&pipeline_layout)
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
+                    .finish(),
+            )
+            .await;
+
+        GenerateFaceEdges {
+            device,
+            bind_group_layout,
+            bind_groups: Default::default(),
+            pipeline,
+            dispatch_config,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        encoder: CommandEncoder,
+        resources: GenerateFaceEdgesResources,
+        face_count: u32,
+    ) -> CommandEncoder {
+        let key = [
+            resources.face_count.binding_key(),
+            resources.triangle_indices.binding_key(),
+            resources.edges_low_vertex.binding_key(),
+            resources.edges_high_vertex.binding_key(),
+            resources.edges_index.binding_key(),
+        ];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
+
+        encoder
+            .begin_compute_pass()
+            .set_pipeline(&self.pipeline)
+            .set_bind_groups(&*bind_group)
+            .dispatch_workgroups(self.dispatch_config.workgroups(face_count))
+            .end()
+    }
+}
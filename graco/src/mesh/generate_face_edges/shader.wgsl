@@ -0,0 +1,43 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> face_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> triangle_indices: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> edges_low_vertex: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> edges_high_vertex: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> edges_index: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= face_count {
+        return;
+    }
+
+    let base = index * 3;
+
+    // Each face contributes its 3 edges. An edge is identified by its vertex pair with the lower vertex index first,
+    // so that the edges that two faces share get the same identity, regardless of the winding of either face. The
+    // face an edge belongs to can be recovered from the edge's index by dividing by 3.
+    for (var i = 0u; i < 3; i += 1u) {
+        let a = triangle_indices[base + i];
+        let b = triangle_indices[base + (i + 1) % 3];
+        let edge_index = base + i;
+
+        edges_low_vertex[edge_index] = min(a, b);
+        edges_high_vertex[edge_index] = max(a, b);
+        edges_index[edge_index] = edge_index;
+    }
+}
@@ -0,0 +1,49 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> edge_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> sorted_low_vertex: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> sorted_high_vertex: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> sorted_index: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> edges_shared: array<u32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= edge_count {
+        return;
+    }
+
+    let next = index + 1;
+
+    // The mesh edges are sorted by vertex pair, so the faces that share an edge are stored consecutively. We connect
+    // each face to the next face in the run. For a manifold edge this results in exactly one dual edge; for a
+    // non-manifold edge the faces that share it are connected in a chain. Collapsed edges (of degenerate faces) and
+    // edges between a face and itself are ignored.
+    var is_shared = 0u;
+
+    if next < edge_count {
+        let low = sorted_low_vertex[index];
+        let high = sorted_high_vertex[index];
+        let is_same_edge = low == sorted_low_vertex[next] && high == sorted_high_vertex[next];
+        let is_same_face = sorted_index[index] / 3 == sorted_index[next] / 3;
+
+        if is_same_edge && low != high && !is_same_face {
+            is_shared = 1u;
+        }
+    }
+
+    edges_shared[index] = is_shared;
+}
@@ -0,0 +1,13 @@
+//! Clustering the faces of triangle meshes by coarsening their dual graphs.
+//!
+//! See [ClusterMesh] for an overview of the algorithm.
+
+mod compose_face_clusters;
+mod emit_dual_edges;
+mod generate_face_edges;
+mod mark_shared_edges;
+
+mod cluster_mesh;
+pub use self::cluster_mesh::{
+    ClusterMesh, ClusterMeshConfig, ClusterMeshInput, DualEdgeWeighting, FaceClusterLevel,
+};
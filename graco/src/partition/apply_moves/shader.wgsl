@@ -0,0 +1,96 @@
+#include <src/dispatch.wgsl>
+#include <src/partition/part_weights.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> part_count: u32;
+
+// The balance tolerance as a fixed-point value with 16 fractional bits; see `IMBALANCE_SCALE` in `mod.rs`.
+@group(0) @binding(2)
+var<uniform> imbalance: u32;
+
+@group(0) @binding(3)
+var<storage, read> nodes_weight: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> nodes_accepted_part: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read_write> nodes_part: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read_write> part_weights: array<atomic<u32>>;
+
+// Returns `floor(value * fraction / 2^16)` for a `fraction` with 16 fractional bits, saturated to the largest `u32`.
+fn mul_fixed(value: u32, fraction: u32) -> u32 {
+    let whole = fraction >> 16u;
+    let part = fraction & 0xFFFFu;
+
+    // Neither product can overflow, as both factors are less than `2^16`.
+    let scaled_part = (value >> 16u) * part + (((value & 0xFFFFu) * part) >> 16u);
+
+    if whole > 0u && value > (0xFFFFFFFFu - scaled_part) / whole {
+        return 0xFFFFFFFFu;
+    }
+
+    return value * whole + scaled_part;
+}
+
+// Returns the maximum weight a part may have, which exceeds the average part weight (rounded up) by at most the
+// `imbalance` fraction. This is computed in integer arithmetic, as `f32` can't represent large weights exactly.
+fn max_part_weight(total_weight: u32) -> u32 {
+    var average = total_weight / part_count;
+
+    if total_weight % part_count != 0u {
+        average += 1u;
+    }
+
+    let tolerance = mul_fixed(average, imbalance);
+
+    if tolerance > 0xFFFFFFFFu - average {
+        return 0xFFFFFFFFu;
+    }
+
+    return average + tolerance;
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let current_part = nodes_part[index];
+    let target_part = nodes_accepted_part[index];
+
+    if target_part == current_part {
+        return;
+    }
+
+    // Only move if the target part stays within the balance constraint. We optimistically add the node's weight to the
+    // target part and revert if that exceeds the maximum part weight, so that concurrent moves into the same part
+    // cannot jointly exceed the constraint.
+    let total_weight = atomicLoad(&part_weights[PART_WEIGHTS_TOTAL]);
+    let max_weight = max_part_weight(total_weight);
+
+    let weight = nodes_weight[index];
+    let new_weight = atomicAdd(&part_weights[PART_WEIGHTS_OFFSET + target_part], weight) + weight;
+
+    if new_weight > max_weight {
+        atomicSub(&part_weights[PART_WEIGHTS_OFFSET + target_part], weight);
+
+        return;
+    }
+
+    atomicSub(&part_weights[PART_WEIGHTS_OFFSET + current_part], weight);
+
+    // No other invocation of this kernel reads this node's part, so this write does not race.
+    nodes_part[index] = target_part;
+}
@@ -0,0 +1,118 @@
+#include <src/dispatch.wgsl>
+
+// The maximum number of distinct neighboring parts that are considered as move targets for a node.
+const MAX_CANDIDATE_PARTS = 16u;
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<uniform> negative_gain_factor: f32;
+
+@group(0) @binding(3)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> nodes_edges: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> nodes_edge_weights: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> nodes_part: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read_write> nodes_move_part: array<u32>;
+
+@group(0) @binding(8)
+var<storage, read_write> nodes_move_gain: array<i32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let current_part = nodes_part[index];
+
+    let edges_start = nodes_edge_offset[index];
+    var edges_end = edge_ref_count;
+
+    if index < node_count - 1 {
+        edges_end = nodes_edge_offset[index + 1];
+    }
+
+    // Accumulate the connectivity to each distinct neighboring part in a table that holds the first
+    // `MAX_CANDIDATE_PARTS` parts the node's edges point to; edges into further parts are ignored. A node can only be
+    // adjacent to more parts than that if the part count is large and the partition is fragmented around the node. The
+    // node then has many weak connections rather than 1 strong one, which rarely yields a move with a positive gain.
+    var internal = 0u;
+    var candidate_parts: array<u32, MAX_CANDIDATE_PARTS>;
+    var candidate_connectivity: array<u32, MAX_CANDIDATE_PARTS>;
+    var candidate_count = 0u;
+
+    for (var i = edges_start; i < edges_end; i += 1u) {
+        let part = nodes_part[nodes_edges[i]];
+        let weight = nodes_edge_weights[i];
+
+        if part == current_part {
+            internal += weight;
+
+            continue;
+        }
+
+        var slot = 0u;
+
+        while slot < candidate_count && candidate_parts[slot] != part {
+            slot += 1u;
+        }
+
+        if slot == candidate_count {
+            if candidate_count == MAX_CANDIDATE_PARTS {
+                continue;
+            }
+
+            candidate_parts[slot] = part;
+            candidate_connectivity[slot] = 0u;
+            candidate_count += 1u;
+        }
+
+        candidate_connectivity[slot] += weight;
+    }
+
+    // Find the other part this node is most strongly connected to, preferring the lower part id on ties.
+    var best_part = current_part;
+    var best_connectivity = 0u;
+
+    for (var slot = 0u; slot < candidate_count; slot += 1u) {
+        let part = candidate_parts[slot];
+        let connectivity = candidate_connectivity[slot];
+
+        if connectivity > best_connectivity || (connectivity == best_connectivity && part < best_part) {
+            best_part = part;
+            best_connectivity = connectivity;
+        }
+    }
+
+    let gain = i32(best_connectivity) - i32(internal);
+
+    // Like Jet, we also consider moves with a (slightly) negative gain, as these may allow the partition to escape a
+    // local minimum; the filter step only keeps such a move if neighboring moves turn it into a positive gain.
+    let min_gain = -floor(negative_gain_factor * f32(internal));
+
+    if best_part != current_part && f32(gain) > min_gain {
+        nodes_move_part[index] = best_part;
+        nodes_move_gain[index] = gain;
+    } else {
+        nodes_move_part[index] = current_part;
+        nodes_move_gain[index] = 0;
+    }
+}
@@ -0,0 +1,31 @@
+#include <src/dispatch.wgsl>
+#include <src/partition/part_weights.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<storage, read> nodes_weight: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> nodes_part: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> part_weights: array<atomic<u32>>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let weight = nodes_weight[index];
+
+    atomicAdd(&part_weights[PART_WEIGHTS_TOTAL], weight);
+    atomicAdd(&part_weights[PART_WEIGHTS_OFFSET + nodes_part[index]], weight);
+}
@@ -0,0 +1,189 @@
+#include <src/dispatch.wgsl>
+#include <src/partition/metrics.wgsl>
+#include <src/u64.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> nodes_edges: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> nodes_edge_weights: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> nodes_weight: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> nodes_part: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read_write> metrics: array<atomic<u32>>;
+
+// The number of part ids covered by the bit mask that is used to count the distinct parts a node is adjacent to.
+const PART_MASK_WORDS = 8u;
+const PART_MASK_SIZE = PART_MASK_WORDS * 32u;
+
+// The global metrics are first accumulated per workgroup, so that only a single invocation per workgroup contends for
+// the global counters.
+var<workgroup> workgroup_edge_cut: array<atomic<u32>, 2>;
+var<workgroup> workgroup_communication_volume: atomic<u32>;
+var<workgroup> workgroup_boundary_node_count: atomic<u32>;
+
+// Adds the 64-bit `value` to the 64-bit value stored at `index` and `index + 1` in the metrics buffer.
+fn metrics_add_u64(index: u32, value: vec2<u32>) {
+    let previous = atomicAdd(&metrics[index], value.x);
+    let high = value.y + u64_carry(previous, value.x);
+
+    if high > 0 {
+        atomicAdd(&metrics[index + 1], high);
+    }
+}
+
+fn workgroup_edge_cut_add(value: vec2<u32>) {
+    let previous = atomicAdd(&workgroup_edge_cut[0], value.x);
+    let high = value.y + u64_carry(previous, value.x);
+
+    if high > 0 {
+        atomicAdd(&workgroup_edge_cut[1], high);
+    }
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if local_index == 0 {
+        atomicStore(&workgroup_edge_cut[0], 0u);
+        atomicStore(&workgroup_edge_cut[1], 0u);
+        atomicStore(&workgroup_communication_volume, 0u);
+        atomicStore(&workgroup_boundary_node_count, 0u);
+    }
+
+    workgroupBarrier();
+
+    // Note that we cannot return early for out-of-range invocations, as all invocations must reach the barriers.
+    if index < node_count {
+        let part = nodes_part[index];
+
+        let edges_start = nodes_edge_offset[index];
+        var edges_end = edge_ref_count;
+
+        if index < node_count - 1 {
+            edges_end = nodes_edge_offset[index + 1];
+        }
+
+        // A node's summed edge weight may not fit in 32 bits, so we accumulate it as a 64-bit value.
+        var edge_weight = vec2(0u);
+        var cut = vec2(0u);
+        var is_boundary = false;
+        var min_adjacent_part = 0xFFFFFFFFu;
+
+        for (var i = edges_start; i < edges_end; i += 1u) {
+            let weight = nodes_edge_weights[i];
+            let neighbor_part = nodes_part[nodes_edges[i]];
+
+            edge_weight = u64_add_u32(edge_weight, weight);
+
+            if neighbor_part != part {
+                cut = u64_add_u32(cut, weight);
+                is_boundary = true;
+                min_adjacent_part = min(min_adjacent_part, neighbor_part);
+            }
+        }
+
+        // Count the distinct adjacent parts with a bit mask that covers a window of `PART_MASK_SIZE` part ids, starting
+        // at the smallest adjacent part id. Adjacent parts beyond the window are counted in further passes over the
+        // edges. Each pass starts its window at the smallest adjacent part id the previous pass skipped, so empty
+        // windows cost nothing and the number of passes is bounded by the number of distinct adjacent parts, no
+        // matter how far apart their ids are.
+        var adjacent_part_count = 0u;
+
+        if is_boundary {
+            var window_start = min_adjacent_part;
+
+            loop {
+                var mask: array<u32, PART_MASK_WORDS>;
+                var has_next_window = false;
+                var next_window_start = 0xFFFFFFFFu;
+
+                for (var i = edges_start; i < edges_end; i += 1u) {
+                    let neighbor_part = nodes_part[nodes_edges[i]];
+
+                    // Parts below the window were counted by an earlier pass.
+                    if neighbor_part == part || neighbor_part < window_start {
+                        continue;
+                    }
+
+                    let offset = neighbor_part - window_start;
+
+                    if offset >= PART_MASK_SIZE {
+                        has_next_window = true;
+                        next_window_start = min(next_window_start, neighbor_part);
+
+                        continue;
+                    }
+
+                    let word = offset / 32u;
+                    let bit = 1u << (offset % 32u);
+
+                    if (mask[word] & bit) == 0u {
+                        mask[word] |= bit;
+                        adjacent_part_count += 1u;
+                    }
+                }
+
+                if !has_next_window {
+                    break;
+                }
+
+                window_start = next_window_start;
+            }
+        }
+
+        let part_base = METRICS_PARTS_OFFSET + part * METRICS_PART_ENTRY_SIZE;
+
+        atomicAdd(&metrics[part_base + METRICS_PART_NODE_WEIGHT], nodes_weight[index]);
+
+        if any(edge_weight > vec2(0u)) {
+            metrics_add_u64(part_base + METRICS_PART_EDGE_WEIGHT, edge_weight);
+        }
+
+        if is_boundary {
+            atomicAdd(&metrics[part_base + METRICS_PART_BOUNDARY_NODE_COUNT], 1u);
+
+            workgroup_edge_cut_add(cut);
+
+            atomicAdd(&workgroup_communication_volume, adjacent_part_count);
+            atomicAdd(&workgroup_boundary_node_count, 1u);
+        }
+    }
+
+    workgroupBarrier();
+
+    if local_index == 0 {
+        let edge_cut_low = atomicLoad(&workgroup_edge_cut[0]);
+        let edge_cut_high = atomicLoad(&workgroup_edge_cut[1]);
+
+        if edge_cut_low > 0 || edge_cut_high > 0 {
+            metrics_add_u64(METRICS_EDGE_CUT, vec2(edge_cut_low, edge_cut_high));
+        }
+
+        let communication_volume = atomicLoad(&workgroup_communication_volume);
+
+        if communication_volume > 0 {
+            atomicAdd(&metrics[METRICS_COMMUNICATION_VOLUME], communication_volume);
+            atomicAdd(&metrics[METRICS_BOUNDARY_NODE_COUNT], atomicLoad(&workgroup_boundary_node_count));
+        }
+    }
+}
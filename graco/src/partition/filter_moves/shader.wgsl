@@ -0,0 +1,96 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> nodes_edges: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> nodes_edge_weights: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> nodes_part: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> nodes_move_part: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read> nodes_move_gain: array<i32>;
+
+@group(0) @binding(8)
+var<storage, read_write> nodes_accepted_part: array<u32>;
+
+// Returns `true` if the move proposed for node `a` takes precedence over the move proposed for node `b`: moves with a
+// higher gain go first, ties are broken by node index.
+fn precedes(a: u32, gain_a: i32, b: u32, gain_b: i32) -> bool {
+    return gain_a > gain_b || (gain_a == gain_b && a < b);
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let current_part = nodes_part[index];
+    let move_part = nodes_move_part[index];
+
+    if move_part == current_part {
+        nodes_accepted_part[index] = current_part;
+
+        return;
+    }
+
+    let gain = nodes_move_gain[index];
+
+    let edges_start = nodes_edge_offset[index];
+    var edges_end = edge_ref_count;
+
+    if index < node_count - 1 {
+        edges_end = nodes_edge_offset[index + 1];
+    }
+
+    // Resolve conflicts between concurrent moves (the Jet "afterburner"): recompute the gain of this node's move under
+    // the assumption that all neighboring moves that take precedence over it have been applied. Two adjacent nodes that
+    // would each move into the other's part, for example, may both have a positive gain individually, but the move
+    // that goes second sees the first node in its target part and is rejected if that no longer pays off.
+    var to_move_part = 0u;
+    var to_current_part = 0u;
+
+    for (var i = edges_start; i < edges_end; i += 1u) {
+        let neighbor = nodes_edges[i];
+
+        var neighbor_part = nodes_part[neighbor];
+        let neighbor_move_part = nodes_move_part[neighbor];
+
+        if neighbor_move_part != neighbor_part && precedes(neighbor, nodes_move_gain[neighbor], index, gain) {
+            neighbor_part = neighbor_move_part;
+        }
+
+        if neighbor_part == move_part {
+            to_move_part += nodes_edge_weights[i];
+        } else if neighbor_part == current_part {
+            to_current_part += nodes_edge_weights[i];
+        }
+    }
+
+    // Only accept moves with a strictly positive gain; moves without gain would only shuffle nodes between parts (and
+    // may oscillate between iterations) without improving the cut.
+    if to_move_part > to_current_part {
+        nodes_accepted_part[index] = move_part;
+    } else {
+        nodes_accepted_part[index] = current_part;
+    }
+}
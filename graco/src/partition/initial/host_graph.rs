@@ -0,0 +1,120 @@
+use std::error::Error;
+use std::future::join;
+use std::ops::Range;
+
+use empa::buffer;
+use empa::device::Device;
+
+use crate::partition::initial::InitialPartitionInput;
+
+/// A copy of a graph in host memory, in the same CSR layout that the GPU pipelines use.
+#[derive(Clone, PartialEq, Debug)]
+pub struct HostGraph {
+    pub nodes_edge_offset: Vec<u32>,
+    pub nodes_edges: Vec<u32>,
+    pub nodes_edge_weights: Vec<u32>,
+    pub nodes_weight: Vec<u32>,
+}
+
+impl HostGraph {
+    /// Reads the graph described by the `input` back from device memory.
+    ///
+    /// Only the first [node_count](InitialPartitionInput::node_count) and
+    /// [edge_ref_count](InitialPartitionInput::edge_ref_count) elements of the input buffers are
+    /// read back.
+    pub async fn read_back<U0, U1, U2, U3>(
+        device: &Device,
+        input: InitialPartitionInput<'_, U0, U1, U2, U3>,
+    ) -> Result<Self, Box<dyn Error>>
+    where
+        U0: buffer::CopySrc,
+        U1: buffer::CopySrc,
+        U2: buffer::CopySrc,
+        U3: buffer::CopySrc,
+    {
+        let InitialPartitionInput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            nodes_weight,
+            node_count,
+            edge_ref_count,
+        } = input;
+
+        let node_count = node_count as usize;
+        let edge_ref_count = edge_ref_count as usize;
+
+        let nodes_edge_offset_readback = device
+            .create_slice_buffer_zeroed(node_count, buffer::Usages::copy_dst().and_map_read());
+        let nodes_edges_readback = device
+            .create_slice_buffer_zeroed(edge_ref_count, buffer::Usages::copy_dst().and_map_read());
+        let nodes_edge_weights_readback = device
+            .create_slice_buffer_zeroed(edge_ref_count, buffer::Usages::copy_dst().and_map_read());
+        let nodes_weight_readback = device
+            .create_slice_buffer_zeroed(node_count, buffer::Usages::copy_dst().and_map_read());
+
+        let mut encoder = device.create_command_encoder();
+
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            nodes_edge_offset.get(..node_count).unwrap(),
+            nodes_edge_offset_readback.view(),
+        );
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            nodes_edges.get(..edge_ref_count).unwrap(),
+            nodes_edges_readback.view(),
+        );
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            nodes_edge_weights.get(..edge_ref_count).unwrap(),
+            nodes_edge_weights_readback.view(),
+        );
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            nodes_weight.get(..node_count).unwrap(),
+            nodes_weight_readback.view(),
+        );
+
+        device.queue().submit(encoder.finish());
+
+        let (r0, r1, r2, r3) = join!(
+            nodes_edge_offset_readback.map_read(),
+            nodes_edges_readback.map_read(),
+            nodes_edge_weights_readback.map_read(),
+            nodes_weight_readback.map_read(),
+        )
+        .await;
+
+        r0?;
+        r1?;
+        r2?;
+        r3?;
+
+        let graph = HostGraph {
+            nodes_edge_offset: nodes_edge_offset_readback.mapped().to_vec(),
+            nodes_edges: nodes_edges_readback.mapped().to_vec(),
+            nodes_edge_weights: nodes_edge_weights_readback.mapped().to_vec(),
+            nodes_weight: nodes_weight_readback.mapped().to_vec(),
+        };
+
+        nodes_edge_offset_readback.unmap();
+        nodes_edges_readback.unmap();
+        nodes_edge_weights_readback.unmap();
+        nodes_weight_readback.unmap();
+
+        Ok(graph)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes_edge_offset.len()
+    }
+
+    /// Returns the range of the edge refs of the given `node` in the edge lists.
+    pub fn node_edge_range(&self, node: usize) -> Range<usize> {
+        let start = self.nodes_edge_offset[node] as usize;
+        let end = self
+            .nodes_edge_offset
+            .get(node + 1)
+            .map(|offset| *offset as usize)
+            .unwrap_or(self.nodes_edges.len());
+
+        start..end
+    }
+}
@@ -0,0 +1,69 @@
+use std::error::Error;
+
+use empa::buffer;
+use empa::buffer::Buffer;
+use empa::device::Device;
+use empa::type_flag::{O, X};
+
+use crate::partition::initial::{recursive_bisection, HostGraph};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct InitialPartitionConfig {
+    /// The number of parts to partition the graph into.
+    pub part_count: u32,
+    /// The balance tolerance: the weight of each part may exceed the average part weight (the
+    /// total node weight divided by the `part_count`) by at most this fraction.
+    pub imbalance: f32,
+    /// The number of bisections that are computed from different starting nodes for each
+    /// bisection step; the bisection with the smallest edge cut is kept.
+    pub attempts: usize,
+    /// The maximum number of Fiduccia-Mattheyses refinement passes that are run on each bisection.
+    pub refinement_passes: usize,
+    /// The seed for the pseudo-random selection of starting nodes.
+    pub seed: u64,
+}
+
+impl Default for InitialPartitionConfig {
+    fn default() -> Self {
+        InitialPartitionConfig {
+            part_count: 2,
+            imbalance: 0.03,
+            attempts: 4,
+            refinement_passes: 8,
+            seed: 1,
+        }
+    }
+}
+
+pub struct InitialPartitionInput<'a, U0, U1, U2, U3> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    pub nodes_weight: buffer::View<'a, [u32], U3>,
+    /// The number of nodes in the graph; the node buffers may be larger.
+    pub node_count: u32,
+    /// The number of edge refs in the graph; the edge buffers may be larger.
+    pub edge_ref_count: u32,
+}
+
+/// Reads the graph described by the `input` back from device memory, partitions it with
+/// [recursive_bisection] and uploads the resulting part ids.
+///
+/// The returned buffer holds a part id for each of the `node_count` nodes in the graph and can be
+/// copied into the first level of the uncoarsening.
+pub async fn initial_partition<U0, U1, U2, U3>(
+    device: &Device,
+    input: InitialPartitionInput<'_, U0, U1, U2, U3>,
+    config: &InitialPartitionConfig,
+) -> Result<Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, X, O, O>>, Box<dyn Error>>
+where
+    U0: buffer::CopySrc,
+    U1: buffer::CopySrc,
+    U2: buffer::CopySrc,
+    U3: buffer::CopySrc,
+{
+    let graph = HostGraph::read_back(device, input).await?;
+    let nodes_part = recursive_bisection(&graph, config);
+
+    Ok(device.create_buffer(nodes_part, buffer::Usages::storage_binding().and_copy_src()))
+}
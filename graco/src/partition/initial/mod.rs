@@ -0,0 +1,17 @@
+//! Computes an initial partition for the coarsest level of a coarsening hierarchy on the CPU.
+//!
+//! Once the hierarchy is small (a few hundred nodes), reading it back and running a high-quality
+//! serial algorithm is cheap and produces better partitions than the GPU heuristics. See
+//! [initial_partition] for reading back a graph, partitioning it and uploading the resulting part
+//! ids in one go, or use [HostGraph::read_back] and [recursive_bisection] directly.
+
+mod host_graph;
+pub use self::host_graph::HostGraph;
+
+mod initial_partition;
+pub use self::initial_partition::{
+    initial_partition, InitialPartitionConfig, InitialPartitionInput,
+};
+
+mod recursive_bisection;
+pub use self::recursive_bisection::recursive_bisection;
@@ -0,0 +1,628 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use crate::partition::initial::{HostGraph, InitialPartitionConfig};
+
+// An FM pass is abandoned once this many consecutive moves have not improved on the best cut found
+// during the pass, as a long run of moves without improvement rarely leads anywhere.
+const MAX_MOVES_WITHOUT_IMPROVEMENT: usize = 64;
+
+/// Partitions a graph into [part_count](InitialPartitionConfig::part_count) parts by recursive
+/// bisection.
+///
+/// Each bisection grows one side from a starting node by repeatedly adding the boundary node that
+/// reduces the edge cut the most (greedy graph growing) until it holds its share of the node
+/// weight, and then improves the cut with Fiduccia-Mattheyses refinement. The two sides are then
+/// bisected recursively until the requested number of parts is reached. The part count need not be
+/// a power of two: a side that receives an odd number of parts receives a proportional share of
+/// the node weight.
+///
+/// The graph must be undirected (each edge is stored once for each of the nodes it connects).
+/// Returns the part id for each node.
+pub fn recursive_bisection(graph: &HostGraph, config: &InitialPartitionConfig) -> Vec<u32> {
+    assert!(
+        config.part_count > 0,
+        "`part_count` must be greater than `0`"
+    );
+
+    let node_count = graph.node_count();
+
+    // Distribute the imbalance tolerance over the levels of the recursion, as the imbalances of the
+    // bisections compound.
+    let depth = config
+        .part_count
+        .next_power_of_two()
+        .trailing_zeros()
+        .max(1);
+    let bisection_imbalance = (1.0 + config.imbalance).powf(1.0 / depth as f32) - 1.0;
+
+    let mut bisection = RecursiveBisection {
+        graph,
+        config,
+        bisection_imbalance,
+        rng: oorandom::Rand32::new(config.seed),
+        nodes_local_index: vec![u32::MAX; node_count],
+        nodes_part: vec![0; node_count],
+    };
+
+    bisection.partition((0..node_count as u32).collect(), 0, config.part_count);
+
+    bisection.nodes_part
+}
+
+struct RecursiveBisection<'a> {
+    graph: &'a HostGraph,
+    config: &'a InitialPartitionConfig,
+    bisection_imbalance: f32,
+    rng: oorandom::Rand32,
+    // Scratch space for building induced subgraphs.
+    nodes_local_index: Vec<u32>,
+    nodes_part: Vec<u32>,
+}
+
+impl RecursiveBisection<'_> {
+    fn partition(&mut self, nodes: Vec<u32>, first_part: u32, part_count: u32) {
+        if part_count == 1 || nodes.len() <= 1 {
+            for node in nodes {
+                self.nodes_part[node as usize] = first_part;
+            }
+
+            return;
+        }
+
+        let part_count_0 = part_count / 2;
+        let part_count_1 = part_count - part_count_0;
+
+        let subgraph = Subgraph::induced(self.graph, &nodes, &mut self.nodes_local_index);
+
+        let total_weight = subgraph.total_weight();
+        let target_weight_0 = total_weight * part_count_0 as u64 / part_count as u64;
+        let target_weight_1 = total_weight - target_weight_0;
+        let max_weights = [
+            ((1.0 + self.bisection_imbalance) * target_weight_0 as f32).ceil() as u64,
+            ((1.0 + self.bisection_imbalance) * target_weight_1 as f32).ceil() as u64,
+        ];
+
+        let sides = subgraph.bisect(target_weight_0, max_weights, self.config, &mut self.rng);
+
+        let mut nodes_0 = Vec::new();
+        let mut nodes_1 = Vec::new();
+
+        for (node, side) in nodes.into_iter().zip(sides) {
+            if side == 0 {
+                nodes_0.push(node);
+            } else {
+                nodes_1.push(node);
+            }
+        }
+
+        self.partition(nodes_0, first_part, part_count_0);
+        self.partition(nodes_1, first_part + part_count_0, part_count_1);
+    }
+}
+
+/// A subgraph with local node indices and explicit end offsets, induced by a subset of the nodes
+/// of a [HostGraph].
+struct Subgraph {
+    nodes_edge_offset: Vec<usize>,
+    nodes_edges: Vec<u32>,
+    nodes_edge_weights: Vec<u32>,
+    nodes_weight: Vec<u32>,
+}
+
+impl Subgraph {
+    fn induced(graph: &HostGraph, nodes: &[u32], nodes_local_index: &mut [u32]) -> Self {
+        for (local_index, node) in nodes.iter().enumerate() {
+            nodes_local_index[*node as usize] = local_index as u32;
+        }
+
+        let mut nodes_edge_offset = Vec::with_capacity(nodes.len() + 1);
+        let mut nodes_edges = Vec::new();
+        let mut nodes_edge_weights = Vec::new();
+        let mut nodes_weight = Vec::with_capacity(nodes.len());
+
+        nodes_edge_offset.push(0);
+
+        for node in nodes {
+            for edge in graph.node_edge_range(*node as usize) {
+                let local_index = nodes_local_index[graph.nodes_edges[edge] as usize];
+
+                if local_index != u32::MAX {
+                    nodes_edges.push(local_index);
+                    nodes_edge_weights.push(graph.nodes_edge_weights[edge]);
+                }
+            }
+
+            nodes_edge_offset.push(nodes_edges.len());
+            nodes_weight.push(graph.nodes_weight[*node as usize]);
+        }
+
+        // Reset the scratch space for the next subgraph.
+        for node in nodes {
+            nodes_local_index[*node as usize] = u32::MAX;
+        }
+
+        Subgraph {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            nodes_weight,
+        }
+    }
+
+    fn node_count(&self) -> usize {
+        self.nodes_weight.len()
+    }
+
+    fn edges(&self, node: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+        let range = self.nodes_edge_offset[node]..self.nodes_edge_offset[node + 1];
+
+        self.nodes_edges[range.clone()]
+            .iter()
+            .zip(&self.nodes_edge_weights[range])
+            .map(|(target, weight)| (*target as usize, *weight as i64))
+    }
+
+    fn total_weight(&self) -> u64 {
+        self.nodes_weight.iter().map(|weight| *weight as u64).sum()
+    }
+
+    fn side_weights(&self, sides: &[usize]) -> [u64; 2] {
+        let mut weights = [0; 2];
+
+        for (node, side) in sides.iter().enumerate() {
+            weights[*side] += self.nodes_weight[node] as u64;
+        }
+
+        weights
+    }
+
+    fn cut(&self, sides: &[usize]) -> i64 {
+        let mut cut = 0;
+
+        for node in 0..self.node_count() {
+            for (target, weight) in self.edges(node) {
+                if sides[node] != sides[target] {
+                    cut += weight;
+                }
+            }
+        }
+
+        // Each cut edge was counted once for each of its 2 edge refs.
+        cut / 2
+    }
+
+    /// Returns the reduction in edge cut that would result from moving the `node` to the other
+    /// side.
+    fn move_gain(&self, sides: &[usize], node: usize) -> i64 {
+        let mut gain = 0;
+
+        for (target, weight) in self.edges(node) {
+            if sides[target] == sides[node] {
+                gain -= weight;
+            } else {
+                gain += weight;
+            }
+        }
+
+        gain
+    }
+
+    /// Splits the subgraph into 2 sides, such that side `0` has a weight close to
+    /// `target_weight_0` and neither side exceeds its maximum weight, if possible.
+    ///
+    /// Returns the side for each node.
+    fn bisect(
+        &self,
+        target_weight_0: u64,
+        max_weights: [u64; 2],
+        config: &InitialPartitionConfig,
+        rng: &mut oorandom::Rand32,
+    ) -> Vec<usize> {
+        let node_count = self.node_count();
+
+        let mut best: Option<((u64, i64), Vec<usize>)> = None;
+
+        for attempt in 0..config.attempts.max(1) {
+            let start = rng.rand_range(0..node_count as u32) as usize;
+
+            // For the first attempt, start from a node on the periphery of the graph, which tends
+            // to produce a more compact region than starting from a node in the middle.
+            let start = if attempt == 0 {
+                self.pseudo_peripheral_node(start)
+            } else {
+                start
+            };
+
+            let mut sides = self.grow(start, target_weight_0, max_weights[0]);
+
+            for _ in 0..config.refinement_passes {
+                if !self.refine(&mut sides, max_weights) {
+                    break;
+                }
+            }
+
+            // Prefer balanced bisections, then bisections with a smaller cut.
+            let weights = self.side_weights(&sides);
+            let overload = weights[0].saturating_sub(max_weights[0])
+                + weights[1].saturating_sub(max_weights[1]);
+            let score = (overload, self.cut(&sides));
+
+            if best
+                .as_ref()
+                .map(|(best_score, _)| score < *best_score)
+                .unwrap_or(true)
+            {
+                best = Some((score, sides));
+            }
+        }
+
+        best.unwrap().1
+    }
+
+    fn pseudo_peripheral_node(&self, start: usize) -> usize {
+        let mut visited = vec![false; self.node_count()];
+        let mut queue = VecDeque::new();
+        let mut last = start;
+
+        visited[start] = true;
+        queue.push_back(start);
+
+        while let Some(node) = queue.pop_front() {
+            last = node;
+
+            for (target, _) in self.edges(node) {
+                if !visited[target] {
+                    visited[target] = true;
+                    queue.push_back(target);
+                }
+            }
+        }
+
+        last
+    }
+
+    /// Greedy graph growing: grows side `0` from the `start` node until it reaches the
+    /// `target_weight`, by repeatedly adding the node that reduces the edge cut the most.
+    fn grow(&self, start: usize, target_weight: u64, max_weight: u64) -> Vec<usize> {
+        let node_count = self.node_count();
+
+        let mut sides = vec![1; node_count];
+        let mut gains: Vec<i64> = (0..node_count)
+            .map(|node| self.move_gain(&sides, node))
+            .collect();
+        let mut queue = BinaryHeap::new();
+        let mut weight = 0;
+
+        // Used to find a new starting node if the region's component is exhausted before the
+        // target weight is reached.
+        let mut next_unassigned = 0;
+
+        queue.push((gains[start], Reverse(start)));
+
+        while weight < target_weight {
+            let node = match queue.pop() {
+                Some((gain, Reverse(node))) => {
+                    if sides[node] == 0 || gain != gains[node] {
+                        continue;
+                    }
+
+                    node
+                }
+                None => {
+                    while next_unassigned < node_count && sides[next_unassigned] == 0 {
+                        next_unassigned += 1;
+                    }
+
+                    if next_unassigned == node_count {
+                        break;
+                    }
+
+                    next_unassigned
+                }
+            };
+
+            let node_weight = self.nodes_weight[node] as u64;
+
+            if weight + node_weight > max_weight {
+                // Skip this node; if there is no other node that fits, then we move on to the
+                // unassigned nodes in order, so make sure we don't get stuck on this node.
+                if node == next_unassigned {
+                    next_unassigned += 1;
+                }
+
+                continue;
+            }
+
+            sides[node] = 0;
+            weight += node_weight;
+
+            for (target, edge_weight) in self.edges(node) {
+                if sides[target] == 1 {
+                    gains[target] += 2 * edge_weight;
+                    queue.push((gains[target], Reverse(target)));
+                }
+            }
+        }
+
+        sides
+    }
+
+    /// Runs a single Fiduccia-Mattheyses pass: repeatedly moves the unlocked node with the highest
+    /// gain to the other side (if that side has capacity) and locks it, then rolls back to the
+    /// point during the pass where the cut was smallest.
+    ///
+    /// Returns `true` if the pass improved the cut.
+    fn refine(&self, sides: &mut [usize], max_weights: [u64; 2]) -> bool {
+        let node_count = self.node_count();
+
+        let mut weights = self.side_weights(sides);
+        let mut gains: Vec<i64> = (0..node_count)
+            .map(|node| self.move_gain(sides, node))
+            .collect();
+        let mut locked = vec![false; node_count];
+
+        // A separate queue for the nodes on each side, so that we can find the best move out of
+        // either side.
+        let mut queues = [BinaryHeap::new(), BinaryHeap::new()];
+
+        for node in 0..node_count {
+            queues[sides[node]].push((gains[node], Reverse(node)));
+        }
+
+        let mut moves = Vec::new();
+        let mut cut_delta = 0;
+        let mut best_cut_delta = 0;
+        let mut best_move_count = 0;
+
+        while moves.len() - best_move_count < MAX_MOVES_WITHOUT_IMPROVEMENT {
+            // Discard stale entries, then find the best feasible move.
+            let mut best_move: Option<(i64, usize)> = None;
+
+            for (side, queue) in queues.iter_mut().enumerate() {
+                while let Some(&(gain, Reverse(node))) = queue.peek() {
+                    if locked[node] || sides[node] != side || gain != gains[node] {
+                        queue.pop();
+
+                        continue;
+                    }
+
+                    let target_weight = weights[1 - side] + self.nodes_weight[node] as u64;
+
+                    if target_weight > max_weights[1 - side] {
+                        // This node does not fit on the other side; it is requeued if its gain
+                        // changes.
+                        queue.pop();
+
+                        continue;
+                    }
+
+                    if best_move
+                        .map(|(best_gain, _)| gain > best_gain)
+                        .unwrap_or(true)
+                    {
+                        best_move = Some((gain, node));
+                    }
+
+                    break;
+                }
+            }
+
+            let (gain, node) = match best_move {
+                Some(best_move) => best_move,
+                None => break,
+            };
+
+            let side = sides[node];
+            let node_weight = self.nodes_weight[node] as u64;
+
+            sides[node] = 1 - side;
+            weights[side] -= node_weight;
+            weights[1 - side] += node_weight;
+            locked[node] = true;
+            cut_delta -= gain;
+            moves.push(node);
+
+            for (target, _) in self.edges(node) {
+                if !locked[target] {
+                    gains[target] = self.move_gain(sides, target);
+                    queues[sides[target]].push((gains[target], Reverse(target)));
+                }
+            }
+
+            if cut_delta < best_cut_delta {
+                best_cut_delta = cut_delta;
+                best_move_count = moves.len();
+            }
+        }
+
+        // Roll back the moves that were made after the best cut was found.
+        for node in moves.drain(best_move_count..) {
+            sides[node] = 1 - sides[node];
+        }
+
+        best_cut_delta < 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `width` by `height` grid graph with unit node weights, in which each node is connected to
+    /// its horizontal and vertical neighbors by an edge with a weight of `edge_weight(a, b)`.
+    fn grid(width: u32, height: u32, edge_weight: impl Fn(u32, u32) -> u32) -> HostGraph {
+        let mut graph = HostGraph {
+            nodes_edge_offset: Vec::new(),
+            nodes_edges: Vec::new(),
+            nodes_edge_weights: Vec::new(),
+            nodes_weight: Vec::new(),
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let node = y * width + x;
+                let mut neighbors = Vec::new();
+
+                if y > 0 {
+                    neighbors.push(node - width);
+                }
+
+                if x > 0 {
+                    neighbors.push(node - 1);
+                }
+
+                if x < width - 1 {
+                    neighbors.push(node + 1);
+                }
+
+                if y < height - 1 {
+                    neighbors.push(node + width);
+                }
+
+                graph.nodes_edge_offset.push(graph.nodes_edges.len() as u32);
+
+                for neighbor in neighbors {
+                    graph.nodes_edges.push(neighbor);
+                    graph.nodes_edge_weights.push(edge_weight(node, neighbor));
+                }
+
+                graph.nodes_weight.push(1);
+            }
+        }
+
+        graph
+    }
+
+    fn part_weights(graph: &HostGraph, nodes_part: &[u32], part_count: u32) -> Vec<u64> {
+        let mut weights = vec![0; part_count as usize];
+
+        for (node, part) in nodes_part.iter().enumerate() {
+            weights[*part as usize] += graph.nodes_weight[node] as u64;
+        }
+
+        weights
+    }
+
+    #[test]
+    fn test_balanced_parts() {
+        let graph = grid(8, 8, |_, _| 1);
+        let config = InitialPartitionConfig {
+            part_count: 4,
+            imbalance: 0.0,
+            ..Default::default()
+        };
+
+        let nodes_part = recursive_bisection(&graph, &config);
+
+        assert_eq!(part_weights(&graph, &nodes_part, 4), vec![16; 4]);
+    }
+
+    #[test]
+    fn test_balanced_parts_within_imbalance() {
+        let graph = grid(16, 16, |a, b| 1 + (a + b) % 5);
+        let config = InitialPartitionConfig {
+            part_count: 4,
+            imbalance: 0.1,
+            ..Default::default()
+        };
+
+        let nodes_part = recursive_bisection(&graph, &config);
+
+        // The imbalance is distributed over the bisections, but each bisection rounds its maximum
+        // side weight up to a whole node.
+        let max_weight = (1.1f32 * 64.0).ceil() as u64 + 1;
+
+        for weight in part_weights(&graph, &nodes_part, 4) {
+            assert!(
+                weight <= max_weight,
+                "part weight `{}` exceeds `{}`",
+                weight,
+                max_weight
+            );
+        }
+    }
+
+    #[test]
+    fn test_odd_part_count_covers_all_parts() {
+        let graph = grid(6, 5, |_, _| 1);
+
+        for part_count in [3, 5, 7] {
+            let config = InitialPartitionConfig {
+                part_count,
+                imbalance: 0.0,
+                ..Default::default()
+            };
+
+            let nodes_part = recursive_bisection(&graph, &config);
+
+            assert!(nodes_part.iter().all(|part| *part < part_count));
+
+            let weights = part_weights(&graph, &nodes_part, part_count);
+
+            assert!(
+                weights.iter().all(|weight| *weight > 0),
+                "empty part for `{}` parts: {:?}",
+                part_count,
+                weights
+            );
+
+            // A side that receives an odd number of parts receives a proportional share of the
+            // node weight, so that all parts end up with (close to) the same weight.
+            let min = *weights.iter().min().unwrap();
+            let max = *weights.iter().max().unwrap();
+
+            assert!(
+                max - min <= 1,
+                "unbalanced `{}` parts: {:?}",
+                part_count,
+                weights
+            );
+        }
+    }
+
+    #[test]
+    fn test_single_part() {
+        let graph = grid(4, 4, |_, _| 1);
+        let config = InitialPartitionConfig {
+            part_count: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(recursive_bisection(&graph, &config), vec![0; 16]);
+    }
+
+    #[test]
+    fn test_refinement_never_increases_cut() {
+        let graph = grid(8, 8, |a, b| 1 + (a + b) % 5);
+        let node_count = graph.node_count();
+        let nodes: Vec<u32> = (0..node_count as u32).collect();
+        let subgraph = Subgraph::induced(&graph, &nodes, &mut vec![u32::MAX; node_count]);
+
+        let mut rng = oorandom::Rand32::new(7);
+
+        for max_weights in [[64, 64], [33, 33]] {
+            for _ in 0..8 {
+                // A random balanced assignment: 32 nodes on either side.
+                let mut sides: Vec<usize> = (0..node_count).map(|node| node % 2).collect();
+
+                for i in (1..node_count).rev() {
+                    sides.swap(i, rng.rand_range(0..i as u32 + 1) as usize);
+                }
+
+                for _ in 0..4 {
+                    let cut_before = subgraph.cut(&sides);
+                    let improved = subgraph.refine(&mut sides, max_weights);
+                    let cut_after = subgraph.cut(&sides);
+
+                    assert!(cut_after <= cut_before, "{} > {}", cut_after, cut_before);
+                    assert_eq!(improved, cut_after < cut_before);
+
+                    let weights = subgraph.side_weights(&sides);
+
+                    assert!(weights[0] <= max_weights[0] && weights[1] <= max_weights[1]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// Layout of the raw metrics buffer, see `metrics.wgsl`. Must be kept in sync.
+const METRICS_EDGE_CUT: usize = 0;
+const METRICS_COMMUNICATION_VOLUME: usize = 2;
+const METRICS_BOUNDARY_NODE_COUNT: usize = 3;
+const METRICS_PARTS_OFFSET: usize = 4;
+
+const METRICS_PART_NODE_WEIGHT: usize = 0;
+const METRICS_PART_EDGE_WEIGHT: usize = 1;
+const METRICS_PART_BOUNDARY_NODE_COUNT: usize = 3;
+const METRICS_PART_ENTRY_SIZE: usize = 4;
+
+/// Returns the length of the `u32` slice buffer [PartitionMetrics] writes its raw metrics to for a
+/// partition with the given number of parts.
+///
+/// [PartitionMetrics]: crate::partition::PartitionMetrics
+pub const fn partition_metrics_len(part_count: u32) -> usize {
+    METRICS_PARTS_OFFSET + part_count as usize * METRICS_PART_ENTRY_SIZE
+}
+
+/// The metrics for a single part of a partition.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct PartMetrics {
+    /// The sum of the weights of the nodes in the part.
+    pub node_weight: u32,
+    /// The sum of the weights of the edge refs of the nodes in the part, including the edges that
+    /// connect to other parts.
+    pub edge_weight: u64,
+    /// The number of nodes in the part that are connected to at least one node in another part.
+    pub boundary_node_count: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct PartitionMetricsReport {
+    /// The sum of the weights of the edges that connect nodes in different parts.
+    pub edge_cut: u64,
+    /// The total communication volume: the sum over all nodes of the number of parts other than
+    /// the node's own part that the node is connected to.
+    pub communication_volume: u32,
+    /// The number of nodes that are connected to at least one node in another part.
+    pub boundary_node_count: u32,
+    /// The metrics for each part, indexed by part id.
+    pub parts: Vec<PartMetrics>,
+}
+
+impl PartitionMetricsReport {
+    /// Decodes the raw metrics that were written by [PartitionMetrics] and read back to the host.
+    ///
+    /// Panics if `raw` is shorter than [partition_metrics_len] for the `part_count`.
+    ///
+    /// [PartitionMetrics]: crate::partition::PartitionMetrics
+    pub fn from_raw(raw: &[u32], part_count: u32) -> Self {
+        assert!(
+            raw.len() >= partition_metrics_len(part_count),
+            "raw metrics must contain at least {} elements",
+            partition_metrics_len(part_count)
+        );
+
+        let decode_u64 = |index: usize| (raw[index] as u64) | ((raw[index + 1] as u64) << 32);
+
+        let parts = (0..part_count as usize)
+            .map(|part| {
+                let base = METRICS_PARTS_OFFSET + part * METRICS_PART_ENTRY_SIZE;
+
+                PartMetrics {
+                    node_weight: raw[base + METRICS_PART_NODE_WEIGHT],
+                    edge_weight: decode_u64(base + METRICS_PART_EDGE_WEIGHT),
+                    boundary_node_count: raw[base + METRICS_PART_BOUNDARY_NODE_COUNT],
+                }
+            })
+            .collect();
+
+        PartitionMetricsReport {
+            // Each cut edge was counted once for each of its 2 edge refs.
+            edge_cut: decode_u64(METRICS_EDGE_CUT) / 2,
+            communication_volume: raw[METRICS_COMMUNICATION_VOLUME],
+            boundary_node_count: raw[METRICS_BOUNDARY_NODE_COUNT],
+            parts,
+        }
+    }
+
+    pub fn total_node_weight(&self) -> u64 {
+        self.parts.iter().map(|part| part.node_weight as u64).sum()
+    }
+
+    /// The imbalance of the partition: the factor by which the heaviest part exceeds the average
+    /// part weight, minus `1`.
+    ///
+    /// A perfectly balanced partition has an imbalance of `0.0`.
+    pub fn imbalance(&self) -> f64 {
+        let total_node_weight = self.total_node_weight();
+
+        if self.parts.is_empty() || total_node_weight == 0 {
+            return 0.0;
+        }
+
+        let max_node_weight = self
+            .parts
+            .iter()
+            .map(|part| part.node_weight)
+            .max()
+            .unwrap_or(0);
+        let average_node_weight = total_node_weight as f64 / self.parts.len() as f64;
+
+        max_node_weight as f64 / average_node_weight - 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_raw() {
+        let mut raw = vec![0; partition_metrics_len(2)];
+
+        // An edge cut of `2^33 + 6` edge refs, which is `2^32 + 3` edges.
+        raw[METRICS_EDGE_CUT] = 6;
+        raw[METRICS_EDGE_CUT + 1] = 2;
+        raw[METRICS_COMMUNICATION_VOLUME] = 7;
+        raw[METRICS_BOUNDARY_NODE_COUNT] = 5;
+
+        let part_1 = METRICS_PARTS_OFFSET + METRICS_PART_ENTRY_SIZE;
+
+        raw[METRICS_PARTS_OFFSET + METRICS_PART_NODE_WEIGHT] = 10;
+        raw[METRICS_PARTS_OFFSET + METRICS_PART_EDGE_WEIGHT] = u32::MAX;
+        raw[METRICS_PARTS_OFFSET + METRICS_PART_BOUNDARY_NODE_COUNT] = 2;
+        raw[part_1 + METRICS_PART_NODE_WEIGHT] = 30;
+        raw[part_1 + METRICS_PART_EDGE_WEIGHT] = 1;
+        raw[part_1 + METRICS_PART_EDGE_WEIGHT + 1] = 1;
+        raw[part_1 + METRICS_PART_BOUNDARY_NODE_COUNT] = 3;
+
+        let report = PartitionMetricsReport::from_raw(&raw, 2);
+
+        assert_eq!(
+            report,
+            PartitionMetricsReport {
+                edge_cut: (1 << 32) + 3,
+                communication_volume: 7,
+                boundary_node_count: 5,
+                parts: vec![
+                    PartMetrics {
+                        node_weight: 10,
+                        edge_weight: u32::MAX as u64,
+                        boundary_node_count: 2,
+                    },
+                    PartMetrics {
+                        node_weight: 30,
+                        edge_weight: (1 << 32) + 1,
+                        boundary_node_count: 3,
+                    },
+                ],
+            }
+        );
+        assert_eq!(report.total_node_weight(), 40);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_raw_too_short() {
+        PartitionMetricsReport::from_raw(&[0; 7], 1);
+    }
+
+    fn report_with_node_weights(node_weights: &[u32]) -> PartitionMetricsReport {
+        PartitionMetricsReport {
+            parts: node_weights
+                .iter()
+                .map(|node_weight| PartMetrics {
+                    node_weight: *node_weight,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_imbalance() {
+        assert_eq!(report_with_node_weights(&[10, 10, 10, 10]).imbalance(), 0.0);
+        assert_eq!(report_with_node_weights(&[15, 5]).imbalance(), 0.5);
+        assert_eq!(report_with_node_weights(&[0, 0, 30]).imbalance(), 2.0);
+    }
+
+    #[test]
+    fn test_imbalance_empty() {
+        assert_eq!(report_with_node_weights(&[]).imbalance(), 0.0);
+        assert_eq!(report_with_node_weights(&[0, 0]).imbalance(), 0.0);
+    }
+}
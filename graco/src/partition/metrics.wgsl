@@ -0,0 +1,14 @@
+#pragma once
+
+// Layout of the raw partition metrics buffer. Values that are sums of edge weights are stored as 64-bit values that
+// are split over 2 entries (low word first). The global metrics are followed by `METRICS_PART_ENTRY_SIZE` entries for
+// each part. Must be kept in sync with `metrics.rs`.
+const METRICS_EDGE_CUT = 0u;
+const METRICS_COMMUNICATION_VOLUME = 2u;
+const METRICS_BOUNDARY_NODE_COUNT = 3u;
+const METRICS_PARTS_OFFSET = 4u;
+
+const METRICS_PART_NODE_WEIGHT = 0u;
+const METRICS_PART_EDGE_WEIGHT = 1u;
+const METRICS_PART_BOUNDARY_NODE_COUNT = 3u;
+const METRICS_PART_ENTRY_SIZE = 4u;
@@ -0,0 +1,26 @@
+mod apply_moves;
+mod compute_moves;
+mod compute_part_weights;
+mod compute_partition_metrics;
+mod filter_moves;
+mod project_partition;
+
+pub mod initial;
+
+mod metrics;
+pub use self::metrics::{partition_metrics_len, PartMetrics, PartitionMetricsReport};
+
+mod partition_graph;
+pub use self::partition_graph::{
+    PartitionConfig, PartitionGraph, PartitionInput, PartitionSummary,
+};
+
+mod partition_metrics;
+pub use self::partition_metrics::{
+    PartitionMetrics, PartitionMetricsCounts, PartitionMetricsInput,
+};
+
+mod refine_partition;
+pub use self::refine_partition::{
+    RefinePartition, RefinePartitionConfig, RefinePartitionCounts, RefinePartitionInput,
+};
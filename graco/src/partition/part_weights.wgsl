@@ -0,0 +1,5 @@
+#pragma once
+
+// Layout of the part weights buffer used during refinement: the total node weight, followed by the weight of each part.
+const PART_WEIGHTS_TOTAL = 0u;
+const PART_WEIGHTS_OFFSET = 1u;
@@ -0,0 +1,296 @@
+use std::error::Error;
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::{Buffer, Storage};
+use empa::device::Device;
+
+use crate::context::Context;
+use crate::hierarchy::{BuildHierarchy, HierarchyConfig, HierarchyInput};
+use crate::matching::MatchPairsByEdgeWeightConfig;
+use crate::partition::initial::{initial_partition, InitialPartitionConfig, InitialPartitionInput};
+use crate::partition::project_partition::{ProjectPartition, ProjectPartitionResources};
+use crate::partition::{
+    partition_metrics_len, PartitionMetrics, PartitionMetricsInput, PartitionMetricsReport,
+    RefinePartition, RefinePartitionConfig, RefinePartitionCounts, RefinePartitionInput,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PartitionConfig {
+    /// The number of parts to partition the graph into.
+    pub part_count: u32,
+    /// The balance tolerance: the weight of each part may exceed the average part weight (the
+    /// total node weight divided by the `part_count`) by at most this fraction.
+    pub imbalance: f32,
+    /// Coarsening stops once a level has no more than this many nodes.
+    pub coarsest_node_count: u32,
+    /// The maximum number of levels to coarsen.
+    pub max_levels: usize,
+    /// The number of refinement rounds that are run on each level during uncoarsening.
+    pub refinement_rounds: usize,
+    /// The configuration of the matching that is used to coarsen the graph.
+    pub matching: MatchPairsByEdgeWeightConfig,
+}
+
+impl Default for PartitionConfig {
+    fn default() -> Self {
+        PartitionConfig {
+            part_count: 2,
+            imbalance: 0.03,
+            coarsest_node_count: 256,
+            max_levels: 32,
+            refinement_rounds: 8,
+            matching: Default::default(),
+        }
+    }
+}
+
+pub struct PartitionInput<'a, U0, U1, U2> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    /// The weight of each node, or `None` if all nodes have a weight of `1`.
+    pub nodes_weight: Option<Storage<'a, [u32]>>,
+}
+
+/// The quality of a partition computed by [PartitionGraph::partition].
+#[derive(Clone, PartialEq, Debug)]
+pub struct PartitionSummary {
+    /// The sum of the weights of the edges that connect nodes in different parts.
+    pub edge_cut: u64,
+    /// The sum of the weights of the nodes in each part.
+    pub part_weights: Vec<u32>,
+}
+
+/// Partitions a graph into a given number of parts of roughly equal node weight, such that the
+/// total weight of the edges between different parts is small.
+///
+/// This is a multilevel partitioner in the style of METIS: it repeatedly matches and coarsens the
+/// graph (see [MatchPairsByEdgeWeight] and [CoarsenGraph]) until the graph is small, computes an
+/// initial partition for the coarsest level on the CPU (see [recursive_bisection]), and then
+/// projects the partition back onto the finer levels one level at a time, refining it on the GPU at
+/// each level (see [RefinePartition]).
+///
+/// The graph must be undirected (each edge is stored once for each of the nodes it connects) and
+/// is described by buffers that are exactly sized to its node count and edge ref count.
+///
+/// [MatchPairsByEdgeWeight]: crate::matching::MatchPairsByEdgeWeight
+/// [CoarsenGraph]: crate::CoarsenGraph
+/// [recursive_bisection]: crate::partition::initial::recursive_bisection
+pub struct PartitionGraph {
+    device: Device,
+    config: PartitionConfig,
+    build_hierarchy: BuildHierarchy,
+    project_partition: Rc<ProjectPartition>,
+    partition_metrics: PartitionMetrics,
+    refine_partition: RefinePartition,
+}
+
+impl PartitionGraph {
+    pub async fn init(device: Device, config: PartitionConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](PartitionGraph::init), but obtains the sub-pipelines from the given
+    /// `context`, so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context, config: PartitionConfig) -> Self {
+        assert!(
+            config.part_count > 0,
+            "`part_count` must be greater than `0`"
+        );
+
+        let device = context.device().clone();
+
+        let (build_hierarchy, project_partition, partition_metrics, refine_partition) = join!(
+            BuildHierarchy::init_with_context(context, config.matching),
+            context.get_or_init_kernel(ProjectPartition::init),
+            PartitionMetrics::init_with_context(context),
+            RefinePartition::init_with_context(
+                context,
+                RefinePartitionConfig {
+                    part_count: config.part_count,
+                    imbalance: config.imbalance,
+                    rounds: config.refinement_rounds,
+                    ..Default::default()
+                }
+            ),
+        )
+        .await;
+
+        PartitionGraph {
+            device,
+            config,
+            build_hierarchy,
+            project_partition,
+            partition_metrics,
+            refine_partition,
+        }
+    }
+
+    /// Partitions the graph described by the `input` and writes the part id for each node to
+    /// `nodes_part`.
+    ///
+    /// Submits its own command buffers, as the number of levels in the hierarchy is only known
+    /// after reading back the node count of each coarse level. Returns the edge cut and the part
+    /// weights of the final partition.
+    pub async fn partition<U0, U1, U2, U3>(
+        &mut self,
+        input: PartitionInput<'_, U0, U1, U2>,
+        nodes_part: buffer::View<'_, [u32], U3>,
+    ) -> Result<PartitionSummary, Box<dyn Error>>
+    where
+        U0: buffer::CopySrc,
+        U1: buffer::CopySrc,
+        U2: buffer::CopySrc,
+        U3: buffer::CopyDst,
+    {
+        let PartitionInput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            nodes_weight,
+        } = input;
+
+        assert_eq!(
+            nodes_part.len(),
+            nodes_edge_offset.len(),
+            "`nodes_part` must have the same length as `nodes_edge_offset`"
+        );
+
+        let levels = self
+            .build_hierarchy
+            .build(
+                HierarchyInput {
+                    nodes_edge_offset,
+                    nodes_edges,
+                    nodes_edge_weights,
+                    nodes_weight,
+                },
+                HierarchyConfig {
+                    coarsest_node_count: self.config.coarsest_node_count,
+                    max_levels: self.config.max_levels,
+                },
+            )
+            .await?;
+
+        // The part ids for each level; unlike the buffers of the hierarchy levels, these are
+        // exactly sized to the level's node count.
+        let levels_part: Vec<Buffer<[u32], _>> = levels
+            .iter()
+            .map(|level| {
+                self.device.create_slice_buffer_zeroed(
+                    level.node_count as usize,
+                    buffer::Usages::storage_binding()
+                        .and_copy_dst()
+                        .and_copy_src(),
+                )
+            })
+            .collect();
+
+        // Compute the initial partition for the coarsest level.
+        let coarsest_level = levels.last().unwrap();
+        let initial_part = initial_partition(
+            &self.device,
+            InitialPartitionInput {
+                nodes_edge_offset: coarsest_level.nodes_edge_offset.view(),
+                nodes_edges: coarsest_level.nodes_edges.view(),
+                nodes_edge_weights: coarsest_level.nodes_edge_weights.view(),
+                nodes_weight: coarsest_level.nodes_weight.view(),
+                node_count: coarsest_level.node_count,
+                edge_ref_count: coarsest_level.edge_ref_count,
+            },
+            &InitialPartitionConfig {
+                part_count: self.config.part_count,
+                imbalance: self.config.imbalance,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let mut encoder = self.device.create_command_encoder();
+
+        encoder = encoder
+            .copy_buffer_to_buffer_slice(initial_part.view(), levels_part.last().unwrap().view());
+
+        // Uncoarsen: project the partition from each level onto the next finer level and refine
+        // it.
+        for index in (0..levels.len()).rev() {
+            let level = &levels[index];
+
+            if let Some(coarse_level) = levels.get(index + 1) {
+                encoder = self.project_partition.encode(
+                    encoder,
+                    ProjectPartitionResources {
+                        fine_node_count: level.node_count_buffer.uniform(),
+                        fine_nodes_mapping: coarse_level.fine_nodes_mapping.storage(),
+                        coarse_nodes_part: levels_part[index + 1].storage(),
+                        fine_nodes_part: levels_part[index].storage(),
+                    },
+                    level.node_count,
+                );
+            }
+
+            encoder = self.refine_partition.encode(
+                encoder,
+                RefinePartitionInput {
+                    nodes_edge_offset: level.nodes_edge_offset.view(),
+                    nodes_edges: level.nodes_edges.view(),
+                    nodes_edge_weights: level.nodes_edge_weights.view(),
+                    nodes_weight: level.nodes_weight.view(),
+                    counts: Some(RefinePartitionCounts {
+                        node_count: level.node_count_buffer.uniform(),
+                        edge_ref_count: level.edge_ref_count_buffer.uniform(),
+                    }),
+                },
+                levels_part[index].view(),
+            );
+        }
+
+        // Compute the edge cut and part weights of the final partition.
+        let base_level = &levels[0];
+
+        let metrics = self.device.create_slice_buffer_zeroed(
+            partition_metrics_len(self.config.part_count),
+            buffer::Usages::storage_binding()
+                .and_copy_dst()
+                .and_copy_src(),
+        );
+
+        encoder = self.partition_metrics.encode(
+            encoder,
+            PartitionMetricsInput {
+                nodes_edge_offset: base_level.nodes_edge_offset.view(),
+                nodes_edges: base_level.nodes_edges.view(),
+                nodes_edge_weights: base_level.nodes_edge_weights.view(),
+                nodes_weight: base_level.nodes_weight.view(),
+                nodes_part: levels_part[0].view(),
+                counts: None,
+            },
+            metrics.view(),
+        );
+        encoder = encoder.copy_buffer_to_buffer_slice(levels_part[0].view(), nodes_part);
+
+        let metrics_readback = self
+            .device
+            .create_slice_buffer_zeroed(metrics.len(), buffer::Usages::copy_dst().and_map_read());
+
+        encoder = encoder.copy_buffer_to_buffer_slice(metrics.view(), metrics_readback.view());
+
+        self.device.queue().submit(encoder.finish());
+
+        metrics_readback.map_read().await?;
+
+        let report =
+            PartitionMetricsReport::from_raw(&metrics_readback.mapped(), self.config.part_count);
+
+        metrics_readback.unmap();
+
+        let summary = PartitionSummary {
+            edge_cut: report.edge_cut,
+            part_weights: report.parts.iter().map(|part| part.node_weight).collect(),
+        };
+
+        Ok(summary)
+    }
+}
@@ -0,0 +1,170 @@
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::{Buffer, Uniform};
+use empa::command::{CommandEncoder, DispatchWorkgroups};
+use empa::device::Device;
+use empa::type_flag::{O, X};
+
+use crate::coarsen_graph::generate_dispatches::{GenerateDispatches, GenerateDispatchesResources};
+use crate::context::Context;
+use crate::counts_fallback::FallbackCountsCache;
+use crate::partition::compute_partition_metrics::{
+    ComputePartitionMetrics, ComputePartitionMetricsResources,
+};
+
+pub struct PartitionMetricsCounts<'a> {
+    pub node_count: Uniform<'a, u32>,
+    pub edge_ref_count: Uniform<'a, u32>,
+}
+
+pub struct PartitionMetricsInput<'a, U0, U1, U2, U3, U4> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    pub nodes_weight: buffer::View<'a, [u32], U3>,
+    pub nodes_part: buffer::View<'a, [u32], U4>,
+    pub counts: Option<PartitionMetricsCounts<'a>>,
+}
+
+/// Computes quality metrics for a partition of a CSR graph that resides in device memory, without
+/// reading the partition back to the host.
+///
+/// Computes the edge cut, the communication volume, the number of boundary nodes and the node
+/// weight, edge weight and boundary node count of each part. The metrics are written to a raw
+/// `u32` slice buffer with a length of (at least) [partition_metrics_len] for the part count;
+/// read this buffer back to the host and decode it with [PartitionMetricsReport::from_raw].
+///
+/// The graph must be undirected (each edge is stored once for each of the nodes it connects).
+///
+/// [partition_metrics_len]: crate::partition::partition_metrics_len
+/// [PartitionMetricsReport::from_raw]: crate::partition::PartitionMetricsReport::from_raw
+pub struct PartitionMetrics {
+    device: Device,
+    counts_cache: FallbackCountsCache,
+    generate_dispatches: Rc<GenerateDispatches>,
+    compute_partition_metrics: Rc<ComputePartitionMetrics>,
+    node_count_dispatch: Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+    edge_ref_count_dispatch:
+        Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+}
+
+impl PartitionMetrics {
+    pub async fn init(device: Device) -> Self {
+        Self::init_with_context(&Context::new(device)).await
+    }
+
+    /// Same as [init](PartitionMetrics::init), but obtains the sub-pipelines from the given
+    /// `context`, so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context) -> Self {
+        let device = context.device().clone();
+
+        let (generate_dispatches, compute_partition_metrics) = join!(
+            context.get_or_init_kernel(GenerateDispatches::init),
+            context.get_or_init_kernel(ComputePartitionMetrics::init),
+        )
+        .await;
+
+        let node_count_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
+        let edge_ref_count_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
+
+        let counts_cache = FallbackCountsCache::new(device.clone());
+
+        PartitionMetrics {
+            device,
+            counts_cache,
+            generate_dispatches,
+            compute_partition_metrics,
+            node_count_dispatch,
+            edge_ref_count_dispatch,
+        }
+    }
+
+    /// Encodes the computation of the metrics for the partition in the `input` into the `metrics`
+    /// buffer.
+    ///
+    /// The length of the `metrics` buffer determines the part count: every part id in the
+    /// partition must be less than the part count for which [partition_metrics_len] returns the
+    /// buffer's length.
+    ///
+    /// [partition_metrics_len]: crate::partition::partition_metrics_len
+    pub fn encode<U0, U1, U2, U3, U4, U5>(
+        &mut self,
+        mut encoder: CommandEncoder,
+        input: PartitionMetricsInput<U0, U1, U2, U3, U4>,
+        metrics: buffer::View<[u32], U5>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+        U4: buffer::StorageBinding,
+        U5: buffer::StorageBinding + buffer::CopyDst,
+    {
+        let PartitionMetricsInput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            nodes_weight,
+            nodes_part,
+            counts,
+        } = input;
+
+        let dispatch_indirect = counts.is_some();
+
+        if let Some(counts) = counts.as_ref() {
+            encoder = self.generate_dispatches.encode(
+                encoder,
+                GenerateDispatchesResources {
+                    node_count: counts.node_count.clone(),
+                    edge_ref_count: counts.edge_ref_count.clone(),
+                    node_count_dispatch: self.node_count_dispatch.storage(),
+                    edge_ref_count_dispatch: self.edge_ref_count_dispatch.storage(),
+                },
+            );
+        }
+
+        let fallback_node_count = nodes_edge_offset.len() as u32;
+        let fallback_edge_ref_count = nodes_edges.len() as u32;
+        let counts_fallback = self.counts_cache.get(
+            counts.map(|c| (c.node_count, c.edge_ref_count)),
+            (fallback_node_count, fallback_edge_ref_count),
+        );
+
+        encoder = encoder.clear_buffer_slice(metrics);
+        encoder = self.compute_partition_metrics.encode(
+            encoder,
+            ComputePartitionMetricsResources {
+                node_count: counts_fallback.node_count(),
+                edge_ref_count: counts_fallback.edge_ref_count(),
+                nodes_edge_offset: nodes_edge_offset.storage(),
+                nodes_edges: nodes_edges.storage(),
+                nodes_edge_weights: nodes_edge_weights.storage(),
+                nodes_weight: nodes_weight.storage(),
+                nodes_part: nodes_part.storage(),
+                metrics: metrics.storage(),
+            },
+            dispatch_indirect,
+            self.node_count_dispatch.view(),
+            fallback_node_count,
+        );
+
+        encoder
+    }
+}
@@ -0,0 +1,332 @@
+use std::future::join;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use empa::buffer;
+use empa::buffer::{Buffer, Uniform};
+use empa::command::{CommandEncoder, DispatchWorkgroups};
+use empa::device::Device;
+use empa::type_flag::{O, X};
+
+use crate::coarsen_graph::generate_dispatches::{GenerateDispatches, GenerateDispatchesResources};
+use crate::context::Context;
+use crate::counts_fallback::FallbackCountsCache;
+use crate::partition::apply_moves::{ApplyMoves, ApplyMovesResources, IMBALANCE_SCALE};
+use crate::partition::compute_moves::{ComputeMoves, ComputeMovesResources};
+use crate::partition::compute_part_weights::{ComputePartWeights, ComputePartWeightsResources};
+use crate::partition::filter_moves::{FilterMoves, FilterMovesResources};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RefinePartitionConfig {
+    /// The number of parts in the partitions that are to be refined.
+    pub part_count: u32,
+    /// The balance tolerance: a move is rejected if it would cause the weight of the target part to
+    /// exceed the average part weight (the total node weight divided by the `part_count`) by more
+    /// than this fraction.
+    pub imbalance: f32,
+    /// The number of refinement rounds. Each round moves all nodes for which a beneficial move was
+    /// found concurrently.
+    pub rounds: usize,
+    /// Determines how negative the gain of a move may be for the move to still be considered,
+    /// relative to the node's connectivity to its current part.
+    ///
+    /// A move with a negative gain is only applied if, after resolving conflicts with
+    /// neighboring moves, it no longer has a negative gain. Higher values let the refinement
+    /// explore more aggressively; `0.0` only considers moves with a positive gain.
+    pub negative_gain_factor: f32,
+}
+
+impl Default for RefinePartitionConfig {
+    fn default() -> Self {
+        RefinePartitionConfig {
+            part_count: 2,
+            imbalance: 0.03,
+            rounds: 8,
+            negative_gain_factor: 0.25,
+        }
+    }
+}
+
+pub struct RefinePartitionCounts<'a> {
+    pub node_count: Uniform<'a, u32>,
+    pub edge_ref_count: Uniform<'a, u32>,
+}
+
+pub struct RefinePartitionInput<'a, U0, U1, U2, U3> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    pub nodes_weight: buffer::View<'a, [u32], U3>,
+    pub counts: Option<RefinePartitionCounts<'a>>,
+}
+
+/// Improves a partition of a graph by moving boundary nodes to the neighboring part with the best
+/// gain, while respecting a balance constraint.
+///
+/// The refinement is a size-constrained label propagation in the style of the Jet refiner. Each
+/// round proceeds in 3 steps:
+///
+/// 1. For every node, find the neighboring part that it is most strongly connected to, and compute
+///    the gain (the reduction in edge cut) of moving it there.
+/// 2. Resolve conflicts between concurrent moves: recompute the gain of each move as if all
+///    neighboring moves with a higher gain had already been applied, and discard the moves for
+///    which the recomputed gain is not positive (moves without gain are discarded as well, as they
+///    would only shuffle nodes between parts).
+/// 3. Apply the remaining moves for which the target part has capacity.
+///
+/// The graph must be undirected (each edge is stored once for each of the nodes it connects).
+/// The partition is given as a part id for each node and is updated in place.
+pub struct RefinePartition {
+    device: Device,
+    config: RefinePartitionConfig,
+    counts_cache: FallbackCountsCache,
+    generate_dispatches: Rc<GenerateDispatches>,
+    compute_part_weights: Rc<ComputePartWeights>,
+    compute_moves: Rc<ComputeMoves>,
+    filter_moves: Rc<FilterMoves>,
+    apply_moves: Rc<ApplyMoves>,
+    node_count_dispatch: Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+    edge_ref_count_dispatch:
+        Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+    part_count: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+    imbalance: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+    negative_gain_factor: Buffer<f32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+    part_weights: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, X, O, O, O>>,
+    workspace: Mutex<Option<Arc<Workspace>>>,
+}
+
+impl RefinePartition {
+    pub async fn init(device: Device, config: RefinePartitionConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](RefinePartition::init), but obtains the sub-pipelines from the given
+    /// `context`, so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context, config: RefinePartitionConfig) -> Self {
+        assert!(
+            config.part_count > 0,
+            "`part_count` must be greater than `0`"
+        );
+
+        let device = context.device().clone();
+
+        let (generate_dispatches, compute_part_weights, compute_moves, filter_moves, apply_moves) =
+            join!(
+                context.get_or_init_kernel(GenerateDispatches::init),
+                context.get_or_init_kernel(ComputePartWeights::init),
+                context.get_or_init_kernel(ComputeMoves::init),
+                context.get_or_init_kernel(FilterMoves::init),
+                context.get_or_init_kernel(ApplyMoves::init),
+            )
+            .await;
+
+        let node_count_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
+        let edge_ref_count_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
+
+        let part_count = device.create_buffer(config.part_count, buffer::Usages::uniform_binding());
+        let imbalance = device.create_buffer(
+            (config.imbalance * IMBALANCE_SCALE).round() as u32,
+            buffer::Usages::uniform_binding(),
+        );
+        let negative_gain_factor = device.create_buffer(
+            config.negative_gain_factor,
+            buffer::Usages::uniform_binding(),
+        );
+
+        // The total weight, followed by the weight of each part; see `part_weights.wgsl`.
+        let part_weights = device.create_slice_buffer_zeroed(
+            config.part_count as usize + 1,
+            buffer::Usages::storage_binding().and_copy_dst(),
+        );
+
+        let counts_cache = FallbackCountsCache::new(device.clone());
+
+        RefinePartition {
+            device,
+            config,
+            counts_cache,
+            generate_dispatches,
+            compute_part_weights,
+            compute_moves,
+            filter_moves,
+            apply_moves,
+            node_count_dispatch,
+            edge_ref_count_dispatch,
+            part_count,
+            imbalance,
+            negative_gain_factor,
+            part_weights,
+            workspace: Mutex::new(None),
+        }
+    }
+
+    /// Encodes the refinement of the partition in `nodes_part` for the graph described by the
+    /// `input`.
+    ///
+    /// Every part id in `nodes_part` must be less than the configured
+    /// [part_count](RefinePartitionConfig::part_count). If the input partition violates the balance
+    /// constraint, then the refinement will not move nodes into the overweight parts, but will
+    /// also not attempt to move nodes out of them if that does not improve the edge cut.
+    pub fn encode<U0, U1, U2, U3, U4>(
+        &self,
+        mut encoder: CommandEncoder,
+        input: RefinePartitionInput<U0, U1, U2, U3>,
+        nodes_part: buffer::View<[u32], U4>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+        U4: buffer::StorageBinding,
+    {
+        let RefinePartitionInput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            nodes_weight,
+            counts,
+        } = input;
+
+        let dispatch_indirect = counts.is_some();
+
+        let workspace = self.reserve_workspace(nodes_edge_offset.len());
+
+        if let Some(counts) = counts.as_ref() {
+            encoder = self.generate_dispatches.encode(
+                encoder,
+                GenerateDispatchesResources {
+                    node_count: counts.node_count.clone(),
+                    edge_ref_count: counts.edge_ref_count.clone(),
+                    node_count_dispatch: self.node_count_dispatch.storage(),
+                    edge_ref_count_dispatch: self.edge_ref_count_dispatch.storage(),
+                },
+            );
+        }
+
+        let fallback_node_count = nodes_edge_offset.len() as u32;
+        let fallback_edge_ref_count = nodes_edges.len() as u32;
+        let counts_fallback = self.counts_cache.get(
+            counts.map(|c| (c.node_count, c.edge_ref_count)),
+            (fallback_node_count, fallback_edge_ref_count),
+        );
+
+        encoder = encoder.clear_buffer_slice(self.part_weights.view());
+        encoder = self.compute_part_weights.encode(
+            encoder,
+            ComputePartWeightsResources {
+                node_count: counts_fallback.node_count(),
+                nodes_weight: nodes_weight.storage(),
+                nodes_part: nodes_part.storage(),
+                part_weights: self.part_weights.storage(),
+            },
+            dispatch_indirect,
+            self.node_count_dispatch.view(),
+            fallback_node_count,
+        );
+
+        // The part weights are kept up to date by the `apply_moves` kernel, so we only need to
+        // compute them once.
+        for _ in 0..self.config.rounds {
+            encoder = self.compute_moves.encode(
+                encoder,
+                ComputeMovesResources {
+                    node_count: counts_fallback.node_count(),
+                    edge_ref_count: counts_fallback.edge_ref_count(),
+                    negative_gain_factor: self.negative_gain_factor.uniform(),
+                    nodes_edge_offset: nodes_edge_offset.storage(),
+                    nodes_edges: nodes_edges.storage(),
+                    nodes_edge_weights: nodes_edge_weights.storage(),
+                    nodes_part: nodes_part.storage(),
+                    nodes_move_part: workspace.nodes_move_part.storage(),
+                    nodes_move_gain: workspace.nodes_move_gain.storage(),
+                },
+                dispatch_indirect,
+                self.node_count_dispatch.view(),
+                fallback_node_count,
+            );
+            encoder = self.filter_moves.encode(
+                encoder,
+                FilterMovesResources {
+                    node_count: counts_fallback.node_count(),
+                    edge_ref_count: counts_fallback.edge_ref_count(),
+                    nodes_edge_offset: nodes_edge_offset.storage(),
+                    nodes_edges: nodes_edges.storage(),
+                    nodes_edge_weights: nodes_edge_weights.storage(),
+                    nodes_part: nodes_part.storage(),
+                    nodes_move_part: workspace.nodes_move_part.storage(),
+                    nodes_move_gain: workspace.nodes_move_gain.storage(),
+                    nodes_accepted_part: workspace.nodes_accepted_part.storage(),
+                },
+                dispatch_indirect,
+                self.node_count_dispatch.view(),
+                fallback_node_count,
+            );
+            encoder = self.apply_moves.encode(
+                encoder,
+                ApplyMovesResources {
+                    node_count: counts_fallback.node_count(),
+                    part_count: self.part_count.uniform(),
+                    imbalance: self.imbalance.uniform(),
+                    nodes_weight: nodes_weight.storage(),
+                    nodes_accepted_part: workspace.nodes_accepted_part.storage(),
+                    nodes_part: nodes_part.storage(),
+                    part_weights: self.part_weights.storage(),
+                },
+                dispatch_indirect,
+                self.node_count_dispatch.view(),
+                fallback_node_count,
+            );
+        }
+
+        encoder
+    }
+
+    fn reserve_workspace(&self, node_capacity: usize) -> Arc<Workspace> {
+        let mut workspace = self.workspace.lock().unwrap();
+
+        let sufficient = workspace
+            .as_ref()
+            .map(|workspace| workspace.nodes_move_part.len() >= node_capacity)
+            .unwrap_or(false);
+
+        if !sufficient {
+            *workspace = Some(Arc::new(Workspace::new(&self.device, node_capacity)));
+        }
+
+        workspace.as_ref().unwrap().clone()
+    }
+}
+
+struct Workspace {
+    nodes_move_part: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    nodes_move_gain: Buffer<[i32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+    nodes_accepted_part: Buffer<[u32], buffer::Usages<O, O, X, O, O, O, O, O, O, O>>,
+}
+
+impl Workspace {
+    fn new(device: &Device, node_capacity: usize) -> Self {
+        Workspace {
+            nodes_move_part: device
+                .create_slice_buffer_zeroed(node_capacity, buffer::Usages::storage_binding()),
+            nodes_move_gain: device
+                .create_slice_buffer_zeroed(node_capacity, buffer::Usages::storage_binding()),
+            nodes_accepted_part: device
+                .create_slice_buffer_zeroed(node_capacity, buffer::Usages::storage_binding()),
+        }
+    }
+}
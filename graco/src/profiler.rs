@@ -0,0 +1,231 @@
+use std::error::Error;
+use std::fmt;
+
+use empa::buffer;
+use empa::buffer::Buffer;
+use empa::command::CommandEncoder;
+use empa::device::Device;
+use empa::query::TimestampQuerySet;
+use empa::type_flag::{O, X};
+
+/// Records GPU timestamps between the named stages of a pipeline.
+///
+/// Pass a profiler to one of the `encode_profiled` methods (e.g.
+/// [CoarsenGraph::encode_profiled](crate::CoarsenGraph::encode_profiled)) to have the pipeline
+/// write a timestamp after each of its stages. Multiple pipelines may be profiled with the same
+/// profiler. After all stages have been encoded, call [resolve](Profiler::resolve) on an encoder
+/// to copy the timestamps into a readable buffer, submit the encoder, and then call
+/// [read](Profiler::read) to obtain the per-stage breakdown.
+///
+/// Requires a device that was created with the
+/// [TimestampQuery](empa::adapter::Feature::TimestampQuery) feature.
+pub struct Profiler {
+    query_set: TimestampQuerySet,
+    capacity: usize,
+    // For each timestamp, the label of the stage that ends at that timestamp, or `None` if the
+    // timestamp starts a new sequence of stages.
+    labels: Vec<Option<&'static str>>,
+    timestamps: Buffer<[u64], buffer::Usages<X, O, O, O, O, O, O, X, O, O>>,
+    timestamps_readback: Buffer<[u64], buffer::Usages<O, O, O, O, O, O, X, O, O, X>>,
+}
+
+impl Profiler {
+    /// Creates a new profiler that can record up to `capacity` timestamps before it needs to be
+    /// [cleared](Profiler::clear).
+    ///
+    /// Each profiled `encode` call uses 1 timestamp to mark its start, plus 1 timestamp for each
+    /// of its stages.
+    pub fn new(device: &Device, capacity: usize) -> Self {
+        let query_set = device.create_timestamp_query_set(capacity as u32);
+        let timestamps = device
+            .create_slice_buffer_zeroed(capacity, buffer::Usages::query_resolve().and_copy_src());
+        let timestamps_readback =
+            device.create_slice_buffer_zeroed(capacity, buffer::Usages::copy_dst().and_map_read());
+
+        Profiler {
+            query_set,
+            capacity,
+            labels: Vec::with_capacity(capacity),
+            timestamps,
+            timestamps_readback,
+        }
+    }
+
+    /// Starts a new sequence of stages.
+    ///
+    /// Any GPU work encoded between the previous timestamp and this timestamp will not be
+    /// attributed to any stage.
+    pub fn start(&mut self, encoder: CommandEncoder) -> CommandEncoder {
+        self.write_timestamp(encoder, None)
+    }
+
+    /// Marks the end of the stage labeled `label`, which started at the previous timestamp.
+    ///
+    /// Panics if there was no previous timestamp.
+    pub fn stage(&mut self, encoder: CommandEncoder, label: &'static str) -> CommandEncoder {
+        assert!(
+            !self.labels.is_empty(),
+            "a stage must be preceded by a call to `start`"
+        );
+
+        self.write_timestamp(encoder, Some(label))
+    }
+
+    fn write_timestamp(
+        &mut self,
+        encoder: CommandEncoder,
+        label: Option<&'static str>,
+    ) -> CommandEncoder {
+        let index = self.labels.len();
+
+        assert!(
+            index < self.capacity,
+            "profiler capacity of `{}` timestamps exceeded",
+            self.capacity
+        );
+
+        self.labels.push(label);
+
+        encoder.write_timestamp(&self.query_set, index as u32)
+    }
+
+    /// Encodes the commands that make the recorded timestamps available to [read](Profiler::read).
+    pub fn resolve(&self, encoder: CommandEncoder) -> CommandEncoder {
+        let encoder =
+            encoder.resolve_timestamp_query_set(&self.query_set, 0, self.timestamps.view());
+
+        encoder.copy_buffer_to_buffer_slice(self.timestamps.view(), self.timestamps_readback.view())
+    }
+
+    /// Reads back the timestamps and returns the per-stage breakdown.
+    ///
+    /// The commands encoded by [resolve](Profiler::resolve) must have been submitted.
+    pub async fn read(&self) -> Result<Profile, Box<dyn Error>> {
+        self.timestamps_readback.map_read().await?;
+
+        let mut stages = Vec::new();
+
+        {
+            let timestamps = self.timestamps_readback.mapped();
+
+            for (index, label) in self.labels.iter().enumerate() {
+                if let Some(label) = *label {
+                    // Timestamps are not guaranteed to be monotonic on all platforms.
+                    let duration_ns = timestamps[index].saturating_sub(timestamps[index - 1]);
+
+                    stages.push(ProfileStage { label, duration_ns });
+                }
+            }
+        }
+
+        self.timestamps_readback.unmap();
+
+        Ok(Profile { stages })
+    }
+
+    /// Discards all recorded timestamps, so that the profiler may be reused.
+    pub fn clear(&mut self) {
+        self.labels.clear();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ProfileStage {
+    pub label: &'static str,
+    pub duration_ns: u64,
+}
+
+/// The per-stage breakdown produced by a [Profiler].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Profile {
+    stages: Vec<ProfileStage>,
+}
+
+impl Profile {
+    /// The stages in the order in which they were recorded.
+    pub fn stages(&self) -> &[ProfileStage] {
+        &self.stages
+    }
+
+    /// The total duration of all stages, in nanoseconds.
+    pub fn total_ns(&self) -> u64 {
+        self.stages.iter().map(|stage| stage.duration_ns).sum()
+    }
+
+    /// The total duration for each distinct label (e.g. a stage that runs once per round of an
+    /// iterative algorithm), in the order in which each label was first recorded.
+    pub fn totals_by_label(&self) -> Vec<ProfileStage> {
+        let mut totals: Vec<ProfileStage> = Vec::new();
+
+        for stage in &self.stages {
+            if let Some(total) = totals.iter_mut().find(|total| total.label == stage.label) {
+                total.duration_ns += stage.duration_ns;
+            } else {
+                totals.push(*stage);
+            }
+        }
+
+        totals
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let totals = self.totals_by_label();
+        let total_ns = self.total_ns();
+        let label_width = totals
+            .iter()
+            .map(|stage| stage.label.len())
+            .max()
+            .unwrap_or(0);
+
+        for stage in &totals {
+            let percentage = if total_ns > 0 {
+                stage.duration_ns as f64 / total_ns as f64 * 100.0
+            } else {
+                0.0
+            };
+
+            writeln!(
+                f,
+                "{:<label_width$}  {:>12} ns  {:>5.1}%",
+                stage.label,
+                stage.duration_ns,
+                percentage,
+                label_width = label_width
+            )?;
+        }
+
+        write!(
+            f,
+            "{:<label_width$}  {:>12} ns",
+            "total",
+            total_ns,
+            label_width = label_width
+        )
+    }
+}
+
+/// Helper for pipelines that accept an optional profiler.
+pub(crate) fn profile_stage(
+    profiler: &mut Option<&mut Profiler>,
+    encoder: CommandEncoder,
+    label: &'static str,
+) -> CommandEncoder {
+    if let Some(profiler) = profiler {
+        profiler.stage(encoder, label)
+    } else {
+        encoder
+    }
+}
+
+pub(crate) fn profile_start(
+    profiler: &mut Option<&mut Profiler>,
+    encoder: CommandEncoder,
+) -> CommandEncoder {
+    if let Some(profiler) = profiler {
+        profiler.start(encoder)
+    } else {
+        encoder
+    }
+}
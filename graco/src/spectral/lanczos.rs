@@ -0,0 +1,413 @@
+/// The weighted Laplacian `L = D - W` of an undirected graph, where `W` holds the edge weights and
+/// `D` is the diagonal matrix of node degrees (the summed weights of each node's edges).
+pub(crate) struct Laplacian<'a> {
+    nodes_edge_offset: &'a [u32],
+    nodes_edges: &'a [u32],
+    nodes_edge_weights: &'a [u32],
+    nodes_degree: Vec<f64>,
+}
+
+impl<'a> Laplacian<'a> {
+    pub(crate) fn new(
+        nodes_edge_offset: &'a [u32],
+        nodes_edges: &'a [u32],
+        nodes_edge_weights: &'a [u32],
+    ) -> Self {
+        let mut laplacian = Laplacian {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            nodes_degree: Vec::with_capacity(nodes_edge_offset.len()),
+        };
+
+        for node in 0..nodes_edge_offset.len() {
+            let degree = laplacian
+                .edge_range(node)
+                .map(|i| nodes_edge_weights[i] as f64)
+                .sum();
+
+            laplacian.nodes_degree.push(degree);
+        }
+
+        laplacian
+    }
+
+    pub(crate) fn node_count(&self) -> usize {
+        self.nodes_edge_offset.len()
+    }
+
+    fn edge_range(&self, node: usize) -> std::ops::Range<usize> {
+        let start = self.nodes_edge_offset[node] as usize;
+        let end = self
+            .nodes_edge_offset
+            .get(node + 1)
+            .map(|offset| *offset as usize)
+            .unwrap_or(self.nodes_edges.len());
+
+        start..end
+    }
+
+    /// Computes `L x` and stores the result in `y`.
+    fn apply(&self, x: &[f64], y: &mut [f64]) {
+        for (node, y) in y.iter_mut().enumerate() {
+            let mut value = self.nodes_degree[node] * x[node];
+
+            for i in self.edge_range(node) {
+                value -= self.nodes_edge_weights[i] as f64 * x[self.nodes_edges[i] as usize];
+            }
+
+            *y = value;
+        }
+    }
+}
+
+/// An eigenvalue and its (unit length) eigenvector.
+pub(crate) struct EigenPair {
+    pub(crate) value: f64,
+    pub(crate) vector: Vec<f64>,
+}
+
+/// Approximates the `count` smallest non-trivial eigenpairs of the `laplacian` with the Lanczos
+/// method, using at most `iterations` Lanczos steps.
+///
+/// The constant vector is always an eigenvector of a Laplacian with an eigenvalue of `0`; it is
+/// deflated by keeping the Krylov basis orthogonal to it. The basis is fully reorthogonalized at
+/// each step, which is affordable for the small graphs this is meant for and avoids spurious
+/// copies of converged eigenvalues. If fewer than `count` eigenpairs can be found (because the
+/// graph has too few nodes), the remaining eigenvectors are zero.
+pub(crate) fn smallest_eigenpairs(
+    laplacian: &Laplacian,
+    count: usize,
+    iterations: usize,
+    rng: &mut oorandom::Rand32,
+) -> Vec<EigenPair> {
+    let n = laplacian.node_count();
+    let constant = 1.0 / (n as f64).sqrt();
+
+    let deflate = |v: &mut [f64]| {
+        let projection: f64 = v.iter().sum::<f64>() * constant;
+
+        v.iter_mut().for_each(|x| *x -= projection * constant);
+    };
+
+    let mut basis: Vec<Vec<f64>> = Vec::new();
+    let mut alpha: Vec<f64> = Vec::new();
+    let mut beta: Vec<f64> = Vec::new();
+
+    let mut q: Vec<f64> = (0..n).map(|_| rng.rand_float() as f64 - 0.5).collect();
+
+    deflate(&mut q);
+
+    let mut norm = dot(&q, &q).sqrt();
+
+    // The Krylov space can not be larger than the space orthogonal to the constant vector.
+    let iterations = iterations.min(n.saturating_sub(1));
+
+    while basis.len() < iterations && norm > f64::EPSILON {
+        q.iter_mut().for_each(|x| *x /= norm);
+
+        let mut w = vec![0.0; n];
+
+        laplacian.apply(&q, &mut w);
+
+        let a = dot(&w, &q);
+
+        alpha.push(a);
+        basis.push(q);
+
+        // Full reorthogonalization against all basis vectors (which subsumes the three-term
+        // recurrence) and the constant vector; repeated twice for numerical stability.
+        for _ in 0..2 {
+            for b in &basis {
+                let projection = dot(&w, b);
+
+                w.iter_mut().zip(b).for_each(|(x, y)| *x -= projection * y);
+            }
+
+            deflate(&mut w);
+        }
+
+        norm = dot(&w, &w).sqrt();
+
+        // An invariant subspace was found if the norm (relative to the Rayleigh quotient) vanishes;
+        // the Lanczos process then ends.
+        if norm <= 1e-10 * a.abs().max(1.0) {
+            break;
+        }
+
+        beta.push(norm);
+        q = w;
+    }
+
+    let k = basis.len();
+
+    // The eigenpairs of the tridiagonal matrix `T` give the Ritz values and the coordinates of the
+    // Ritz vectors in the Krylov basis.
+    let mut tridiagonal = vec![0.0; k * k];
+
+    for (i, a) in alpha.iter().enumerate() {
+        tridiagonal[i * k + i] = *a;
+    }
+
+    for (i, b) in beta.iter().take(k.saturating_sub(1)).enumerate() {
+        tridiagonal[i * k + i + 1] = *b;
+        tridiagonal[(i + 1) * k + i] = *b;
+    }
+
+    let (values, vectors) = symmetric_eigen(tridiagonal, k);
+
+    let mut order: Vec<usize> = (0..k).collect();
+
+    order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+
+    (0..count)
+        .map(|i| {
+            let mut vector = vec![0.0; n];
+
+            let Some(&column) = order.get(i) else {
+                return EigenPair { value: 0.0, vector };
+            };
+
+            for (j, b) in basis.iter().enumerate() {
+                let coefficient = vectors[j * k + column];
+
+                vector
+                    .iter_mut()
+                    .zip(b)
+                    .for_each(|(x, y)| *x += coefficient * y);
+            }
+
+            // Eigenvectors are only defined up to their sign; make the sign deterministic by
+            // making the component with the largest magnitude positive.
+            let largest = vector
+                .iter()
+                .copied()
+                .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+                .unwrap_or(0.0);
+
+            if largest < 0.0 {
+                vector.iter_mut().for_each(|x| *x = -*x);
+            }
+
+            EigenPair {
+                value: values[column],
+                vector,
+            }
+        })
+        .collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Computes the eigenvalues and eigenvectors of the symmetric `n` by `n` matrix `a` (stored in
+/// row-major order) with the cyclic Jacobi method.
+///
+/// Returns the eigenvalues and a row-major matrix that stores the corresponding eigenvectors as its
+/// columns.
+fn symmetric_eigen(mut a: Vec<f64>, n: usize) -> (Vec<f64>, Vec<f64>) {
+    const MAX_SWEEPS: usize = 64;
+
+    let mut v = vec![0.0; n * n];
+
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+
+    let scale: f64 = a.iter().map(|x| x * x).sum();
+
+    for _ in 0..MAX_SWEEPS {
+        let mut off_diagonal = 0.0;
+
+        for p in 0..n {
+            for q in (p + 1)..n {
+                off_diagonal += a[p * n + q] * a[p * n + q];
+            }
+        }
+
+        if off_diagonal <= 1e-30 * scale {
+            break;
+        }
+
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let apq = a[p * n + q];
+
+                if apq == 0.0 {
+                    continue;
+                }
+
+                // Choose the rotation that zeroes `a[p][q]`, taking the smaller of the two possible
+                // rotation angles.
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for k in 0..n {
+                    let akp = a[k * n + p];
+                    let akq = a[k * n + q];
+
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+
+                for k in 0..n {
+                    let apk = a[p * n + k];
+                    let aqk = a[q * n + k];
+
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+
+                for k in 0..n {
+                    let vkp = v[k * n + p];
+                    let vkq = v[k * n + q];
+
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    let values = (0..n).map(|i| a[i * n + i]).collect();
+
+    (values, v)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    /// Returns the CSR representation of a path graph with `n` nodes and unit edge weights.
+    fn path_graph(n: usize) -> (Vec<u32>, Vec<u32>, Vec<u32>) {
+        let mut nodes_edge_offset = Vec::new();
+        let mut nodes_edges = Vec::new();
+
+        for node in 0..n {
+            nodes_edge_offset.push(nodes_edges.len() as u32);
+
+            if node > 0 {
+                nodes_edges.push(node as u32 - 1);
+            }
+
+            if node + 1 < n {
+                nodes_edges.push(node as u32 + 1);
+            }
+        }
+
+        let nodes_edge_weights = vec![1; nodes_edges.len()];
+
+        (nodes_edge_offset, nodes_edges, nodes_edge_weights)
+    }
+
+    #[test]
+    fn test_symmetric_eigen() {
+        let (values, vectors) =
+            symmetric_eigen(vec![2.0, 1.0, 0.0, 1.0, 2.0, 0.0, 0.0, 0.0, 5.0], 3);
+
+        let mut sorted = values.clone();
+
+        sorted.sort_by(f64::total_cmp);
+
+        for (value, expected) in sorted.iter().zip([1.0, 3.0, 5.0]) {
+            assert!((value - expected).abs() < 1e-12);
+        }
+
+        // The columns of `vectors` are orthonormal eigenvectors.
+        let a = [[2.0, 1.0, 0.0], [1.0, 2.0, 0.0], [0.0, 0.0, 5.0]];
+
+        for (column, value) in values.iter().enumerate() {
+            let v: Vec<f64> = (0..3).map(|row| vectors[row * 3 + column]).collect();
+
+            assert!((dot(&v, &v) - 1.0).abs() < 1e-12);
+
+            for row in 0..3 {
+                let av: f64 = (0..3).map(|k| a[row][k] * v[k]).sum();
+
+                assert!((av - value * v[row]).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_path_graph_spectrum() {
+        let n = 10;
+        let (nodes_edge_offset, nodes_edges, nodes_edge_weights) = path_graph(n);
+        let laplacian = Laplacian::new(&nodes_edge_offset, &nodes_edges, &nodes_edge_weights);
+        let mut rng = oorandom::Rand32::new(7);
+
+        let pairs = smallest_eigenpairs(&laplacian, 3, n, &mut rng);
+
+        assert_eq!(pairs.len(), 3);
+
+        for (k, pair) in pairs.iter().enumerate() {
+            // The eigenvalues of the path graph's Laplacian are `2 - 2 cos(k π / n)`; `k = 0` is
+            // the deflated constant vector.
+            let expected = 2.0 - 2.0 * (((k + 1) as f64) * PI / n as f64).cos();
+
+            assert!((pair.value - expected).abs() < 1e-8);
+            assert!((dot(&pair.vector, &pair.vector) - 1.0).abs() < 1e-8);
+            assert!(pair.vector.iter().sum::<f64>().abs() < 1e-8);
+
+            let mut lv = vec![0.0; n];
+
+            laplacian.apply(&pair.vector, &mut lv);
+
+            for (lv, v) in lv.iter().zip(&pair.vector) {
+                assert!((lv - pair.value * v).abs() < 1e-6);
+            }
+
+            // The sign is chosen so that the component with the largest magnitude is positive.
+            let largest = pair
+                .vector
+                .iter()
+                .copied()
+                .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+                .unwrap();
+
+            assert!(largest > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_more_pairs_than_nodes() {
+        let n = 3;
+        let (nodes_edge_offset, nodes_edges, nodes_edge_weights) = path_graph(n);
+        let laplacian = Laplacian::new(&nodes_edge_offset, &nodes_edges, &nodes_edge_weights);
+        let mut rng = oorandom::Rand32::new(7);
+
+        let pairs = smallest_eigenpairs(&laplacian, 4, 16, &mut rng);
+
+        assert_eq!(pairs.len(), 4);
+
+        // Only `n - 1` eigenpairs are orthogonal to the constant vector: the eigenvalues of the
+        // path graph with 3 nodes are `0`, `1` and `3`.
+        assert!((pairs[0].value - 1.0).abs() < 1e-8);
+        assert!((pairs[1].value - 3.0).abs() < 1e-8);
+
+        for pair in &pairs[2..] {
+            assert_eq!(pair.value, 0.0);
+            assert_eq!(pair.vector, vec![0.0; n]);
+        }
+    }
+
+    #[test]
+    fn test_single_node() {
+        let laplacian = Laplacian::new(&[0], &[], &[]);
+        let mut rng = oorandom::Rand32::new(7);
+
+        let pairs = smallest_eigenpairs(&laplacian, 2, 16, &mut rng);
+
+        assert_eq!(pairs.len(), 2);
+
+        for pair in &pairs {
+            assert_eq!(pair.value, 0.0);
+            assert_eq!(pair.vector, vec![0.0]);
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Multilevel spectral embedding of graphs.
+//!
+//! See [SpectralEmbedding] for an overview of the algorithm.
+
+mod lanczos;
+mod prolong_coordinates;
+mod smooth_coordinates;
+
+mod spectral_embedding;
+pub use self::spectral_embedding::{SpectralConfig, SpectralEmbedding, SpectralInput};
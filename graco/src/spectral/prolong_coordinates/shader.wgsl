@@ -0,0 +1,36 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> fine_node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> dimensions: u32;
+
+@group(0) @binding(2)
+var<storage, read> fine_nodes_mapping: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> coarse_nodes_coordinates: array<f32>;
+
+@group(0) @binding(4)
+var<storage, read_write> fine_nodes_coordinates: array<f32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= fine_node_count {
+        return;
+    }
+
+    // Piecewise constant prolongation: a fine node starts out with the coordinates of its coarse node.
+    let coarse_base = fine_nodes_mapping[index] * dimensions;
+    let fine_base = index * dimensions;
+
+    for (var i = 0u; i < dimensions; i += 1u) {
+        fine_nodes_coordinates[fine_base + i] = coarse_nodes_coordinates[coarse_base + i];
+    }
+}
@@ -0,0 +1,73 @@
+#include <src/dispatch.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<uniform> dimensions: u32;
+
+@group(0) @binding(3)
+var<uniform> smoothing_weight: f32;
+
+@group(0) @binding(4)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> nodes_edges: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> nodes_edge_weights: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read> nodes_coordinates: array<f32>;
+
+@group(0) @binding(8)
+var<storage, read_write> nodes_next_coordinates: array<f32>;
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    let edges_start = nodes_edge_offset[index];
+    var edges_end = edge_ref_count;
+
+    if index < node_count - 1 {
+        edges_end = nodes_edge_offset[index + 1];
+    }
+
+    var total_weight = 0.0;
+
+    for (var i = edges_start; i < edges_end; i += 1u) {
+        total_weight += f32(nodes_edge_weights[i]);
+    }
+
+    let base = index * dimensions;
+
+    for (var d = 0u; d < dimensions; d += 1u) {
+        var value = nodes_coordinates[base + d];
+
+        // Move the node towards the weighted average of its neighbors; isolated nodes are left unchanged.
+        if total_weight > 0.0 {
+            var average = 0.0;
+
+            for (var i = edges_start; i < edges_end; i += 1u) {
+                average += f32(nodes_edge_weights[i]) * nodes_coordinates[nodes_edges[i] * dimensions + d];
+            }
+
+            average /= total_weight;
+            value += smoothing_weight * (average - value);
+        }
+
+        nodes_next_coordinates[base + d] = value;
+    }
+}
@@ -0,0 +1,365 @@
+use std::error::Error;
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::Buffer;
+use empa::command::CommandEncoder;
+use empa::device::Device;
+use empa::type_flag::{O, X};
+
+use crate::context::Context;
+use crate::hierarchy::{BuildHierarchy, HierarchyConfig, HierarchyInput, HierarchyLevel};
+use crate::matching::MatchPairsByEdgeWeightConfig;
+use crate::spectral::lanczos::{smallest_eigenpairs, Laplacian};
+use crate::spectral::prolong_coordinates::{ProlongCoordinates, ProlongCoordinatesResources};
+use crate::spectral::smooth_coordinates::{SmoothCoordinates, SmoothCoordinatesResources};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpectralConfig {
+    /// The number of spectral coordinates computed for each node, which is the number of
+    /// non-trivial eigenvectors of the coarsest level's Laplacian that are used.
+    pub dimensions: usize,
+    /// The maximum number of Lanczos steps used to approximate the eigenvectors of the coarsest
+    /// level's Laplacian.
+    pub lanczos_iterations: usize,
+    /// The number of smoothing sweeps applied on each finer level after projecting the coordinates
+    /// of the next coarser level; `0` disables smoothing.
+    pub smoothing_sweeps: usize,
+    /// The fraction of the distance to the weighted average of its neighbors by which a node's
+    /// coordinates move in each smoothing sweep.
+    pub smoothing_weight: f32,
+    /// Coarsening stops once a level has no more than this many nodes.
+    pub coarsest_node_count: u32,
+    /// The maximum number of levels to coarsen.
+    pub max_levels: usize,
+    /// The seed for the random starting vector of the Lanczos method.
+    pub seed: u64,
+    /// The configuration of the matching that is used to coarsen the graph.
+    pub matching: MatchPairsByEdgeWeightConfig,
+}
+
+impl Default for SpectralConfig {
+    fn default() -> Self {
+        SpectralConfig {
+            dimensions: 2,
+            lanczos_iterations: 128,
+            smoothing_sweeps: 2,
+            smoothing_weight: 0.5,
+            coarsest_node_count: 256,
+            max_levels: 32,
+            seed: 1,
+            matching: Default::default(),
+        }
+    }
+}
+
+pub struct SpectralInput<'a, U0, U1, U2> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+}
+
+/// Computes spectral coordinates for the nodes of a graph.
+///
+/// Coarsens the graph into a hierarchy of levels (see [BuildHierarchy]) and reads back the
+/// coarsest level, for which the eigenvectors that belong to the smallest non-trivial eigenvalues
+/// of the weighted Laplacian are approximated on the host with the Lanczos method. Each node of the
+/// coarsest level gets the corresponding components of these eigenvectors as its coordinates.
+/// The coordinates are then projected back through the hierarchy with the fine-to-coarse node
+/// mappings (each fine node starts out with the coordinates of its coarse node), optionally
+/// followed by smoothing sweeps on each level that move the nodes towards the weighted average of
+/// their neighbors.
+///
+/// The graph must be undirected (each edge is stored once for each of the nodes it connects) and
+/// is described by buffers that are exactly sized to its node count and edge ref count. For a
+/// graph with multiple connected components, the first eigenvectors separate the components rather
+/// than describing the structure within them.
+pub struct SpectralEmbedding {
+    device: Device,
+    config: SpectralConfig,
+    build_hierarchy: BuildHierarchy,
+    prolong_coordinates: Rc<ProlongCoordinates>,
+    smooth_coordinates: Rc<SmoothCoordinates>,
+    dimensions: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+    smoothing_weight: Buffer<f32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
+}
+
+impl SpectralEmbedding {
+    pub async fn init(device: Device, config: SpectralConfig) -> Self {
+        Self::init_with_context(&Context::new(device), config).await
+    }
+
+    /// Same as [init](SpectralEmbedding::init), but obtains the sub-pipelines from the given
+    /// `context`, so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context, config: SpectralConfig) -> Self {
+        assert!(
+            config.dimensions > 0,
+            "`dimensions` must be greater than `0`"
+        );
+
+        let device = context.device().clone();
+
+        let (build_hierarchy, prolong_coordinates, smooth_coordinates) = join!(
+            BuildHierarchy::init_with_context(context, config.matching),
+            context.get_or_init_kernel(ProlongCoordinates::init),
+            context.get_or_init_kernel(SmoothCoordinates::init),
+        )
+        .await;
+
+        let dimensions =
+            device.create_buffer(config.dimensions as u32, buffer::Usages::uniform_binding());
+        let smoothing_weight =
+            device.create_buffer(config.smoothing_weight, buffer::Usages::uniform_binding());
+
+        SpectralEmbedding {
+            device,
+            config,
+            build_hierarchy,
+            prolong_coordinates,
+            smooth_coordinates,
+            dimensions,
+            smoothing_weight,
+        }
+    }
+
+    /// Computes spectral coordinates for the graph described by the `input` and writes them to
+    /// `nodes_coordinates`, which stores the [SpectralConfig::dimensions] coordinates of each node
+    /// consecutively.
+    ///
+    /// Returns the approximated eigenvalues of the coarsest level's Laplacian that belong to the
+    /// eigenvectors used for each dimension, in ascending order.
+    ///
+    /// Submits its own command buffers, as the number of levels in the hierarchy is only known
+    /// after reading back the node count of each coarse level, and the coarsest level is read back
+    /// to compute its eigenvectors.
+    pub async fn embed<U0, U1, U2, U3>(
+        &mut self,
+        input: SpectralInput<'_, U0, U1, U2>,
+        nodes_coordinates: buffer::View<'_, [f32], U3>,
+    ) -> Result<Vec<f64>, Box<dyn Error>>
+    where
+        U0: buffer::CopySrc,
+        U1: buffer::CopySrc,
+        U2: buffer::CopySrc,
+        U3: buffer::CopyDst,
+    {
+        let SpectralInput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+        } = input;
+
+        let dimensions = self.config.dimensions;
+
+        assert_eq!(
+            nodes_coordinates.len(),
+            nodes_edge_offset.len() * dimensions,
+            "`nodes_coordinates` must have a length equal to the length of `nodes_edge_offset` \
+            times the number of dimensions"
+        );
+
+        let levels = self
+            .build_hierarchy
+            .build(
+                HierarchyInput {
+                    nodes_edge_offset,
+                    nodes_edges,
+                    nodes_edge_weights,
+                    nodes_weight: None,
+                },
+                HierarchyConfig {
+                    coarsest_node_count: self.config.coarsest_node_count,
+                    max_levels: self.config.max_levels,
+                },
+            )
+            .await?;
+
+        let coarsest_level = levels.last().unwrap();
+        let (eigenvalues, coarsest_coordinates) = self.coarsest_coordinates(coarsest_level).await?;
+
+        // The coordinates for each level; unlike the buffers of the hierarchy levels, these are
+        // exactly sized to the level's node count.
+        let levels_coordinates: Vec<Buffer<[f32], _>> = levels
+            .iter()
+            .map(|level| {
+                self.device.create_slice_buffer_zeroed(
+                    level.node_count as usize * dimensions,
+                    buffer::Usages::storage_binding()
+                        .and_copy_dst()
+                        .and_copy_src(),
+                )
+            })
+            .collect();
+
+        // Scratch buffer for the smoothing sweeps, shared by all levels; coarse levels are never
+        // larger than the base level.
+        let next_coordinates = self.device.create_slice_buffer_zeroed(
+            levels[0].node_count as usize * dimensions,
+            buffer::Usages::storage_binding().and_copy_src(),
+        );
+
+        let coarsest_coordinates = self
+            .device
+            .create_buffer(coarsest_coordinates, buffer::Usages::copy_src());
+
+        let mut encoder = self.device.create_command_encoder();
+
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            coarsest_coordinates.view(),
+            levels_coordinates.last().unwrap().view(),
+        );
+
+        // Project the coordinates of each coarse level onto the next finer level and smooth them.
+        for index in (0..levels.len() - 1).rev() {
+            let level = &levels[index];
+            let coarse_level = &levels[index + 1];
+
+            encoder = self.prolong_coordinates.encode(
+                encoder,
+                ProlongCoordinatesResources {
+                    fine_node_count: level.node_count_buffer.uniform(),
+                    dimensions: self.dimensions.uniform(),
+                    fine_nodes_mapping: coarse_level.fine_nodes_mapping.storage(),
+                    coarse_nodes_coordinates: levels_coordinates[index + 1].storage(),
+                    fine_nodes_coordinates: levels_coordinates[index].storage(),
+                },
+                level.node_count,
+            );
+            encoder = self.encode_smoothing(
+                encoder,
+                level,
+                &levels_coordinates[index],
+                &next_coordinates,
+            );
+        }
+
+        encoder =
+            encoder.copy_buffer_to_buffer_slice(levels_coordinates[0].view(), nodes_coordinates);
+
+        self.device.queue().submit(encoder.finish());
+
+        Ok(eigenvalues)
+    }
+
+    /// Reads back the `level` and computes the coordinates of its nodes from the eigenvectors of
+    /// its Laplacian.
+    ///
+    /// Returns the eigenvalues and the coordinates, with the coordinates of each node stored
+    /// consecutively.
+    async fn coarsest_coordinates(
+        &self,
+        level: &HierarchyLevel,
+    ) -> Result<(Vec<f64>, Vec<f32>), Box<dyn Error>> {
+        let node_count = level.node_count as usize;
+        let edge_ref_count = level.edge_ref_count as usize;
+        let dimensions = self.config.dimensions;
+
+        let nodes_edge_offset_readback = self
+            .device
+            .create_slice_buffer_zeroed(node_count, buffer::Usages::copy_dst().and_map_read());
+        let nodes_edges_readback = self
+            .device
+            .create_slice_buffer_zeroed(edge_ref_count, buffer::Usages::copy_dst().and_map_read());
+        let nodes_edge_weights_readback = self
+            .device
+            .create_slice_buffer_zeroed(edge_ref_count, buffer::Usages::copy_dst().and_map_read());
+
+        let mut encoder = self.device.create_command_encoder();
+
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            level.nodes_edge_offset.view().get(..node_count).unwrap(),
+            nodes_edge_offset_readback.view(),
+        );
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            level.nodes_edges.view().get(..edge_ref_count).unwrap(),
+            nodes_edges_readback.view(),
+        );
+        encoder = encoder.copy_buffer_to_buffer_slice(
+            level
+                .nodes_edge_weights
+                .view()
+                .get(..edge_ref_count)
+                .unwrap(),
+            nodes_edge_weights_readback.view(),
+        );
+
+        self.device.queue().submit(encoder.finish());
+
+        let (r0, r1, r2) = join!(
+            nodes_edge_offset_readback.map_read(),
+            nodes_edges_readback.map_read(),
+            nodes_edge_weights_readback.map_read()
+        )
+        .await;
+
+        r0?;
+        r1?;
+        r2?;
+
+        let eigenpairs = {
+            let nodes_edge_offset = nodes_edge_offset_readback.mapped();
+            let nodes_edges = nodes_edges_readback.mapped();
+            let nodes_edge_weights = nodes_edge_weights_readback.mapped();
+
+            let laplacian = Laplacian::new(&nodes_edge_offset, &nodes_edges, &nodes_edge_weights);
+            let mut rng = oorandom::Rand32::new(self.config.seed);
+
+            smallest_eigenpairs(
+                &laplacian,
+                dimensions,
+                self.config.lanczos_iterations,
+                &mut rng,
+            )
+        };
+
+        nodes_edge_offset_readback.unmap();
+        nodes_edges_readback.unmap();
+        nodes_edge_weights_readback.unmap();
+
+        let eigenvalues = eigenpairs.iter().map(|pair| pair.value).collect();
+        let mut coordinates = vec![0.0; node_count * dimensions];
+
+        for (dimension, pair) in eigenpairs.iter().enumerate() {
+            for (node, value) in pair.vector.iter().enumerate() {
+                coordinates[node * dimensions + dimension] = *value as f32;
+            }
+        }
+
+        Ok((eigenvalues, coordinates))
+    }
+
+    fn encode_smoothing(
+        &self,
+        mut encoder: CommandEncoder,
+        level: &HierarchyLevel,
+        nodes_coordinates: &Buffer<[f32], buffer::Usages<O, O, X, O, O, O, X, X, O, O>>,
+        next_coordinates: &Buffer<[f32], buffer::Usages<O, O, X, O, O, O, O, X, O, O>>,
+    ) -> CommandEncoder {
+        let len = level.node_count as usize * self.config.dimensions;
+
+        for _ in 0..self.config.smoothing_sweeps {
+            encoder = self.smooth_coordinates.encode(
+                encoder,
+                SmoothCoordinatesResources {
+                    node_count: level.node_count_buffer.uniform(),
+                    edge_ref_count: level.edge_ref_count_buffer.uniform(),
+                    dimensions: self.dimensions.uniform(),
+                    smoothing_weight: self.smoothing_weight.uniform(),
+                    nodes_edge_offset: level.nodes_edge_offset.storage(),
+                    nodes_edges: level.nodes_edges.storage(),
+                    nodes_edge_weights: level.nodes_edge_weights.storage(),
+                    nodes_coordinates: nodes_coordinates.storage(),
+                    nodes_next_coordinates: next_coordinates.storage(),
+                },
+                level.node_count,
+            );
+            encoder = encoder.copy_buffer_to_buffer_slice(
+                next_coordinates.view().get(..len).unwrap(),
+                nodes_coordinates.view(),
+            );
+        }
+
+        encoder
+    }
+}
@@ -0,0 +1,38 @@
+#pragma once
+
+// WGSL has no 64-bit integers. Values that may not fit in 32 bits (typically sums of many edge weights) are stored as
+// 2 consecutive `u32` entries (low word first) and are handled as `vec2<u32>` (low, high) in shaders. The host decodes
+// them as `low | (high << 32)`.
+
+// Adds the 32-bit `value` to the 64-bit `a`.
+fn u64_add_u32(a: vec2<u32>, value: u32) -> vec2<u32> {
+    return u64_add(a, vec2(value, 0u));
+}
+
+// Adds the 64-bit `b` to the 64-bit `a`.
+fn u64_add(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let low = a.x + b.x;
+
+    return vec2(low, a.y + b.y + u64_carry(a.x, b.x));
+}
+
+// Returns the carry into the high word when adding `low` to the low word `previous`.
+//
+// Pointers into storage or workgroup memory can not be passed to functions, so shaders that atomically add a 64-bit
+// value to a buffer define a small wrapper for that buffer that follows this pattern:
+//
+//     let previous = atomicAdd(&buffer[i], value.x);
+//     let high = value.y + u64_carry(previous, value.x);
+//
+//     if high > 0 {
+//         atomicAdd(&buffer[i + 1], high);
+//     }
+//
+// Concurrent additions to the same value may be observed with the carry not yet applied, but the final value is exact.
+fn u64_carry(previous: u32, low: u32) -> u32 {
+    return select(0u, 1u, previous + low < previous);
+}
+
+fn u64_to_f32(a: vec2<u32>) -> f32 {
+    return f32(a.y) * 4294967296.0 + f32(a.x);
+}
@@ -0,0 +1,123 @@
+#include <src/dispatch.wgsl>
+#include <src/validate/report.wgsl>
+
+@group(0) @binding(0)
+var<uniform> node_count: u32;
+
+@group(0) @binding(1)
+var<uniform> edge_ref_count: u32;
+
+@group(0) @binding(2)
+var<storage, read> nodes_edge_offset: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> nodes_edges: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> nodes_edge_weights: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read_write> report: array<atomic<u32>>;
+
+const MAX_NODE_COUNT = 0x40000000u;
+
+fn report_violation(kind: u32, node: u32, edge: u32) {
+    let base = kind * REPORT_ENTRY_SIZE;
+
+    atomicAdd(&report[base], 1u);
+    atomicMin(&report[base + 1], node);
+    atomicMin(&report[base + 2], edge);
+}
+
+// Returns the edge range for the given node, clamped to the edge list such that we never read out-of-bounds, even if
+// the offsets are malformed.
+fn edge_range(node: u32) -> vec2<u32> {
+    let edge_list_len = min(edge_ref_count, arrayLength(&nodes_edges));
+
+    let start = min(nodes_edge_offset[node], edge_list_len);
+
+    var end = edge_list_len;
+
+    if node < node_count - 1 {
+        end = min(nodes_edge_offset[node + 1], edge_list_len);
+    }
+
+    return vec2(start, max(start, end));
+}
+
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
+
+    if index >= node_count {
+        return;
+    }
+
+    if index == 0 {
+        if node_count >= MAX_NODE_COUNT {
+            report_violation(VIOLATION_NODE_COUNT_TOO_LARGE, REPORT_NONE, REPORT_NONE);
+        }
+
+        if nodes_edge_offset[0] != 0 {
+            report_violation(VIOLATION_NON_ZERO_FIRST_EDGE_OFFSET, 0u, REPORT_NONE);
+        }
+
+        if arrayLength(&nodes_edge_weights) < min(edge_ref_count, arrayLength(&nodes_edges)) {
+            report_violation(VIOLATION_EDGE_WEIGHT_COUNT_MISMATCH, REPORT_NONE, REPORT_NONE);
+        }
+    }
+
+    let offset = nodes_edge_offset[index];
+
+    if offset > edge_ref_count {
+        report_violation(VIOLATION_EDGE_OFFSET_OUT_OF_BOUNDS, index, REPORT_NONE);
+    }
+
+    if index < node_count - 1 && offset > nodes_edge_offset[index + 1] {
+        report_violation(VIOLATION_NON_MONOTONIC_EDGE_OFFSET, index, REPORT_NONE);
+    }
+
+    let range = edge_range(index);
+    let weight_count = arrayLength(&nodes_edge_weights);
+
+    for (var i = range.x; i < range.y; i += 1u) {
+        let target_node = nodes_edges[i];
+
+        if target_node >= node_count {
+            report_violation(VIOLATION_EDGE_TARGET_OUT_OF_BOUNDS, index, i);
+
+            continue;
+        }
+
+        // Search the target node's edge list for the reverse edge. This is a linear search, so for high degree nodes
+        // this can get expensive, but this validation pass is meant as a debugging aid, not as something to run in
+        // production.
+        let target_range = edge_range(target_node);
+
+        var found_reverse = false;
+        var found_matching_weight = false;
+
+        for (var j = target_range.x; j < target_range.y; j += 1u) {
+            if nodes_edges[j] == index {
+                found_reverse = true;
+
+                // Edges without a weight have already been reported as a weight count mismatch, only compare the
+                // weights that are present.
+                if i >= weight_count || j >= weight_count || nodes_edge_weights[j] == nodes_edge_weights[i] {
+                    found_matching_weight = true;
+
+                    break;
+                }
+            }
+        }
+
+        if !found_reverse {
+            report_violation(VIOLATION_MISSING_REVERSE_EDGE, index, i);
+        } else if !found_matching_weight {
+            report_violation(VIOLATION_ASYMMETRIC_EDGE_WEIGHT, index, i);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+mod find_violations;
+mod reset_report;
+
+mod report;
+pub use self::report::{ValidationIssue, ValidationReport, ViolationKind, VALIDATION_REPORT_LEN};
+
+mod validate_graph;
+pub use self::validate_graph::{ValidateGraph, ValidateGraphCounts, ValidateGraphInput};
+
+mod validate_host;
+pub use self::validate_host::validate_host;
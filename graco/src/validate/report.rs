@@ -0,0 +1,196 @@
+use std::fmt;
+
+/// The kinds of malformations [validate_host] and [ValidateGraph] check for.
+///
+/// [validate_host]: crate::validate::validate_host
+/// [ValidateGraph]: crate::validate::ValidateGraph
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[repr(u32)]
+pub enum ViolationKind {
+    /// The graph has `2^30` nodes or more; the coarsening and matching pipelines pack state into
+    /// the 2 most significant bits of node indices.
+    NodeCountTooLarge = 0,
+    /// The edge offset for the first node is not `0`.
+    NonZeroFirstEdgeOffset = 1,
+    /// A node's edge offset is greater than the edge offset of the next node.
+    NonMonotonicEdgeOffset = 2,
+    /// A node's edge offset is greater than the edge reference count.
+    EdgeOffsetOutOfBounds = 3,
+    /// An edge points to a node index that is not smaller than the node count.
+    EdgeTargetOutOfBounds = 4,
+    /// For an edge `a -> b`, there is no edge `b -> a`.
+    MissingReverseEdge = 5,
+    /// For an edge `a -> b`, there is an edge `b -> a`, but its weight differs.
+    AsymmetricEdgeWeight = 6,
+    /// The edge weight list does not hold exactly one weight for each edge reference (the GPU
+    /// validation only detects a weight list that is too short, as bound buffers may be larger
+    /// than the edge reference count).
+    EdgeWeightCountMismatch = 7,
+}
+
+impl ViolationKind {
+    pub const ALL: [ViolationKind; 8] = [
+        ViolationKind::NodeCountTooLarge,
+        ViolationKind::NonZeroFirstEdgeOffset,
+        ViolationKind::NonMonotonicEdgeOffset,
+        ViolationKind::EdgeOffsetOutOfBounds,
+        ViolationKind::EdgeTargetOutOfBounds,
+        ViolationKind::MissingReverseEdge,
+        ViolationKind::AsymmetricEdgeWeight,
+        ViolationKind::EdgeWeightCountMismatch,
+    ];
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            ViolationKind::NodeCountTooLarge => "node count exceeds 2^30",
+            ViolationKind::NonZeroFirstEdgeOffset => "first edge offset is not 0",
+            ViolationKind::NonMonotonicEdgeOffset => "edge offsets are not monotonic",
+            ViolationKind::EdgeOffsetOutOfBounds => "edge offset exceeds edge count",
+            ViolationKind::EdgeTargetOutOfBounds => "edge target exceeds node count",
+            ViolationKind::MissingReverseEdge => "missing reverse edge",
+            ViolationKind::AsymmetricEdgeWeight => "reverse edge weight differs",
+            ViolationKind::EdgeWeightCountMismatch => "edge weight count differs from edge count",
+        };
+
+        f.write_str(description)
+    }
+}
+
+// Each violation kind occupies 3 consecutive entries in the raw report: the number of violations
+// found, the lowest offending node index and the lowest offending edge index. Must be kept in sync
+// with `report.wgsl`.
+const REPORT_ENTRY_SIZE: usize = 3;
+const REPORT_NONE: u32 = 0xFFFFFFFF;
+
+/// The length of the `u32` slice buffer [ValidateGraph] writes its raw report to.
+///
+/// [ValidateGraph]: crate::validate::ValidateGraph
+pub const VALIDATION_REPORT_LEN: usize = ViolationKind::ALL.len() * REPORT_ENTRY_SIZE;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ValidationIssue {
+    pub kind: ViolationKind,
+    /// The lowest offending node index, if the violation can be attributed to a node.
+    pub node: Option<u32>,
+    /// The lowest offending edge index, if the violation can be attributed to an edge.
+    pub edge: Option<u32>,
+    /// The total number of violations of this kind.
+    pub count: u32,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} occurrence(s)", self.kind, self.count)?;
+
+        if let Some(node) = self.node {
+            write!(f, ", first at node {}", node)?;
+        }
+
+        if let Some(edge) = self.edge {
+            write!(f, ", first at edge {}", edge)?;
+        }
+
+        f.write_str(")")
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct ValidationReport {
+    issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Decodes a raw report that was written by [ValidateGraph] and read back to the host.
+    ///
+    /// Panics if `raw` is shorter than [VALIDATION_REPORT_LEN].
+    ///
+    /// [ValidateGraph]: crate::validate::ValidateGraph
+    pub fn from_raw(raw: &[u32]) -> Self {
+        assert!(
+            raw.len() >= VALIDATION_REPORT_LEN,
+            "raw report must contain at least {} elements",
+            VALIDATION_REPORT_LEN
+        );
+
+        let decode_index = |value: u32| {
+            if value == REPORT_NONE {
+                None
+            } else {
+                Some(value)
+            }
+        };
+
+        let issues = ViolationKind::ALL
+            .iter()
+            .filter_map(|&kind| {
+                let base = kind as usize * REPORT_ENTRY_SIZE;
+                let count = raw[base];
+
+                (count > 0).then(|| ValidationIssue {
+                    kind,
+                    node: decode_index(raw[base + 1]),
+                    edge: decode_index(raw[base + 2]),
+                    count,
+                })
+            })
+            .collect();
+
+        ValidationReport { issues }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
+
+    pub fn issue(&self, kind: ViolationKind) -> Option<&ValidationIssue> {
+        self.issues.iter().find(|issue| issue.kind == kind)
+    }
+
+    pub(crate) fn record(&mut self, kind: ViolationKind, node: Option<u32>, edge: Option<u32>) {
+        if let Some(issue) = self.issues.iter_mut().find(|issue| issue.kind == kind) {
+            issue.count += 1;
+            issue.node = min_option(issue.node, node);
+            issue.edge = min_option(issue.edge, edge);
+        } else {
+            self.issues.push(ValidationIssue {
+                kind,
+                node,
+                edge,
+                count: 1,
+            });
+            self.issues.sort_by_key(|issue| issue.kind as u32);
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.issues.is_empty() {
+            return f.write_str("graph is valid");
+        }
+
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+
+            issue.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn min_option(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
@@ -0,0 +1,18 @@
+#pragma once
+
+const VIOLATION_NODE_COUNT_TOO_LARGE = 0u;
+const VIOLATION_NON_ZERO_FIRST_EDGE_OFFSET = 1u;
+const VIOLATION_NON_MONOTONIC_EDGE_OFFSET = 2u;
+const VIOLATION_EDGE_OFFSET_OUT_OF_BOUNDS = 3u;
+const VIOLATION_EDGE_TARGET_OUT_OF_BOUNDS = 4u;
+const VIOLATION_MISSING_REVERSE_EDGE = 5u;
+const VIOLATION_ASYMMETRIC_EDGE_WEIGHT = 6u;
+const VIOLATION_EDGE_WEIGHT_COUNT_MISMATCH = 7u;
+
+const VIOLATION_KIND_COUNT = 8u;
+
+// Each violation kind occupies 3 consecutive entries in the report: the number of violations found, the lowest
+// offending node index, and the lowest offending edge index.
+const REPORT_ENTRY_SIZE = 3u;
+
+const REPORT_NONE = 0xFFFFFFFFu;
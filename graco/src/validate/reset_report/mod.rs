@@ -1,5 +1,5 @@
 use empa::access_mode::ReadWrite;
-use empa::buffer::{Storage, Uniform};
+use empa::buffer::Storage;
 use empa::command::{CommandEncoder, DispatchWorkgroups, ResourceBindingCommandEncoder};
 use empa::compute_pipeline::{
     ComputePipeline, ComputePipelineDescriptorBuilder, ComputeStageBuilder,
@@ -8,28 +8,26 @@ use empa::device::Device;
 use empa::resource_binding::BindGroupLayout;
 use empa::shader_module::{shader_source, ShaderSource};
 
+use crate::counts_fallback::{BindGroupCache, Binding};
+
 const SHADER: ShaderSource = shader_source!("shader.wgsl");
 
 #[derive(empa::resource_binding::Resources)]
-pub struct GenerateDispatchResources<'a> {
+pub struct ResetReportResources<'a> {
     #[resource(binding = 0, visibility = "COMPUTE")]
-    pub group_size: Uniform<'a, u32>,
-    #[resource(binding = 1, visibility = "COMPUTE")]
-    pub count: Uniform<'a, u32>,
-    #[resource(binding = 2, visibility = "COMPUTE")]
-    pub dispatch: Storage<'a, DispatchWorkgroups, ReadWrite>,
+    pub report: Storage<'a, [u32], ReadWrite>,
 }
 
-type ResourcesLayout =
-    <GenerateDispatchResources<'static> as empa::resource_binding::Resources>::Layout;
+type ResourcesLayout = <ResetReportResources<'static> as empa::resource_binding::Resources>::Layout;
 
-pub struct GenerateDispatch {
+pub struct ResetReport {
     device: Device,
     bind_group_layout: BindGroupLayout<ResourcesLayout>,
+    bind_groups: BindGroupCache<ResourcesLayout>,
     pipeline: ComputePipeline<(ResourcesLayout,)>,
 }
 
-impl GenerateDispatch {
+impl ResetReport {
     pub async fn init(device: Device) -> Self {
         let shader = device.create_shader_module(&SHADER);
 
@@ -45,9 +43,10 @@ impl GenerateDispatch {
             )
             .await;
 
-        GenerateDispatch {
+        ResetReport {
             device,
             bind_group_layout,
+            bind_groups: Default::default(),
             pipeline,
         }
     }
@@ -55,16 +54,18 @@ impl GenerateDispatch {
     pub fn encode(
         &self,
         encoder: CommandEncoder,
-        resources: GenerateDispatchResources,
+        resources: ResetReportResources,
     ) -> CommandEncoder {
-        let bind_group = self
-            .device
-            .create_bind_group(&self.bind_group_layout, resources);
+        let key = [resources.report.binding_key()];
+        let bind_group = self.bind_groups.get_or_create(&key, || {
+            self.device
+                .create_bind_group(&self.bind_group_layout, resources)
+        });
 
         encoder
             .begin_compute_pass()
             .set_pipeline(&self.pipeline)
-            .set_bind_groups(&bind_group)
+            .set_bind_groups(&*bind_group)
             .dispatch_workgroups(DispatchWorkgroups {
                 count_x: 1,
                 count_y: 1,
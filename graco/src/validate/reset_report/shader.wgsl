@@ -0,0 +1,15 @@
+#include <src/validate/report.wgsl>
+
+@group(0) @binding(0)
+var<storage, read_write> report: array<u32>;
+
+@compute @workgroup_size(1, 1, 1)
+fn main() {
+    for (var kind = 0u; kind < VIOLATION_KIND_COUNT; kind += 1u) {
+        let base = kind * REPORT_ENTRY_SIZE;
+
+        report[base] = 0u;
+        report[base + 1] = REPORT_NONE;
+        report[base + 2] = REPORT_NONE;
+    }
+}
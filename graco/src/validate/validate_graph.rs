@@ -0,0 +1,159 @@
+use std::future::join;
+use std::rc::Rc;
+
+use empa::buffer;
+use empa::buffer::{Buffer, Uniform};
+use empa::command::{CommandEncoder, DispatchWorkgroups};
+use empa::device::Device;
+use empa::type_flag::{O, X};
+
+use crate::coarsen_graph::generate_dispatches::{GenerateDispatches, GenerateDispatchesResources};
+use crate::context::Context;
+use crate::counts_fallback::FallbackCountsCache;
+use crate::validate::find_violations::{FindViolations, FindViolationsResources};
+use crate::validate::reset_report::{ResetReport, ResetReportResources};
+
+pub struct ValidateGraphCounts<'a> {
+    pub node_count: Uniform<'a, u32>,
+    pub edge_ref_count: Uniform<'a, u32>,
+}
+
+pub struct ValidateGraphInput<'a, U0, U1, U2> {
+    pub nodes_edge_offset: buffer::View<'a, [u32], U0>,
+    pub nodes_edges: buffer::View<'a, [u32], U1>,
+    pub nodes_edge_weights: buffer::View<'a, [u32], U2>,
+    pub counts: Option<ValidateGraphCounts<'a>>,
+}
+
+/// Checks a CSR graph that resides in device memory for malformations that would cause the
+/// matching and coarsening pipelines to produce garbage results.
+///
+/// Writes a raw report to a `u32` slice buffer with a length of (at least)
+/// [VALIDATION_REPORT_LEN]; read this buffer back to the host and decode it with
+/// [ValidationReport::from_raw].
+///
+/// [VALIDATION_REPORT_LEN]: crate::validate::VALIDATION_REPORT_LEN
+/// [ValidationReport::from_raw]: crate::validate::ValidationReport::from_raw
+pub struct ValidateGraph {
+    device: Device,
+    counts_cache: FallbackCountsCache,
+    generate_dispatches: Rc<GenerateDispatches>,
+    reset_report: Rc<ResetReport>,
+    find_violations: Rc<FindViolations>,
+    node_count_dispatch: Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+    edge_ref_count_dispatch:
+        Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+}
+
+impl ValidateGraph {
+    pub async fn init(device: Device) -> Self {
+        Self::init_with_context(&Context::new(device)).await
+    }
+
+    /// Same as [init](ValidateGraph::init), but obtains the sub-pipelines from the given `context`,
+    /// so that they may be shared with other components.
+    pub async fn init_with_context(context: &Context) -> Self {
+        let device = context.device().clone();
+
+        let (generate_dispatches, reset_report, find_violations) = join!(
+            context.get_or_init_kernel(GenerateDispatches::init),
+            context.get_or_init("init", ResetReport::init),
+            context.get_or_init_kernel(FindViolations::init),
+        )
+        .await;
+
+        let node_count_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
+        let edge_ref_count_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 1,
+                count_y: 1,
+                count_z: 1,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
+
+        let counts_cache = FallbackCountsCache::new(device.clone());
+
+        ValidateGraph {
+            device,
+            counts_cache,
+            generate_dispatches,
+            reset_report,
+            find_violations,
+            node_count_dispatch,
+            edge_ref_count_dispatch,
+        }
+    }
+
+    pub fn encode<U0, U1, U2, U3>(
+        &self,
+        mut encoder: CommandEncoder,
+        input: ValidateGraphInput<U0, U1, U2>,
+        report: buffer::View<[u32], U3>,
+    ) -> CommandEncoder
+    where
+        U0: buffer::StorageBinding,
+        U1: buffer::StorageBinding,
+        U2: buffer::StorageBinding,
+        U3: buffer::StorageBinding,
+    {
+        let ValidateGraphInput {
+            nodes_edge_offset,
+            nodes_edges,
+            nodes_edge_weights,
+            counts,
+        } = input;
+
+        let dispatch_indirect = counts.is_some();
+
+        if let Some(counts) = counts.as_ref() {
+            encoder = self.generate_dispatches.encode(
+                encoder,
+                GenerateDispatchesResources {
+                    node_count: counts.node_count.clone(),
+                    edge_ref_count: counts.edge_ref_count.clone(),
+                    node_count_dispatch: self.node_count_dispatch.storage(),
+                    edge_ref_count_dispatch: self.edge_ref_count_dispatch.storage(),
+                },
+            );
+        }
+
+        let fallback_node_count = nodes_edge_offset.len() as u32;
+        let fallback_edge_ref_count = nodes_edges.len() as u32;
+        let counts_fallback = self.counts_cache.get(
+            counts.map(|c| (c.node_count, c.edge_ref_count)),
+            (fallback_node_count, fallback_edge_ref_count),
+        );
+
+        encoder = self.reset_report.encode(
+            encoder,
+            ResetReportResources {
+                report: report.storage(),
+            },
+        );
+
+        encoder = self.find_violations.encode(
+            encoder,
+            FindViolationsResources {
+                node_count: counts_fallback.node_count(),
+                edge_ref_count: counts_fallback.edge_ref_count(),
+                nodes_edge_offset: nodes_edge_offset.storage(),
+                nodes_edges: nodes_edges.storage(),
+                nodes_edge_weights: nodes_edge_weights.storage(),
+                report: report.storage(),
+            },
+            dispatch_indirect,
+            self.node_count_dispatch.view(),
+            fallback_node_count,
+        );
+
+        encoder
+    }
+}
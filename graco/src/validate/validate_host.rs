@@ -0,0 +1,272 @@
+use crate::validate::{ValidationReport, ViolationKind};
+
+const MAX_NODE_COUNT: usize = 1 << 30;
+
+/// Checks a CSR graph that resides in host memory for malformations that would cause the matching
+/// and coarsening pipelines to produce garbage results.
+///
+/// Performs the same checks as [ValidateGraph], see [ViolationKind] for details.
+///
+/// [ValidateGraph]: crate::validate::ValidateGraph
+pub fn validate_host(
+    nodes_edge_offset: &[u32],
+    nodes_edges: &[u32],
+    nodes_edge_weights: &[u32],
+) -> ValidationReport {
+    validate_host_with_max_node_count(
+        nodes_edge_offset,
+        nodes_edges,
+        nodes_edge_weights,
+        MAX_NODE_COUNT,
+    )
+}
+
+fn validate_host_with_max_node_count(
+    nodes_edge_offset: &[u32],
+    nodes_edges: &[u32],
+    nodes_edge_weights: &[u32],
+    max_node_count: usize,
+) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    let node_count = nodes_edge_offset.len();
+    let edge_ref_count = nodes_edges.len();
+
+    if node_count >= max_node_count {
+        report.record(ViolationKind::NodeCountTooLarge, None, None);
+    }
+
+    if nodes_edge_weights.len() != edge_ref_count {
+        report.record(ViolationKind::EdgeWeightCountMismatch, None, None);
+    }
+
+    if node_count == 0 {
+        return report;
+    }
+
+    if nodes_edge_offset[0] != 0 {
+        report.record(ViolationKind::NonZeroFirstEdgeOffset, Some(0), None);
+    }
+
+    // Clamp the edge ranges to the edge list, so that malformed offsets don't cause us to panic.
+    let edge_range = |node: usize| {
+        let start = (nodes_edge_offset[node] as usize).min(edge_ref_count);
+        let end = nodes_edge_offset
+            .get(node + 1)
+            .map(|&end| end as usize)
+            .unwrap_or(edge_ref_count)
+            .min(edge_ref_count);
+
+        start..end.max(start)
+    };
+
+    for node in 0..node_count {
+        let offset = nodes_edge_offset[node];
+
+        if offset as usize > edge_ref_count {
+            report.record(
+                ViolationKind::EdgeOffsetOutOfBounds,
+                Some(node as u32),
+                None,
+            );
+        }
+
+        if let Some(&next_offset) = nodes_edge_offset.get(node + 1) {
+            if offset > next_offset {
+                report.record(
+                    ViolationKind::NonMonotonicEdgeOffset,
+                    Some(node as u32),
+                    None,
+                );
+            }
+        }
+
+        for edge in edge_range(node) {
+            let target = nodes_edges[edge] as usize;
+
+            if target >= node_count {
+                report.record(
+                    ViolationKind::EdgeTargetOutOfBounds,
+                    Some(node as u32),
+                    Some(edge as u32),
+                );
+
+                continue;
+            }
+
+            let weight = nodes_edge_weights.get(edge);
+            let mut found_reverse = false;
+            let mut found_matching_weight = false;
+
+            for reverse_edge in edge_range(target) {
+                if nodes_edges[reverse_edge] as usize == node {
+                    found_reverse = true;
+
+                    // Edges without a weight have already been reported as a weight count mismatch,
+                    // only compare the weights that are present.
+                    let reverse_weight = nodes_edge_weights.get(reverse_edge);
+
+                    if weight.is_none() || reverse_weight.is_none() || reverse_weight == weight {
+                        found_matching_weight = true;
+
+                        break;
+                    }
+                }
+            }
+
+            if !found_reverse {
+                report.record(
+                    ViolationKind::MissingReverseEdge,
+                    Some(node as u32),
+                    Some(edge as u32),
+                );
+            } else if !found_matching_weight {
+                report.record(
+                    ViolationKind::AsymmetricEdgeWeight,
+                    Some(node as u32),
+                    Some(edge as u32),
+                );
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single_issue(
+        report: &ValidationReport,
+        kind: ViolationKind,
+    ) -> (Option<u32>, Option<u32>, u32) {
+        let issue = report
+            .issue(kind)
+            .unwrap_or_else(|| panic!("expected a `{:?}` violation in: {}", kind, report));
+
+        (issue.node, issue.edge, issue.count)
+    }
+
+    #[test]
+    fn test_valid_graph() {
+        // A triangle.
+        let report = validate_host(&[0, 2, 4], &[1, 2, 0, 2, 0, 1], &[1, 2, 1, 3, 2, 3]);
+
+        assert!(report.is_valid(), "{}", report);
+    }
+
+    #[test]
+    fn test_empty_graph() {
+        assert!(validate_host(&[], &[], &[]).is_valid());
+    }
+
+    #[test]
+    fn test_node_count_too_large() {
+        let report = validate_host_with_max_node_count(&[0, 1], &[1, 0], &[1, 1], 2);
+
+        assert_eq!(
+            single_issue(&report, ViolationKind::NodeCountTooLarge),
+            (None, None, 1)
+        );
+        assert_eq!(report.issues().len(), 1);
+    }
+
+    #[test]
+    fn test_non_zero_first_edge_offset() {
+        // Node 0's range skips the first edge and holds a self-loop instead.
+        let report = validate_host(&[1, 2], &[1, 0], &[1, 1]);
+
+        assert_eq!(
+            single_issue(&report, ViolationKind::NonZeroFirstEdgeOffset),
+            (Some(0), None, 1)
+        );
+        assert_eq!(report.issues().len(), 1);
+    }
+
+    #[test]
+    fn test_non_monotonic_edge_offset() {
+        let report = validate_host(&[0, 2, 1], &[1, 2], &[1, 1]);
+
+        assert_eq!(
+            single_issue(&report, ViolationKind::NonMonotonicEdgeOffset),
+            (Some(1), None, 1)
+        );
+    }
+
+    #[test]
+    fn test_edge_offset_out_of_bounds() {
+        let report = validate_host(&[0, 5], &[1, 0], &[1, 1]);
+
+        assert_eq!(
+            single_issue(&report, ViolationKind::EdgeOffsetOutOfBounds),
+            (Some(1), None, 1)
+        );
+    }
+
+    #[test]
+    fn test_edge_target_out_of_bounds() {
+        let report = validate_host(&[0, 1], &[1, 7], &[1, 1]);
+
+        assert_eq!(
+            single_issue(&report, ViolationKind::EdgeTargetOutOfBounds),
+            (Some(1), Some(1), 1)
+        );
+        assert_eq!(report.issues().len(), 2);
+        assert_eq!(
+            single_issue(&report, ViolationKind::MissingReverseEdge),
+            (Some(0), Some(0), 1)
+        );
+    }
+
+    #[test]
+    fn test_missing_reverse_edge() {
+        let report = validate_host(&[0, 1], &[1], &[1]);
+
+        assert_eq!(
+            single_issue(&report, ViolationKind::MissingReverseEdge),
+            (Some(0), Some(0), 1)
+        );
+        assert_eq!(report.issues().len(), 1);
+    }
+
+    #[test]
+    fn test_asymmetric_edge_weight() {
+        let report = validate_host(&[0, 1], &[1, 0], &[1, 2]);
+
+        assert_eq!(
+            single_issue(&report, ViolationKind::AsymmetricEdgeWeight),
+            (Some(0), Some(0), 2)
+        );
+        assert_eq!(report.issues().len(), 1);
+    }
+
+    #[test]
+    fn test_edge_weight_count_mismatch() {
+        // Neither edge has a weight, which must not count as a symmetric weight.
+        let report = validate_host(&[0, 1], &[1, 0], &[]);
+
+        assert_eq!(
+            single_issue(&report, ViolationKind::EdgeWeightCountMismatch),
+            (None, None, 1)
+        );
+        assert_eq!(report.issues().len(), 1);
+
+        let report = validate_host(&[0, 1], &[1, 0], &[1, 1, 1]);
+
+        assert_eq!(
+            single_issue(&report, ViolationKind::EdgeWeightCountMismatch),
+            (None, None, 1)
+        );
+        assert_eq!(report.issues().len(), 1);
+    }
+
+    #[test]
+    fn test_asymmetric_edge_weight_with_missing_weights() {
+        // Only the first edge has a weight, so its weight can't be compared with the reverse edge.
+        let report = validate_host(&[0, 1], &[1, 0], &[1]);
+
+        assert!(report.issue(ViolationKind::AsymmetricEdgeWeight).is_none());
+        assert_eq!(report.issues().len(), 1);
+    }
+}
@@ -0,0 +1,11 @@
+#pragma once
+
+// These are overridden with the values of graco's `DispatchConfig` that the pipeline was created with. Both constants
+// must be declared, even if a shader doesn't use them, as the pipelines set both.
+override group_size: u32 = 256;
+override max_workgroups_per_dimension: u32 = 65535;
+
+// Returns the linear invocation index for a (potentially 2D) dispatch produced by graco's `GenerateDispatch`.
+fn global_index(global_id: vec3<u32>, num_workgroups: vec3<u32>) -> u32 {
+    return global_id.x + global_id.y * num_workgroups.x * group_size;
+}
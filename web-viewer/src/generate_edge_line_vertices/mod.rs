@@ -8,11 +8,10 @@ use empa::device::Device;
 use empa::resource_binding::BindGroupLayout;
 use empa::shader_module::{shader_source, ShaderSource};
 use empa::{abi, buffer};
+use graco::DispatchConfig;
 
 use crate::edge_vertex::EdgeVertex;
 
-const GROUP_SIZE: u32 = 256;
-
 const SHADER: ShaderSource = shader_source!("shader.wgsl");
 
 #[derive(empa::resource_binding::Resources)]
@@ -52,7 +51,7 @@ pub struct GenerateEdgeLineVertices {
 }
 
 impl GenerateEdgeLineVertices {
-    pub async fn init(device: Device) -> Self {
+    pub async fn init(device: Device, dispatch_config: DispatchConfig) -> Self {
         let shader = device.create_shader_module(&SHADER);
 
         let bind_group_layout = device.create_bind_group_layout::<ResourcesLayout>();
@@ -62,7 +61,11 @@ impl GenerateEdgeLineVertices {
             .create_compute_pipeline(
                 &ComputePipelineDescriptorBuilder::begin()
                     .layout(&pipeline_layout)
-                    .compute(ComputeStageBuilder::begin(&shader, "main").finish())
+                    .compute(
+                        ComputeStageBuilder::begin(&shader, "main")
+                            .pipeline_constants(&dispatch_config.constants())
+                            .finish(),
+                    )
                     .finish(),
             )
             .await;
@@ -74,10 +77,15 @@ impl GenerateEdgeLineVertices {
         }
     }
 
-    pub fn encode<U0, U1, U2, U3, U4, U5, U6>(
+    /// Encodes the generation of the edge line vertices.
+    ///
+    /// The `dispatch` must cover the node count; it is expected to be generated with
+    /// [graco::GenerateDispatch] from the same [DispatchConfig] this kernel was initialized with.
+    pub fn encode<U0, U1, U2, U3, U4, U5, U6, U7>(
         &self,
         encoder: CommandEncoder,
         input: GenerateEdgeLineVerticesInput<U0, U1, U2, U3, U4, U5, U6>,
+        dispatch: buffer::View<DispatchWorkgroups, U7>,
     ) -> CommandEncoder
     where
         U0: buffer::UniformBinding,
@@ -87,6 +95,7 @@ impl GenerateEdgeLineVertices {
         U4: buffer::StorageBinding,
         U5: buffer::StorageBinding,
         U6: buffer::StorageBinding,
+        U7: buffer::Indirect,
     {
         let GenerateEdgeLineVerticesInput {
             node_count,
@@ -98,8 +107,6 @@ impl GenerateEdgeLineVertices {
             edge_vertices,
         } = input;
 
-        let workgroups = (nodes_edge_offset.len() as u32).div_ceil(GROUP_SIZE);
-
         let bind_group = self.device.create_bind_group(
             &self.bind_group_layout,
             Resources {
@@ -117,11 +124,7 @@ impl GenerateEdgeLineVertices {
             .begin_compute_pass()
             .set_pipeline(&self.pipeline)
             .set_bind_groups(&bind_group)
-            .dispatch_workgroups(DispatchWorkgroups {
-                count_x: workgroups,
-                count_y: 1,
-                count_z: 1,
-            })
+            .dispatch_workgroups_indirect(dispatch)
             .end()
     }
 }
@@ -1,3 +1,5 @@
+#include <src/dispatch.wgsl>
+
 struct EdgeVertex {
     position: array<f32, 2>,
     color: array<f32, 3>,
@@ -24,9 +26,12 @@ var<storage, read> nodes_matching: array<u32>;
 @group(0) @binding(6)
 var<storage, read_write> edge_vertices: array<EdgeVertex>;
 
-@compute @workgroup_size(256, 1, 1)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let index = global_id.x;
+@compute @workgroup_size(group_size, 1, 1)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_index(global_id, num_workgroups);
 
     if index >= node_count {
         return;
@@ -1,7 +1,8 @@
 use std::future::join;
+use std::rc::Rc;
 
 use empa::buffer::Buffer;
-use empa::command::{CommandEncoder, Draw, DrawIndexed, RenderPassDescriptor};
+use empa::command::{CommandEncoder, DispatchWorkgroups, Draw, DrawIndexed, RenderPassDescriptor};
 use empa::device::Device;
 use empa::render_target::{FloatAttachment, LoadOp, RenderTarget, StoreOp};
 use empa::texture::format::rgba8unorm;
@@ -10,6 +11,7 @@ use empa::type_flag::{O, X};
 use empa::{abi, buffer, texture};
 use empa_glam::ToAbi;
 use glam::{Mat3, Vec2};
+use graco::{Context, GenerateDispatch, GenerateDispatchResources};
 
 use crate::draw_edge_lines::DrawEdgeLines;
 use crate::draw_point_triangles::DrawPointTriangles;
@@ -29,28 +31,41 @@ pub struct GraphRendererInput<'a, U0, U1, U2, U3, U4, U5, U6> {
 
 pub struct GraphRenderer {
     device: Device,
-    generate_dispatches: GenerateDispatches,
-    generate_edge_line_vertices: GenerateEdgeLineVertices,
-    draw_point_triangles: DrawPointTriangles,
-    draw_edge_lines: DrawEdgeLines,
+    generate_dispatches: Rc<GenerateDispatches>,
+    generate_dispatch: Rc<GenerateDispatch>,
+    generate_edge_line_vertices: Rc<GenerateEdgeLineVertices>,
+    draw_point_triangles: Rc<DrawPointTriangles>,
+    draw_edge_lines: Rc<DrawEdgeLines>,
     triangle_index_count: Buffer<u32, buffer::Usages<O, O, O, X, O, O, O, O, O, O>>,
     draw_point_triangles_dispatch:
         Buffer<DrawIndexed, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
     draw_edge_lines_dispatch: Buffer<Draw, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
+    generate_edge_line_vertices_dispatch:
+        Buffer<DispatchWorkgroups, buffer::Usages<O, X, X, O, O, O, O, O, O, O>>,
 }
 
 impl GraphRenderer {
     pub async fn init(device: Device) -> Self {
+        Self::init_with_context(&Context::new(device)).await
+    }
+
+    /// Same as [init](GraphRenderer::init), but obtains the pipelines from the given `context`, so
+    /// that they may be shared with other renderers and graco components.
+    pub async fn init_with_context(context: &Context) -> Self {
+        let device = context.device().clone();
+
         let (
             generate_dispatches,
+            generate_dispatch,
             generate_edge_line_vertices,
             draw_point_triangles,
             draw_edge_lines,
         ) = join!(
-            GenerateDispatches::init(device.clone()),
-            GenerateEdgeLineVertices::init(device.clone()),
-            DrawPointTriangles::init(device.clone()),
-            DrawEdgeLines::init(device.clone()),
+            context.get_or_init("init", GenerateDispatches::init),
+            context.get_or_init_kernel(GenerateDispatch::init),
+            context.get_or_init_kernel(GenerateEdgeLineVertices::init),
+            context.get_or_init("init", DrawPointTriangles::init),
+            context.get_or_init("init", DrawEdgeLines::init),
         )
         .await;
 
@@ -75,16 +90,26 @@ impl GraphRenderer {
             },
             buffer::Usages::storage_binding().and_indirect(),
         );
+        let generate_edge_line_vertices_dispatch = device.create_buffer(
+            DispatchWorkgroups {
+                count_x: 0,
+                count_y: 0,
+                count_z: 0,
+            },
+            buffer::Usages::storage_binding().and_indirect(),
+        );
 
         GraphRenderer {
             device,
             generate_dispatches,
+            generate_dispatch,
             generate_edge_line_vertices,
             draw_point_triangles,
             draw_edge_lines,
             triangle_index_count,
             draw_point_triangles_dispatch,
             draw_edge_lines_dispatch,
+            generate_edge_line_vertices_dispatch,
         }
     }
 
@@ -128,6 +153,14 @@ impl GraphRenderer {
             },
         );
 
+        encoder = self.generate_dispatch.encode(
+            encoder,
+            GenerateDispatchResources {
+                count: node_count.uniform(),
+                dispatch: self.generate_edge_line_vertices_dispatch.storage(),
+            },
+        );
+
         encoder = self.generate_edge_line_vertices.encode(
             encoder,
             GenerateEdgeLineVerticesInput {
@@ -139,6 +172,7 @@ impl GraphRenderer {
                 nodes_matching,
                 edge_vertices: edge_vertices.view(),
             },
+            self.generate_edge_line_vertices_dispatch.view(),
         );
 
         let translation = Mat3::from_translation(Vec2::new(-1.0, -1.0));